@@ -0,0 +1,9 @@
+-- 食品溯源主表 (此前通过手工建表创建，这里补充为迁移，已存在时跳过)
+CREATE TABLE IF NOT EXISTS traceability_data (
+    product_id VARCHAR(255) NOT NULL PRIMARY KEY,
+    metadata_json JSON NOT NULL,
+    onchain_metadata_hash VARCHAR(66) NOT NULL,
+    blockchain_transaction_hash VARCHAR(66) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
+);
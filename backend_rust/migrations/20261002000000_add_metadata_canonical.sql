@@ -0,0 +1,5 @@
+-- 保存 RFC 8785 (JCS) 规范化后的元数据原始字节
+-- MySQL 的 JSON 列会重新排序键并规范化空白，无法据此重现哈希，因此单独保存参与哈希计算的文本
+-- 早期记录为 NULL，读取时从 metadata_json 重新规范化
+ALTER TABLE traceability_data
+    ADD COLUMN metadata_canonical LONGTEXT CHARACTER SET utf8mb4 COLLATE utf8mb4_bin NULL AFTER metadata_json;
@@ -0,0 +1,26 @@
+-- 后端自行索引的 RecordAdded 事件
+-- productId 是 indexed string，日志中只有 keccak256(productId)，与数据库记录对账时按该哈希匹配
+CREATE TABLE IF NOT EXISTS chain_events (
+    id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    contract_address VARCHAR(42) NOT NULL,
+    block_number BIGINT UNSIGNED NOT NULL,
+    block_hash VARCHAR(66) NOT NULL,
+    transaction_hash VARCHAR(66) NOT NULL,
+    log_index INT UNSIGNED NOT NULL,
+    product_id_hash VARCHAR(66) NOT NULL,
+    metadata_hash VARCHAR(66) NOT NULL,
+    recorder VARCHAR(42) NOT NULL,
+    event_timestamp BIGINT UNSIGNED NOT NULL, -- 事件中的 block.timestamp
+    indexed_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY uq_chain_events_tx_log (transaction_hash, log_index),
+    KEY idx_chain_events_block (contract_address, block_number),
+    KEY idx_chain_events_product (product_id_hash)
+);
+
+-- 索引器游标：每个合约已处理到的区块及其哈希，用于断点续扫和重组检测
+CREATE TABLE IF NOT EXISTS indexer_cursors (
+    contract_address VARCHAR(42) NOT NULL PRIMARY KEY,
+    last_block BIGINT UNSIGNED NOT NULL,
+    last_block_hash VARCHAR(66) NOT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
+);
@@ -0,0 +1,29 @@
+-- 数据库与链上事件对账任务
+CREATE TABLE IF NOT EXISTS reconciliation_runs (
+    id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    contract_address VARCHAR(42) NOT NULL,
+    trigger_source VARCHAR(16) NOT NULL, -- manual / scheduled
+    db_record_count BIGINT UNSIGNED NOT NULL DEFAULT 0,
+    chain_event_count BIGINT UNSIGNED NOT NULL DEFAULT 0,
+    finding_count BIGINT UNSIGNED NOT NULL DEFAULT 0,
+    started_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    finished_at TIMESTAMP NULL
+);
+
+-- 对账发现的差异
+-- missing_on_chain: 数据库记录没有对应的链上事件
+-- missing_in_db: 链上事件没有对应的数据库记录
+-- hash_mismatch: 产品最新的链上哈希与数据库不一致 (addRecord 会静默覆盖 records[productId])
+CREATE TABLE IF NOT EXISTS reconciliation_findings (
+    id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    run_id BIGINT UNSIGNED NOT NULL,
+    finding_type VARCHAR(32) NOT NULL,
+    product_id VARCHAR(255) NULL,          -- 链上事件只有 productId 的哈希，missing_in_db 时为空
+    product_id_hash VARCHAR(66) NOT NULL,
+    db_metadata_hash VARCHAR(66) NULL,
+    chain_metadata_hash VARCHAR(66) NULL,
+    transaction_hash VARCHAR(66) NULL,
+    block_number BIGINT UNSIGNED NULL,
+    KEY idx_reconciliation_findings_run (run_id, finding_type),
+    CONSTRAINT fk_reconciliation_findings_run FOREIGN KEY (run_id) REFERENCES reconciliation_runs (id) ON DELETE CASCADE
+);
@@ -0,0 +1,10 @@
+-- 重新上链 (re-anchor) 进度：链被重置后把所有记录重新提交到新部署的合约，中断后可从这里续跑
+CREATE TABLE IF NOT EXISTS reanchor_progress (
+    target_contract VARCHAR(42) NOT NULL,
+    product_id VARCHAR(255) NOT NULL,
+    transaction_hash VARCHAR(66) NULL,
+    status VARCHAR(16) NOT NULL, -- submitted / confirmed / failed
+    last_error TEXT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    PRIMARY KEY (target_contract, product_id)
+);
@@ -0,0 +1,18 @@
+-- 上链历史：每次上链一行，支持重新上链、多条链以及合约迁移
+-- traceability_data.blockchain_transaction_hash 保留为最近一次上链的交易哈希，兼容旧客户端
+CREATE TABLE IF NOT EXISTS record_anchors (
+    id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    product_id VARCHAR(255) NOT NULL,
+    chain_id BIGINT UNSIGNED NULL,         -- 旧数据迁移时未知
+    contract_address VARCHAR(42) NULL,     -- 旧数据迁移时未知
+    transaction_hash VARCHAR(66) NOT NULL,
+    block_number BIGINT UNSIGNED NULL,
+    block_timestamp TIMESTAMP NULL,        -- 上链区块的时间戳 (RecordAdded 事件中的 timestamp)
+    status VARCHAR(16) NOT NULL,           -- confirmed: 已通过回执校验；legacy: 从旧列迁移、未校验
+    anchored_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    KEY idx_record_anchors_product (product_id, id),
+    CONSTRAINT fk_record_anchors_product FOREIGN KEY (product_id) REFERENCES traceability_data (product_id) ON DELETE CASCADE
+);
+
+INSERT INTO record_anchors (product_id, transaction_hash, status, anchored_at)
+SELECT product_id, blockchain_transaction_hash, 'legacy', created_at FROM traceability_data;
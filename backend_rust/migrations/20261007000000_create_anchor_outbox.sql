@@ -0,0 +1,23 @@
+-- 上链发件箱 (transactional outbox)：后端托管上链的记录与发件箱条目在同一事务中写入，
+-- 由后台任务负责提交交易、失败重试并跟踪状态 pending -> submitted -> confirmed / failed
+-- 托管上链的记录在交易确认前没有交易哈希
+ALTER TABLE traceability_data MODIFY blockchain_transaction_hash VARCHAR(66) NULL;
+
+CREATE TABLE IF NOT EXISTS anchor_outbox (
+    id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    product_id VARCHAR(255) NOT NULL,
+    chain_id BIGINT UNSIGNED NOT NULL,
+    contract_address VARCHAR(42) NOT NULL,
+    metadata_hash VARCHAR(66) NOT NULL,
+    status VARCHAR(16) NOT NULL,            -- pending / submitted / confirmed / failed
+    attempts INT UNSIGNED NOT NULL DEFAULT 0, -- 已尝试发送交易的次数
+    transaction_hash VARCHAR(66) NULL,      -- 最近一次发送的交易
+    last_error TEXT NULL,
+    next_attempt_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    submitted_at TIMESTAMP NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    KEY idx_anchor_outbox_due (status, next_attempt_at),
+    KEY idx_anchor_outbox_product (product_id, id),
+    CONSTRAINT fk_anchor_outbox_product FOREIGN KEY (product_id) REFERENCES traceability_data (product_id) ON DELETE CASCADE
+);
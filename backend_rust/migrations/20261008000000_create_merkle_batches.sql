@@ -0,0 +1,34 @@
+-- Merkle 批量上链：按时间窗口收集记录哈希组成 Merkle 树，只把根提交上链
+-- 批次状态: open (收集中) -> sealed (已计算根，等待发送) -> submitted -> confirmed，超过最大发送次数后为 failed
+CREATE TABLE IF NOT EXISTS merkle_batches (
+    id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    chain_id BIGINT UNSIGNED NOT NULL,
+    contract_address VARCHAR(42) NOT NULL,
+    status VARCHAR(16) NOT NULL,
+    leaf_count INT UNSIGNED NOT NULL DEFAULT 0,
+    merkle_root VARCHAR(66) NULL,           -- 封存时计算
+    attempts INT UNSIGNED NOT NULL DEFAULT 0,
+    transaction_hash VARCHAR(66) NULL,
+    block_number BIGINT UNSIGNED NULL,
+    last_error TEXT NULL,
+    next_attempt_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    submitted_at TIMESTAMP NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    sealed_at TIMESTAMP NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    KEY idx_merkle_batches_status (status, next_attempt_at)
+);
+
+-- 批次中的叶子：每条记录一行，封存时写入叶子序号和到根的路径
+CREATE TABLE IF NOT EXISTS merkle_leaves (
+    id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    product_id VARCHAR(255) NOT NULL,
+    batch_id BIGINT UNSIGNED NOT NULL,
+    leaf_hash VARCHAR(66) NOT NULL,
+    leaf_index INT UNSIGNED NULL,
+    proof JSON NULL,                        -- [{"position": "left" | "right", "hash": "0x..."}]
+    UNIQUE KEY uq_merkle_leaves_product (product_id),
+    KEY idx_merkle_leaves_batch (batch_id, id),
+    CONSTRAINT fk_merkle_leaves_product FOREIGN KEY (product_id) REFERENCES traceability_data (product_id) ON DELETE CASCADE,
+    CONSTRAINT fk_merkle_leaves_batch FOREIGN KEY (batch_id) REFERENCES merkle_batches (id)
+);
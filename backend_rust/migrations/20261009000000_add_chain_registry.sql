@@ -0,0 +1,26 @@
+-- 多链支持：记录标记上链所在的链与合约，链上事件、索引游标与对账任务按 (链 ID, 合约地址) 区分
+-- 不同的本地链实例会部署出相同的合约地址，仅凭地址无法区分
+ALTER TABLE traceability_data
+    ADD COLUMN chain_id BIGINT UNSIGNED NULL AFTER blockchain_transaction_hash,
+    ADD COLUMN contract_address VARCHAR(42) NULL AFTER chain_id,
+    ADD KEY idx_traceability_data_chain (chain_id, contract_address);
+-- 已有记录的链与合约在服务启动时按默认链补全 (迁移时无法得知链配置)
+
+-- chain_events 与 indexer_cursors 是可以从链上重建的缓存，清空后由索引器按新的键重新索引
+DELETE FROM chain_events;
+DELETE FROM indexer_cursors;
+
+ALTER TABLE chain_events
+    ADD COLUMN chain_id BIGINT UNSIGNED NOT NULL AFTER id,
+    DROP INDEX uq_chain_events_tx_log,
+    DROP INDEX idx_chain_events_block,
+    ADD UNIQUE KEY uq_chain_events_tx_log (chain_id, transaction_hash, log_index),
+    ADD KEY idx_chain_events_block (chain_id, contract_address, block_number);
+
+ALTER TABLE indexer_cursors
+    DROP PRIMARY KEY,
+    ADD COLUMN chain_id BIGINT UNSIGNED NOT NULL FIRST,
+    ADD PRIMARY KEY (chain_id, contract_address);
+
+ALTER TABLE reconciliation_runs
+    ADD COLUMN chain_id BIGINT UNSIGNED NULL AFTER id; -- 早期对账任务为 NULL
@@ -0,0 +1,5 @@
+-- EIP-712 签名记录：保存生产者对 FoodRecord(productId, metadataHash) 的签名及由签名恢复出的地址
+ALTER TABLE traceability_data
+    ADD COLUMN recorder VARCHAR(42) NULL AFTER contract_address,
+    ADD COLUMN recorder_signature VARCHAR(132) NULL AFTER recorder,
+    ADD KEY idx_traceability_data_recorder (recorder);
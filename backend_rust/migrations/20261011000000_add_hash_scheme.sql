@@ -0,0 +1,4 @@
+-- 元数据哈希方案：已有记录由早期前端计算 keccak256(JSON.stringify(metadata))，按表单的键顺序序列化，
+-- 标记为 legacy-json-stringify-keccak256 并按该顺序复核；新记录默认使用 EIP-712 结构化哈希 (hashStruct(FoodMetadata))
+ALTER TABLE traceability_data
+    ADD COLUMN hash_scheme VARCHAR(32) NOT NULL DEFAULT 'legacy-json-stringify-keccak256' AFTER onchain_metadata_hash;
@@ -0,0 +1,22 @@
+-- Sign-In With Ethereum (EIP-4361) 登录
+-- auth_nonces: GET /api/auth/nonce 签发的一次性随机数，登录成功后标记为已使用，防止签名消息被重放
+-- auth_sessions: 登录成功后签发的会话，只保存令牌的 keccak256，数据库泄露时令牌不可直接使用
+CREATE TABLE IF NOT EXISTS auth_nonces (
+    nonce VARCHAR(64) NOT NULL PRIMARY KEY,
+    expires_at TIMESTAMP NOT NULL,
+    used_at TIMESTAMP NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    KEY idx_auth_nonces_expires (expires_at)
+);
+
+CREATE TABLE IF NOT EXISTS auth_sessions (
+    id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    token_hash VARCHAR(66) NOT NULL,
+    address VARCHAR(42) NOT NULL,           -- 登录的钱包地址 (小写)
+    chain_id BIGINT UNSIGNED NOT NULL,      -- SIWE 消息中的 Chain ID
+    domain VARCHAR(255) NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY uk_auth_sessions_token (token_hash),
+    KEY idx_auth_sessions_address (address)
+);
@@ -0,0 +1,26 @@
+-- 哈希链审计日志：后端对业务数据的每次写入在同一事务中追加一条审计记录
+-- entry_hash = keccak256(JCS{id, actor, action, target, payloadHash, prevHash, createdAt})，prev_hash 指向上一条记录的 entry_hash，
+-- 直接修改、删除或插入任意一条记录都会使之后的链接校验失败 (GET /api/admin/audit-log/verify 或 verify-audit-log 命令)
+CREATE TABLE IF NOT EXISTS audit_log (
+    id BIGINT UNSIGNED NOT NULL PRIMARY KEY,   -- 从 1 开始连续递增，由 audit_chain_head 分配
+    actor VARCHAR(64) NOT NULL,                -- 登录地址、system:<后台任务> 或 cli
+    action VARCHAR(64) NOT NULL,               -- 例如 record.create / record.anchor / reconciliation.run
+    target VARCHAR(255) NULL,                  -- 操作对象，例如产品ID或批次ID
+    payload LONGTEXT CHARACTER SET utf8mb4 COLLATE utf8mb4_bin NOT NULL, -- JCS 规范化的操作内容
+    payload_hash VARCHAR(66) NOT NULL,
+    prev_hash VARCHAR(66) NOT NULL,
+    entry_hash VARCHAR(66) NOT NULL,
+    created_at DATETIME(3) NOT NULL,
+    KEY idx_audit_log_target (target),
+    KEY idx_audit_log_action (action)
+);
+
+-- 链头：追加时 SELECT ... FOR UPDATE 串行化并发写入，同时记录最后一条的哈希，截断链尾也能被发现
+CREATE TABLE IF NOT EXISTS audit_chain_head (
+    id TINYINT UNSIGNED NOT NULL PRIMARY KEY,
+    last_entry_id BIGINT UNSIGNED NOT NULL,
+    last_hash VARCHAR(66) NOT NULL
+);
+
+INSERT INTO audit_chain_head (id, last_entry_id, last_hash)
+VALUES (1, 0, '0x0000000000000000000000000000000000000000000000000000000000000000');
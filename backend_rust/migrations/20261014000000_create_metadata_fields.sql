@@ -0,0 +1,25 @@
+-- salted-fields-v1 元数据哈希方案：每个字段加随机盐单独承诺，上链的是全部字段承诺的根
+-- metadata_fields: 字段明文 (JCS 规范化)、盐值与承诺；traceability_data.metadata_json 只保存公开字段
+-- metadata_disclosures: 面向特定受众的选择性披露，凭令牌查看选定字段的明文与盐值，只保存令牌的 keccak256
+CREATE TABLE IF NOT EXISTS metadata_fields (
+    product_id VARCHAR(255) NOT NULL,
+    field_name VARCHAR(255) NOT NULL,
+    value_canonical TEXT NOT NULL,
+    salt VARCHAR(66) NOT NULL,
+    commitment VARCHAR(66) NOT NULL,
+    is_public BOOLEAN NOT NULL DEFAULT FALSE,
+    PRIMARY KEY (product_id, field_name)
+);
+
+CREATE TABLE IF NOT EXISTS metadata_disclosures (
+    id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    token_hash VARCHAR(66) NOT NULL,
+    product_id VARCHAR(255) NOT NULL,
+    audience VARCHAR(255) NOT NULL,
+    fields JSON NOT NULL,                   -- 披露的字段名 ["origin", ...]
+    created_by VARCHAR(64) NOT NULL,        -- 创建者 (登录地址)
+    expires_at TIMESTAMP NULL,              -- 为空表示长期有效
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY uk_metadata_disclosures_token (token_hash),
+    KEY idx_metadata_disclosures_product (product_id)
+);
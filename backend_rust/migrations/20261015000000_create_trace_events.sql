@@ -0,0 +1,19 @@
+-- 多环节供应链事件：采收 → 加工 → 包装 → 仓储 → 运输 → 零售，每个产品一条按追加顺序编号的事件链
+-- event_hash = keccak256(JCS{productId, sequence, stage, actor, location, occurredAt, payload, prevEventHash})，
+-- prev_event_hash 指向同一产品上一条事件的 event_hash (第一条为全零)，修改、删除或插入任意一条事件都会使之后的哈希校验失败
+CREATE TABLE IF NOT EXISTS trace_events (
+    id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    product_id VARCHAR(255) NOT NULL,
+    sequence INT UNSIGNED NOT NULL,            -- 同一产品内从 1 开始按追加顺序编号
+    stage VARCHAR(16) NOT NULL,                -- harvest / processing / packaging / storage / shipping / retail
+    actor VARCHAR(255) NOT NULL,               -- 执行该环节的组织或人员
+    location VARCHAR(255) NOT NULL,
+    occurred_at DATETIME(3) NOT NULL,          -- 事件发生时间 (由提交者填写)，时间线按该列排序
+    payload JSON NOT NULL,                     -- 各环节的结构化数据，字段见 trace_events::StagePayload
+    prev_event_hash VARCHAR(66) NOT NULL,
+    event_hash VARCHAR(66) NOT NULL,
+    recorded_by VARCHAR(64) NOT NULL,          -- 提交者 (登录地址)
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY uk_trace_events_sequence (product_id, sequence),
+    KEY idx_trace_events_occurred (product_id, occurred_at)
+);
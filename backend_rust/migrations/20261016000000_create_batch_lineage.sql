@@ -0,0 +1,15 @@
+-- 批次谱系：父批次拆分 (split) 为托盘/小批次、与其他批次混合 (merge)、或加工 (transform) 为新产品
+-- 每行是一条父 → 子的边，GET /api/food-records/{product_id}/upstream 与 /downstream 用递归 CTE 沿边遍历
+CREATE TABLE IF NOT EXISTS batch_lineage (
+    id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    parent_product_id VARCHAR(255) NOT NULL,
+    child_product_id VARCHAR(255) NOT NULL,
+    relationship VARCHAR(16) NOT NULL,         -- split / merge / transform
+    parent_quantity DOUBLE NULL,               -- 从父批次投入的数量
+    child_quantity DOUBLE NULL,                -- 子批次中来自该父批次的数量
+    unit VARCHAR(32) NULL,
+    created_by VARCHAR(64) NOT NULL,           -- 提交者 (登录地址)
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY uk_batch_lineage_edge (parent_product_id, child_product_id),
+    KEY idx_batch_lineage_child (child_product_id)
+);
@@ -0,0 +1,25 @@
+-- 召回：发起时列出直接受影响的产品，后端沿批次谱系 (batch_lineage) 向下游传播，可达的批次全部记为受影响
+-- 状态: open → in_progress → closed (open 也可直接 closed)，closed 之后不能再变更
+CREATE TABLE IF NOT EXISTS recalls (
+    id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    reason TEXT NOT NULL,
+    severity_class VARCHAR(16) NOT NULL,        -- class_i / class_ii / class_iii
+    initiating_organization VARCHAR(255) NOT NULL,
+    status VARCHAR(16) NOT NULL,
+    propagation_truncated BOOLEAN NOT NULL DEFAULT FALSE, -- 下游遍历达到深度或行数上限，可能有批次未被标记
+    opened_by VARCHAR(64) NOT NULL,             -- 发起者 (登录地址)
+    opened_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    closed_at TIMESTAMP NULL,
+    KEY idx_recalls_status (status)
+);
+
+CREATE TABLE IF NOT EXISTS recall_products (
+    recall_id BIGINT UNSIGNED NOT NULL,
+    product_id VARCHAR(255) NOT NULL,
+    source VARCHAR(16) NOT NULL,                -- direct (发起时列出) / downstream (经谱系传播)
+    depth INT UNSIGNED NOT NULL,                -- 与直接受影响产品之间的边数，direct 为 0
+    via_product_id VARCHAR(255) NULL,           -- downstream 产品由哪个直接受影响的产品传播而来
+    PRIMARY KEY (recall_id, product_id),
+    KEY idx_recall_products_product (product_id)
+);
@@ -0,0 +1,38 @@
+-- 认证证书登记：有机、HACCP、ISO 22000、GAP、清真等证书，关联持证组织与具体的产品记录
+-- document_hash 为证书文件 (PDF 等) 的哈希，证书原件由持证方保存，核对时重新计算哈希比对
+CREATE TABLE IF NOT EXISTS organizations (
+    id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY uk_organizations_name (name)
+);
+
+CREATE TABLE IF NOT EXISTS certificates (
+    id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    certificate_type VARCHAR(16) NOT NULL,      -- organic / haccp / iso_22000 / gap / halal
+    issuer VARCHAR(255) NOT NULL,               -- 发证机构
+    certificate_number VARCHAR(255) NOT NULL,
+    scope TEXT NOT NULL,                        -- 认证范围 (产品、场所或工艺)
+    valid_from DATE NOT NULL,
+    valid_until DATE NOT NULL,                  -- 有效期最后一天
+    document_hash VARCHAR(66) NOT NULL,
+    created_by VARCHAR(64) NOT NULL,            -- 登记者 (登录地址)
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY uk_certificates_number (issuer, certificate_number),
+    KEY idx_certificates_valid_until (valid_until)
+);
+
+CREATE TABLE IF NOT EXISTS certificate_organizations (
+    certificate_id BIGINT UNSIGNED NOT NULL,
+    organization_id BIGINT UNSIGNED NOT NULL,
+    PRIMARY KEY (certificate_id, organization_id),
+    KEY idx_certificate_organizations_org (organization_id)
+);
+
+-- 证书覆盖的产品记录，批次可以在证书登记之后陆续关联
+CREATE TABLE IF NOT EXISTS certificate_products (
+    certificate_id BIGINT UNSIGNED NOT NULL,
+    product_id VARCHAR(255) NOT NULL,
+    PRIMARY KEY (certificate_id, product_id),
+    KEY idx_certificate_products_product (product_id)
+);
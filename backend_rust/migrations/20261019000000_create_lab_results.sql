@@ -0,0 +1,16 @@
+-- 实验室检测结果：每行一个分析物 (农药残留、重金属、微生物等) 的测定值，按产品类别与法域的最大残留限量 (MRL) 表判定
+-- 限量表不入库，由 MRL_TABLES_PATH 指定的 JSON 文件配置 (见 mrl_tables.example.json)，修改限量后重新查询即按新表判定
+CREATE TABLE IF NOT EXISTS lab_results (
+    id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    product_id VARCHAR(255) NOT NULL,
+    analyte VARCHAR(255) NOT NULL,              -- 分析物，例如 chlorpyrifos、lead
+    method VARCHAR(255) NOT NULL,               -- 检测方法，例如 GB 23200.113-2018、LC-MS/MS
+    measured_value DOUBLE NOT NULL,
+    unit VARCHAR(32) NOT NULL,                  -- 例如 mg/kg、µg/kg、CFU/g
+    laboratory VARCHAR(255) NOT NULL,
+    sample_date DATE NOT NULL,
+    report_hash VARCHAR(66) NULL,               -- 检测报告文件 (PDF 等) 的 32 字节哈希
+    created_by VARCHAR(64) NOT NULL,            -- 提交者 (登录地址)
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    KEY idx_lab_results_product (product_id)
+);
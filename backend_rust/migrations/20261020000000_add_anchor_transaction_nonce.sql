@@ -0,0 +1,9 @@
+-- 已发送交易的 nonce 与签名后的原始交易：超时未打包时重新广播同一笔交易，
+-- 只有该 nonce 已被其他交易占用 (原交易不可能再被打包) 时才用新的 nonce 重新发送，避免同一条目被重复上链
+ALTER TABLE anchor_outbox
+    ADD COLUMN transaction_nonce BIGINT UNSIGNED NULL AFTER transaction_hash,
+    ADD COLUMN raw_transaction MEDIUMTEXT NULL AFTER transaction_nonce;
+
+ALTER TABLE merkle_batches
+    ADD COLUMN transaction_nonce BIGINT UNSIGNED NULL AFTER transaction_hash,
+    ADD COLUMN raw_transaction MEDIUMTEXT NULL AFTER transaction_nonce;
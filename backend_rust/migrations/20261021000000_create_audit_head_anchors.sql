@@ -0,0 +1,16 @@
+-- 审计链头上链：后台任务定期把链头哈希通过 addRecord("audit-head", head_hash) 写入合约，
+-- 数据库中的审计日志被整体重算 (每条记录的哈希都自洽) 后，与链上锚定的哈希对不上
+CREATE TABLE IF NOT EXISTS audit_head_anchors (
+    id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    entry_id BIGINT UNSIGNED NOT NULL,        -- 锚定时链头的最后一条记录
+    head_hash VARCHAR(66) NOT NULL,
+    chain_id BIGINT UNSIGNED NOT NULL,
+    contract_address VARCHAR(42) NOT NULL,
+    status VARCHAR(16) NOT NULL,              -- submitted / confirmed / failed
+    transaction_hash VARCHAR(66) NOT NULL,
+    block_number BIGINT UNSIGNED NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    KEY idx_audit_head_anchors_hash (head_hash),
+    KEY idx_audit_head_anchors_status (status, id)
+);
@@ -0,0 +1,9 @@
+-- 创建记录的登录地址：未签名的记录由创建者管理 (例如创建选择性披露授权)，匿名创建 (AUTH_REQUIRED=false) 的记录为空
+ALTER TABLE traceability_data
+    ADD COLUMN created_by VARCHAR(64) NULL AFTER recorder_signature;
+
+-- 已有记录从 record.create 审计记录中补全创建者 (只取登录地址，匿名创建的仍为空)
+UPDATE traceability_data t
+JOIN audit_log a ON a.action = 'record.create' AND a.target = t.product_id
+SET t.created_by = a.actor
+WHERE a.actor LIKE '0x%';
@@ -0,0 +1,4 @@
+-- 产品类别：实验室检测结果按 (法域, 类别) 选择 MRL 限量表，类别随产品保存，不再由查询参数指定
+-- 提交检测结果时登记 (POST /api/food-records/{product_id}/lab-results 的 category)，登记后不能改为其他类别
+ALTER TABLE traceability_data
+    ADD COLUMN product_category VARCHAR(64) NULL AFTER created_by;
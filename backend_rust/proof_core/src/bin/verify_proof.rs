@@ -0,0 +1,38 @@
+// 离线验证 GET /api/food-records/{product_id}/proof 导出的证明包，不连接数据库和以太坊节点
+// 用法: cargo run --bin verify_proof -- <bundle.json>   (省略文件名时从标准输入读取)
+use std::io::Read;
+use proof_core::proof::{self, ProofBundle};
+
+fn main() {
+    let path = std::env::args().nth(1);
+    let mut content = String::new();
+    let read = match &path {
+        Some(path) => std::fs::read_to_string(path).map(|text| content = text),
+        None => std::io::stdin().read_to_string(&mut content).map(|_| ()),
+    };
+    if let Err(e) = read {
+        eprintln!("读取证明包失败: {}", e);
+        std::process::exit(2);
+    }
+    let bundle: ProofBundle = match serde_json::from_str(&content) {
+        Ok(bundle) => bundle,
+        Err(e) => {
+            eprintln!("证明包格式错误: {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    let verification = proof::verify_proof_bundle(&bundle);
+    println!("产品ID: {}", bundle.product_id);
+    for check in &verification.checks {
+        println!("[{}] {}: {}", if check.passed { "通过" } else { "失败" }, check.name, check.detail);
+    }
+    if !verification.valid {
+        println!("证明包无效。");
+        std::process::exit(1);
+    }
+    println!(
+        "证明包有效。请在区块浏览器或自己的节点上确认链 ID {} 的区块 {} 的哈希为 {}。",
+        bundle.anchor.chain_id, bundle.anchor.block_number, verification.block_hash
+    );
+}
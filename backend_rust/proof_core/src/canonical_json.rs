@@ -0,0 +1,246 @@
+// RFC 8785 JSON Canonicalization Scheme (JCS)
+// 同一个 JSON 值无论键顺序、空白或数字写法如何，规范化后的字节都完全相同，
+// 因此可以在任何语言中重现元数据哈希，也不受 MySQL JSON 列重新排序键的影响
+use serde_json::{Map, Number, Value as JsonValue};
+use crate::error::CoreError;
+
+// 将 JSON 值序列化为 JCS 规范形式的字符串
+pub fn canonicalize(value: &JsonValue) -> Result<String, CoreError> {
+    let mut output = String::new();
+    write_value(value, &mut output, true)?;
+    Ok(output)
+}
+
+// 与 ECMAScript JSON.stringify 相同的紧凑文本，但不排序键：顶层对象先按 key_order 输出其中存在的键，
+// 其余的键与嵌套对象保持 serde_json 读取时的顺序；用于重现早期前端按插入顺序序列化的元数据
+pub fn stringify_in_order(value: &JsonValue, key_order: &[&str]) -> Result<String, CoreError> {
+    let map = match value {
+        JsonValue::Object(map) => map,
+        _ => {
+            let mut output = String::new();
+            write_value(value, &mut output, false)?;
+            return Ok(output);
+        }
+    };
+    let ordered = key_order
+        .iter()
+        .filter_map(|key| map.get_key_value(*key))
+        .chain(map.iter().filter(|(key, _)| !key_order.contains(&key.as_str())));
+    let mut output = String::from("{");
+    for (i, (key, value)) in ordered.enumerate() {
+        if i > 0 {
+            output.push(',');
+        }
+        write_string(key, &mut output);
+        output.push(':');
+        write_value(value, &mut output, false)?;
+    }
+    output.push('}');
+    Ok(output)
+}
+
+fn write_value(value: &JsonValue, output: &mut String, sort_keys: bool) -> Result<(), CoreError> {
+    match value {
+        JsonValue::Null => output.push_str("null"),
+        JsonValue::Bool(b) => output.push_str(if *b { "true" } else { "false" }),
+        JsonValue::Number(n) => output.push_str(&format_number(n)?),
+        JsonValue::String(s) => write_string(s, output),
+        JsonValue::Array(items) => {
+            output.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+                write_value(item, output, sort_keys)?;
+            }
+            output.push(']');
+        }
+        JsonValue::Object(map) => write_object(map, output, sort_keys)?,
+    }
+    Ok(())
+}
+
+// 对象的键按 UTF-16 码元顺序排序 (RFC 8785 第 3.2.3 节)，sort_keys 为 false 时保持原顺序
+fn write_object(map: &Map<String, JsonValue>, output: &mut String, sort_keys: bool) -> Result<(), CoreError> {
+    let mut entries: Vec<(&String, &JsonValue)> = map.iter().collect();
+    if sort_keys {
+        entries.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+    }
+
+    output.push('{');
+    for (i, (key, value)) in entries.into_iter().enumerate() {
+        if i > 0 {
+            output.push(',');
+        }
+        write_string(key, output);
+        output.push(':');
+        write_value(value, output, sort_keys)?;
+    }
+    output.push('}');
+    Ok(())
+}
+
+// 字符串转义规则与 ECMAScript JSON.stringify 一致：只转义引号、反斜杠和控制字符
+fn write_string(s: &str, output: &mut String) {
+    output.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\u{08}' => output.push_str("\\b"),
+            '\t' => output.push_str("\\t"),
+            '\n' => output.push_str("\\n"),
+            '\u{0C}' => output.push_str("\\f"),
+            '\r' => output.push_str("\\r"),
+            c if (c as u32) < 0x20 => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+}
+
+// 数字按 IEEE 754 双精度处理，并使用 ECMAScript Number.prototype.toString 的格式输出
+fn format_number(n: &Number) -> Result<String, CoreError> {
+    let value = n
+        .as_f64()
+        .ok_or_else(|| CoreError::InvalidInput(format!("无法规范化的数字: {}", n)))?;
+    if !value.is_finite() {
+        return Err(CoreError::InvalidInput(format!("JSON 中不允许出现非有限数字: {}", n)));
+    }
+    if value == 0.0 {
+        return Ok("0".to_string()); // -0 也输出为 0
+    }
+
+    // Rust 的 {:e} 输出最短往返表示，例如 "1.2345e3"，从中取出有效数字和指数
+    let sign = if value < 0.0 { "-" } else { "" };
+    let scientific = format!("{:e}", value.abs());
+    let (mantissa, exponent) = scientific
+        .split_once('e')
+        .ok_or_else(|| CoreError::InternalError(format!("数字格式化失败: {}", scientific)))?;
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let exponent: i32 = exponent
+        .parse()
+        .map_err(|_| CoreError::InternalError(format!("数字格式化失败: {}", scientific)))?;
+    let digits = prefer_even_digits(value.abs(), digits, exponent);
+
+    let k = digits.len() as i32; // 有效数字位数
+    let n = exponent + 1; // 小数点相对于有效数字起始处的位置
+
+    let body = if k <= n && n <= 21 {
+        format!("{}{}", digits, "0".repeat((n - k) as usize))
+    } else if 0 < n && n <= 21 {
+        format!("{}.{}", &digits[..n as usize], &digits[n as usize..])
+    } else if -6 < n && n <= 0 {
+        format!("0.{}{}", "0".repeat((-n) as usize), digits)
+    } else {
+        let exponent_sign = if n > 0 { "+" } else { "-" };
+        let fraction = if k > 1 { format!(".{}", &digits[1..]) } else { String::new() };
+        format!("{}{}e{}{}", &digits[..1], fraction, exponent_sign, (n - 1).abs())
+    };
+    Ok(format!("{}{}", sign, body))
+}
+
+// 精确值恰好落在两个同样短的候选中间时，Rust 取较大者，ECMAScript 取末位为偶数的一个 (例如 1424953923781206.25 输出 ...206.2)
+fn prefer_even_digits(value: f64, digits: String, exponent: i32) -> String {
+    let Ok(shortest) = digits.parse::<u64>() else {
+        return digits;
+    };
+    if shortest % 2 == 0 {
+        return digits;
+    }
+    // 双精度数的十进制展开是有限的 (最多 767 位有效数字)，足够多的位数即为精确值
+    let exact = format!("{:.800e}", value);
+    let Some((exact_mantissa, exact_exponent)) = exact.split_once('e') else {
+        return digits;
+    };
+    let exact_digits = exact_mantissa.replace('.', "");
+    let exact_digits = exact_digits.trim_end_matches('0');
+    for candidate in [shortest - 1, shortest + 1] {
+        let candidate_digits = candidate.to_string();
+        if candidate_digits.len() != digits.len() || exact_exponent != exponent.to_string() {
+            continue;
+        }
+        let midpoint = format!("{}5", shortest.min(candidate));
+        let round_trips = format!("{}e{}", candidate_digits, exponent + 1 - digits.len() as i32).parse::<f64>() == Ok(value);
+        if exact_digits == midpoint && round_trips {
+            return candidate_digits;
+        }
+    }
+    digits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn number(bits: u64) -> String {
+        format_number(&Number::from_f64(f64::from_bits(bits)).unwrap()).unwrap()
+    }
+
+    // RFC 8785 附录 B 的数字样例 (NaN 与 Infinity 不是合法的 JSON 数字，不在此列)
+    #[test]
+    fn formats_rfc8785_appendix_b_numbers() {
+        let cases: [(u64, &str); 24] = [
+            (0x0000000000000000, "0"),
+            (0x8000000000000000, "0"),
+            (0x0000000000000001, "5e-324"),
+            (0x8000000000000001, "-5e-324"),
+            (0x7fefffffffffffff, "1.7976931348623157e+308"),
+            (0xffefffffffffffff, "-1.7976931348623157e+308"),
+            (0x4340000000000000, "9007199254740992"),
+            (0xc340000000000000, "-9007199254740992"),
+            (0x4430000000000000, "295147905179352830000"),
+            (0x44b52d02c7e14af5, "9.999999999999997e+22"),
+            (0x44b52d02c7e14af6, "1e+23"),
+            (0x44b52d02c7e14af7, "1.0000000000000001e+23"),
+            (0x444b1ae4d6e2ef4e, "999999999999999700000"),
+            (0x444b1ae4d6e2ef4f, "999999999999999900000"),
+            (0x444b1ae4d6e2ef50, "1e+21"),
+            (0x3eb0c6f7a0b5ed8c, "9.999999999999997e-7"),
+            (0x3eb0c6f7a0b5ed8d, "0.000001"),
+            (0x41b3de4355555553, "333333333.3333332"),
+            (0x41b3de4355555554, "333333333.33333325"),
+            (0x41b3de4355555555, "333333333.3333333"),
+            (0x41b3de4355555556, "333333333.3333334"),
+            (0x41b3de4355555557, "333333333.33333343"),
+            (0xbecbf647612f3696, "-0.0000033333333333333333"),
+            (0x43143ff3c1cb0959, "1424953923781206.2"),
+        ];
+        for (bits, expected) in cases {
+            assert_eq!(number(bits), expected, "0x{:016x}", bits);
+        }
+    }
+
+    // RFC 8785 第 3.2.2 节的样例
+    #[test]
+    fn canonicalizes_rfc8785_example() {
+        let input = r#"{
+            "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+            "string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+            "literals": [null, true, false]
+        }"#;
+        let value: JsonValue = serde_json::from_str(input).unwrap();
+        assert_eq!(
+            canonicalize(&value).unwrap(),
+            r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#
+        );
+    }
+
+    // RFC 8785 第 3.2.3 节：键按 UTF-16 码元排序，补充平面字符 (代理对) 排在 U+FB33 之前
+    #[test]
+    fn sorts_keys_by_utf16_code_units() {
+        let value = json!({
+            "\u{20ac}": "Euro Sign",
+            "\r": "Carriage Return",
+            "\u{fb33}": "Hebrew Letter Dalet With Dagesh",
+            "1": "One",
+            "\u{1f600}": "Emoji: Grinning Face",
+            "\u{80}": "Control",
+            "\u{f6}": "Latin Small Letter O With Diaeresis",
+        });
+        let canonical: Map<String, JsonValue> = serde_json::from_str(&canonicalize(&value).unwrap()).unwrap();
+        let keys: Vec<&str> = canonical.keys().map(String::as_str).collect();
+        assert_eq!(keys, ["\r", "1", "\u{80}", "\u{f6}", "\u{20ac}", "\u{1f600}", "\u{fb33}"]);
+    }
+}
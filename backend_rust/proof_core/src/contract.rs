@@ -0,0 +1,199 @@
+// FoodTraceability 合约的 ABI 编码 / 解码
+// 合约接口很小 (string / bytes32 / address / uint256)，这里直接手写编码，避免引入完整的 ABI 框架
+// 每个编码函数对应一个 FunctionBinding，启动时与 Hardhat 编译产物中的 ABI 核对 (见 artifacts.rs)，合约接口变化时拒绝启动
+use crate::error::CoreError;
+use crate::rpc_types::{decode_hex_bytes, RpcLog};
+use crate::hashing::{keccak256, to_hex_string};
+
+// RecordAdded(string indexed productId, bytes32 indexed metadataHash, address indexed recorder, uint256 timestamp)
+pub const RECORD_ADDED_SIGNATURE: &str = "RecordAdded(string,bytes32,address,uint256)";
+pub const RECORD_ADDED_INDEXED: [bool; 4] = [true, true, true, false];
+
+// 编码函数所调用的合约函数：名称、参数类型与返回值类型
+#[derive(Debug)]
+pub struct FunctionBinding {
+    pub name: &'static str,
+    pub inputs: &'static [&'static str],
+    pub outputs: &'static [&'static str],
+}
+
+impl FunctionBinding {
+    pub fn signature(&self) -> String {
+        format!("{}({})", self.name, self.inputs.join(","))
+    }
+}
+
+pub const ADD_RECORD: FunctionBinding = FunctionBinding {
+    name: "addRecord",
+    inputs: &["string", "bytes32"],
+    outputs: &[],
+};
+pub const GET_METADATA_HASH: FunctionBinding = FunctionBinding {
+    name: "getMetadataHash",
+    inputs: &["string"],
+    outputs: &["bytes32"],
+};
+pub const RECORDS: FunctionBinding = FunctionBinding {
+    name: "records",
+    inputs: &["string"],
+    outputs: &["bytes32", "address", "uint256"],
+};
+pub const CHECK_METADATA_HASH_EXISTS: FunctionBinding = FunctionBinding {
+    name: "checkMetadataHashExists",
+    inputs: &["bytes32"],
+    outputs: &["bool"],
+};
+
+pub const FUNCTION_BINDINGS: [&FunctionBinding; 4] = [&ADD_RECORD, &GET_METADATA_HASH, &RECORDS, &CHECK_METADATA_HASH_EXISTS];
+
+// 合约 records(productId) 返回的结构
+#[derive(Debug, Clone)]
+pub struct OnChainRecord {
+    pub metadata_hash: String, // bytes32，带 0x 前缀
+    pub recorder: String,      // address，带 0x 前缀
+    pub timestamp: u64,        // 区块时间戳，为 0 表示该产品ID从未上链
+}
+
+// 解码后的 RecordAdded 事件
+// productId 是 indexed string，日志中只保存 keccak256(productId)，无法还原原文
+#[derive(Debug, Clone)]
+pub struct RecordAddedEvent {
+    pub product_id_hash: String,
+    pub metadata_hash: String,
+    pub recorder: String,
+    pub timestamp: u64,
+}
+
+// 函数选择器：keccak256(函数签名) 的前 4 个字节
+fn selector(function: &FunctionBinding) -> [u8; 4] {
+    let hash = keccak256(function.signature().as_bytes());
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+// 将 u64 编码为 32 字节大端 uint256
+fn encode_u256(value: u64) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+// 编码只有一个 string 参数的调用：选择器 + 偏移量 + 长度 + 按 32 字节补齐的内容
+fn encode_single_string_call(function: &FunctionBinding, value: &str) -> Vec<u8> {
+    let bytes = value.as_bytes();
+    let padded_len = bytes.len().div_ceil(32) * 32;
+    let mut data = Vec::with_capacity(4 + 64 + padded_len);
+    data.extend_from_slice(&selector(function));
+    data.extend_from_slice(&encode_u256(32));
+    data.extend_from_slice(&encode_u256(bytes.len() as u64));
+    data.extend_from_slice(bytes);
+    data.resize(4 + 64 + padded_len, 0);
+    data
+}
+
+// 解析 0x 开头的 32 字节十六进制哈希
+pub fn parse_bytes32(value: &str) -> Result<[u8; 32], CoreError> {
+    let bytes = hex::decode(value.trim().trim_start_matches("0x"))
+        .map_err(|_| CoreError::InvalidInput(format!("无效的 bytes32 十六进制值: {}", value)))?;
+    bytes
+        .try_into()
+        .map_err(|_| CoreError::InvalidInput(format!("bytes32 长度必须为 32 字节: {}", value)))
+}
+
+// 取返回数据中的第 index 个 32 字节字
+fn word(data: &[u8], index: usize) -> Result<&[u8], CoreError> {
+    data.get(index * 32..(index + 1) * 32)
+        .ok_or_else(|| CoreError::BlockchainError(format!("合约返回数据长度不足: {} 字节", data.len())))
+}
+
+fn decode_u64(word: &[u8]) -> Result<u64, CoreError> {
+    if word[..24].iter().any(|b| *b != 0) {
+        return Err(CoreError::BlockchainError("uint256 数值超出 u64 范围。".to_string()));
+    }
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(&word[24..]);
+    Ok(u64::from_be_bytes(buf))
+}
+
+fn decode_address(word: &[u8]) -> String {
+    format!("0x{}", hex::encode(&word[12..]))
+}
+
+// getMetadataHash(string) -> bytes32
+pub fn encode_get_metadata_hash(product_id: &str) -> Vec<u8> {
+    encode_single_string_call(&GET_METADATA_HASH, product_id)
+}
+
+pub fn decode_get_metadata_hash(data: &[u8]) -> Result<String, CoreError> {
+    Ok(to_hex_string(word(data, 0)?))
+}
+
+// records(string) -> (bytes32 metadataHash, address recorder, uint256 timestamp)
+pub fn encode_records(product_id: &str) -> Vec<u8> {
+    encode_single_string_call(&RECORDS, product_id)
+}
+
+pub fn decode_records(data: &[u8]) -> Result<OnChainRecord, CoreError> {
+    Ok(OnChainRecord {
+        metadata_hash: to_hex_string(word(data, 0)?),
+        recorder: decode_address(word(data, 1)?),
+        timestamp: decode_u64(word(data, 2)?)?,
+    })
+}
+
+// addRecord(string _productId, bytes32 _metadataHash)
+// 动态参数放在尾部：头部依次为 string 的偏移量 (0x40) 和 bytes32
+pub fn encode_add_record(product_id: &str, metadata_hash: &[u8; 32]) -> Vec<u8> {
+    let bytes = product_id.as_bytes();
+    let padded_len = bytes.len().div_ceil(32) * 32;
+    let mut data = Vec::with_capacity(4 + 96 + padded_len);
+    data.extend_from_slice(&selector(&ADD_RECORD));
+    data.extend_from_slice(&encode_u256(64));
+    data.extend_from_slice(metadata_hash);
+    data.extend_from_slice(&encode_u256(bytes.len() as u64));
+    data.extend_from_slice(bytes);
+    data.resize(4 + 96 + padded_len, 0);
+    data
+}
+
+// checkMetadataHashExists(bytes32) -> bool
+pub fn encode_check_metadata_hash_exists(metadata_hash: &[u8; 32]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(4 + 32);
+    data.extend_from_slice(&selector(&CHECK_METADATA_HASH_EXISTS));
+    data.extend_from_slice(metadata_hash);
+    data
+}
+
+pub fn decode_bool(data: &[u8]) -> Result<bool, CoreError> {
+    Ok(decode_u64(word(data, 0)?)? != 0)
+}
+
+// RecordAdded 事件的 topic0
+pub fn record_added_topic() -> String {
+    to_hex_string(&keccak256(RECORD_ADDED_SIGNATURE.as_bytes()))
+}
+
+// indexed string 参数在 topic 中的取值：keccak256(utf8 字节)
+pub fn product_id_topic(product_id: &str) -> String {
+    to_hex_string(&keccak256(product_id.as_bytes()))
+}
+
+// 解码 RecordAdded 日志，topic0 不匹配时返回 None
+pub fn decode_record_added(log: &RpcLog) -> Result<Option<RecordAddedEvent>, CoreError> {
+    if log.topics.first().map(|t| t.to_lowercase()) != Some(record_added_topic()) {
+        return Ok(None);
+    }
+    if log.topics.len() != 4 {
+        return Err(CoreError::BlockchainError(format!("RecordAdded 日志的 topic 数量异常: {}", log.topics.len())));
+    }
+    let data = decode_hex_bytes(&log.data)?;
+    let recorder_topic = decode_hex_bytes(&log.topics[3])?;
+    if recorder_topic.len() != 32 {
+        return Err(CoreError::BlockchainError("RecordAdded 日志的 recorder topic 长度异常。".to_string()));
+    }
+    Ok(Some(RecordAddedEvent {
+        product_id_hash: log.topics[1].to_lowercase(),
+        metadata_hash: log.topics[2].to_lowercase(),
+        recorder: decode_address(&recorder_topic),
+        timestamp: decode_u64(word(&data, 0)?)?,
+    }))
+}
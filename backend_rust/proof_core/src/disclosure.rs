@@ -0,0 +1,26 @@
+// salted-fields-v1 的字段承诺与承诺根 (方案说明见 backend_rust/src/disclosure.rs)
+// 离线验证证明包时由公开字段的明文与盐值重新计算承诺，并核对全部承诺的根
+use std::collections::BTreeMap;
+use crate::contract;
+use crate::error::CoreError;
+use crate::hashing::{keccak256, to_hex_string};
+
+pub const FIELD_COMMITMENT_ENCODING: &str = "keccak256(keccak256(utf8(field)) || salt || keccak256(utf8(JCS(value))))";
+pub const ROOT_ENCODING: &str = "keccak256(fieldCommitment[0] || ... || fieldCommitment[n-1])，按字段名的 UTF-8 字节序排列";
+
+pub fn field_commitment(field: &str, value_canonical: &str, salt: &str) -> Result<[u8; 32], CoreError> {
+    let mut data = Vec::with_capacity(96);
+    data.extend_from_slice(&keccak256(field.as_bytes()));
+    data.extend_from_slice(&contract::parse_bytes32(salt)?);
+    data.extend_from_slice(&keccak256(value_canonical.as_bytes()));
+    Ok(keccak256(&data))
+}
+
+// BTreeMap 按字段名字节序迭代，即 ROOT_ENCODING 规定的顺序
+pub fn commitment_root(commitments: &BTreeMap<String, String>) -> Result<String, CoreError> {
+    let mut data = Vec::with_capacity(32 * commitments.len());
+    for commitment in commitments.values() {
+        data.extend_from_slice(&contract::parse_bytes32(commitment)?);
+    }
+    Ok(to_hex_string(&keccak256(&data)))
+}
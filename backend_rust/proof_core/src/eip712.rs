@@ -0,0 +1,239 @@
+// EIP-712 类型化数据签名
+// 生产者用钱包对 FoodRecord(productId, metadataHash) 签名 (eth_signTypedData_v4)，后端恢复签名者地址作为记录的 recorder，
+// 域绑定链 ID 与合约地址，签名不能被拿到其他链或合约上重放
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use crate::contract;
+use crate::error::CoreError;
+use crate::hashing::{keccak256, to_hex_string};
+
+pub const DOMAIN_NAME: &str = "FoodTraceability";
+pub const DOMAIN_VERSION: &str = "1";
+
+const DOMAIN_TYPE: &str = "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)";
+pub const FOOD_RECORD_TYPE: &str = "FoodRecord(string productId,bytes32 metadataHash)";
+pub const FOOD_METADATA_TYPE: &str = "FoodMetadata(string productId,string productName,string producerInfo,string productionDate,string origin,string processingSteps)";
+
+// FoodMetadata 的成员，顺序与 FOOD_METADATA_TYPE 一致
+pub const FOOD_METADATA_FIELDS: [&str; 6] = ["productId", "productName", "producerInfo", "productionDate", "origin", "processingSteps"];
+
+// 客户端签名时使用的域，随 GET /api/chain/config 返回
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Eip712Domain {
+    pub name: &'static str,
+    pub version: &'static str,
+    pub chain_id: u64,
+    pub verifying_contract: String,
+}
+
+impl Eip712Domain {
+    pub fn new(chain_id: u64, verifying_contract: &str) -> Self {
+        Eip712Domain {
+            name: DOMAIN_NAME,
+            version: DOMAIN_VERSION,
+            chain_id,
+            verifying_contract: verifying_contract.to_lowercase(),
+        }
+    }
+
+    pub fn separator(&self) -> Result<[u8; 32], CoreError> {
+        let mut data = Vec::with_capacity(32 * 5);
+        data.extend_from_slice(&keccak256(DOMAIN_TYPE.as_bytes()));
+        data.extend_from_slice(&keccak256(self.name.as_bytes()));
+        data.extend_from_slice(&keccak256(self.version.as_bytes()));
+        data.extend_from_slice(&encode_u256(self.chain_id));
+        data.extend_from_slice(&encode_address(&self.verifying_contract)?);
+        Ok(keccak256(&data))
+    }
+}
+
+fn encode_u256(value: u64) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+// address 左侧补零到 32 字节
+fn encode_address(address: &str) -> Result<[u8; 32], CoreError> {
+    let bytes: [u8; 20] = hex::decode(address.trim_start_matches("0x"))
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| CoreError::InvalidInput(format!("无效的地址: {}", address)))?;
+    let mut word = [0u8; 32];
+    word[12..].copy_from_slice(&bytes);
+    Ok(word)
+}
+
+// hashStruct(FoodRecord)：string 编码为 keccak256(utf8)，bytes32 原样编码
+pub fn food_record_hash(product_id: &str, metadata_hash: &str) -> Result<[u8; 32], CoreError> {
+    let mut data = Vec::with_capacity(32 * 3);
+    data.extend_from_slice(&keccak256(FOOD_RECORD_TYPE.as_bytes()));
+    data.extend_from_slice(&keccak256(product_id.as_bytes()));
+    data.extend_from_slice(&contract::parse_bytes32(metadata_hash)?);
+    Ok(keccak256(&data))
+}
+
+// hashStruct(FoodMetadata)：元数据只能包含 FOOD_METADATA_FIELDS 中的字符串字段，缺少的字段按空字符串编码
+// 不依赖 JSON 文本的键顺序和格式，任何语言按 EIP-712 规则都能得到相同的哈希
+pub fn food_metadata_hash(metadata: &JsonValue) -> Result<[u8; 32], CoreError> {
+    let object = metadata
+        .as_object()
+        .ok_or_else(|| CoreError::InvalidInput("元数据必须是 JSON 对象。".to_string()))?;
+    if let Some(key) = object.keys().find(|key| !FOOD_METADATA_FIELDS.contains(&key.as_str())) {
+        return Err(CoreError::InvalidInput(format!("EIP-712 元数据哈希不包含字段 '{}'。", key)));
+    }
+    let mut data = Vec::with_capacity(32 * (FOOD_METADATA_FIELDS.len() + 1));
+    data.extend_from_slice(&keccak256(FOOD_METADATA_TYPE.as_bytes()));
+    for field in FOOD_METADATA_FIELDS {
+        let value = match object.get(field) {
+            None => "",
+            Some(JsonValue::String(value)) => value.as_str(),
+            Some(_) => return Err(CoreError::InvalidInput(format!("元数据字段 '{}' 必须是字符串。", field))),
+        };
+        data.extend_from_slice(&keccak256(value.as_bytes()));
+    }
+    Ok(keccak256(&data))
+}
+
+// 待签名摘要：keccak256(0x19 0x01 || domainSeparator || hashStruct(message))
+pub fn signing_digest(domain: &Eip712Domain, struct_hash: &[u8; 32]) -> Result<[u8; 32], CoreError> {
+    let mut data = Vec::with_capacity(2 + 64);
+    data.extend_from_slice(&[0x19, 0x01]);
+    data.extend_from_slice(&domain.separator()?);
+    data.extend_from_slice(struct_hash);
+    Ok(keccak256(&data))
+}
+
+// 从 65 字节签名 (r || s || v，v 为 27/28 或 0/1) 恢复签名者地址 (小写，带 0x 前缀)
+pub fn recover_signer(digest: &[u8; 32], signature: &str) -> Result<String, CoreError> {
+    let invalid = || CoreError::InvalidInput(format!("无效的签名: {}", signature));
+    let bytes = hex::decode(signature.trim().trim_start_matches("0x")).map_err(|_| invalid())?;
+    if bytes.len() != 65 {
+        return Err(invalid());
+    }
+    let v = match bytes[64] {
+        27 | 28 => bytes[64] - 27,
+        0 | 1 => bytes[64],
+        _ => return Err(invalid()),
+    };
+    let mut signature_value = Signature::from_slice(&bytes[..64]).map_err(|_| invalid())?;
+    let mut recovery_id = RecoveryId::from_byte(v).ok_or_else(invalid)?;
+    // 高 s 值的签名与对应的低 s 签名等价，规范化后翻转 y 奇偶位
+    if let Some(normalized) = signature_value.normalize_s() {
+        signature_value = normalized;
+        recovery_id = RecoveryId::new(!recovery_id.is_y_odd(), recovery_id.is_x_reduced());
+    }
+    let key = VerifyingKey::recover_from_prehash(digest, &signature_value, recovery_id).map_err(|_| invalid())?;
+    let public_key = key.to_encoded_point(false);
+    Ok(to_hex_string(&keccak256(&public_key.as_bytes()[1..])[12..]))
+}
+
+// 恢复 FoodRecord 签名的签名者
+pub fn recover_food_record_signer(
+    domain: &Eip712Domain,
+    product_id: &str,
+    metadata_hash: &str,
+    signature: &str,
+) -> Result<String, CoreError> {
+    let digest = signing_digest(domain, &food_record_hash(product_id, metadata_hash)?)?;
+    recover_signer(&digest, signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::ecdsa::SigningKey;
+
+    // EIP-712 规范中的 Mail 示例 (eth_signTypedData_v4 的测试向量)
+    const MAIL_SEPARATOR: &str = "0xf2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f";
+    const MAIL_STRUCT_HASH: &str = "0xc52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e";
+    const MAIL_DIGEST: &str = "0xbe609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2";
+    const MAIL_SIGNER: &str = "0xcd2a3d9f938e13cd947ec05abc7fe734df8dd826"; // 私钥 keccak256("cow")
+    const MAIL_R: &str = "4355c47d63924e8a72e509b65029052eb6c299d53a04e167c5775fd466751c9d";
+    const MAIL_S: &str = "07299936d304c153f6443dfa05f40ff007d72911b6f72307f996231605b91562";
+    // secp256k1 的阶 n，n - s 为等价的高 s 签名
+    const CURVE_ORDER: &str = "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141";
+
+    fn mail_domain() -> Eip712Domain {
+        Eip712Domain {
+            name: "Ether Mail",
+            version: "1",
+            chain_id: 1,
+            verifying_contract: "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC".to_string(),
+        }
+    }
+
+    // hashStruct(Mail)，Mail(Person from,Person to,string contents)Person(string name,address wallet)
+    fn mail_struct_hash() -> [u8; 32] {
+        const PERSON_TYPE: &str = "Person(string name,address wallet)";
+        let person = |name: &str, wallet: &str| {
+            let mut data = Vec::new();
+            data.extend_from_slice(&keccak256(PERSON_TYPE.as_bytes()));
+            data.extend_from_slice(&keccak256(name.as_bytes()));
+            data.extend_from_slice(&encode_address(wallet).unwrap());
+            keccak256(&data)
+        };
+        let mut data = Vec::new();
+        data.extend_from_slice(&keccak256(format!("Mail(Person from,Person to,string contents){}", PERSON_TYPE).as_bytes()));
+        data.extend_from_slice(&person("Cow", "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"));
+        data.extend_from_slice(&person("Bob", "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB"));
+        data.extend_from_slice(&keccak256(b"Hello, Bob!"));
+        keccak256(&data)
+    }
+
+    // 256 位大端减法 a - b (a >= b)
+    fn sub_be(a: &[u8], b: &[u8]) -> Vec<u8> {
+        let mut result = vec![0u8; a.len()];
+        let mut borrow = 0i16;
+        for i in (0..a.len()).rev() {
+            let mut diff = a[i] as i16 - b[i] as i16 - borrow;
+            borrow = if diff < 0 { 1 } else { 0 };
+            if diff < 0 {
+                diff += 256;
+            }
+            result[i] = diff as u8;
+        }
+        result
+    }
+
+    #[test]
+    fn computes_mail_example_digest() {
+        let domain = mail_domain();
+        assert_eq!(to_hex_string(&domain.separator().unwrap()), MAIL_SEPARATOR);
+        let struct_hash = mail_struct_hash();
+        assert_eq!(to_hex_string(&struct_hash), MAIL_STRUCT_HASH);
+        assert_eq!(to_hex_string(&signing_digest(&domain, &struct_hash).unwrap()), MAIL_DIGEST);
+    }
+
+    #[test]
+    fn recovers_mail_example_signer() {
+        let digest = contract::parse_bytes32(MAIL_DIGEST).unwrap();
+        for v in ["1c", "01"] {
+            assert_eq!(recover_signer(&digest, &format!("0x{}{}{}", MAIL_R, MAIL_S, v)).unwrap(), MAIL_SIGNER);
+        }
+        // 高 s 签名翻转 v 后恢复出同一个地址
+        let high_s = hex::encode(sub_be(&hex::decode(CURVE_ORDER).unwrap(), &hex::decode(MAIL_S).unwrap()));
+        assert_eq!(recover_signer(&digest, &format!("0x{}{}1b", MAIL_R, high_s)).unwrap(), MAIL_SIGNER);
+        // v 取错时恢复出的是其他地址
+        assert_ne!(recover_signer(&digest, &format!("0x{}{}1b", MAIL_R, MAIL_S)).unwrap(), MAIL_SIGNER);
+        assert!(recover_signer(&digest, &format!("0x{}{}1d", MAIL_R, MAIL_S)).is_err());
+        assert!(recover_signer(&digest, &format!("0x{}{}", MAIL_R, MAIL_S)).is_err());
+    }
+
+    #[test]
+    fn food_record_signature_is_bound_to_domain() {
+        let signing_key = SigningKey::from_slice(&keccak256(b"cow")).unwrap();
+        let domain = Eip712Domain::new(31337, "0x5FbDB2315678afecb367f032d93F642f64180aa3");
+        let metadata_hash = to_hex_string(&keccak256(b"metadata"));
+        let digest = signing_digest(&domain, &food_record_hash("apple-001", &metadata_hash).unwrap()).unwrap();
+        let (signature, recovery_id) = signing_key.sign_prehash_recoverable(&digest).unwrap();
+        let signature = format!("0x{}{:02x}", hex::encode(signature.to_bytes()), 27 + recovery_id.to_byte());
+
+        assert_eq!(recover_food_record_signer(&domain, "apple-001", &metadata_hash, &signature).unwrap(), MAIL_SIGNER);
+        let other_chain = Eip712Domain::new(1, "0x5FbDB2315678afecb367f032d93F642f64180aa3");
+        assert_ne!(recover_food_record_signer(&other_chain, "apple-001", &metadata_hash, &signature).unwrap(), MAIL_SIGNER);
+        assert_ne!(recover_food_record_signer(&domain, "apple-002", &metadata_hash, &signature).unwrap(), MAIL_SIGNER);
+    }
+}
@@ -0,0 +1,27 @@
+use std::fmt;
+
+// 证明核心库的错误，后端通过 From 转换为同名的 AppError 变体 (见 backend_rust/src/errors.rs)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CoreError {
+    InvalidInput(String),    // 例如哈希、签名或 RLP 数据格式错误
+    InternalError(String),   // 通用内部错误
+    BlockchainError(String), // 合约返回数据或事件日志无法解析
+}
+
+impl fmt::Display for CoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CoreError::InvalidInput(msg) => write!(f, "Invalid Input: {}", msg),
+            CoreError::InternalError(msg) => write!(f, "Internal Server Error: {}", msg),
+            CoreError::BlockchainError(msg) => write!(f, "Blockchain error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for CoreError {}
+
+impl From<serde_json::Error> for CoreError {
+    fn from(err: serde_json::Error) -> Self {
+        CoreError::InvalidInput(format!("JSON 解析或序列化错误: {}", err))
+    }
+}
@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+use crate::canonical_json;
+use crate::error::CoreError;
+
+// 计算任意字节的 Keccak256 哈希 (与 ethers.keccak256 相同的算法)
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+// 将 32 字节哈希格式化为带 0x 前缀的小写十六进制字符串
+pub fn to_hex_string(hash: &[u8]) -> String {
+    format!("0x{}", hex::encode(hash))
+}
+
+// 规范化客户端提交的哈希字符串：去除空白、统一小写、补全 0x 前缀，便于比较
+pub fn normalize_hash(hash: &str) -> String {
+    let trimmed = hash.trim().to_lowercase();
+    if trimmed.starts_with("0x") {
+        trimmed
+    } else {
+        format!("0x{}", trimmed)
+    }
+}
+
+// 元数据哈希方案，随记录保存在 traceability_data.hash_scheme，验证时按记录自身的方案重新计算
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HashScheme {
+    #[serde(rename = "legacy-json-stringify-keccak256")]
+    LegacyJsonStringifyKeccak256, // keccak256(JSON.stringify(metadata))，引入哈希方案之前由前端计算的记录，只用于复核
+    #[serde(rename = "jcs-keccak256")]
+    JcsKeccak256, // keccak256(JCS 规范化 JSON 文本)
+    #[default]
+    #[serde(rename = "eip712-food-metadata-v1")]
+    Eip712FoodMetadataV1, // EIP-712 hashStruct(FoodMetadata)，见 eip712.rs
+    #[serde(rename = "salted-fields-v1")]
+    SaltedFieldsV1, // 每个字段加盐承诺后的根，支持选择性披露，见 disclosure.rs
+}
+
+impl HashScheme {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HashScheme::LegacyJsonStringifyKeccak256 => "legacy-json-stringify-keccak256",
+            HashScheme::JcsKeccak256 => "jcs-keccak256",
+            HashScheme::Eip712FoodMetadataV1 => "eip712-food-metadata-v1",
+            HashScheme::SaltedFieldsV1 => "salted-fields-v1",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, CoreError> {
+        match value {
+            "legacy-json-stringify-keccak256" => Ok(HashScheme::LegacyJsonStringifyKeccak256),
+            "jcs-keccak256" => Ok(HashScheme::JcsKeccak256),
+            "eip712-food-metadata-v1" => Ok(HashScheme::Eip712FoodMetadataV1),
+            "salted-fields-v1" => Ok(HashScheme::SaltedFieldsV1),
+            _ => Err(CoreError::InternalError(format!("未知的元数据哈希方案 '{}'。", value))),
+        }
+    }
+}
+
+// 早期前端表单 (AddFoodPage) 构造元数据的键顺序，JSON.stringify 按此顺序输出
+pub const LEGACY_FIELD_ORDER: [&str; 6] = ["productId", "productName", "producerInfo", "productionDate", "origin", "processingSteps"];
+
+// legacy-json-stringify-keccak256：按早期前端的键顺序重建 JSON.stringify(metadata) 文本后计算 keccak256
+// MySQL JSON 列不保留键顺序，因此不能直接对存储的文本做哈希
+pub fn legacy_stringify_hash(metadata: &serde_json::Value) -> Result<String, CoreError> {
+    let text = canonical_json::stringify_in_order(metadata, &LEGACY_FIELD_ORDER)?;
+    Ok(to_hex_string(&keccak256(text.as_bytes())))
+}
+
+// 对已规范化的 JSON 文本计算哈希
+pub fn hash_canonical_json(canonical_json: &str) -> String {
+    to_hex_string(&keccak256(canonical_json.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn legacy_hash_follows_frontend_field_order() {
+        // MySQL JSON 列返回的键按长度排序，与前端提交时的顺序不同
+        let stored = json!({
+            "origin": "山东寿光",
+            "productId": "P-2024-001",
+            "productName": "有机菠菜",
+            "producerInfo": "绿源农场",
+            "productionDate": "2024-03-01T08:00:00.000Z",
+            "processingSteps": "采收\n清洗",
+        });
+        // keccak256('{"productId":"P-2024-001","productName":"有机菠菜",...,"processingSteps":"采收\n清洗"}')
+        assert_eq!(
+            legacy_stringify_hash(&stored).unwrap(),
+            "0x920429cb1e595fbc5cdce544048eb373c80826b2c24a20bcde43078e339b92b5"
+        );
+        assert_ne!(legacy_stringify_hash(&stored).unwrap(), hash_canonical_json(&canonical_json::canonicalize(&stored).unwrap()));
+    }
+}
@@ -0,0 +1,13 @@
+// 证明核心库：元数据哈希、RLP、Merkle Patricia Trie、Merkle 批次树、合约 ABI 与离线证明包校验
+// 不依赖数据库与 HTTP 框架，后端 (backend_rust) 与离线验证程序 (bin/verify_proof.rs) 共用
+pub mod error;
+pub mod hashing;
+pub mod canonical_json;
+pub mod rpc_types;
+pub mod contract;
+pub mod eip712;
+pub mod merkle;
+pub mod rlp;
+pub mod trie;
+pub mod disclosure;
+pub mod proof;
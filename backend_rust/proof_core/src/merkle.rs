@@ -0,0 +1,183 @@
+// Merkle 树批量上链
+// 一个批次内的记录哈希组成 Merkle 树，只把根通过 addRecord("merkle-batch:<批次ID>", root) 上链，
+// 每条记录保存从叶子到根的路径，任何人都可以用路径验证记录包含在已上链的根中
+// 叶子与内部节点使用不同前缀 (RFC 6962)，防止把内部节点伪装成叶子
+use serde::{Deserialize, Serialize};
+use crate::contract;
+use crate::error::CoreError;
+use crate::hashing::{keccak256, to_hex_string};
+
+pub const MERKLE_ANCHOR_KEY_PREFIX: &str = "merkle-batch:";
+pub const LEAF_ENCODING: &str = "keccak256(0x00 || keccak256(utf8(productId)) || metadataHash)";
+pub const NODE_ENCODING: &str = "keccak256(0x01 || left || right)";
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+// 路径中的一步：兄弟节点的哈希及其位于当前节点的哪一侧
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ProofStep {
+    pub position: SiblingPosition,
+    pub hash: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SiblingPosition {
+    Left,
+    Right,
+}
+
+// 批次根上链时使用的 productId
+pub fn anchor_key(batch_id: u64) -> String {
+    format!("{}{}", MERKLE_ANCHOR_KEY_PREFIX, batch_id)
+}
+
+pub fn leaf_hash(product_id: &str, metadata_hash: &str) -> Result<[u8; 32], CoreError> {
+    let mut data = Vec::with_capacity(1 + 64);
+    data.push(LEAF_PREFIX);
+    data.extend_from_slice(&keccak256(product_id.as_bytes()));
+    data.extend_from_slice(&contract::parse_bytes32(metadata_hash)?);
+    Ok(keccak256(&data))
+}
+
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut data = [0u8; 65];
+    data[0] = NODE_PREFIX;
+    data[1..33].copy_from_slice(left);
+    data[33..].copy_from_slice(right);
+    keccak256(&data)
+}
+
+// 逐层两两合并构建 Merkle 树，返回根以及每个叶子 (按输入顺序) 的路径
+// 某层节点数为奇数时，最后一个节点直接提升到上一层，不与自身合并
+pub fn build_tree(leaves: &[[u8; 32]]) -> Result<([u8; 32], Vec<Vec<ProofStep>>), CoreError> {
+    if leaves.is_empty() {
+        return Err(CoreError::InternalError("不能为空批次构建 Merkle 树。".to_string()));
+    }
+    let mut proofs: Vec<Vec<ProofStep>> = vec![Vec::new(); leaves.len()];
+    // 当前层每个节点覆盖的叶子下标
+    let mut level: Vec<([u8; 32], Vec<usize>)> = leaves.iter().enumerate().map(|(i, leaf)| (*leaf, vec![i])).collect();
+
+    while level.len() > 1 {
+        let mut next_level = Vec::with_capacity(level.len().div_ceil(2));
+        let mut nodes = level.into_iter();
+        while let Some((left, left_leaves)) = nodes.next() {
+            let Some((right, right_leaves)) = nodes.next() else {
+                next_level.push((left, left_leaves));
+                break;
+            };
+            for &i in &left_leaves {
+                proofs[i].push(ProofStep { position: SiblingPosition::Right, hash: to_hex_string(&right) });
+            }
+            for &i in &right_leaves {
+                proofs[i].push(ProofStep { position: SiblingPosition::Left, hash: to_hex_string(&left) });
+            }
+            let mut covered = left_leaves;
+            covered.extend(right_leaves);
+            next_level.push((node_hash(&left, &right), covered));
+        }
+        level = next_level;
+    }
+    Ok((level[0].0, proofs))
+}
+
+// 沿路径从叶子计算到根，与给定的根比较
+pub fn verify_proof(leaf: &[u8; 32], proof: &[ProofStep], root: &str) -> Result<bool, CoreError> {
+    let mut current = *leaf;
+    for step in proof {
+        let sibling = contract::parse_bytes32(&step.hash)?;
+        current = match step.position {
+            SiblingPosition::Left => node_hash(&sibling, &current),
+            SiblingPosition::Right => node_hash(&current, &sibling),
+        };
+    }
+    Ok(current == contract::parse_bytes32(root)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 期望值由独立实现 (Python keccak256) 按 LEAF_ENCODING / NODE_ENCODING 计算
+    const LEAVES: [(&str, &str, &str); 3] = [
+        (
+            "apple-001",
+            "0xeace293c5a6d650e549036d8da71eeb1375fb480ec5c49d173624621d15cf349",
+            "0x1db468cfc0bd72d5123a9513743c6d4121c60724aea629ceb0dcb6d9e9c0c513",
+        ),
+        (
+            "apple-002",
+            "0xf8170405ca385fbf27bf00802d281882091f3f984ee9d0439414b414d04a5ed0",
+            "0x0bb0d9558becc78ae78352eb7c51fa14a292fbc049ae461405df163584cccb8c",
+        ),
+        (
+            "apple-003",
+            "0xcce41642ec1404e5c6724e5aaddf0bf0c98060c95884a04537062f53451e817f",
+            "0x2e3dd135471db8a2d0b32a40c9e20a83d5efba30255b0bb170eb7e7cefdefd50",
+        ),
+    ];
+    const NODE_01: &str = "0x6faa986078d85073c5e8ef708f7e5861f74e93163df41f2445d4ec860203e232";
+    const ROOT: &str = "0x1a19cfcb6d4ad419e5e647341acf1bb7df5b8f0ff1e56a553cf3598746332519";
+
+    fn leaves() -> Vec<[u8; 32]> {
+        LEAVES.iter().map(|(product_id, metadata_hash, _)| leaf_hash(product_id, metadata_hash).unwrap()).collect()
+    }
+
+    fn step(position: SiblingPosition, hash: &str) -> ProofStep {
+        ProofStep { position, hash: hash.to_string() }
+    }
+
+    #[test]
+    fn hashes_leaves_with_domain_prefix() {
+        for (leaf, (_, _, expected)) in leaves().iter().zip(LEAVES) {
+            assert_eq!(to_hex_string(leaf), expected);
+        }
+    }
+
+    // 3 个叶子：root = node(node(l0, l1), l2)，第 3 个叶子直接提升，不与自身合并
+    #[test]
+    fn builds_tree_with_odd_leaf_promoted() {
+        let (root, proofs) = build_tree(&leaves()).unwrap();
+        assert_eq!(to_hex_string(&root), ROOT);
+        assert_eq!(proofs[0], vec![step(SiblingPosition::Right, LEAVES[1].2), step(SiblingPosition::Right, LEAVES[2].2)]);
+        assert_eq!(proofs[1], vec![step(SiblingPosition::Left, LEAVES[0].2), step(SiblingPosition::Right, LEAVES[2].2)]);
+        assert_eq!(proofs[2], vec![step(SiblingPosition::Left, NODE_01)]);
+    }
+
+    #[test]
+    fn single_leaf_is_its_own_root() {
+        let leaf = leaves()[0];
+        let (root, proofs) = build_tree(&[leaf]).unwrap();
+        assert_eq!(root, leaf);
+        assert_eq!(proofs, vec![Vec::<ProofStep>::new()]);
+        assert!(build_tree(&[]).is_err());
+    }
+
+    #[test]
+    fn every_proof_verifies_and_tampering_fails() {
+        for count in 1..=9u8 {
+            let leaves: Vec<[u8; 32]> = (0..count).map(|i| keccak256(&[i])).collect();
+            let (root, proofs) = build_tree(&leaves).unwrap();
+            let root = to_hex_string(&root);
+            for (i, proof) in proofs.iter().enumerate() {
+                assert!(verify_proof(&leaves[i], proof, &root).unwrap(), "{} 个叶子中的第 {} 个", count, i);
+                assert!(!verify_proof(&keccak256(b"other"), proof, &root).unwrap());
+                if let Some(first) = proof.first() {
+                    let mut flipped = proof.clone();
+                    flipped[0].position = match first.position {
+                        SiblingPosition::Left => SiblingPosition::Right,
+                        SiblingPosition::Right => SiblingPosition::Left,
+                    };
+                    assert!(!verify_proof(&leaves[i], &flipped, &root).unwrap());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn anchor_key_uses_reserved_prefix() {
+        assert_eq!(anchor_key(42), "merkle-batch:42");
+        assert!(anchor_key(42).starts_with(MERKLE_ANCHOR_KEY_PREFIX));
+    }
+}
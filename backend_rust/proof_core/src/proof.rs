@@ -0,0 +1,474 @@
+// 离线证明包
+// GET /api/food-records/{product_id}/proof 导出一个自包含的 JSON：规范化元数据、哈希方案、(Merkle 路径)、
+// 上链交易的回执与解码后的 RecordAdded 事件、区块头字段，以及回执在区块 receiptsRoot 中的 Merkle Patricia 证明。
+// verify_proof_bundle 不访问数据库和节点即可逐项复核；唯一需要从外部确认的是区块哈希确实属于目标链 (区块浏览器或自己的节点)
+// 证明包由后端的 proof::build_proof_bundle 生成
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::BTreeMap;
+use crate::canonical_json;
+use crate::contract;
+use crate::disclosure;
+use crate::eip712;
+use crate::error::CoreError;
+use crate::hashing::{hash_canonical_json, keccak256, legacy_stringify_hash, normalize_hash, to_hex_string, HashScheme};
+use crate::merkle::{self, ProofStep};
+use crate::rlp;
+use crate::rpc_types::{decode_hex_bytes, parse_quantity, RpcLog};
+use crate::trie;
+
+pub const PROOF_BUNDLE_VERSION: &str = "food-traceability-proof-v1";
+
+// 区块头 RLP 的字段顺序；Quantity 为整数 (去掉前导零)，Data 为定长字节
+// baseFeePerGas 之后的字段随分叉 (伦敦、上海、坎昆、布拉格) 依次加入，区块中存在时才参与编码
+#[derive(Debug, Clone, Copy)]
+pub enum HeaderField {
+    Quantity(&'static str),
+    Data(&'static str),
+}
+
+pub const HEADER_FIELDS: [HeaderField; 21] = [
+    HeaderField::Data("parentHash"),
+    HeaderField::Data("sha3Uncles"),
+    HeaderField::Data("miner"),
+    HeaderField::Data("stateRoot"),
+    HeaderField::Data("transactionsRoot"),
+    HeaderField::Data("receiptsRoot"),
+    HeaderField::Data("logsBloom"),
+    HeaderField::Quantity("difficulty"),
+    HeaderField::Quantity("number"),
+    HeaderField::Quantity("gasLimit"),
+    HeaderField::Quantity("gasUsed"),
+    HeaderField::Quantity("timestamp"),
+    HeaderField::Data("extraData"),
+    HeaderField::Data("mixHash"),
+    HeaderField::Data("nonce"),
+    HeaderField::Quantity("baseFeePerGas"),
+    HeaderField::Data("withdrawalsRoot"),
+    HeaderField::Quantity("blobGasUsed"),
+    HeaderField::Quantity("excessBlobGas"),
+    HeaderField::Data("parentBeaconBlockRoot"),
+    HeaderField::Data("requestsHash"),
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ProofBundle {
+    pub version: String,
+    pub product_id: String,
+    pub metadata: BundleMetadata,
+    pub merkle: Option<BundleMerkle>, // 仅 Merkle 批量上链的记录
+    pub anchor: BundleAnchor,
+    pub receipt: JsonValue,      // 节点返回的原始交易回执
+    pub event: BundleEvent,      // 回执中解码后的 RecordAdded 事件
+    pub block_header: JsonValue, // 区块头字段 (节点返回的原始值) 及区块哈希
+    pub receipt_proof: BundleReceiptProof,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleMetadata {
+    pub canonical: String, // JCS 规范化 JSON 文本 (salted-fields-v1 只有公开字段)
+    pub hash_scheme: HashScheme,
+    pub metadata_hash: String,
+    // salted-fields-v1 的全部字段承诺，证明包只证明承诺根已上链，非公开字段的明文通过选择性披露获得
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field_commitments: Option<BTreeMap<String, String>>,
+    // salted-fields-v1 公开字段 (canonical 中的字段) 的盐值，用于由明文重新计算这些字段的承诺
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_salts: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleMerkle {
+    pub batch_id: u64,
+    pub leaf_hash: String,
+    pub leaf_index: u32,
+    pub proof: Vec<ProofStep>,
+    pub root: String,
+}
+
+// 上链交易：addRecord(anchorKey, anchoredHash)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleAnchor {
+    pub chain_id: u64,
+    pub contract_address: String,
+    pub anchor_key: String,    // 普通记录为产品ID，Merkle 记录为 merkle-batch:<批次ID>
+    pub anchored_hash: String, // 普通记录为元数据哈希，Merkle 记录为批次根
+    pub transaction_hash: String,
+    pub transaction_index: u64,
+    pub block_number: u64,
+    pub block_hash: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleEvent {
+    pub log_index: u64, // 在回执 logs 中的位置
+    pub product_id_hash: String,
+    pub metadata_hash: String,
+    pub recorder: String,
+    pub timestamp: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleReceiptProof {
+    pub receipts_root: String,
+    pub key: String,        // rlp(transactionIndex)
+    pub nodes: Vec<String>, // 从根到叶子的节点 RLP 编码
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ProofCheck {
+    pub name: &'static str,
+    pub passed: bool,
+    pub detail: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ProofVerification {
+    pub valid: bool,
+    pub block_hash: String, // 需要与可信来源核对的区块哈希
+    pub checks: Vec<ProofCheck>,
+}
+
+pub fn field<'a>(object: &'a JsonValue, name: &str) -> Result<&'a str, CoreError> {
+    object
+        .get(name)
+        .and_then(JsonValue::as_str)
+        .ok_or_else(|| CoreError::InvalidInput(format!("缺少字段 {}。", name)))
+}
+
+// 0x 十六进制整数 (可能超过 u64) 转为无前导零的大端字节
+fn quantity_bytes(value: &str) -> Result<Vec<u8>, CoreError> {
+    let digits = value.trim_start_matches("0x");
+    let padded = if digits.len() % 2 == 1 { format!("0{}", digits) } else { digits.to_string() };
+    let bytes = decode_hex_bytes(&padded)?;
+    Ok(rlp::trim_leading_zeros(&bytes).to_vec())
+}
+
+// 区块哈希 = keccak256(rlp(区块头字段))
+pub fn block_header_hash(header: &JsonValue) -> Result<[u8; 32], CoreError> {
+    let mut fields = Vec::with_capacity(HEADER_FIELDS.len());
+    for header_field in HEADER_FIELDS {
+        let (name, is_quantity) = match header_field {
+            HeaderField::Quantity(name) => (name, true),
+            HeaderField::Data(name) => (name, false),
+        };
+        let Some(value) = header.get(name).and_then(JsonValue::as_str) else {
+            continue;
+        };
+        let bytes = if is_quantity { quantity_bytes(value)? } else { decode_hex_bytes(value)? };
+        fields.push(rlp::encode_bytes(&bytes));
+    }
+    Ok(keccak256(&rlp::encode_list(&fields)))
+}
+
+// 回执在 receiptsRoot 中的值：rlp([status, cumulativeGasUsed, logsBloom, logs])，类型化交易前面加类型字节
+pub fn encode_receipt(receipt: &JsonValue) -> Result<Vec<u8>, CoreError> {
+    let tx_type = match receipt.get("type").and_then(JsonValue::as_str) {
+        Some(value) => parse_quantity(value)?,
+        None => 0,
+    };
+    // 拜占庭分叉之前的回执保存交易后的状态根而不是 status
+    let status = match receipt.get("status").and_then(JsonValue::as_str) {
+        Some(status) => quantity_bytes(status)?,
+        None => decode_hex_bytes(field(receipt, "root")?)?,
+    };
+    let logs: Vec<RpcLog> = serde_json::from_value(receipt.get("logs").cloned().unwrap_or_default())
+        .map_err(|e| CoreError::InvalidInput(format!("回执的 logs 格式错误: {}", e)))?;
+    let mut encoded_logs = Vec::with_capacity(logs.len());
+    for log in &logs {
+        let topics = log
+            .topics
+            .iter()
+            .map(|topic| decode_hex_bytes(topic).map(|bytes| rlp::encode_bytes(&bytes)))
+            .collect::<Result<Vec<_>, _>>()?;
+        encoded_logs.push(rlp::encode_list(&[
+            rlp::encode_bytes(&decode_hex_bytes(&log.address)?),
+            rlp::encode_list(&topics),
+            rlp::encode_bytes(&decode_hex_bytes(&log.data)?),
+        ]));
+    }
+    let payload = rlp::encode_list(&[
+        rlp::encode_bytes(&status),
+        rlp::encode_bytes(&quantity_bytes(field(receipt, "cumulativeGasUsed")?)?),
+        rlp::encode_bytes(&decode_hex_bytes(field(receipt, "logsBloom")?)?),
+        rlp::encode_list(&encoded_logs),
+    ]);
+    if tx_type == 0 {
+        return Ok(payload);
+    }
+    let mut typed = vec![tx_type as u8];
+    typed.extend_from_slice(&payload);
+    Ok(typed)
+}
+
+// 在回执中找到 anchor 对应的 RecordAdded 事件：合约地址、productId topic 与哈希均一致
+pub fn find_record_added(receipt: &JsonValue, anchor: &BundleAnchor) -> Result<Option<BundleEvent>, CoreError> {
+    let logs: Vec<RpcLog> = serde_json::from_value(receipt.get("logs").cloned().unwrap_or_default())
+        .map_err(|e| CoreError::InvalidInput(format!("回执的 logs 格式错误: {}", e)))?;
+    let product_id_topic = contract::product_id_topic(&anchor.anchor_key);
+    for (index, log) in logs.iter().enumerate() {
+        if !log.address.eq_ignore_ascii_case(&anchor.contract_address) {
+            continue;
+        }
+        if let Some(event) = contract::decode_record_added(log)? {
+            if event.product_id_hash == product_id_topic && event.metadata_hash == normalize_hash(&anchor.anchored_hash) {
+                return Ok(Some(BundleEvent {
+                    log_index: index as u64,
+                    product_id_hash: event.product_id_hash,
+                    metadata_hash: event.metadata_hash,
+                    recorder: event.recorder,
+                    timestamp: event.timestamp,
+                }));
+            }
+        }
+    }
+    Ok(None)
+}
+
+// ------------------------------ 离线验证 ------------------------------
+
+// 单项检查：Ok 为通过说明，Err 为失败原因
+type CheckResult = Result<String, String>;
+type CheckFn = fn(&ProofBundle) -> CheckResult;
+
+fn fail(e: CoreError) -> String {
+    e.to_string()
+}
+
+fn check_metadata(bundle: &ProofBundle) -> CheckResult {
+    let metadata: JsonValue = serde_json::from_str(&bundle.metadata.canonical)
+        .map_err(|e| format!("规范化元数据不是有效的 JSON: {}", e))?;
+    let computed = match bundle.metadata.hash_scheme {
+        HashScheme::LegacyJsonStringifyKeccak256 => legacy_stringify_hash(&metadata).map_err(fail)?,
+        HashScheme::JcsKeccak256 => hash_canonical_json(&bundle.metadata.canonical),
+        HashScheme::Eip712FoodMetadataV1 => to_hex_string(&eip712::food_metadata_hash(&metadata).map_err(fail)?),
+        HashScheme::SaltedFieldsV1 => return check_salted_metadata(bundle, &metadata),
+    };
+    if computed != normalize_hash(&bundle.metadata.metadata_hash) {
+        return Err(format!("按 {} 重新计算的哈希为 {}，证明包中为 {}。", bundle.metadata.hash_scheme.as_str(), computed, bundle.metadata.metadata_hash));
+    }
+    Ok(format!("按 {} 重新计算的元数据哈希为 {}。", bundle.metadata.hash_scheme.as_str(), computed))
+}
+
+// salted-fields-v1：全部承诺的根等于元数据哈希，canonical 中每个公开字段的明文与盐值都能算出列表中的承诺
+fn check_salted_metadata(bundle: &ProofBundle, metadata: &JsonValue) -> CheckResult {
+    let commitments = bundle.metadata.field_commitments.as_ref().ok_or_else(|| "缺少字段承诺 fieldCommitments。".to_string())?;
+    let root = disclosure::commitment_root(commitments).map_err(fail)?;
+    if root != normalize_hash(&bundle.metadata.metadata_hash) {
+        return Err(format!("{} 个字段承诺的根为 {}，证明包中为 {}。", commitments.len(), root, bundle.metadata.metadata_hash));
+    }
+    let public_fields = metadata.as_object().ok_or_else(|| "规范化元数据不是 JSON 对象。".to_string())?;
+    let empty = BTreeMap::new();
+    let salts = bundle.metadata.public_salts.as_ref().unwrap_or(&empty);
+    if let Some(field) = salts.keys().find(|field| !public_fields.contains_key(field.as_str())) {
+        return Err(format!("publicSalts 中的字段 '{}' 不在规范化元数据中。", field));
+    }
+    for (field, value) in public_fields {
+        let salt = salts.get(field).ok_or_else(|| format!("公开字段 '{}' 缺少盐值 (publicSalts)。", field))?;
+        let expected = commitments.get(field).ok_or_else(|| format!("公开字段 '{}' 不在字段承诺中。", field))?;
+        let value_canonical = canonical_json::canonicalize(value).map_err(fail)?;
+        let commitment = to_hex_string(&disclosure::field_commitment(field, &value_canonical, salt).map_err(fail)?);
+        if commitment != normalize_hash(expected) {
+            return Err(format!("公开字段 '{}' 的明文与盐值计算出的承诺为 {}，字段承诺中为 {}。", field, commitment, expected));
+        }
+    }
+    Ok(format!(
+        "{} 个字段承诺的根为 {}，{} 个公开字段的明文与承诺一致 (其余字段需通过选择性披露核对)。",
+        commitments.len(), root, public_fields.len()
+    ))
+}
+
+// 锚定的 (productId, 哈希)：普通记录为记录本身，Merkle 记录需要叶子经路径计算出批次根
+fn check_anchor_target(bundle: &ProofBundle) -> CheckResult {
+    let metadata_hash = normalize_hash(&bundle.metadata.metadata_hash);
+    let anchored_hash = normalize_hash(&bundle.anchor.anchored_hash);
+    let Some(merkle_section) = &bundle.merkle else {
+        if bundle.anchor.anchor_key != bundle.product_id || anchored_hash != metadata_hash {
+            return Err("上链的 productId 与哈希不是该记录本身。".to_string());
+        }
+        return Ok("记录直接上链，上链的 productId 与哈希即为记录本身。".to_string());
+    };
+    if bundle.anchor.anchor_key != merkle::anchor_key(merkle_section.batch_id) {
+        return Err(format!("上链的 productId {} 不是批次 #{} 的锚定键。", bundle.anchor.anchor_key, merkle_section.batch_id));
+    }
+    let leaf = merkle::leaf_hash(&bundle.product_id, &metadata_hash).map_err(fail)?;
+    if to_hex_string(&leaf) != normalize_hash(&merkle_section.leaf_hash) {
+        return Err(format!("叶子哈希应为 {}，证明包中为 {}。", to_hex_string(&leaf), merkle_section.leaf_hash));
+    }
+    if !merkle::verify_proof(&leaf, &merkle_section.proof, &anchored_hash).map_err(fail)? {
+        return Err(format!("叶子沿路径计算不出上链的批次根 {}。", anchored_hash));
+    }
+    Ok(format!("叶子 #{} 经 {} 步路径计算出批次 #{} 的根 {}。", merkle_section.leaf_index, merkle_section.proof.len(), merkle_section.batch_id, anchored_hash))
+}
+
+fn check_receipt(bundle: &ProofBundle) -> CheckResult {
+    let receipt = &bundle.receipt;
+    let anchor = &bundle.anchor;
+    if receipt.get("status").and_then(JsonValue::as_str) != Some("0x1") {
+        return Err("交易执行失败 (status 不是 0x1)。".to_string());
+    }
+    let expect = |name: &str, expected: &str| -> Result<(), String> {
+        let actual = field(receipt, name).map_err(fail)?;
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(format!("回执的 {} 为 {}，期望 {}。", name, actual, expected));
+        }
+        Ok(())
+    };
+    expect("transactionHash", &anchor.transaction_hash)?;
+    expect("blockHash", &anchor.block_hash)?;
+    expect("to", &anchor.contract_address)?;
+    let number = |name: &str| field(receipt, name).and_then(parse_quantity).map_err(fail);
+    if number("blockNumber")? != anchor.block_number || number("transactionIndex")? != anchor.transaction_index {
+        return Err("回执的区块号或交易序号与证明包不一致。".to_string());
+    }
+    Ok(format!("交易 {} 调用合约 {} 成功。", anchor.transaction_hash, anchor.contract_address))
+}
+
+fn check_event(bundle: &ProofBundle) -> CheckResult {
+    let event = find_record_added(&bundle.receipt, &bundle.anchor)
+        .map_err(fail)?
+        .ok_or_else(|| "回执中没有合约发出的、productId 与哈希均一致的 RecordAdded 事件。".to_string())?;
+    let claimed = &bundle.event;
+    if event.log_index != claimed.log_index
+        || event.recorder != claimed.recorder.to_lowercase()
+        || event.timestamp != claimed.timestamp
+        || event.metadata_hash != normalize_hash(&claimed.metadata_hash)
+    {
+        return Err("证明包中的事件与回执日志解码结果不一致。".to_string());
+    }
+    Ok(format!("RecordAdded(keccak256(\"{}\"), {}) 由 {} 发出。", bundle.anchor.anchor_key, event.metadata_hash, event.recorder))
+}
+
+fn check_block_header(bundle: &ProofBundle) -> CheckResult {
+    let header = &bundle.block_header;
+    let computed = to_hex_string(&block_header_hash(header).map_err(fail)?);
+    let claimed = field(header, "hash").map_err(fail)?.to_lowercase();
+    if computed != claimed || claimed != bundle.anchor.block_hash.to_lowercase() {
+        return Err(format!("区块头字段的哈希为 {}，证明包中的区块哈希为 {}。", computed, bundle.anchor.block_hash));
+    }
+    if field(header, "number").and_then(parse_quantity).map_err(fail)? != bundle.anchor.block_number {
+        return Err("区块头的区块号与证明包不一致。".to_string());
+    }
+    Ok(format!("区块头字段的 keccak256(rlp) 为区块 {} 的哈希 {}。", bundle.anchor.block_number, computed))
+}
+
+fn check_receipt_inclusion(bundle: &ProofBundle) -> CheckResult {
+    let proof = &bundle.receipt_proof;
+    let receipts_root = field(&bundle.block_header, "receiptsRoot").map_err(fail)?;
+    let root = contract::parse_bytes32(receipts_root).map_err(fail)?;
+    let key = decode_hex_bytes(&proof.key).map_err(fail)?;
+    if key != rlp::encode_uint(bundle.anchor.transaction_index) {
+        return Err("回执证明的键不是 rlp(transactionIndex)。".to_string());
+    }
+    let nodes = proof.nodes.iter().map(|node| decode_hex_bytes(node)).collect::<Result<Vec<_>, _>>().map_err(fail)?;
+    let value = trie::verify_proof(&root, &key, &nodes)
+        .map_err(fail)?
+        .ok_or_else(|| "回执证明表明该交易序号不在 receiptsRoot 中。".to_string())?;
+    if value != encode_receipt(&bundle.receipt).map_err(fail)? {
+        return Err("receiptsRoot 中该序号的回执与证明包中的回执不一致。".to_string());
+    }
+    Ok(format!("回执经 {} 个节点包含在区块头的 receiptsRoot {} 中。", nodes.len(), receipts_root))
+}
+
+// 逐项离线验证证明包，任一项失败则整体无效
+pub fn verify_proof_bundle(bundle: &ProofBundle) -> ProofVerification {
+    let checks: [(&'static str, CheckFn); 6] = [
+        ("metadata_hash", check_metadata),
+        ("anchor_target", check_anchor_target),
+        ("receipt", check_receipt),
+        ("record_added_event", check_event),
+        ("block_header", check_block_header),
+        ("receipt_inclusion", check_receipt_inclusion),
+    ];
+    let mut checks: Vec<ProofCheck> = checks
+        .into_iter()
+        .map(|(name, check)| match check(bundle) {
+            Ok(detail) => ProofCheck { name, passed: true, detail },
+            Err(detail) => ProofCheck { name, passed: false, detail },
+        })
+        .collect();
+    if bundle.version != PROOF_BUNDLE_VERSION {
+        checks.insert(0, ProofCheck {
+            name: "version",
+            passed: false,
+            detail: format!("不支持的证明包版本 {}。", bundle.version),
+        });
+    }
+    ProofVerification {
+        valid: checks.iter().all(|check| check.passed),
+        block_hash: bundle.anchor.block_hash.to_lowercase(),
+        checks,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SALT_A: &str = "0x0101010101010101010101010101010101010101010101010101010101010101";
+    const SALT_B: &str = "0x0202020202020202020202020202020202020202020202020202020202020202";
+
+    // productName 公开，producerInfo 不公开的 salted-fields-v1 证明包 (只填写元数据部分)
+    fn salted_bundle() -> ProofBundle {
+        let commitment = |field: &str, value: &str, salt: &str| {
+            to_hex_string(&disclosure::field_commitment(field, value, salt).unwrap())
+        };
+        let field_commitments = BTreeMap::from([
+            ("producerInfo".to_string(), commitment("producerInfo", r#""Farm A""#, SALT_B)),
+            ("productName".to_string(), commitment("productName", r#""Apple""#, SALT_A)),
+        ]);
+        let anchor = BundleAnchor {
+            chain_id: 31337,
+            contract_address: String::new(),
+            anchor_key: String::new(),
+            anchored_hash: String::new(),
+            transaction_hash: String::new(),
+            transaction_index: 0,
+            block_number: 0,
+            block_hash: String::new(),
+        };
+        ProofBundle {
+            version: PROOF_BUNDLE_VERSION.to_string(),
+            product_id: "P-1".to_string(),
+            metadata: BundleMetadata {
+                canonical: r#"{"productName":"Apple"}"#.to_string(),
+                hash_scheme: HashScheme::SaltedFieldsV1,
+                metadata_hash: disclosure::commitment_root(&field_commitments).unwrap(),
+                field_commitments: Some(field_commitments),
+                public_salts: Some(BTreeMap::from([("productName".to_string(), SALT_A.to_string())])),
+            },
+            merkle: None,
+            anchor,
+            receipt: JsonValue::Null,
+            event: BundleEvent { log_index: 0, product_id_hash: String::new(), metadata_hash: String::new(), recorder: String::new(), timestamp: 0 },
+            block_header: JsonValue::Null,
+            receipt_proof: BundleReceiptProof { receipts_root: String::new(), key: String::new(), nodes: Vec::new() },
+        }
+    }
+
+    #[test]
+    fn checks_public_fields_of_salted_metadata() {
+        let bundle = salted_bundle();
+        assert!(check_metadata(&bundle).is_ok());
+
+        // 修改公开字段的明文，承诺根不变但字段承诺对不上
+        let mut tampered = bundle.clone();
+        tampered.metadata.canonical = r#"{"productName":"Pear"}"#.to_string();
+        assert!(check_metadata(&tampered).unwrap_err().contains("productName"));
+
+        // 冒充公开了未公开的字段
+        let mut tampered = bundle.clone();
+        tampered.metadata.canonical = r#"{"producerInfo":"Farm B","productName":"Apple"}"#.to_string();
+        assert!(check_metadata(&tampered).unwrap_err().contains("producerInfo"));
+
+        let mut tampered = bundle;
+        tampered.metadata.public_salts = None;
+        assert!(check_metadata(&tampered).is_err());
+    }
+}
@@ -0,0 +1,193 @@
+// RLP 编码 / 解码
+// 签名交易 (signer.rs)、区块头哈希与回执 Merkle Patricia Trie 证明 (trie.rs、proof.rs) 共用
+use crate::error::CoreError;
+
+// 解码结果：字节串或列表
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RlpItem {
+    Bytes(Vec<u8>),
+    List(Vec<RlpItem>),
+}
+
+impl RlpItem {
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            RlpItem::Bytes(bytes) => Some(bytes),
+            RlpItem::List(_) => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[RlpItem]> {
+        match self {
+            RlpItem::Bytes(_) => None,
+            RlpItem::List(items) => Some(items),
+        }
+    }
+}
+
+pub fn trim_leading_zeros(bytes: &[u8]) -> &[u8] {
+    let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+    &bytes[start..]
+}
+
+fn length_prefix(len: usize, short_offset: u8, long_offset: u8) -> Vec<u8> {
+    if len <= 55 {
+        vec![short_offset + len as u8]
+    } else {
+        let len_bytes = (len as u64).to_be_bytes();
+        let len_bytes = trim_leading_zeros(&len_bytes);
+        let mut prefix = vec![long_offset + len_bytes.len() as u8];
+        prefix.extend_from_slice(len_bytes);
+        prefix
+    }
+}
+
+pub fn encode_bytes(bytes: &[u8]) -> Vec<u8> {
+    if bytes.len() == 1 && bytes[0] < 0x80 {
+        return bytes.to_vec();
+    }
+    let mut encoded = length_prefix(bytes.len(), 0x80, 0xb7);
+    encoded.extend_from_slice(bytes);
+    encoded
+}
+
+// 整数按无前导零的大端字节编码，0 编码为空字节串
+pub fn encode_uint(value: u64) -> Vec<u8> {
+    encode_bytes(trim_leading_zeros(&value.to_be_bytes()))
+}
+
+// items 为已编码的元素
+pub fn encode_list(items: &[Vec<u8>]) -> Vec<u8> {
+    let payload_len: usize = items.iter().map(Vec::len).sum();
+    let mut encoded = length_prefix(payload_len, 0xc0, 0xf7);
+    for item in items {
+        encoded.extend_from_slice(item);
+    }
+    encoded
+}
+
+fn invalid(reason: &str) -> CoreError {
+    CoreError::InvalidInput(format!("无效的 RLP 数据: {}", reason))
+}
+
+// 读取 data 开头的一个元素，返回 (元素, 占用的字节数)
+fn decode_item(data: &[u8]) -> Result<(RlpItem, usize), CoreError> {
+    let first = *data.first().ok_or_else(|| invalid("数据不完整"))?;
+    let (is_list, header_len, payload_len) = match first {
+        0x00..=0x7f => return Ok((RlpItem::Bytes(vec![first]), 1)),
+        0x80..=0xb7 => (false, 1, (first - 0x80) as usize),
+        0xb8..=0xbf => (false, 1 + (first - 0xb7) as usize, read_length(data, (first - 0xb7) as usize)?),
+        0xc0..=0xf7 => (true, 1, (first - 0xc0) as usize),
+        0xf8..=0xff => (true, 1 + (first - 0xf7) as usize, read_length(data, (first - 0xf7) as usize)?),
+    };
+    let end = header_len.checked_add(payload_len).filter(|end| *end <= data.len()).ok_or_else(|| invalid("长度超出数据范围"))?;
+    let payload = &data[header_len..end];
+    if !is_list {
+        // 单个小于 0x80 的字节必须直接编码
+        if payload_len == 1 && header_len == 1 && payload[0] < 0x80 {
+            return Err(invalid("非规范的单字节编码"));
+        }
+        return Ok((RlpItem::Bytes(payload.to_vec()), end));
+    }
+    let mut items = Vec::new();
+    let mut offset = 0;
+    while offset < payload.len() {
+        let (item, used) = decode_item(&payload[offset..])?;
+        items.push(item);
+        offset += used;
+    }
+    Ok((RlpItem::List(items), end))
+}
+
+// 长格式的长度字段 (大端，不允许前导零，且必须大于 55)
+fn read_length(data: &[u8], len_of_len: usize) -> Result<usize, CoreError> {
+    let bytes = data.get(1..1 + len_of_len).ok_or_else(|| invalid("数据不完整"))?;
+    if bytes[0] == 0 || len_of_len > 8 {
+        return Err(invalid("非规范的长度编码"));
+    }
+    let len = bytes.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64);
+    if len <= 55 {
+        return Err(invalid("非规范的长度编码"));
+    }
+    usize::try_from(len).map_err(|_| invalid("长度超出范围"))
+}
+
+// 解码恰好一个元素，末尾不允许有多余字节
+pub fn decode(data: &[u8]) -> Result<RlpItem, CoreError> {
+    let (item, used) = decode_item(data)?;
+    if used != data.len() {
+        return Err(invalid("末尾有多余的字节"));
+    }
+    Ok(item)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOREM_55: &[u8] = b"Lorem ipsum dolor sit amet, consectetur adipisicing eli";
+    const LOREM_56: &[u8] = b"Lorem ipsum dolor sit amet, consectetur adipisicing elit";
+
+    fn bytes(hex_str: &str) -> Vec<u8> {
+        hex::decode(hex_str).unwrap()
+    }
+
+    // ethereum/tests RLPTests/rlptest.json 中的样例
+    #[test]
+    fn encodes_ethereum_test_vectors() {
+        assert_eq!(encode_bytes(b""), bytes("80"));
+        assert_eq!(encode_bytes(&[0x00]), bytes("00"));
+        assert_eq!(encode_bytes(&[0x7f]), bytes("7f"));
+        assert_eq!(encode_bytes(b"dog"), bytes("83646f67"));
+        assert_eq!(encode_bytes(LOREM_55), [&[0xb7], LOREM_55].concat());
+        assert_eq!(encode_bytes(LOREM_56), [&[0xb8, 0x38], LOREM_56].concat());
+
+        let uints: [(u64, &str); 8] = [
+            (0, "80"), (1, "01"), (16, "10"), (79, "4f"), (127, "7f"), (128, "8180"), (1000, "8203e8"), (100000, "830186a0"),
+        ];
+        for (value, expected) in uints {
+            assert_eq!(encode_uint(value), bytes(expected), "{}", value);
+        }
+
+        let empty = encode_list(&[]);
+        assert_eq!(empty, bytes("c0"));
+        let strings = encode_list(&[encode_bytes(b"dog"), encode_bytes(b"god"), encode_bytes(b"cat")]);
+        assert_eq!(strings, bytes("cc83646f6783676f6483636174"));
+        // [ [ [], [] ], [] ] 与集合论表示 [ [], [[]], [ [], [[]] ] ]
+        let nested = encode_list(&[encode_list(&[empty.clone(), empty.clone()]), empty.clone()]);
+        assert_eq!(nested, bytes("c4c2c0c0c0"));
+        let one = encode_list(std::slice::from_ref(&empty));
+        let set_theoretic = encode_list(&[empty.clone(), one.clone(), encode_list(&[empty.clone(), one])]);
+        assert_eq!(set_theoretic, bytes("c7c0c1c0c3c0c1c0"));
+    }
+
+    #[test]
+    fn decodes_what_it_encodes() {
+        let encoded = bytes("c7c0c1c0c3c0c1c0");
+        let empty = RlpItem::List(Vec::new());
+        let one = RlpItem::List(vec![empty.clone()]);
+        assert_eq!(decode(&encoded).unwrap(), RlpItem::List(vec![empty.clone(), one.clone(), RlpItem::List(vec![empty, one])]));
+
+        let long = decode(&encode_bytes(LOREM_56)).unwrap();
+        assert_eq!(long.as_bytes(), Some(LOREM_56));
+        assert_eq!(decode(&bytes("8203e8")).unwrap().as_bytes(), Some(&[0x03, 0xe8][..]));
+    }
+
+    // ethereum/tests RLPTests/invalidRLPTest.json 中的非规范编码
+    #[test]
+    fn rejects_non_canonical_encodings() {
+        let invalid = [
+            "8100",       // bytesShouldBeSingleByte00
+            "817f",       // bytesShouldBeSingleByte7F
+            "b800",       // 长格式的长度不超过 55
+            "b90040",     // 长度字段有前导零 (且数据不完整)
+            "f80180",     // wrongSizeList：长格式的列表长度不超过 55
+            "83646f",     // 数据不完整
+            "83646f6700", // 末尾有多余的字节
+            "",
+        ];
+        for encoded in invalid {
+            assert!(decode(&bytes(encoded)).is_err(), "{}", encoded);
+        }
+    }
+}
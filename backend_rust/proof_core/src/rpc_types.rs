@@ -0,0 +1,29 @@
+// 以太坊 JSON-RPC 返回值中证明校验用到的部分：事件日志结构与 0x 十六进制数值
+// 后端的 JSON-RPC 客户端 (backend_rust/src/eth_rpc.rs) 复用这些定义
+use serde::Deserialize;
+use crate::error::CoreError;
+
+// 交易回执 / eth_getLogs 中的事件日志 (待打包的日志中区块相关字段为 null)
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcLog {
+    pub address: String,
+    pub topics: Vec<String>,
+    pub data: String,
+    pub block_number: Option<String>,
+    pub block_hash: Option<String>,
+    pub transaction_hash: Option<String>,
+    pub log_index: Option<String>,
+}
+
+// 解析 0x 十六进制数量 (区块号、日志序号等)
+pub fn parse_quantity(value: &str) -> Result<u64, CoreError> {
+    u64::from_str_radix(value.trim_start_matches("0x"), 16)
+        .map_err(|e| CoreError::BlockchainError(format!("无效的十六进制数量 '{}': {}", value, e)))
+}
+
+// 解析带 0x 前缀的十六进制字节串
+pub fn decode_hex_bytes(value: &str) -> Result<Vec<u8>, CoreError> {
+    hex::decode(value.trim_start_matches("0x"))
+        .map_err(|e| CoreError::BlockchainError(format!("无效的十六进制数据 '{}': {}", value, e)))
+}
@@ -0,0 +1,214 @@
+// 以太坊 Merkle Patricia Trie 包含证明
+// 区块头的 receiptsRoot 是以 rlp(交易序号) 为键、编码后的回执为值的 MPT 根；
+// 后端用同一区块的全部回执重建这棵树并导出从根到目标回执的节点，离线验证只需要区块头和这些节点
+use crate::error::CoreError;
+use crate::hashing::keccak256;
+use crate::rlp::{self, RlpItem};
+
+fn to_nibbles(key: &[u8]) -> Vec<u8> {
+    key.iter().flat_map(|b| [b >> 4, b & 0x0f]).collect()
+}
+
+// hex-prefix 编码：首个半字节的 bit1 标记叶子节点，bit0 标记路径长度为奇数
+fn hex_prefix(nibbles: &[u8], is_leaf: bool) -> Vec<u8> {
+    let flag = if is_leaf { 2 } else { 0 };
+    let mut encoded = Vec::with_capacity(nibbles.len() / 2 + 1);
+    let rest = if nibbles.len() % 2 == 1 {
+        encoded.push(((flag + 1) << 4) | nibbles[0]);
+        &nibbles[1..]
+    } else {
+        encoded.push(flag << 4);
+        nibbles
+    };
+    encoded.extend(rest.chunks(2).map(|pair| (pair[0] << 4) | pair[1]));
+    encoded
+}
+
+fn decode_hex_prefix(encoded: &[u8]) -> Result<(Vec<u8>, bool), CoreError> {
+    let first = *encoded.first().ok_or_else(|| invalid("节点路径为空"))?;
+    let flag = first >> 4;
+    if flag > 3 {
+        return Err(invalid("节点路径的前缀无效"));
+    }
+    let mut nibbles = Vec::with_capacity(encoded.len() * 2);
+    if flag & 1 == 1 {
+        nibbles.push(first & 0x0f);
+    }
+    nibbles.extend(to_nibbles(&encoded[1..]));
+    Ok((nibbles, flag & 2 == 2))
+}
+
+fn invalid(reason: &str) -> CoreError {
+    CoreError::InvalidInput(format!("无效的 Merkle Patricia 证明: {}", reason))
+}
+
+// 子节点引用：编码不足 32 字节的节点直接内嵌，否则引用其哈希
+fn child_reference(encoded: &[u8]) -> Vec<u8> {
+    if encoded.len() < 32 {
+        encoded.to_vec()
+    } else {
+        rlp::encode_bytes(&keccak256(encoded))
+    }
+}
+
+// 构建 items[*].0[depth..] 组成的子树，返回节点编码；
+// 节点位于 target 的路径上且以哈希引用 (或为根) 时加入 proof (子节点先于父节点)
+fn build_node(items: &[(Vec<u8>, &[u8])], depth: usize, target: Option<&[u8]>, proof: &mut Vec<Vec<u8>>) -> Vec<u8> {
+    let encoded = if items.len() == 1 {
+        let (path, value) = &items[0];
+        rlp::encode_list(&[rlp::encode_bytes(&hex_prefix(&path[depth..], true)), rlp::encode_bytes(value)])
+    } else {
+        // 所有键在 depth 之后的公共前缀
+        let first = &items[0].0;
+        let common = (depth..first.len())
+            .take_while(|&i| items.iter().all(|(path, _)| path.get(i) == Some(&first[i])))
+            .count();
+        if common > 0 {
+            let on_path = target.filter(|t| t.get(depth..depth + common) == Some(&first[depth..depth + common]));
+            let child = build_node(items, depth + common, on_path, proof);
+            rlp::encode_list(&[rlp::encode_bytes(&hex_prefix(&first[depth..depth + common], false)), child_reference(&child)])
+        } else {
+            let mut slots = Vec::with_capacity(17);
+            for nibble in 0..16u8 {
+                let group: Vec<(Vec<u8>, &[u8])> = items
+                    .iter()
+                    .filter(|(path, _)| path.get(depth) == Some(&nibble))
+                    .cloned()
+                    .collect();
+                if group.is_empty() {
+                    slots.push(rlp::encode_bytes(&[]));
+                } else {
+                    let on_path = target.filter(|t| t.get(depth) == Some(&nibble));
+                    slots.push(child_reference(&build_node(&group, depth + 1, on_path, proof)));
+                }
+            }
+            // 键以本节点结束时值存放在第 17 个槽位
+            let value = items.iter().find(|(path, _)| path.len() == depth).map(|(_, value)| *value).unwrap_or_default();
+            slots.push(rlp::encode_bytes(value));
+            rlp::encode_list(&slots)
+        }
+    };
+    if target.is_some() && (depth == 0 || encoded.len() >= 32) {
+        proof.push(encoded.clone());
+    }
+    encoded
+}
+
+// 由全部键值对计算树根，并导出 key 的包含证明 (从根到叶子的节点编码)
+pub fn build_proof(entries: &[(Vec<u8>, Vec<u8>)], key: &[u8]) -> Result<([u8; 32], Vec<Vec<u8>>), CoreError> {
+    if !entries.iter().any(|(k, _)| k == key) {
+        return Err(CoreError::InternalError("要证明的键不在树中。".to_string()));
+    }
+    let items: Vec<(Vec<u8>, &[u8])> = entries.iter().map(|(k, v)| (to_nibbles(k), v.as_slice())).collect();
+    let target = to_nibbles(key);
+    let mut proof = Vec::new();
+    let root = build_node(&items, 0, Some(&target), &mut proof);
+    proof.reverse();
+    Ok((keccak256(&root), proof))
+}
+
+// 从根出发按 key 的半字节路径逐个核对节点哈希，返回 key 对应的值；证明 key 不在树中时返回 None
+pub fn verify_proof(root: &[u8; 32], key: &[u8], proof: &[Vec<u8>]) -> Result<Option<Vec<u8>>, CoreError> {
+    let nibbles = to_nibbles(key);
+    let mut position = 0;
+    let mut proof_nodes = proof.iter();
+    let mut expected_hash = Some(*root);
+    let mut embedded: Option<RlpItem> = None;
+
+    loop {
+        let node = match (expected_hash.take(), embedded.take()) {
+            (Some(hash), _) => {
+                let encoded = proof_nodes.next().ok_or_else(|| invalid("证明节点不足"))?;
+                if keccak256(encoded) != hash {
+                    return Err(invalid("节点哈希与父节点的引用不一致"));
+                }
+                rlp::decode(encoded)?
+            }
+            (None, Some(node)) => node,
+            (None, None) => return Ok(None),
+        };
+        let items = node.as_list().ok_or_else(|| invalid("节点不是列表"))?;
+
+        let next = match items.len() {
+            17 => {
+                if position == nibbles.len() {
+                    let value = items[16].as_bytes().ok_or_else(|| invalid("分支节点的值不是字节串"))?;
+                    return Ok((!value.is_empty()).then(|| value.to_vec()));
+                }
+                let child = &items[nibbles[position] as usize];
+                position += 1;
+                child
+            }
+            2 => {
+                let (path, is_leaf) = decode_hex_prefix(items[0].as_bytes().ok_or_else(|| invalid("节点路径不是字节串"))?)?;
+                if is_leaf {
+                    if nibbles[position..] != path[..] {
+                        return Ok(None);
+                    }
+                    let value = items[1].as_bytes().ok_or_else(|| invalid("叶子节点的值不是字节串"))?;
+                    return Ok(Some(value.to_vec()));
+                }
+                if !nibbles[position..].starts_with(&path) {
+                    return Ok(None);
+                }
+                position += path.len();
+                &items[1]
+            }
+            _ => return Err(invalid("节点的元素数量既不是 2 也不是 17")),
+        };
+
+        match next {
+            RlpItem::Bytes(bytes) if bytes.is_empty() => return Ok(None),
+            RlpItem::Bytes(bytes) => {
+                let hash: [u8; 32] = bytes.as_slice().try_into().map_err(|_| invalid("子节点引用的长度不是 32 字节"))?;
+                expected_hash = Some(hash);
+            }
+            RlpItem::List(_) => embedded = Some(next.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ethereum/tests TrieTests/trieanyorder.json 中的样例：(键值对, 树根)
+    const VECTORS: [(&[(&str, &str)], &str); 5] = [
+        (&[("doe", "reindeer"), ("dog", "puppy"), ("dogglesworth", "cat")], "8aad789dff2f538bca5d8ea56e8abe10f4c7ba3a5dea95fea4cd6e7c3a1168d3"),
+        (&[("do", "verb"), ("horse", "stallion"), ("doge", "coin"), ("dog", "puppy")], "5991bb8c6514148a29db676a14ac506cd2cd5775ace63c30a4fe457715e9ac84"),
+        (&[("foo", "bar"), ("food", "bass")], "17beaa1648bafa633cda809c90c04af50fc8aed3cb40d16efbddee6fdf63c4c3"),
+        (&[("be", "e"), ("dog", "puppy"), ("bed", "d")], "3f67c7a47520f79faa29255d2d3c084a7a6df0453116ed7232ff10277a8be68b"),
+        (&[("test", "test"), ("te", "testy")], "8452568af70d8d140f58d941338542f645fcca50094b20f3c3d8c3df49337928"),
+    ];
+
+    fn entries(pairs: &[(&str, &str)]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        pairs.iter().map(|(key, value)| (key.as_bytes().to_vec(), value.as_bytes().to_vec())).collect()
+    }
+
+    #[test]
+    fn matches_ethereum_trie_roots_and_proves_every_key() {
+        for (pairs, expected_root) in VECTORS {
+            let entries = entries(pairs);
+            for (key, value) in &entries {
+                let (root, proof) = build_proof(&entries, key).unwrap();
+                assert_eq!(hex::encode(root), expected_root);
+                assert_eq!(verify_proof(&root, key, &proof).unwrap().as_deref(), Some(value.as_slice()));
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_tampered_proofs_and_absent_keys() {
+        let entries = entries(VECTORS[2].0);
+        let (root, proof) = build_proof(&entries, b"foo").unwrap();
+        // 路径在扩展节点处分叉的键不在树中
+        assert_eq!(verify_proof(&root, b"fox", &proof).unwrap(), None);
+
+        let mut tampered = proof.clone();
+        let last = tampered[0].len() - 1;
+        tampered[0][last] ^= 0x01;
+        assert!(verify_proof(&root, b"foo", &tampered).is_err());
+        assert!(verify_proof(&root, b"foo", &[]).is_err());
+        assert!(build_proof(&entries, b"fox").is_err());
+    }
+}
@@ -0,0 +1,193 @@
+// Hardhat 编译产物与部署清单
+// 启动时读取 artifacts/contracts/FoodTraceability.sol/FoodTraceability.json 中的 ABI，核对 contract.rs 的编码函数与当前合约接口一致，
+// 并通过 GET /api/chain/config 提供给前端；部署脚本 (scripts/deploy.js) 把合约地址和部署区块写入 deployments/<chainId>.json，
+// 链配置未指定合约地址时从这里读取，重新部署后不再需要手动修改前后端的地址和 ABI
+use chrono::{DateTime, Utc};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::contract::{self, FunctionBinding};
+use crate::errors::AppError;
+use crate::eth_rpc::decode_hex_bytes;
+
+// 默认路径相对于 backend_rust 目录 (cargo run 的工作目录)
+const DEFAULT_ARTIFACT_PATH: &str = "../blockchain_hardhat/artifacts/contracts/FoodTraceability.sol/FoodTraceability.json";
+const DEFAULT_DEPLOYMENTS_DIR: &str = "../blockchain_hardhat/deployments";
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct AbiParam {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub indexed: bool,
+    #[serde(default)]
+    pub components: Vec<AbiParam>, // tuple 类型的成员
+}
+
+impl AbiParam {
+    // 函数签名中使用的规范类型，tuple 展开为 (成员类型,...)
+    fn canonical_type(&self) -> String {
+        match self.kind.strip_prefix("tuple") {
+            Some(suffix) => format!("({}){}", join_types(&self.components), suffix),
+            None => self.kind.clone(),
+        }
+    }
+}
+
+fn join_types(params: &[AbiParam]) -> String {
+    params.iter().map(AbiParam::canonical_type).collect::<Vec<_>>().join(",")
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct AbiItem {
+    #[serde(rename = "type")]
+    pub kind: String, // function / event / constructor / error ...
+    pub name: Option<String>,
+    #[serde(default)]
+    pub inputs: Vec<AbiParam>,
+    #[serde(default)]
+    pub outputs: Vec<AbiParam>,
+}
+
+impl AbiItem {
+    pub fn signature(&self) -> String {
+        format!("{}({})", self.name.as_deref().unwrap_or_default(), join_types(&self.inputs))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HardhatArtifact {
+    contract_name: String,
+    abi: JsonValue,
+    bytecode: String, // 创建字节码，带 0x 前缀
+}
+
+// 合约编译产物：原始 ABI 原样提供给客户端，解析后的条目用于核对，字节码用于 deploy 命令
+#[derive(Debug, Clone)]
+pub struct ContractArtifact {
+    pub contract_name: String,
+    pub abi: JsonValue,
+    pub bytecode: Vec<u8>,
+    items: Vec<AbiItem>,
+}
+
+impl ContractArtifact {
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| AppError::InternalError(format!("无法读取合约编译产物 {}: {}", path.display(), e)))?;
+        let artifact: HardhatArtifact = serde_json::from_str(&content)
+            .map_err(|e| AppError::InternalError(format!("合约编译产物 {} 格式错误: {}", path.display(), e)))?;
+        let items: Vec<AbiItem> = serde_json::from_value(artifact.abi.clone())
+            .map_err(|e| AppError::InternalError(format!("合约编译产物 {} 的 ABI 格式错误: {}", path.display(), e)))?;
+        let bytecode = decode_hex_bytes(&artifact.bytecode)?;
+        Ok(ContractArtifact { contract_name: artifact.contract_name, abi: artifact.abi, bytecode, items })
+    }
+
+    // 读取 CONTRACT_ARTIFACT_PATH 指定的编译产物；显式配置的文件不存在时报错，
+    // 默认路径不存在时 (尚未运行 npx hardhat compile) 返回 None 并告警：此时编码函数没有与合约 ABI 核对
+    pub fn from_env() -> Result<Option<Self>, AppError> {
+        let configured = env::var("CONTRACT_ARTIFACT_PATH").ok();
+        let path = PathBuf::from(configured.as_deref().unwrap_or(DEFAULT_ARTIFACT_PATH));
+        if !path.exists() {
+            if configured.is_some() {
+                return Err(AppError::InternalError(format!("CONTRACT_ARTIFACT_PATH 指定的合约编译产物 {} 不存在。", path.display())));
+            }
+            let signatures: Vec<String> = contract::FUNCTION_BINDINGS.iter().map(|binding| binding.signature()).collect();
+            warn!(
+                "未找到合约编译产物 {}，{} 与事件 {} 的编码未经 ABI 核对，合约接口变化时上链与验证会出错；请先运行 npx hardhat compile 或配置 CONTRACT_ARTIFACT_PATH。",
+                path.display(), signatures.join(" / "), contract::RECORD_ADDED_SIGNATURE
+            );
+            return Ok(None);
+        }
+        let artifact = Self::load(&path)?;
+        artifact.check_bindings()?;
+        Ok(Some(artifact))
+    }
+
+    fn find(&self, kind: &str, signature: &str) -> Option<&AbiItem> {
+        self.items.iter().find(|item| item.kind == kind && item.signature() == signature)
+    }
+
+    // 核对 contract.rs 中每个编码函数的参数与返回值类型，以及 RecordAdded 事件的 indexed 参数
+    pub fn check_bindings(&self) -> Result<(), AppError> {
+        for binding in contract::FUNCTION_BINDINGS {
+            self.check_function(binding)?;
+        }
+        let event = self.find("event", contract::RECORD_ADDED_SIGNATURE).ok_or_else(|| {
+            AppError::InternalError(format!("合约 ABI 中没有事件 {}。", contract::RECORD_ADDED_SIGNATURE))
+        })?;
+        let indexed: Vec<bool> = event.inputs.iter().map(|input| input.indexed).collect();
+        if indexed != contract::RECORD_ADDED_INDEXED {
+            return Err(AppError::InternalError(format!(
+                "合约 ABI 中事件 {} 的 indexed 参数与后端解码不一致: {:?}", contract::RECORD_ADDED_SIGNATURE, indexed
+            )));
+        }
+        Ok(())
+    }
+
+    fn check_function(&self, binding: &FunctionBinding) -> Result<(), AppError> {
+        let signature = binding.signature();
+        let function = self
+            .find("function", &signature)
+            .ok_or_else(|| AppError::InternalError(format!("合约 ABI 中没有函数 {}。", signature)))?;
+        let outputs: Vec<String> = function.outputs.iter().map(AbiParam::canonical_type).collect();
+        if outputs != binding.outputs {
+            return Err(AppError::InternalError(format!(
+                "合约 ABI 中函数 {} 的返回值 ({}) 与后端解码 ({}) 不一致。",
+                signature, outputs.join(","), binding.outputs.join(",")
+            )));
+        }
+        Ok(())
+    }
+}
+
+// 部署清单 (deployments/<chainId>.json)，由 scripts/deploy.js 或后端 deploy 命令写入
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DeploymentManifest {
+    pub chain_id: u64,
+    pub network: String,
+    pub contract_name: String,
+    pub address: String,
+    pub deploy_block: u64,
+    pub transaction_hash: String,
+    pub deployer: String,
+    pub deployed_at: DateTime<Utc>,
+}
+
+pub fn deployments_dir() -> PathBuf {
+    PathBuf::from(env::var("DEPLOYMENTS_DIR").unwrap_or_else(|_| DEFAULT_DEPLOYMENTS_DIR.to_string()))
+}
+
+// 读取指定链的部署清单，不存在时返回 None
+pub fn load_deployment(dir: &Path, chain_id: u64) -> Result<Option<DeploymentManifest>, AppError> {
+    let path = dir.join(format!("{}.json", chain_id));
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path)
+        .map_err(|e| AppError::InvalidInput(format!("无法读取部署清单 {}: {}", path.display(), e)))?;
+    let manifest: DeploymentManifest = serde_json::from_str(&content)
+        .map_err(|e| AppError::InvalidInput(format!("部署清单 {} 格式错误: {}", path.display(), e)))?;
+    if manifest.chain_id != chain_id {
+        return Err(AppError::InvalidInput(format!(
+            "部署清单 {} 的链 ID {} 与文件名不一致。", path.display(), manifest.chain_id
+        )));
+    }
+    Ok(Some(manifest))
+}
+
+// 写入 (覆盖) 指定链的部署清单，返回文件路径
+pub fn write_deployment(dir: &Path, manifest: &DeploymentManifest) -> Result<PathBuf, AppError> {
+    let path = dir.join(format!("{}.json", manifest.chain_id));
+    fs::create_dir_all(dir)
+        .map_err(|e| AppError::InternalError(format!("无法创建部署清单目录 {}: {}", dir.display(), e)))?;
+    let content = serde_json::to_string_pretty(manifest)?;
+    fs::write(&path, content + "\n")
+        .map_err(|e| AppError::InternalError(format!("无法写入部署清单 {}: {}", path.display(), e)))?;
+    Ok(path)
+}
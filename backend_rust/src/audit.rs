@@ -0,0 +1,412 @@
+// 哈希链审计日志
+// 创建记录、上链确认、对账、登录等写操作在同一事务中调用 db::append_audit_log_db 追加一条记录；
+// 每条记录的哈希覆盖上一条记录的哈希，修改审计日志本身会在校验时从被改动的那一条开始断链；
+// 校验时还把 traceability_data 的当前内容与最近一次 record.create / 上链审计记录核对，发现绕过后端直接修改的记录，
+// 并与托管账户 addRecord("audit-head", 链头哈希) 锚定的链头比对 (见 audit_anchor.rs)，整条日志被重算也会被发现
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{json, Value as JsonValue};
+use std::collections::{HashMap, HashSet};
+use crate::audit_anchor;
+use crate::canonical_json;
+use crate::db;
+use crate::errors::AppError;
+use crate::hashing::{hash_canonical_json, normalize_hash};
+use crate::verification;
+use crate::models::{
+    AppState, AuditAnchoredHead, AuditBrokenLink, AuditLogEntry, AuditRecordMismatch, AuditVerificationResponse, AuditedRecordRow,
+};
+
+pub const GENESIS_HASH: &str = "0x0000000000000000000000000000000000000000000000000000000000000000";
+
+pub const ACTOR_ANONYMOUS: &str = "anonymous"; // AUTH_REQUIRED=false 时未登录的请求
+pub const ACTOR_CLI: &str = "cli";
+pub const ACTOR_OUTBOX: &str = "system:outbox";
+pub const ACTOR_MERKLE: &str = "system:merkle";
+pub const ACTOR_RECONCILIATION: &str = "system:reconciliation";
+pub const ACTOR_STARTUP: &str = "system:startup";
+
+// 审计链头上链时使用的 productId，记录不能使用
+pub const HEAD_ANCHOR_KEY: &str = "audit-head";
+
+const VERIFY_PAGE_SIZE: u32 = 1000;
+const MAX_LISTED_MISMATCHES: usize = 100;
+
+// 待追加的审计记录，payload 在写入前做 JCS 规范化
+#[derive(Debug, Clone)]
+pub struct NewAuditEntry {
+    pub actor: String,
+    pub action: &'static str,
+    pub target: Option<String>,
+    pub payload: JsonValue,
+}
+
+impl NewAuditEntry {
+    pub fn new(actor: &str, action: &'static str, target: Option<&str>, payload: JsonValue) -> Self {
+        NewAuditEntry { actor: actor.to_string(), action, target: target.map(str::to_string), payload }
+    }
+}
+
+// 参与哈希的时间精确到毫秒，与 DATETIME(3) 列一致
+pub fn truncate_to_millis(time: DateTime<Utc>) -> DateTime<Utc> {
+    DateTime::from_timestamp_millis(time.timestamp_millis()).unwrap_or(time)
+}
+
+pub fn canonical_payload(payload: &JsonValue) -> Result<(String, String), AppError> {
+    let canonical = canonical_json::canonicalize(payload)?;
+    let hash = hash_canonical_json(&canonical);
+    Ok((canonical, hash))
+}
+
+// entry_hash = keccak256(JCS{id, actor, action, target, payloadHash, prevHash, createdAt})
+pub fn entry_hash(
+    id: u64,
+    actor: &str,
+    action: &str,
+    target: Option<&str>,
+    payload_hash: &str,
+    prev_hash: &str,
+    created_at: DateTime<Utc>,
+) -> Result<String, AppError> {
+    let content = json!({
+        "id": id,
+        "actor": actor,
+        "action": action,
+        "target": target,
+        "payloadHash": payload_hash,
+        "prevHash": prev_hash,
+        "createdAt": created_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+    });
+    Ok(hash_canonical_json(&canonical_json::canonicalize(&content)?))
+}
+
+// 检查单条记录，返回第一处不一致的原因
+fn check_entry(entry: &AuditLogEntry, expected_id: u64, expected_prev_hash: &str) -> Result<Option<String>, AppError> {
+    if entry.id != expected_id {
+        return Ok(Some(format!("记录编号不连续：期望 #{}，实际 #{} (中间的记录被删除或插入)。", expected_id, entry.id)));
+    }
+    if entry.prev_hash != expected_prev_hash {
+        return Ok(Some(format!("prev_hash {} 与上一条记录的哈希 {} 不一致。", entry.prev_hash, expected_prev_hash)));
+    }
+    let Ok(payload) = serde_json::from_str::<JsonValue>(&entry.payload) else {
+        return Ok(Some("payload 不是有效的 JSON (payload 被修改)。".to_string()));
+    };
+    let (_, payload_hash) = canonical_payload(&payload)?;
+    if entry.payload_hash != payload_hash {
+        return Ok(Some(format!("payload 的哈希为 {}，记录的 payload_hash 为 {} (payload 被修改)。", payload_hash, entry.payload_hash)));
+    }
+    let computed = entry_hash(
+        entry.id, &entry.actor, &entry.action, entry.target.as_deref(), &entry.payload_hash, &entry.prev_hash, entry.created_at,
+    )?;
+    if entry.entry_hash != computed {
+        return Ok(Some(format!("重新计算的哈希为 {}，记录的 entry_hash 为 {} (记录内容被修改)。", computed, entry.entry_hash)));
+    }
+    Ok(None)
+}
+
+// 审计记录中每个产品最近一次的状态
+#[derive(Default)]
+struct LoggedRecord {
+    created: Option<JsonValue>,                    // 最近一次 record.create 的 payload
+    transaction_hash: Option<(u64, Option<String>)>, // 最近一次写入交易哈希的审计记录编号及哈希
+}
+
+// 按审计记录累积每个产品的期望状态
+#[derive(Default)]
+struct RecordLog {
+    records: HashMap<String, LoggedRecord>,
+    batch_anchors: HashMap<u64, (u64, String)>, // Merkle 批次 -> (审计记录编号, 交易哈希)
+}
+
+impl RecordLog {
+    fn apply(&mut self, entry: &AuditLogEntry) {
+        let Ok(payload) = serde_json::from_str::<JsonValue>(&entry.payload) else {
+            return;
+        };
+        let text = |key: &str| payload.get(key).and_then(JsonValue::as_str).map(str::to_string);
+        match entry.action.as_str() {
+            "record.create" => {
+                let Some(product_id) = text("productId") else { return };
+                let record = self.records.entry(product_id).or_default();
+                record.transaction_hash = Some((entry.id, text("transactionHash")));
+                record.created = Some(payload);
+            }
+            "record.anchor" | "record.reanchor" => {
+                let Some(product_id) = text("productId") else { return };
+                self.records.entry(product_id).or_default().transaction_hash = Some((entry.id, text("transactionHash")));
+            }
+            "merkle_batch.anchor" => {
+                let batch_id = entry.target.as_deref().and_then(|target| target.parse().ok());
+                if let (Some(batch_id), Some(transaction_hash)) = (batch_id, text("transactionHash")) {
+                    self.batch_anchors.insert(batch_id, (entry.id, transaction_hash));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // 当前行与审计记录不一致的原因
+    fn check(&self, row: &AuditedRecordRow) -> Option<String> {
+        let logged = self.records.get(&row.product_id);
+        let created = logged.and_then(|logged| logged.created.as_ref())?;
+        let logged_text = |key: &str| created.get(key).and_then(JsonValue::as_str).map(str::to_lowercase);
+        let mut reasons = Vec::new();
+        if logged_text("metadataHash") != Some(normalize_hash(&row.onchain_metadata_hash)) {
+            reasons.push(format!("元数据哈希 {} 与 record.create 记录的 {:?} 不一致", row.onchain_metadata_hash, logged_text("metadataHash")));
+        }
+        if logged_text("hashScheme").as_deref() != Some(row.hash_scheme.as_str()) {
+            reasons.push(format!("哈希方案 {} 与 record.create 记录的 {:?} 不一致", row.hash_scheme, logged_text("hashScheme")));
+        }
+        if logged_text("recorder") != row.recorder.as_deref().map(str::to_lowercase) {
+            reasons.push(format!("记录者 {:?} 与 record.create 记录的 {:?} 不一致", row.recorder, logged_text("recorder")));
+        }
+        // 最近一次写入交易哈希的审计记录：创建、上链确认、重新上链或所在 Merkle 批次的上链
+        let batch_anchor = row
+            .merkle_batch_id
+            .and_then(|batch_id| self.batch_anchors.get(&batch_id))
+            .map(|(id, hash)| (*id, Some(hash.clone())));
+        let expected = logged
+            .and_then(|logged| logged.transaction_hash.clone())
+            .into_iter()
+            .chain(batch_anchor)
+            .max_by_key(|(id, _)| *id)
+            .and_then(|(_, hash)| hash)
+            .map(|hash| hash.to_lowercase());
+        if expected != row.blockchain_transaction_hash.as_deref().map(str::to_lowercase) {
+            reasons.push(format!("交易哈希 {:?} 与审计记录的 {:?} 不一致", row.blockchain_transaction_hash, expected));
+        }
+        if reasons.is_empty() {
+            None
+        } else {
+            Some(format!("{} (记录被绕过后端修改)。", reasons.join("；")))
+        }
+    }
+}
+
+// 逐条复核 audit_head_anchors 中已确认的锚定：重新获取交易回执，要求其中有 "audit-head" 与链头哈希对应的 RecordAdded 事件，
+// 且记录者是托管签名账户。不读取合约的 records("audit-head")：addRecord 对任何地址开放，这个键可以被任何人覆盖
+// 返回核实的锚定，以及第一条无法核实的锚定的记录编号与原因
+async fn verified_anchors(app_state: &AppState) -> Result<(Vec<AuditAnchoredHead>, Option<(u64, String)>), AppError> {
+    let anchors = db::list_audit_head_anchors_db(&app_state.db_pool, audit_anchor::ANCHOR_STATUS_CONFIRMED).await?;
+    if anchors.is_empty() {
+        return Ok((Vec::new(), None));
+    }
+    let signer = app_state.signer.as_ref().ok_or_else(|| {
+        AppError::InvalidInput("未配置托管签名账户 (SIGNER_PRIVATE_KEY)，无法确认锚定交易的记录者。".to_string())
+    })?;
+    let mut verified = Vec::with_capacity(anchors.len());
+    for anchor in anchors {
+        let eth_client = &app_state.chains.resolve(Some(anchor.chain_id))?.eth_client;
+        let validated = match verification::validate_anchor_transaction(
+            eth_client, &anchor.contract_address, &anchor.transaction_hash, HEAD_ANCHOR_KEY, &anchor.head_hash,
+        ).await {
+            Ok(validated) => validated,
+            Err(AppError::AnchorValidationFailed(reason)) => {
+                return Ok((verified, Some((anchor.entry_id, format!("链头锚定 #{} 无法在链上核实: {}", anchor.id, reason)))));
+            }
+            Err(e) => return Err(e),
+        };
+        if !validated.recorder.eq_ignore_ascii_case(signer.address()) {
+            let reason = format!(
+                "链头锚定 #{} 的交易 {} 由 {} 记录，不是托管签名账户 {}。", anchor.id, anchor.transaction_hash, validated.recorder, signer.address()
+            );
+            return Ok((verified, Some((anchor.entry_id, reason))));
+        }
+        verified.push(AuditAnchoredHead {
+            entry_id: anchor.entry_id,
+            head_hash: normalize_hash(&anchor.head_hash),
+            transaction_hash: validated.transaction_hash,
+            anchored_at: validated.timestamp,
+        });
+    }
+    Ok((verified, None))
+}
+
+// 从第一条记录开始逐条校验整条链，核对链头与链上锚定的链头，再把 traceability_data 与审计记录核对
+pub async fn verify_audit_chain(app_state: &AppState) -> Result<AuditVerificationResponse, AppError> {
+    let pool = &app_state.db_pool;
+    let (head_entry_id, head_hash) = db::get_audit_chain_head_db(pool).await?;
+    let mut response = AuditVerificationResponse {
+        valid: true,
+        verified_entries: 0,
+        head_entry_id,
+        head_hash: head_hash.clone(),
+        first_broken: None,
+        anchored_head: None,
+        anchors_verified: 0,
+        anchor_check_error: None,
+        records_checked: 0,
+        unaudited_records: 0,
+        record_mismatch_count: 0,
+        record_mismatches: Vec::new(),
+    };
+    let broken = |response: &mut AuditVerificationResponse, entry_id: u64, reason: String| {
+        response.valid = false;
+        response.first_broken = Some(AuditBrokenLink { entry_id, reason });
+    };
+
+    // 链上核实过的 (记录编号, 链头哈希)
+    let anchored = match verified_anchors(app_state).await {
+        Ok((anchored, None)) => anchored,
+        Ok((_, Some((entry_id, reason)))) => {
+            broken(&mut response, entry_id, reason);
+            return Ok(response);
+        }
+        Err(e) => {
+            response.anchor_check_error = Some(e.to_string());
+            Vec::new()
+        }
+    };
+    response.anchors_verified = anchored.len() as u64;
+
+    let mut record_log = RecordLog::default();
+    let mut first_entry_at = None;
+    let (mut last_id, mut last_hash) = (0u64, GENESIS_HASH.to_string());
+    loop {
+        let entries = db::list_audit_log_after_db(pool, last_id, VERIFY_PAGE_SIZE).await?;
+        if entries.is_empty() {
+            break;
+        }
+        for entry in entries {
+            if let Some(reason) = check_entry(&entry, last_id + 1, &last_hash)? {
+                broken(&mut response, entry.id, reason);
+                return Ok(response);
+            }
+            if let Some(head) = anchored.iter().find(|head| head.entry_id == entry.id && head.head_hash != entry.entry_hash) {
+                let reason = format!("哈希 {} 与链上锚定的 {} 不一致 (审计日志在锚定后被重写)。", entry.entry_hash, head.head_hash);
+                broken(&mut response, entry.id, reason);
+                return Ok(response);
+            }
+            first_entry_at.get_or_insert(entry.created_at);
+            record_log.apply(&entry);
+            last_id = entry.id;
+            last_hash = entry.entry_hash;
+            response.verified_entries += 1;
+        }
+    }
+
+    // 链尾被删除时剩余记录仍能自洽，需要与链头比对
+    if last_id != head_entry_id || last_hash != head_hash {
+        let reason = format!(
+            "链头记录最后一条为 #{} ({})，但审计日志只校验到 #{} ({})，链尾的记录被删除或链头被修改。",
+            head_entry_id, head_hash, last_id, last_hash
+        );
+        broken(&mut response, last_id + 1, reason);
+        return Ok(response);
+    }
+    let latest_anchor = anchored.into_iter().max_by_key(|head| head.entry_id);
+    if let Some(head) = latest_anchor.as_ref().filter(|head| head.entry_id > last_id) {
+        let reason = format!("链上锚定的链头为 #{}，但审计日志只有 {} 条记录 (链尾的记录被删除)。", head.entry_id, last_id);
+        broken(&mut response, last_id + 1, reason);
+        return Ok(response);
+    }
+    response.anchored_head = latest_anchor;
+
+    // 审计日志完整时，逐条核对记录的当前内容
+    let rows = db::list_audited_records_db(pool).await?;
+    for row in &rows {
+        let reason = if record_log.records.get(&row.product_id).is_some_and(|logged| logged.created.is_some()) {
+            record_log.check(row)
+        } else if first_entry_at.is_none_or(|first| row.created_at < first) {
+            response.unaudited_records += 1;
+            continue;
+        } else {
+            Some("没有 record.create 审计记录 (记录绕过后端写入)。".to_string())
+        };
+        response.records_checked += 1;
+        if let Some(reason) = reason {
+            response.record_mismatch_count += 1;
+            if response.record_mismatches.len() < MAX_LISTED_MISMATCHES {
+                response.record_mismatches.push(AuditRecordMismatch { product_id: row.product_id.clone(), reason });
+            }
+        }
+    }
+    let existing: HashSet<&str> = rows.iter().map(|row| row.product_id.as_str()).collect();
+    let mut deleted: Vec<&String> = record_log
+        .records
+        .iter()
+        .filter(|(product_id, logged)| logged.created.is_some() && !existing.contains(product_id.as_str()))
+        .map(|(product_id, _)| product_id)
+        .collect();
+    deleted.sort();
+    for product_id in deleted {
+        response.record_mismatch_count += 1;
+        if response.record_mismatches.len() < MAX_LISTED_MISMATCHES {
+            response.record_mismatches.push(AuditRecordMismatch {
+                product_id: product_id.clone(),
+                reason: "审计日志中有 record.create，但记录已不在 traceability_data 中 (记录被删除)。".to_string(),
+            });
+        }
+    }
+    response.valid = response.record_mismatch_count == 0;
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASH: &str = "0x1111111111111111111111111111111111111111111111111111111111111111";
+
+    fn entry(id: u64, action: &str, target: &str, payload: JsonValue) -> AuditLogEntry {
+        AuditLogEntry {
+            id,
+            actor: ACTOR_CLI.to_string(),
+            action: action.to_string(),
+            target: Some(target.to_string()),
+            payload: payload.to_string(),
+            payload_hash: String::new(),
+            prev_hash: String::new(),
+            entry_hash: String::new(),
+            created_at: Utc::now(),
+        }
+    }
+
+    fn row(transaction_hash: Option<&str>, merkle_batch_id: Option<u64>) -> AuditedRecordRow {
+        AuditedRecordRow {
+            product_id: "P-1".to_string(),
+            onchain_metadata_hash: HASH.to_string(),
+            hash_scheme: "jcs-keccak256".to_string(),
+            recorder: None,
+            blockchain_transaction_hash: transaction_hash.map(str::to_string),
+            merkle_batch_id,
+            created_at: Utc::now(),
+        }
+    }
+
+    fn record_log(entries: &[AuditLogEntry]) -> RecordLog {
+        let mut log = RecordLog::default();
+        entries.iter().for_each(|entry| log.apply(entry));
+        log
+    }
+
+    fn create(id: u64, transaction_hash: Option<&str>) -> AuditLogEntry {
+        entry(id, "record.create", "P-1", json!({
+            "productId": "P-1", "metadataHash": HASH, "hashScheme": "jcs-keccak256", "transactionHash": transaction_hash, "recorder": null,
+        }))
+    }
+
+    #[test]
+    fn accepts_rows_matching_latest_logged_state() {
+        let log = record_log(&[
+            create(1, None),
+            entry(2, "record.anchor", "P-1", json!({ "productId": "P-1", "transactionHash": "0xAA" })),
+        ]);
+        assert_eq!(log.check(&row(Some("0xaa"), None)), None);
+
+        // Merkle 批次上链晚于记录自身的审计记录时，以批次的交易哈希为准
+        let log = record_log(&[create(1, None), entry(2, "merkle_batch.anchor", "7", json!({ "transactionHash": "0xbb" }))]);
+        assert_eq!(log.check(&row(Some("0xbb"), Some(7))), None);
+    }
+
+    #[test]
+    fn reports_rows_changed_outside_the_backend() {
+        let log = record_log(&[create(1, Some("0xaa"))]);
+        let mut tampered = row(Some("0xaa"), None);
+        tampered.onchain_metadata_hash = "0x2222222222222222222222222222222222222222222222222222222222222222".to_string();
+        assert!(log.check(&tampered).unwrap().contains("元数据哈希"));
+        assert!(log.check(&row(Some("0xcc"), None)).unwrap().contains("交易哈希"));
+        let mut signed = row(Some("0xaa"), None);
+        signed.recorder = Some("0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266".to_string());
+        assert!(log.check(&signed).unwrap().contains("记录者"));
+    }
+}
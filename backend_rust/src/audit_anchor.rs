@@ -0,0 +1,110 @@
+// 审计链头上链后台任务
+// 每隔 AUDIT_ANCHOR_INTERVAL_SECS 秒，链头有新记录时由托管账户发送 addRecord("audit-head", 链头哈希) 到默认链的合约，
+// 并在 audit_head_anchors 中记录锚定的记录编号；数据库管理员重写整条审计日志后，校验时与链上的哈希对不上
+// 锚定本身不写审计日志，否则每次锚定都会产生新的链头
+use actix_web::web;
+use log::{info, warn};
+use std::time::Duration;
+use crate::audit;
+use crate::config::env_or;
+use crate::db;
+use crate::errors::AppError;
+use crate::models::AppState;
+use crate::outbox::OutboxConfig;
+use crate::verification;
+
+pub const ANCHOR_STATUS_SUBMITTED: &str = "submitted";
+pub const ANCHOR_STATUS_CONFIRMED: &str = "confirmed";
+pub const ANCHOR_STATUS_FAILED: &str = "failed";
+
+#[derive(Debug, Clone)]
+pub struct AuditAnchorConfig {
+    pub interval: Duration, // 为 0 时不启动
+}
+
+impl AuditAnchorConfig {
+    pub fn from_env() -> Self {
+        AuditAnchorConfig { interval: Duration::from_secs(env_or("AUDIT_ANCHOR_INTERVAL_SECS", 3600)) }
+    }
+}
+
+// 启动链头上链任务，未配置托管签名账户或间隔为 0 时不启动
+pub fn spawn_audit_anchor(app_state: web::Data<AppState>, config: AuditAnchorConfig, outbox_config: OutboxConfig) {
+    if app_state.signer.is_none() || config.interval.is_zero() {
+        warn!("审计链头上链任务未启动 (需要 SIGNER_PRIVATE_KEY 且 AUDIT_ANCHOR_INTERVAL_SECS 大于 0)，审计日志被整体重写时无法发现。");
+        return;
+    }
+    info!("审计链头上链任务已启动: 每 {} 秒锚定一次", config.interval.as_secs());
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config.interval);
+        loop {
+            interval.tick().await;
+            if let Err(e) = anchor_head(&app_state, &outbox_config).await {
+                warn!("审计链头上链失败: {}", e);
+            }
+        }
+    });
+}
+
+// 先确认已发送的锚定交易，仍在交易池中时本轮不再发送；链头有新记录时发送新的锚定
+async fn anchor_head(app_state: &AppState, outbox_config: &OutboxConfig) -> Result<(), AppError> {
+    let Some(signer) = app_state.signer.as_ref() else {
+        return Ok(());
+    };
+    for anchor in db::list_audit_head_anchors_db(&app_state.db_pool, ANCHOR_STATUS_SUBMITTED).await? {
+        if !confirm_anchor(app_state, anchor.id, anchor.chain_id, &anchor.contract_address, &anchor.transaction_hash, &anchor.head_hash).await? {
+            return Ok(());
+        }
+    }
+
+    let (entry_id, head_hash) = db::get_audit_chain_head_db(&app_state.db_pool).await?;
+    let latest = db::get_latest_audit_head_anchor_db(&app_state.db_pool).await?;
+    if entry_id == 0 || latest.is_some_and(|anchor| anchor.entry_id == entry_id) {
+        return Ok(());
+    }
+    let chain = app_state.chains.default_chain();
+    let sent = signer
+        .submit_add_record(&chain.eth_client, chain.chain_id(), chain.contract_address(), audit::HEAD_ANCHOR_KEY, &head_hash)
+        .await?;
+    let id = db::insert_audit_head_anchor_db(
+        &app_state.db_pool, entry_id, &head_hash, chain.chain_id(), chain.contract_address(), &sent.hash,
+    ).await?;
+    info!("审计链头 #{} ({}) 已发送上链: {}", entry_id, head_hash, sent.hash);
+    if chain.eth_client.wait_for_receipt(&sent.hash, outbox_config.receipt_timeout).await?.is_some() {
+        confirm_anchor(app_state, id, chain.chain_id(), chain.contract_address(), &sent.hash, &head_hash).await?;
+    }
+    Ok(())
+}
+
+// 已打包时校验 RecordAdded 事件并标记为 confirmed；被节点丢弃时标记为 failed (重复锚定同一链头无害)；
+// 仍在交易池中返回 false
+async fn confirm_anchor(
+    app_state: &AppState,
+    id: u64,
+    chain_id: u64,
+    contract_address: &str,
+    transaction_hash: &str,
+    head_hash: &str,
+) -> Result<bool, AppError> {
+    let eth_client = &app_state.chains.resolve(Some(chain_id))?.eth_client;
+    if eth_client.get_transaction_receipt(transaction_hash).await?.is_none() {
+        if eth_client.transaction_known(transaction_hash).await? {
+            return Ok(false);
+        }
+        warn!("审计链头锚定交易 {} 已被节点丢弃。", transaction_hash);
+        db::update_audit_head_anchor_db(&app_state.db_pool, id, ANCHOR_STATUS_FAILED, None).await?;
+        return Ok(true);
+    }
+    match verification::validate_anchor_transaction(eth_client, contract_address, transaction_hash, audit::HEAD_ANCHOR_KEY, head_hash).await {
+        Ok(anchor) => {
+            db::update_audit_head_anchor_db(&app_state.db_pool, id, ANCHOR_STATUS_CONFIRMED, Some(anchor.block_number)).await?;
+            info!("审计链头 {} 已上链确认: 交易 {}，区块 {}", head_hash, transaction_hash, anchor.block_number);
+        }
+        Err(AppError::AnchorValidationFailed(reason)) => {
+            warn!("审计链头锚定交易 {} 校验失败: {}", transaction_hash, reason);
+            db::update_audit_head_anchor_db(&app_state.db_pool, id, ANCHOR_STATUS_FAILED, None).await?;
+        }
+        Err(e) => return Err(e),
+    }
+    Ok(true)
+}
@@ -0,0 +1,211 @@
+// 登录会话 (Sign-In With Ethereum)
+// 用户用 addRecord 所用的同一个钱包签名 SIWE 消息登录 (见 siwe.rs 与 handlers/auth.rs)，得到绑定该地址的会话令牌；
+// 写接口通过 WriteAccess 提取器校验 Authorization: Bearer <token> 与写入白名单，管理接口通过 AdminAccess 校验管理员地址
+use actix_web::{dev::Payload, http::header, web, FromRequest, HttpRequest};
+use log::warn;
+use rand::{rngs::OsRng, RngCore};
+use std::collections::HashSet;
+use std::env;
+use std::future::Future;
+use std::pin::Pin;
+use crate::audit;
+use crate::config::env_or;
+use crate::db;
+use crate::errors::AppError;
+use crate::hashing::{keccak256, to_hex_string};
+use crate::models::{AppState, AuthSession};
+
+#[derive(Debug, Clone)]
+pub struct AuthConfig {
+    pub required: bool,       // 为 false 时未携带令牌的写请求也放行 (本地调试、脚本)，携带的令牌仍会校验
+    pub domains: Vec<String>, // SIWE 消息允许的 domain (前端页面的 host[:port])
+    pub nonce_ttl_secs: u64,
+    pub session_ttl_secs: u64,
+    pub allowed_addresses: Option<HashSet<String>>, // 允许写入的地址 (小写)，为 None 时任何登录地址都可写入
+    pub admin_addresses: HashSet<String>,           // 管理员地址 (小写)，可访问 /api/admin/* 并变更召回状态，同时允许写入
+}
+
+// 逗号分隔的地址列表，统一为小写
+fn address_list(value: &str) -> HashSet<String> {
+    value.split(',').map(|a| a.trim().to_lowercase()).filter(|a| !a.is_empty()).collect()
+}
+
+impl AuthConfig {
+    pub fn from_env() -> Self {
+        let domains = env::var("SIWE_DOMAINS").unwrap_or_else(|_| "localhost:5173,127.0.0.1:5173".to_string());
+        let admin_addresses = address_list(&env::var("AUTH_ADMIN_ADDRESSES").unwrap_or_default());
+        if admin_addresses.is_empty() {
+            warn!("未配置 AUTH_ADMIN_ADDRESSES，管理接口与召回状态变更只能在 AUTH_REQUIRED=false 时匿名调用。");
+        }
+        AuthConfig {
+            required: env_or("AUTH_REQUIRED", true),
+            domains: domains.split(',').map(|d| d.trim().to_string()).filter(|d| !d.is_empty()).collect(),
+            nonce_ttl_secs: env_or("SIWE_NONCE_TTL_SECS", 600u64).max(1),
+            session_ttl_secs: env_or("SESSION_TTL_SECS", 86400u64).max(1),
+            allowed_addresses: env::var("AUTH_ALLOWED_ADDRESSES").ok().map(|value| address_list(&value)),
+            admin_addresses,
+        }
+    }
+
+    pub fn is_admin(&self, address: &str) -> bool {
+        self.admin_addresses.contains(&address.to_lowercase())
+    }
+
+    pub fn may_write(&self, address: &str) -> bool {
+        self.is_admin(address)
+            || self.allowed_addresses.as_ref().is_none_or(|allowed| allowed.contains(&address.to_lowercase()))
+    }
+}
+
+// 随机十六进制字符串 (不带 0x)，用作 SIWE nonce 与会话令牌
+pub fn random_hex(len_bytes: usize) -> String {
+    let mut bytes = vec![0u8; len_bytes];
+    OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+// 数据库只保存令牌的 keccak256
+pub fn hash_token(token: &str) -> String {
+    to_hex_string(&keccak256(token.as_bytes()))
+}
+
+fn bearer_token(req: &HttpRequest) -> Option<String> {
+    req.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty())
+}
+
+// 校验请求携带的会话令牌，未携带令牌且 AUTH_REQUIRED=false 时为 None
+async fn request_session(
+    app_state: Option<web::Data<AppState>>,
+    token: Option<String>,
+) -> Result<(web::Data<AppState>, Option<AuthSession>), AppError> {
+    let app_state = app_state.ok_or_else(|| AppError::InternalError("未配置应用状态。".to_string()))?;
+    let session = match token {
+        Some(token) => Some(
+            db::get_auth_session_db(&app_state.db_pool, &hash_token(&token))
+                .await?
+                .ok_or_else(|| AppError::Unauthorized("会话不存在或已过期，请重新登录。".to_string()))?,
+        ),
+        None if app_state.auth.required => {
+            return Err(AppError::Unauthorized("请先使用钱包登录 (Sign-In With Ethereum)。".to_string()));
+        }
+        None => None,
+    };
+    Ok((app_state, session))
+}
+
+// 写接口的访问凭证：登录地址须在 AUTH_ALLOWED_ADDRESSES (或管理员) 中；只有 AUTH_REQUIRED=false 且未携带令牌时为 None
+pub struct WriteAccess(pub Option<AuthSession>);
+
+impl WriteAccess {
+    // 审计日志中的操作者：登录地址，未登录时为 anonymous
+    pub fn actor(&self) -> &str {
+        self.0.as_ref().map(|session| session.address.as_str()).unwrap_or(audit::ACTOR_ANONYMOUS)
+    }
+
+    pub fn is_admin(&self, auth: &AuthConfig) -> bool {
+        self.0.as_ref().is_some_and(|session| auth.is_admin(&session.address))
+    }
+
+    // 记录的所有者：EIP-712 签名者，未签名的记录为创建时的登录地址；action 用于错误信息，如 "披露该记录的字段"
+    pub fn require_record_owner(&self, recorder: Option<&str>, created_by: Option<&str>, action: &str) -> Result<(), AppError> {
+        let address = self.0.as_ref().map(|session| session.address.as_str());
+        match (recorder, created_by, address) {
+            (Some(recorder), _, Some(address)) if recorder == address => Ok(()),
+            (None, Some(creator), Some(address)) if creator == address => Ok(()),
+            (Some(recorder), _, _) => Err(AppError::Forbidden(format!("只有记录签名者 {} 可以{}。", recorder, action))),
+            (None, Some(creator), _) => Err(AppError::Forbidden(format!("只有记录创建者 {} 可以{}。", creator, action))),
+            (None, None, _) => Err(AppError::Forbidden(format!(
+                "该记录没有签名者，也不是由登录用户创建的 (匿名创建)，无法确认所有者，不能{}。", action
+            ))),
+        }
+    }
+}
+
+impl FromRequest for WriteAccess {
+    type Error = AppError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let app_state = req.app_data::<web::Data<AppState>>().cloned();
+        let token = bearer_token(req);
+        Box::pin(async move {
+            let (app_state, session) = request_session(app_state, token).await?;
+            if let Some(session) = &session {
+                if !app_state.auth.may_write(&session.address) {
+                    return Err(AppError::Forbidden(format!("地址 {} 不在允许写入的地址列表中。", session.address)));
+                }
+            }
+            Ok(WriteAccess(session))
+        })
+    }
+}
+
+// 管理接口的访问凭证：登录地址须在 AUTH_ADMIN_ADDRESSES 中；只有 AUTH_REQUIRED=false 且未携带令牌时为 None
+pub struct AdminAccess(pub Option<AuthSession>);
+
+impl AdminAccess {
+    pub fn actor(&self) -> &str {
+        self.0.as_ref().map(|session| session.address.as_str()).unwrap_or(audit::ACTOR_ANONYMOUS)
+    }
+}
+
+impl FromRequest for AdminAccess {
+    type Error = AppError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let app_state = req.app_data::<web::Data<AppState>>().cloned();
+        let token = bearer_token(req);
+        Box::pin(async move {
+            let (app_state, session) = request_session(app_state, token).await?;
+            if let Some(session) = &session {
+                if !app_state.auth.is_admin(&session.address) {
+                    return Err(AppError::Forbidden(format!("地址 {} 不是管理员。", session.address)));
+                }
+            }
+            Ok(AdminAccess(session))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADMIN: &str = "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266";
+    const WRITER: &str = "0x70997970c51812dc3a010c7d01b50e0d17dc79c8";
+    const OTHER: &str = "0x3c44cdddb6a900fa2b585dd299e03d12fa4293bc";
+
+    fn config(allowed: Option<&str>, admins: &str) -> AuthConfig {
+        AuthConfig {
+            required: true,
+            domains: Vec::new(),
+            nonce_ttl_secs: 600,
+            session_ttl_secs: 86400,
+            allowed_addresses: allowed.map(address_list),
+            admin_addresses: address_list(admins),
+        }
+    }
+
+    #[test]
+    fn allow_list_restricts_writers() {
+        let config = config(Some(&format!(" {} ,", WRITER.to_uppercase().replace("0X", "0x"))), ADMIN);
+        assert!(config.may_write(WRITER));
+        assert!(config.may_write(ADMIN)); // 管理员同时允许写入
+        assert!(!config.may_write(OTHER));
+        assert!(config.is_admin(&ADMIN.to_uppercase().replace("0X", "0x")));
+        assert!(!config.is_admin(WRITER));
+    }
+
+    #[test]
+    fn without_allow_list_any_signed_in_address_may_write() {
+        let config = config(None, "");
+        assert!(config.may_write(OTHER));
+        assert!(!config.is_admin(OTHER));
+    }
+}
@@ -0,0 +1,134 @@
+// 认证证书登记与有效性判断
+// 记录详情按元数据中的 productionDate 判断关联的每张证书在生产当天是否处于有效期内
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use crate::contract;
+use crate::errors::AppError;
+use crate::hashing::normalize_hash;
+use crate::models::{Certificate, CertificateRequest, CertificateValidity, CertificationStatus, MetadataField};
+use crate::validation::{check_text, MAX_TEXT_LEN};
+
+pub const DEFAULT_EXPIRING_DAYS: u32 = 30;
+pub const MAX_EXPIRING_DAYS: u32 = 3650;
+
+const MAX_SCOPE_LEN: usize = 4000;
+const MAX_LINKS: usize = 1000;
+const PRODUCTION_DATE_FIELD: &str = "productionDate";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CertificateType {
+    #[serde(rename = "organic")]
+    Organic,
+    #[serde(rename = "haccp")]
+    Haccp,
+    #[serde(rename = "iso_22000")]
+    Iso22000,
+    #[serde(rename = "gap")]
+    Gap, // 良好农业规范
+    #[serde(rename = "halal")]
+    Halal,
+}
+
+impl CertificateType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CertificateType::Organic => "organic",
+            CertificateType::Haccp => "haccp",
+            CertificateType::Iso22000 => "iso_22000",
+            CertificateType::Gap => "gap",
+            CertificateType::Halal => "halal",
+        }
+    }
+}
+
+// 去除空白并去重
+pub fn normalize_list(name: &str, values: &[String]) -> Result<Vec<String>, AppError> {
+    let mut values: Vec<String> = values.iter().map(|value| value.trim().to_string()).collect();
+    values.sort();
+    values.dedup();
+    if values.len() > MAX_LINKS {
+        return Err(AppError::InvalidInput(format!("{} 不能超过 {} 项。", name, MAX_LINKS)));
+    }
+    if let Some(value) = values.iter().find(|value| value.is_empty() || value.len() > MAX_TEXT_LEN) {
+        return Err(AppError::InvalidInput(format!("{} 中的 '{}' 为空或超过 {} 个字符。", name, value, MAX_TEXT_LEN)));
+    }
+    Ok(values)
+}
+
+// 校验请求，返回规范化的证书文件哈希
+pub fn validate_request(request: &CertificateRequest) -> Result<String, AppError> {
+    check_text("issuer", &request.issuer, MAX_TEXT_LEN)?;
+    check_text("certificateNumber", &request.certificate_number, MAX_TEXT_LEN)?;
+    check_text("scope", &request.scope, MAX_SCOPE_LEN)?;
+    if request.valid_until < request.valid_from {
+        return Err(AppError::InvalidInput("validUntil 不能早于 validFrom。".to_string()));
+    }
+    contract::parse_bytes32(&request.document_hash)
+        .map_err(|_| AppError::InvalidInput("documentHash 必须是 32 字节十六进制哈希。".to_string()))?;
+    Ok(normalize_hash(&request.document_hash))
+}
+
+// 元数据中的生产日期，接受 YYYY-MM-DD 或以其开头的时间 (如 RFC 3339)；
+// salted-fields-v1 记录只使用公开字段，生产日期未公开时视为未知，不能从证书有效性反推出来
+pub fn production_date(metadata: &JsonValue, fields: &[MetadataField]) -> Option<NaiveDate> {
+    let value = match fields.iter().find(|field| field.field_name == PRODUCTION_DATE_FIELD) {
+        Some(field) if !field.is_public => return None,
+        Some(field) => serde_json::from_str(&field.value_canonical).ok()?,
+        None => metadata.get(PRODUCTION_DATE_FIELD)?.clone(),
+    };
+    let text = value.as_str()?.trim();
+    NaiveDate::parse_from_str(text.get(..10)?, "%Y-%m-%d").ok()
+}
+
+// 每张证书在生产当天是否有效；生产日期未知时无法判断，没有关联证书时也不给出结论
+pub fn evaluate(production_date: Option<NaiveDate>, certificates: Vec<Certificate>) -> CertificationStatus {
+    let certificates: Vec<CertificateValidity> = certificates
+        .into_iter()
+        .map(|certificate| CertificateValidity {
+            valid_on_production_date: production_date
+                .map(|date| certificate.valid_from <= date && date <= certificate.valid_until),
+            certificate,
+        })
+        .collect();
+    let all_valid_on_production_date = match production_date {
+        Some(_) if !certificates.is_empty() => {
+            Some(certificates.iter().all(|c| c.valid_on_production_date == Some(true)))
+        }
+        _ => None,
+    };
+    CertificationStatus { production_date, all_valid_on_production_date, certificates }
+}
+
+pub fn clamp_days(days: Option<u32>) -> u32 {
+    days.unwrap_or(DEFAULT_EXPIRING_DAYS).min(MAX_EXPIRING_DAYS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn field(value_canonical: &str, is_public: bool) -> MetadataField {
+        MetadataField {
+            field_name: PRODUCTION_DATE_FIELD.to_string(),
+            value_canonical: value_canonical.to_string(),
+            salt: String::new(),
+            commitment: String::new(),
+            is_public,
+        }
+    }
+
+    #[test]
+    fn private_production_date_is_unknown() {
+        let metadata = json!({ "productionDate": "2026-03-01" });
+        assert_eq!(production_date(&metadata, &[field("\"2026-03-01\"", false)]), None);
+    }
+
+    #[test]
+    fn reads_public_production_date() {
+        let expected = NaiveDate::from_ymd_opt(2026, 3, 1);
+        assert_eq!(production_date(&json!({}), &[field("\"2026-03-01T08:00:00Z\"", true)]), expected);
+        assert_eq!(production_date(&json!({ "productionDate": "2026-03-01" }), &[]), expected);
+    }
+}
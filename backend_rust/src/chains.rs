@@ -0,0 +1,165 @@
+// 链注册表：后端支持的链及其 RPC 节点、FoodTraceability 合约地址、部署区块与确认数
+// 通过 CHAINS_CONFIG 指定 JSON 配置文件 (见 chains.example.json)，本地 Hardhat、测试网和联盟链可以同时存在；
+// 未配置时按 ETH_RPC_URL / CHAIN_ID / CONTRACT_ADDRESS / INDEXER_START_BLOCK / INDEXER_CONFIRMATIONS 生成单链配置
+// 没有指定合约地址的链从部署脚本写入的部署清单 (DEPLOYMENTS_DIR/<chainId>.json) 读取地址和部署区块
+use log::{info, warn};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::path::Path;
+use crate::artifacts;
+use crate::config::env_or;
+use crate::errors::AppError;
+use crate::eth_rpc::EthClient;
+
+// 既没有配置也没有部署清单时使用 Hardhat 本地网络上的首次部署地址
+const HARDHAT_FIRST_DEPLOYMENT_ADDRESS: &str = "0x5fbdb2315678afecb367f032d93f642f64180aa3";
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainConfig {
+    pub name: String,
+    pub chain_id: u64,
+    pub rpc_url: String,
+    #[serde(default)]
+    pub contract_address: String, // 为空时从部署清单读取
+    #[serde(default)]
+    pub deploy_block: Option<u64>, // 合约部署区块，索引器从这里开始扫描；未配置时取部署清单，否则为 0
+    #[serde(default = "default_confirmations")]
+    pub confirmations: u64,
+}
+
+fn default_confirmations() -> u64 {
+    2
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct RegistryFile {
+    default_chain_id: Option<u64>, // 未指定时使用第一条链
+    chains: Vec<ChainConfig>,
+}
+
+// 注册表中的一条链及其 RPC 客户端
+#[derive(Clone)]
+pub struct Chain {
+    pub config: ChainConfig,
+    pub eth_client: EthClient,
+}
+
+impl Chain {
+    pub fn chain_id(&self) -> u64 {
+        self.config.chain_id
+    }
+
+    pub fn contract_address(&self) -> &str {
+        &self.config.contract_address
+    }
+
+    pub fn deploy_block(&self) -> u64 {
+        self.config.deploy_block.unwrap_or(0)
+    }
+}
+
+pub struct ChainRegistry {
+    chains: Vec<Chain>,
+    default_chain_id: u64,
+}
+
+impl ChainRegistry {
+    pub fn new(mut configs: Vec<ChainConfig>, default_chain_id: Option<u64>, deployments_dir: &Path) -> Result<Self, AppError> {
+        let Some(first) = configs.first() else {
+            return Err(AppError::InvalidInput("链配置为空。".to_string()));
+        };
+        let default_chain_id = default_chain_id.unwrap_or(first.chain_id);
+        let mut seen = HashSet::new();
+        for config in configs.iter_mut() {
+            if !seen.insert(config.chain_id) {
+                return Err(AppError::InvalidInput(format!("链 ID {} 重复配置。", config.chain_id)));
+            }
+            apply_deployment(config, deployments_dir)?;
+            config.contract_address = config.contract_address.to_lowercase();
+        }
+        let chains: Vec<Chain> = configs
+            .into_iter()
+            .map(|config| Chain { eth_client: EthClient::new(&config.rpc_url), config })
+            .collect();
+        if !chains.iter().any(|chain| chain.chain_id() == default_chain_id) {
+            return Err(AppError::InvalidInput(format!("默认链 ID {} 不在链配置中。", default_chain_id)));
+        }
+        Ok(ChainRegistry { chains, default_chain_id })
+    }
+
+    pub fn from_env() -> Result<Self, AppError> {
+        if let Ok(path) = env::var("CHAINS_CONFIG") {
+            let content = fs::read_to_string(&path)
+                .map_err(|e| AppError::InvalidInput(format!("无法读取链配置文件 {}: {}", path, e)))?;
+            let file: RegistryFile = serde_json::from_str(&content)
+                .map_err(|e| AppError::InvalidInput(format!("链配置文件 {} 格式错误: {}", path, e)))?;
+            return Self::new(file.chains, file.default_chain_id, &artifacts::deployments_dir());
+        }
+
+        // 默认值对应 Hardhat 本地网络
+        let parse_u64 = |name: &str| env::var(name).ok().and_then(|v| v.parse().ok());
+        let config = ChainConfig {
+            name: env_or("CHAIN_NAME", "hardhat".to_string()),
+            chain_id: parse_u64("CHAIN_ID").unwrap_or(1337),
+            rpc_url: env_or("ETH_RPC_URL", "http://127.0.0.1:8545".to_string()),
+            contract_address: env_or("CONTRACT_ADDRESS", String::new()),
+            deploy_block: parse_u64("INDEXER_START_BLOCK"),
+            confirmations: parse_u64("INDEXER_CONFIRMATIONS").unwrap_or(default_confirmations()),
+        };
+        Self::new(vec![config], None, &artifacts::deployments_dir())
+    }
+
+    pub fn default_chain(&self) -> &Chain {
+        self.get(self.default_chain_id).expect("默认链在构造时已校验")
+    }
+
+    pub fn get(&self, chain_id: u64) -> Option<&Chain> {
+        self.chains.iter().find(|chain| chain.chain_id() == chain_id)
+    }
+
+    // 按链 ID 查找，未指定时使用默认链
+    pub fn resolve(&self, chain_id: Option<u64>) -> Result<&Chain, AppError> {
+        match chain_id {
+            Some(chain_id) => self
+                .get(chain_id)
+                .ok_or_else(|| AppError::InvalidInput(format!("未配置链 ID {}。", chain_id))),
+            None => Ok(self.default_chain()),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Chain> {
+        self.chains.iter()
+    }
+}
+
+// 用部署清单补全未配置的合约地址与部署区块；显式配置的地址与清单不一致时以配置为准
+fn apply_deployment(config: &mut ChainConfig, deployments_dir: &Path) -> Result<(), AppError> {
+    let Some(manifest) = artifacts::load_deployment(deployments_dir, config.chain_id)? else {
+        if config.contract_address.is_empty() {
+            warn!(
+                "链 {} 未配置合约地址且没有部署清单，使用 Hardhat 首次部署地址 {}",
+                config.chain_id, HARDHAT_FIRST_DEPLOYMENT_ADDRESS
+            );
+            config.contract_address = HARDHAT_FIRST_DEPLOYMENT_ADDRESS.to_string();
+        }
+        return Ok(());
+    };
+    if config.contract_address.is_empty() {
+        info!(
+            "链 {} 的合约地址取自部署清单 ({}): {}，部署交易 {}",
+            config.chain_id, manifest.network, manifest.address, manifest.transaction_hash
+        );
+        config.contract_address = manifest.address;
+        config.deploy_block.get_or_insert(manifest.deploy_block);
+    } else if !config.contract_address.eq_ignore_ascii_case(&manifest.address) {
+        warn!(
+            "链 {} 配置的合约地址 {} 与部署清单中的 {} 不一致，使用配置的地址。",
+            config.chain_id, config.contract_address, manifest.address
+        );
+    }
+    Ok(())
+}
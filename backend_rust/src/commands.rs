@@ -0,0 +1,126 @@
+// 后端管理命令：`backend_rust <command> [args...]`
+// 与 HTTP 服务共用数据库连接和链配置，执行完成后退出进程
+use std::env;
+use crate::artifacts;
+use crate::audit::{self, NewAuditEntry};
+use crate::db;
+use crate::deploy;
+use crate::errors::AppError;
+use crate::models::AppState;
+use crate::reanchor;
+use crate::signer::LocalSigner;
+
+pub const USAGE: &str = "\
+用法: backend_rust [command]
+  (无参数)                                      启动 HTTP 服务
+  deploy [--chain <chain_id>]                   用 Hardhat 编译产物部署 FoodTraceability，并写入部署清单
+  reanchor <new_contract_address> [--chain <chain_id>] [--from <account>]
+                                                把所有记录重新提交到新部署的合约 (可中断续跑)，默认为默认链
+  verify-audit-log                              校验审计日志哈希链与链上锚定的链头，核对产品记录";
+
+// 取出 `--name value` 形式的可选参数
+fn option_value(args: &[String], name: &str) -> Option<String> {
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|i| args.get(i + 1))
+        .cloned()
+}
+
+fn chain_option(args: &[String]) -> Result<Option<u64>, AppError> {
+    option_value(args, "--chain")
+        .map(|v| v.parse::<u64>().map_err(|_| AppError::InvalidInput(format!("无效的链 ID '{}'。", v))))
+        .transpose()
+}
+
+pub async fn run_command(
+    command: &str,
+    args: &[String],
+    app_state: &AppState,
+) -> Result<(), AppError> {
+    match command {
+        "deploy" => {
+            let chain = app_state.chains.resolve(chain_option(args)?)?;
+            let artifact = app_state.contract_artifact.as_ref().ok_or_else(|| {
+                AppError::InvalidInput("未找到合约编译产物，请先在 blockchain_hardhat 中运行 npx hardhat compile。".to_string())
+            })?;
+            // 部署账户：DEPLOYER_PRIVATE_KEY，未配置时使用托管签名账户 SIGNER_PRIVATE_KEY
+            let deployer = match env::var("DEPLOYER_PRIVATE_KEY") {
+                Ok(key) if !key.trim().is_empty() => Some(LocalSigner::from_private_key(&key)?),
+                _ => None,
+            };
+            let signer = deployer.as_ref().or(app_state.signer.as_ref()).ok_or_else(|| {
+                AppError::InvalidInput("未配置部署账户私钥 (DEPLOYER_PRIVATE_KEY 或 SIGNER_PRIVATE_KEY)。".to_string())
+            })?;
+            let (manifest, path) = deploy::run_deploy(chain, signer, artifact, &artifacts::deployments_dir()).await?;
+            db::insert_audit_log_db(&app_state.db_pool, &NewAuditEntry::new(
+                audit::ACTOR_CLI, "contract.deploy", Some(&manifest.address), serde_json::to_value(&manifest)?,
+            )).await?;
+            println!(
+                "合约 {} 已部署到链 {}: {} (区块 {}，交易 {})",
+                manifest.contract_name, manifest.chain_id, manifest.address, manifest.deploy_block, manifest.transaction_hash
+            );
+            println!("部署清单已写入 {}，重启服务后生效。", path.display());
+            println!(
+                "如果 CHAINS_CONFIG 或 CONTRACT_ADDRESS 显式指定了该链的合约地址，请删除或改为新地址；已有记录可运行 `reanchor {} --chain {}` 重新上链。",
+                manifest.address, manifest.chain_id
+            );
+            Ok(())
+        }
+        "reanchor" => {
+            let target_contract = args
+                .first()
+                .filter(|arg| !arg.starts_with("--"))
+                .ok_or_else(|| AppError::InvalidInput(format!("缺少新合约地址。\n{}", USAGE)))?;
+            let chain = app_state.chains.resolve(chain_option(args)?)?;
+            let summary = reanchor::run_reanchor(
+                &app_state.db_pool,
+                &chain.eth_client,
+                chain.chain_id(),
+                target_contract,
+                option_value(args, "--from"),
+            ).await?;
+            println!(
+                "重新上链完成: 共 {} 条，成功 {} 条，等待回执 {} 条，失败 {} 条。再次运行本命令会复查等待中的交易并重试失败的记录。",
+                summary.total, summary.confirmed, summary.pending, summary.failed
+            );
+            println!(
+                "确认无误后将链 {} 的合约地址 (CHAINS_CONFIG 或 CONTRACT_ADDRESS) 设置为 {} 并重启服务。",
+                chain.chain_id(), target_contract.to_lowercase()
+            );
+            Ok(())
+        }
+        "verify-audit-log" => {
+            let verification = audit::verify_audit_chain(app_state).await?;
+            if let Some(error) = &verification.anchor_check_error {
+                println!("未能核对链上锚定的链头: {}", error);
+            }
+            match verification.first_broken {
+                None if verification.record_mismatch_count > 0 => {
+                    for mismatch in &verification.record_mismatches {
+                        println!("  {}: {}", mismatch.product_id, mismatch.reason);
+                    }
+                    Err(AppError::Conflict(format!(
+                        "审计日志完整，但有 {} 条记录与审计记录不一致。", verification.record_mismatch_count
+                    )))
+                }
+                None => {
+                    println!(
+                        "审计日志完整: 共 {} 条记录，链头 #{} ({})；链上核实 {} 次链头锚定；核对 {} 条产品记录，{} 条早于审计日志启用。",
+                        verification.verified_entries, verification.head_entry_id, verification.head_hash,
+                        verification.anchors_verified, verification.records_checked, verification.unaudited_records
+                    );
+                    Ok(())
+                }
+                Some(broken) => Err(AppError::Conflict(format!(
+                    "审计日志在 #{} 处断链 (之前 {} 条记录校验通过): {}",
+                    broken.entry_id, verification.verified_entries, broken.reason
+                ))),
+            }
+        }
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => Err(AppError::InvalidInput(format!("未知命令 '{}'。\n{}", command, USAGE))),
+    }
+}
@@ -0,0 +1,7 @@
+// 环境变量配置的公共读取函数
+use std::env;
+
+// 未设置或无法解析时使用默认值
+pub fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+    env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+}
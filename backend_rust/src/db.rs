@@ -1,31 +1,207 @@
-use sqlx::MySqlPool;
+use sqlx::{MySql, MySqlConnection, MySqlPool, Transaction};
 use crate::models::{
     FoodRecordRequest, FoodListItem, RawFoodListItem, FoodRecordDetail,
-    PaginatedFoodListResponse, PaginationParams
+    PaginatedFoodListResponse, PaginationParams, NewChainEvent, IndexerCursor,
+    ReconciliationDbRecord, IndexedChainEvent, ReconciliationRun, ReconciliationFinding,
+    NewReconciliationFinding, ReanchorCandidate, RecordAnchor, NewRecordAnchor, AnchorOutboxEntry,
+    MerkleBatch, MerkleLeafRow, MerkleLeafDetail, RecordSignature, AuthSession, AuditLogEntry,
+    MetadataField, DisclosureGrant, NewTraceEvent, TraceEventRow, LineageLinkRequest, LineageLink, LineageWalkEdge,
+    Recall, RecallProduct, ProductRecall, RecallRequest, Certificate, CertificateRequest,
+    LabResult, LabResultInput, AuditedRecordRow, AuditHeadAnchor
 };
 use crate::errors::AppError; // 引入自定义错误
-use serde_json::Value as JsonValue;
+use crate::hashing::CanonicalMetadata;
+use crate::audit::{self, NewAuditEntry};
+use crate::trace_events;
+use crate::lineage::{self, LineageDirection};
+use crate::lab_results;
+use crate::recall::{self, RecallStatus};
+use crate::signer::SentTransaction;
+use serde_json::{json, Value as JsonValue};
 
 pub async fn create_food_record_db(
     pool: &MySqlPool,
+    actor: &str,
     record_data: &FoodRecordRequest,
+    canonical_metadata: &CanonicalMetadata, // 服务端重新计算并校验过的规范化元数据及哈希，而不是直接信任客户端提交的值
+    record_signature: Option<&RecordSignature>,
+    anchor: &NewRecordAnchor, // 已通过回执校验的上链信息
 ) -> Result<u64, AppError> { // 返回 AppError
-    let metadata_string = serde_json::to_string(&record_data.metadata)?; // '?' 会自动调用 From<serde_json::Error>
+    let mut tx = pool.begin().await?;
+    let rows_affected = insert_food_record_db(
+        &mut tx, actor, record_data, canonical_metadata, record_signature, anchor.chain_id, &anchor.contract_address,
+        Some(&anchor.transaction_hash),
+    ).await?;
+    insert_record_anchor_db(&mut tx, anchor).await?;
+    tx.commit().await?;
+    Ok(rows_affected)
+}
+
+// 后端托管上链：记录与发件箱条目在同一事务中写入，交易由后台任务提交，返回发件箱条目 id
+pub async fn create_food_record_with_outbox_db(
+    pool: &MySqlPool,
+    actor: &str,
+    record_data: &FoodRecordRequest,
+    canonical_metadata: &CanonicalMetadata,
+    record_signature: Option<&RecordSignature>,
+    chain_id: u64,
+    contract_address: &str,
+) -> Result<u64, AppError> {
+    let mut tx = pool.begin().await?;
+    insert_food_record_db(&mut tx, actor, record_data, canonical_metadata, record_signature, chain_id, contract_address, None).await?;
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO anchor_outbox (product_id, chain_id, contract_address, metadata_hash, status)
+        VALUES (?, ?, ?, ?, 'pending')
+        "#,
+        record_data.product_id, chain_id, contract_address, canonical_metadata.hash
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(result.last_insert_id())
+}
+
+// Merkle 批量上链：记录与叶子在同一事务中写入当前未满的 open 批次 (没有时新建)，返回批次 id
+pub async fn create_food_record_in_merkle_batch_db(
+    pool: &MySqlPool,
+    actor: &str,
+    record_data: &FoodRecordRequest,
+    canonical_metadata: &CanonicalMetadata,
+    record_signature: Option<&RecordSignature>,
+    leaf_hash: &str,
+    chain_id: u64,
+    contract_address: &str,
+    max_leaves: u32,
+) -> Result<u64, AppError> {
+    let mut tx = pool.begin().await?;
+    insert_food_record_db(&mut tx, actor, record_data, canonical_metadata, record_signature, chain_id, contract_address, None).await?;
+    // FOR UPDATE 与封存批次互斥，保证叶子不会加入正在封存的批次
+    let open_batch = sqlx::query_scalar!(
+        r#"
+        SELECT id FROM merkle_batches
+        WHERE status = 'open' AND chain_id = ? AND contract_address = ? AND leaf_count < ?
+        ORDER BY id LIMIT 1 FOR UPDATE
+        "#,
+        chain_id, contract_address, max_leaves
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    let batch_id = match open_batch {
+        Some(id) => id,
+        None => sqlx::query!(
+            r#"INSERT INTO merkle_batches (chain_id, contract_address, status) VALUES (?, ?, 'open')"#,
+            chain_id, contract_address
+        )
+        .execute(&mut *tx)
+        .await?
+        .last_insert_id(),
+    };
+    sqlx::query!(
+        r#"INSERT INTO merkle_leaves (product_id, batch_id, leaf_hash) VALUES (?, ?, ?)"#,
+        record_data.product_id, batch_id, leaf_hash
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(r#"UPDATE merkle_batches SET leaf_count = leaf_count + 1 WHERE id = ?"#, batch_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(batch_id)
+}
 
+async fn insert_food_record_db(
+    tx: &mut Transaction<'_, MySql>,
+    actor: &str,
+    record_data: &FoodRecordRequest,
+    canonical_metadata: &CanonicalMetadata,
+    record_signature: Option<&RecordSignature>,
+    chain_id: u64,
+    contract_address: &str,
+    transaction_hash: Option<&str>,
+) -> Result<u64, AppError> {
+    // metadata_json (JSON 列) 便于查询，metadata_canonical 保存参与哈希的原始规范化字节
     let result = sqlx::query!(
         r#"
-        INSERT INTO traceability_data (product_id, metadata_json, onchain_metadata_hash, blockchain_transaction_hash) VALUES (?, ?, ?, ?)
+        INSERT INTO traceability_data
+            (product_id, metadata_json, metadata_canonical, onchain_metadata_hash, hash_scheme, blockchain_transaction_hash,
+             chain_id, contract_address, recorder, recorder_signature, created_by)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         "#,
         record_data.product_id,
-        metadata_string,
-        record_data.metadata_hash_on_chain,
-        record_data.transaction_hash
+        canonical_metadata.canonical_json,
+        canonical_metadata.canonical_json,
+        canonical_metadata.hash,
+        canonical_metadata.scheme.as_str(),
+        transaction_hash,
+        chain_id,
+        contract_address,
+        record_signature.map(|s| s.recorder.as_str()),
+        record_signature.map(|s| s.signature.as_str()),
+        (actor != audit::ACTOR_ANONYMOUS).then_some(actor)
     )
-    .execute(pool)
+    .execute(&mut **tx)
     .await?; // '?' 会自动调用 From<SqlxError>
+    for field in &canonical_metadata.fields {
+        sqlx::query!(
+            r#"
+            INSERT INTO metadata_fields (product_id, field_name, value_canonical, salt, commitment, is_public)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+            record_data.product_id, field.field_name, field.value_canonical, field.salt, field.commitment, field.is_public
+        )
+        .execute(&mut **tx)
+        .await?;
+    }
+    append_audit_log_db(tx, &NewAuditEntry::new(actor, "record.create", Some(&record_data.product_id), json!({
+        "productId": record_data.product_id,
+        "metadataHash": canonical_metadata.hash,
+        "hashScheme": canonical_metadata.scheme.as_str(),
+        "chainId": chain_id,
+        "contractAddress": contract_address,
+        "transactionHash": transaction_hash,
+        "recorder": record_signature.map(|s| s.recorder.as_str()),
+    }))).await?;
     Ok(result.rows_affected())
 }
 
+pub async fn insert_record_anchor_db(
+    tx: &mut Transaction<'_, MySql>,
+    anchor: &NewRecordAnchor,
+) -> Result<u64, AppError> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO record_anchors (product_id, chain_id, contract_address, transaction_hash, block_number, block_timestamp, status)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
+        "#,
+        anchor.product_id, anchor.chain_id, anchor.contract_address, anchor.transaction_hash,
+        anchor.block_number, anchor.block_timestamp, anchor.status
+    )
+    .execute(&mut **tx)
+    .await?;
+    Ok(result.last_insert_id())
+}
+
+// 记录的全部上链历史，按时间先后排列
+pub async fn list_record_anchors_db(
+    pool: &MySqlPool,
+    product_id: &str,
+) -> Result<Vec<RecordAnchor>, AppError> {
+    let anchors = sqlx::query_as!(
+        RecordAnchor,
+        r#"
+        SELECT id, chain_id, contract_address, transaction_hash, block_number,
+               block_timestamp as "block_timestamp: chrono::DateTime<chrono::Utc>",
+               status, anchored_at as "anchored_at!: chrono::DateTime<chrono::Utc>"
+        FROM record_anchors WHERE product_id = ? ORDER BY id
+        "#,
+        product_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(anchors)
+}
+
 
 // 示例：获取食品列表的数据库逻辑
 pub async fn get_food_records_list_db(
@@ -51,9 +227,18 @@ pub async fn get_food_records_list_db(
     let raw_records = sqlx::query_as!(
         RawFoodListItem,
         r#"
-        SELECT product_id as "product_id!", CAST(metadata_json AS CHAR) as "metadata_json!",
-               onchain_metadata_hash as "onchain_metadata_hash!", created_at as "created_at!: chrono::DateTime<chrono::Utc>"
-        FROM traceability_data ORDER BY created_at DESC LIMIT ? OFFSET ?
+        SELECT t.product_id as "product_id!", CAST(t.metadata_json AS CHAR) as "metadata_json!",
+               t.onchain_metadata_hash as "onchain_metadata_hash!", t.created_at as "created_at!: chrono::DateTime<chrono::Utc>",
+               a.id as "anchor_id?", a.chain_id as "anchor_chain_id?", a.contract_address as "anchor_contract_address?",
+               a.transaction_hash as "anchor_transaction_hash?", a.block_number as "anchor_block_number?",
+               a.block_timestamp as "anchor_block_timestamp?: chrono::DateTime<chrono::Utc>",
+               a.status as "anchor_status?", a.anchored_at as "anchor_anchored_at?: chrono::DateTime<chrono::Utc>",
+               (SELECT r.status FROM recall_products rp JOIN recalls r ON r.id = rp.recall_id
+                WHERE rp.product_id = t.product_id
+                ORDER BY FIELD(r.status, 'open', 'in_progress', 'closed') LIMIT 1) as "recall_status?: String"
+        FROM traceability_data t
+        LEFT JOIN record_anchors a ON a.id = (SELECT MAX(a2.id) FROM record_anchors a2 WHERE a2.product_id = t.product_id)
+        ORDER BY t.created_at DESC LIMIT ? OFFSET ?
         "#,
         page_size, offset
     )
@@ -65,9 +250,21 @@ pub async fn get_food_records_list_db(
         let product_name = serde_json::from_str::<JsonValue>(&raw_record.metadata_json)
             .ok() // Convert Result to Option
             .and_then(|val| val.get("productName").and_then(|v| v.as_str()).map(String::from));
+        let latest_anchor = match (raw_record.anchor_id, raw_record.anchor_transaction_hash, raw_record.anchor_status, raw_record.anchor_anchored_at) {
+            (Some(id), Some(transaction_hash), Some(status), Some(anchored_at)) => Some(RecordAnchor {
+                id, transaction_hash, status, anchored_at,
+                chain_id: raw_record.anchor_chain_id,
+                contract_address: raw_record.anchor_contract_address,
+                block_number: raw_record.anchor_block_number,
+                block_timestamp: raw_record.anchor_block_timestamp,
+            }),
+            _ => None,
+        };
         food_list_items.push(FoodListItem {
             product_id: raw_record.product_id, product_name,
             onchain_metadata_hash: raw_record.onchain_metadata_hash, created_at: raw_record.created_at,
+            latest_anchor,
+            recall_status: raw_record.recall_status,
         });
     }
     let total_pages = (total_items as f64 / page_size as f64).ceil() as i64;
@@ -84,7 +281,8 @@ pub async fn get_food_record_detail_db(
     let record = sqlx::query_as!(
         FoodRecordDetail,
         r#"
-        SELECT product_id, metadata_json, onchain_metadata_hash, blockchain_transaction_hash,
+        SELECT product_id, metadata_json, metadata_canonical, onchain_metadata_hash, hash_scheme, blockchain_transaction_hash,
+               chain_id, contract_address, recorder, recorder_signature, created_by,
                created_at as "created_at!: chrono::DateTime<chrono::Utc>",
                updated_at as "updated_at!: chrono::DateTime<chrono::Utc>"
         FROM traceability_data WHERE product_id = ?
@@ -96,3 +294,1659 @@ pub async fn get_food_record_detail_db(
         .ok_or_else(|| AppError::NotFound(format!("未找到产品ID为 '{}' 的食品记录。", product_id)))?; // 如果是 None (RowNotFound)，转为 AppError::NotFound
     Ok(record)
 }
+
+// 为多链支持之前写入的记录补全链与合约 (这些记录都在当时唯一配置的链上)，返回更新的行数
+pub async fn backfill_record_chain_db(
+    pool: &MySqlPool,
+    chain_id: u64,
+    contract_address: &str,
+) -> Result<u64, AppError> {
+    let mut tx = pool.begin().await?;
+    let result = sqlx::query!(
+        r#"UPDATE traceability_data SET chain_id = ?, contract_address = ? WHERE chain_id IS NULL"#,
+        chain_id, contract_address
+    )
+    .execute(&mut *tx)
+    .await?;
+    if result.rows_affected() > 0 {
+        append_audit_log_db(&mut tx, &NewAuditEntry::new(audit::ACTOR_STARTUP, "record.backfill_chain", None, json!({
+            "chainId": chain_id,
+            "contractAddress": contract_address,
+            "recordCount": result.rows_affected(),
+        }))).await?;
+    }
+    tx.commit().await?;
+    Ok(result.rows_affected())
+}
+
+// ------------------------------ 链上事件索引 ------------------------------
+
+pub async fn get_indexer_cursor_db(
+    pool: &MySqlPool,
+    chain_id: u64,
+    contract_address: &str,
+) -> Result<Option<IndexerCursor>, AppError> {
+    let cursor = sqlx::query_as!(
+        IndexerCursor,
+        r#"SELECT last_block, last_block_hash FROM indexer_cursors WHERE chain_id = ? AND contract_address = ?"#,
+        chain_id, contract_address
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(cursor)
+}
+
+pub async fn upsert_indexer_cursor_db(
+    tx: &mut Transaction<'_, MySql>,
+    chain_id: u64,
+    contract_address: &str,
+    last_block: u64,
+    last_block_hash: &str,
+) -> Result<(), AppError> {
+    sqlx::query!(
+        r#"
+        INSERT INTO indexer_cursors (chain_id, contract_address, last_block, last_block_hash) VALUES (?, ?, ?, ?)
+        ON DUPLICATE KEY UPDATE last_block = VALUES(last_block), last_block_hash = VALUES(last_block_hash)
+        "#,
+        chain_id, contract_address, last_block, last_block_hash
+    )
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+pub async fn delete_indexer_cursor_db(
+    tx: &mut Transaction<'_, MySql>,
+    chain_id: u64,
+    contract_address: &str,
+) -> Result<(), AppError> {
+    sqlx::query!(
+        r#"DELETE FROM indexer_cursors WHERE chain_id = ? AND contract_address = ?"#,
+        chain_id, contract_address
+    )
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
+
+// 重复扫描同一区块时 (例如游标更新前进程退出) 依靠唯一键忽略已存在的事件
+pub async fn insert_chain_event_db(
+    tx: &mut Transaction<'_, MySql>,
+    event: &NewChainEvent,
+) -> Result<(), AppError> {
+    sqlx::query!(
+        r#"
+        INSERT IGNORE INTO chain_events
+            (chain_id, contract_address, block_number, block_hash, transaction_hash, log_index,
+             product_id_hash, metadata_hash, recorder, event_timestamp)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+        event.chain_id, event.contract_address, event.block_number, event.block_hash, event.transaction_hash, event.log_index,
+        event.product_id_hash, event.metadata_hash, event.recorder, event.event_timestamp
+    )
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+// 已索引事件所在的区块 (区块号, 区块哈希)，从新到旧，用于重组时回溯
+pub async fn list_indexed_event_blocks_db(
+    pool: &MySqlPool,
+    chain_id: u64,
+    contract_address: &str,
+    up_to_block: u64,
+) -> Result<Vec<(u64, String)>, AppError> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT DISTINCT block_number, block_hash FROM chain_events
+        WHERE chain_id = ? AND contract_address = ? AND block_number <= ?
+        ORDER BY block_number DESC
+        "#,
+        chain_id, contract_address, up_to_block
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(|r| (r.block_number, r.block_hash)).collect())
+}
+
+// 删除指定区块之后的所有事件 (链重组回滚)，返回删除的行数
+pub async fn delete_chain_events_after_db(
+    tx: &mut Transaction<'_, MySql>,
+    chain_id: u64,
+    contract_address: &str,
+    block_number: u64,
+) -> Result<u64, AppError> {
+    let result = sqlx::query!(
+        r#"DELETE FROM chain_events WHERE chain_id = ? AND contract_address = ? AND block_number > ?"#,
+        chain_id, contract_address, block_number
+    )
+    .execute(&mut **tx)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+// ------------------------------ 对账 ------------------------------
+
+// 已在指定链与合约上单独上链的记录；发件箱中还有未确认条目 (重新上链中) 的记录不参与对账
+pub async fn list_reconciliation_db_records_db(
+    pool: &MySqlPool,
+    chain_id: u64,
+    contract_address: &str,
+) -> Result<Vec<ReconciliationDbRecord>, AppError> {
+    let records = sqlx::query_as!(
+        ReconciliationDbRecord,
+        r#"
+        SELECT t.product_id, t.onchain_metadata_hash, t.blockchain_transaction_hash as "blockchain_transaction_hash!",
+               (SELECT MAX(a.block_number) FROM record_anchors a
+                WHERE a.product_id = t.product_id AND a.transaction_hash = t.blockchain_transaction_hash) as "anchor_block: u64"
+        FROM traceability_data t
+        WHERE t.blockchain_transaction_hash IS NOT NULL AND t.chain_id = ? AND t.contract_address = ?
+          AND NOT EXISTS (SELECT 1 FROM merkle_leaves l WHERE l.product_id = t.product_id)
+          AND NOT EXISTS (SELECT 1 FROM anchor_outbox o WHERE o.product_id = t.product_id AND o.status <> 'confirmed')
+        "#,
+        chain_id, contract_address
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(records)
+}
+
+pub async fn list_indexed_chain_events_db(
+    pool: &MySqlPool,
+    chain_id: u64,
+    contract_address: &str,
+) -> Result<Vec<IndexedChainEvent>, AppError> {
+    let events = sqlx::query_as!(
+        IndexedChainEvent,
+        r#"
+        SELECT product_id_hash, metadata_hash, transaction_hash, block_number, log_index
+        FROM chain_events WHERE chain_id = ? AND contract_address = ?
+        ORDER BY block_number, log_index
+        "#,
+        chain_id, contract_address
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(events)
+}
+
+pub async fn create_reconciliation_run_db(
+    tx: &mut Transaction<'_, MySql>,
+    chain_id: u64,
+    contract_address: &str,
+    trigger_source: &str,
+) -> Result<u64, AppError> {
+    let result = sqlx::query!(
+        r#"INSERT INTO reconciliation_runs (chain_id, contract_address, trigger_source) VALUES (?, ?, ?)"#,
+        chain_id, contract_address, trigger_source
+    )
+    .execute(&mut **tx)
+    .await?;
+    Ok(result.last_insert_id())
+}
+
+pub async fn insert_reconciliation_finding_db(
+    tx: &mut Transaction<'_, MySql>,
+    run_id: u64,
+    finding: &NewReconciliationFinding,
+) -> Result<(), AppError> {
+    sqlx::query!(
+        r#"
+        INSERT INTO reconciliation_findings
+            (run_id, finding_type, product_id, product_id_hash, db_metadata_hash, chain_metadata_hash, transaction_hash, block_number)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+        run_id, finding.finding_type, finding.product_id, finding.product_id_hash,
+        finding.db_metadata_hash, finding.chain_metadata_hash, finding.transaction_hash, finding.block_number
+    )
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+pub async fn finish_reconciliation_run_db(
+    tx: &mut Transaction<'_, MySql>,
+    run_id: u64,
+    db_record_count: u64,
+    chain_event_count: u64,
+    finding_count: u64,
+) -> Result<(), AppError> {
+    sqlx::query!(
+        r#"
+        UPDATE reconciliation_runs
+        SET db_record_count = ?, chain_event_count = ?, finding_count = ?, finished_at = CURRENT_TIMESTAMP
+        WHERE id = ?
+        "#,
+        db_record_count, chain_event_count, finding_count, run_id
+    )
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+// run_id 为空时返回指定链最近一次已完成的对账
+pub async fn get_reconciliation_run_db(
+    pool: &MySqlPool,
+    run_id: Option<u64>,
+    chain_id: u64,
+) -> Result<Option<ReconciliationRun>, AppError> {
+    let run = sqlx::query_as!(
+        ReconciliationRun,
+        r#"
+        SELECT id, chain_id, contract_address, trigger_source, db_record_count, chain_event_count, finding_count,
+               started_at as "started_at!: chrono::DateTime<chrono::Utc>",
+               finished_at as "finished_at: chrono::DateTime<chrono::Utc>"
+        FROM reconciliation_runs
+        WHERE finished_at IS NOT NULL AND (id = ? OR (? IS NULL AND chain_id = ?))
+        ORDER BY id DESC LIMIT 1
+        "#,
+        run_id, run_id, chain_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(run)
+}
+
+pub async fn list_reconciliation_findings_db(
+    pool: &MySqlPool,
+    run_id: u64,
+    finding_type: Option<&str>,
+    page_size: i64,
+    offset: i64,
+) -> Result<(i64, Vec<ReconciliationFinding>), AppError> {
+    let total_items: i64 = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) as "count!: i64" FROM reconciliation_findings
+        WHERE run_id = ? AND (? IS NULL OR finding_type = ?)
+        "#,
+        run_id, finding_type, finding_type
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let findings = sqlx::query_as!(
+        ReconciliationFinding,
+        r#"
+        SELECT id, run_id, finding_type, product_id, product_id_hash, db_metadata_hash,
+               chain_metadata_hash, transaction_hash, block_number
+        FROM reconciliation_findings
+        WHERE run_id = ? AND (? IS NULL OR finding_type = ?)
+        ORDER BY id LIMIT ? OFFSET ?
+        "#,
+        run_id, finding_type, finding_type, page_size, offset
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok((total_items, findings))
+}
+
+// ------------------------------ 重新上链 ------------------------------
+
+// 列出尚未在目标合约上确认的记录，按产品ID排序保证续跑顺序稳定
+pub async fn list_reanchor_candidates_db(
+    pool: &MySqlPool,
+    chain_id: u64,
+    target_contract: &str,
+) -> Result<Vec<ReanchorCandidate>, AppError> {
+    let candidates = sqlx::query_as!(
+        ReanchorCandidate,
+        r#"
+        SELECT t.product_id, t.onchain_metadata_hash,
+               p.status as "progress_status?", p.transaction_hash as "progress_transaction_hash?"
+        FROM traceability_data t
+        LEFT JOIN reanchor_progress p ON p.product_id = t.product_id AND p.target_contract = ?
+        WHERE t.blockchain_transaction_hash IS NOT NULL AND t.chain_id = ? AND (p.status IS NULL OR p.status <> 'confirmed')
+        ORDER BY t.product_id
+        "#,
+        target_contract, chain_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(candidates)
+}
+
+pub async fn upsert_reanchor_progress_db(
+    pool: &MySqlPool,
+    target_contract: &str,
+    product_id: &str,
+    transaction_hash: Option<&str>,
+    status: &str,
+    last_error: Option<&str>,
+) -> Result<(), AppError> {
+    sqlx::query!(
+        r#"
+        INSERT INTO reanchor_progress (target_contract, product_id, transaction_hash, status, last_error)
+        VALUES (?, ?, ?, ?, ?)
+        ON DUPLICATE KEY UPDATE transaction_hash = COALESCE(VALUES(transaction_hash), transaction_hash),
+                                status = VALUES(status), last_error = VALUES(last_error)
+        "#,
+        target_contract, product_id, transaction_hash, status, last_error
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+// 确认重新上链：记录进度、追加上链历史，并把记录的交易哈希、链与合约更新为新合约上的交易
+pub async fn confirm_reanchor_db(
+    pool: &MySqlPool,
+    anchor: &NewRecordAnchor,
+) -> Result<(), AppError> {
+    let (target_contract, product_id, transaction_hash) = (&anchor.contract_address, &anchor.product_id, &anchor.transaction_hash);
+    let mut tx = pool.begin().await?;
+    sqlx::query!(
+        r#"
+        UPDATE reanchor_progress SET status = 'confirmed', transaction_hash = ?, last_error = NULL
+        WHERE target_contract = ? AND product_id = ?
+        "#,
+        transaction_hash, target_contract, product_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        r#"
+        UPDATE traceability_data SET blockchain_transaction_hash = ?, chain_id = ?, contract_address = ?
+        WHERE product_id = ?
+        "#,
+        transaction_hash, anchor.chain_id, target_contract, product_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    insert_record_anchor_db(&mut tx, anchor).await?;
+    append_audit_log_db(&mut tx, &NewAuditEntry::new(audit::ACTOR_CLI, "record.reanchor", Some(product_id), anchor_payload(anchor))).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+// 上链确认类审计记录的内容
+fn anchor_payload(anchor: &NewRecordAnchor) -> JsonValue {
+    json!({
+        "productId": anchor.product_id,
+        "chainId": anchor.chain_id,
+        "contractAddress": anchor.contract_address,
+        "transactionHash": anchor.transaction_hash,
+        "blockNumber": anchor.block_number,
+    })
+}
+
+// ------------------------------ 上链发件箱 ------------------------------
+
+// 到期需要处理的条目：待发送的，以及已发送、等待确认的
+pub async fn list_due_outbox_entries_db(pool: &MySqlPool, limit: u32) -> Result<Vec<AnchorOutboxEntry>, AppError> {
+    let entries = sqlx::query_as!(
+        AnchorOutboxEntry,
+        r#"
+        SELECT id, product_id, chain_id, contract_address, metadata_hash, status, attempts, transaction_hash, transaction_nonce, raw_transaction, last_error,
+               next_attempt_at as "next_attempt_at!: chrono::DateTime<chrono::Utc>",
+               submitted_at as "submitted_at: chrono::DateTime<chrono::Utc>",
+               created_at as "created_at!: chrono::DateTime<chrono::Utc>",
+               updated_at as "updated_at!: chrono::DateTime<chrono::Utc>"
+        FROM anchor_outbox
+        WHERE status IN ('pending', 'submitted') AND next_attempt_at <= CURRENT_TIMESTAMP
+        ORDER BY next_attempt_at, id
+        LIMIT ?
+        "#,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(entries)
+}
+
+pub async fn get_latest_outbox_entry_db(pool: &MySqlPool, product_id: &str) -> Result<Option<AnchorOutboxEntry>, AppError> {
+    let entry = sqlx::query_as!(
+        AnchorOutboxEntry,
+        r#"
+        SELECT id, product_id, chain_id, contract_address, metadata_hash, status, attempts, transaction_hash, transaction_nonce, raw_transaction, last_error,
+               next_attempt_at as "next_attempt_at!: chrono::DateTime<chrono::Utc>",
+               submitted_at as "submitted_at: chrono::DateTime<chrono::Utc>",
+               created_at as "created_at!: chrono::DateTime<chrono::Utc>",
+               updated_at as "updated_at!: chrono::DateTime<chrono::Utc>"
+        FROM anchor_outbox WHERE product_id = ? ORDER BY id DESC LIMIT 1
+        "#,
+        product_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(entry)
+}
+
+// 交易已广播：保存交易哈希、nonce 与原始交易并计入一次发送
+pub async fn mark_outbox_submitted_db(
+    pool: &MySqlPool,
+    id: u64,
+    sent: &SentTransaction,
+    attempts: u32,
+) -> Result<(), AppError> {
+    sqlx::query!(
+        r#"
+        UPDATE anchor_outbox
+        SET status = 'submitted', transaction_hash = ?, transaction_nonce = ?, raw_transaction = ?, attempts = ?, last_error = NULL,
+            submitted_at = CURRENT_TIMESTAMP, next_attempt_at = CURRENT_TIMESTAMP
+        WHERE id = ?
+        "#,
+        sent.hash, sent.nonce, hex::encode(&sent.raw), attempts, id
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+// 更新状态并在 delay_secs 秒后再次处理 (重试退避、等待回执，或标记为 failed)
+pub async fn reschedule_outbox_entry_db(
+    pool: &MySqlPool,
+    id: u64,
+    status: &str,
+    attempts: u32,
+    last_error: Option<&str>,
+    delay_secs: u64,
+) -> Result<(), AppError> {
+    sqlx::query!(
+        r#"
+        UPDATE anchor_outbox
+        SET status = ?, attempts = ?, last_error = ?, next_attempt_at = CURRENT_TIMESTAMP + INTERVAL ? SECOND
+        WHERE id = ?
+        "#,
+        status, attempts, last_error, delay_secs, id
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+// 交易已确认：更新发件箱状态、追加上链历史，并写回记录的交易哈希
+pub async fn confirm_outbox_entry_db(
+    pool: &MySqlPool,
+    id: u64,
+    anchor: &NewRecordAnchor,
+) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+    sqlx::query!(
+        r#"UPDATE anchor_outbox SET status = 'confirmed', transaction_hash = ?, last_error = NULL WHERE id = ?"#,
+        anchor.transaction_hash, id
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        r#"UPDATE traceability_data SET blockchain_transaction_hash = ? WHERE product_id = ?"#,
+        anchor.transaction_hash, anchor.product_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    insert_record_anchor_db(&mut tx, anchor).await?;
+    append_audit_log_db(&mut tx, &NewAuditEntry::new(audit::ACTOR_OUTBOX, "record.anchor", Some(&anchor.product_id), anchor_payload(anchor))).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+// ------------------------------ Merkle 批量上链 ------------------------------
+
+// 收集时间已超过窗口或叶子数已满的 open 批次
+pub async fn list_sealable_merkle_batches_db(
+    pool: &MySqlPool,
+    window_secs: u64,
+    max_leaves: u32,
+) -> Result<Vec<u64>, AppError> {
+    let ids = sqlx::query_scalar!(
+        r#"
+        SELECT id FROM merkle_batches
+        WHERE status = 'open' AND (created_at <= CURRENT_TIMESTAMP - INTERVAL ? SECOND OR leaf_count >= ?)
+        ORDER BY id
+        "#,
+        window_secs, max_leaves
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(ids)
+}
+
+// 锁定批次，仍为 open 时返回 true
+pub async fn lock_open_merkle_batch_db(tx: &mut Transaction<'_, MySql>, batch_id: u64) -> Result<bool, AppError> {
+    let status = sqlx::query_scalar!(r#"SELECT status FROM merkle_batches WHERE id = ? FOR UPDATE"#, batch_id)
+        .fetch_optional(&mut **tx)
+        .await?;
+    Ok(status.as_deref() == Some("open"))
+}
+
+// 批次中的叶子，按加入顺序排列 (即叶子序号)
+pub async fn list_merkle_batch_leaves_db(tx: &mut Transaction<'_, MySql>, batch_id: u64) -> Result<Vec<MerkleLeafRow>, AppError> {
+    let leaves = sqlx::query_as!(
+        MerkleLeafRow,
+        r#"SELECT id, leaf_hash FROM merkle_leaves WHERE batch_id = ? ORDER BY id"#,
+        batch_id
+    )
+    .fetch_all(&mut **tx)
+    .await?;
+    Ok(leaves)
+}
+
+pub async fn update_merkle_leaf_proof_db(
+    tx: &mut Transaction<'_, MySql>,
+    leaf_id: u64,
+    leaf_index: u32,
+    proof_json: &str,
+) -> Result<(), AppError> {
+    sqlx::query!(
+        r#"UPDATE merkle_leaves SET leaf_index = ?, proof = ? WHERE id = ?"#,
+        leaf_index, proof_json, leaf_id
+    )
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+pub async fn seal_merkle_batch_db(tx: &mut Transaction<'_, MySql>, batch_id: u64, merkle_root: &str) -> Result<(), AppError> {
+    sqlx::query!(
+        r#"
+        UPDATE merkle_batches
+        SET status = 'sealed', merkle_root = ?, sealed_at = CURRENT_TIMESTAMP, next_attempt_at = CURRENT_TIMESTAMP
+        WHERE id = ?
+        "#,
+        merkle_root, batch_id
+    )
+    .execute(&mut **tx)
+    .await?;
+    append_audit_log_db(tx, &NewAuditEntry::new(audit::ACTOR_MERKLE, "merkle_batch.seal", Some(&batch_id.to_string()), json!({
+        "batchId": batch_id,
+        "merkleRoot": merkle_root,
+    }))).await?;
+    Ok(())
+}
+
+// 到期需要发送或等待确认的批次
+pub async fn list_due_merkle_batches_db(pool: &MySqlPool, limit: u32) -> Result<Vec<MerkleBatch>, AppError> {
+    let batches = sqlx::query_as!(
+        MerkleBatch,
+        r#"
+        SELECT id, chain_id, contract_address, status, leaf_count, merkle_root, attempts, transaction_hash, transaction_nonce, raw_transaction, block_number, last_error,
+               submitted_at as "submitted_at: chrono::DateTime<chrono::Utc>",
+               created_at as "created_at!: chrono::DateTime<chrono::Utc>",
+               sealed_at as "sealed_at: chrono::DateTime<chrono::Utc>"
+        FROM merkle_batches
+        WHERE status IN ('sealed', 'submitted') AND next_attempt_at <= CURRENT_TIMESTAMP
+        ORDER BY next_attempt_at, id
+        LIMIT ?
+        "#,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(batches)
+}
+
+// 对账时作为数据库一方的已确认批次根
+pub async fn list_confirmed_merkle_batches_db(
+    pool: &MySqlPool,
+    chain_id: u64,
+    contract_address: &str,
+) -> Result<Vec<MerkleBatch>, AppError> {
+    let batches = sqlx::query_as!(
+        MerkleBatch,
+        r#"
+        SELECT id, chain_id, contract_address, status, leaf_count, merkle_root, attempts, transaction_hash, transaction_nonce, raw_transaction, block_number, last_error,
+               submitted_at as "submitted_at: chrono::DateTime<chrono::Utc>",
+               created_at as "created_at!: chrono::DateTime<chrono::Utc>",
+               sealed_at as "sealed_at: chrono::DateTime<chrono::Utc>"
+        FROM merkle_batches
+        WHERE status = 'confirmed' AND chain_id = ? AND contract_address = ?
+        "#,
+        chain_id, contract_address
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(batches)
+}
+
+pub async fn mark_merkle_batch_submitted_db(
+    pool: &MySqlPool,
+    batch_id: u64,
+    sent: &SentTransaction,
+    attempts: u32,
+) -> Result<(), AppError> {
+    sqlx::query!(
+        r#"
+        UPDATE merkle_batches
+        SET status = 'submitted', transaction_hash = ?, transaction_nonce = ?, raw_transaction = ?, attempts = ?, last_error = NULL,
+            submitted_at = CURRENT_TIMESTAMP, next_attempt_at = CURRENT_TIMESTAMP
+        WHERE id = ?
+        "#,
+        sent.hash, sent.nonce, hex::encode(&sent.raw), attempts, batch_id
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn reschedule_merkle_batch_db(
+    pool: &MySqlPool,
+    batch_id: u64,
+    status: &str,
+    attempts: u32,
+    last_error: Option<&str>,
+    delay_secs: u64,
+) -> Result<(), AppError> {
+    sqlx::query!(
+        r#"
+        UPDATE merkle_batches
+        SET status = ?, attempts = ?, last_error = ?, next_attempt_at = CURRENT_TIMESTAMP + INTERVAL ? SECOND
+        WHERE id = ?
+        "#,
+        status, attempts, last_error, delay_secs, batch_id
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+// 批次根已确认：为批次中的每条记录追加上链历史并写回交易哈希 (anchor.product_id 为批次的 anchor key，不写入)
+pub async fn confirm_merkle_batch_db(
+    pool: &MySqlPool,
+    batch_id: u64,
+    anchor: &NewRecordAnchor,
+) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+    sqlx::query!(
+        r#"
+        UPDATE merkle_batches SET status = 'confirmed', transaction_hash = ?, block_number = ?, last_error = NULL
+        WHERE id = ?
+        "#,
+        anchor.transaction_hash, anchor.block_number, batch_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        r#"
+        UPDATE traceability_data t JOIN merkle_leaves l ON l.product_id = t.product_id
+        SET t.blockchain_transaction_hash = ?
+        WHERE l.batch_id = ?
+        "#,
+        anchor.transaction_hash, batch_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO record_anchors (product_id, chain_id, contract_address, transaction_hash, block_number, block_timestamp, status)
+        SELECT product_id, ?, ?, ?, ?, ?, ? FROM merkle_leaves WHERE batch_id = ?
+        "#,
+        anchor.chain_id, anchor.contract_address, anchor.transaction_hash,
+        anchor.block_number, anchor.block_timestamp, anchor.status, batch_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    // anchor.product_id 为批次的 anchor key (merkle-batch:<批次ID>)
+    append_audit_log_db(&mut tx, &NewAuditEntry::new(audit::ACTOR_MERKLE, "merkle_batch.anchor", Some(&batch_id.to_string()), anchor_payload(anchor))).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+pub async fn get_merkle_leaf_db(pool: &MySqlPool, product_id: &str) -> Result<Option<MerkleLeafDetail>, AppError> {
+    let leaf = sqlx::query_as!(
+        MerkleLeafDetail,
+        r#"
+        SELECT l.product_id, l.batch_id, l.leaf_hash, l.leaf_index,
+               l.proof as "proof: sqlx::types::Json<Vec<crate::merkle::ProofStep>>",
+               b.status as batch_status, b.merkle_root, b.chain_id, b.contract_address, b.transaction_hash, b.block_number
+        FROM merkle_leaves l JOIN merkle_batches b ON b.id = l.batch_id
+        WHERE l.product_id = ?
+        "#,
+        product_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(leaf)
+}
+
+// 保存新签发的 SIWE nonce (顺便清理过期的 nonce)，返回过期时间
+pub async fn create_auth_nonce_db(pool: &MySqlPool, nonce: &str, ttl_secs: u64) -> Result<chrono::DateTime<chrono::Utc>, AppError> {
+    sqlx::query!(r#"DELETE FROM auth_nonces WHERE expires_at < CURRENT_TIMESTAMP"#)
+        .execute(pool)
+        .await?;
+    sqlx::query!(
+        r#"INSERT INTO auth_nonces (nonce, expires_at) VALUES (?, CURRENT_TIMESTAMP + INTERVAL ? SECOND)"#,
+        nonce, ttl_secs
+    )
+    .execute(pool)
+    .await?;
+    let expires_at = sqlx::query_scalar!(
+        r#"SELECT expires_at as "expires_at!: chrono::DateTime<chrono::Utc>" FROM auth_nonces WHERE nonce = ?"#,
+        nonce
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(expires_at)
+}
+
+// 把未使用且未过期的 nonce 标记为已使用，返回是否成功 (条件更新保证并发登录时只有一个请求成功)
+pub async fn consume_auth_nonce_db(pool: &MySqlPool, nonce: &str) -> Result<bool, AppError> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE auth_nonces SET used_at = CURRENT_TIMESTAMP
+        WHERE nonce = ? AND used_at IS NULL AND expires_at > CURRENT_TIMESTAMP
+        "#,
+        nonce
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() == 1)
+}
+
+pub async fn create_auth_session_db(
+    pool: &MySqlPool,
+    token_hash: &str,
+    address: &str,
+    chain_id: u64,
+    domain: &str,
+    ttl_secs: u64,
+) -> Result<AuthSession, AppError> {
+    let mut tx = pool.begin().await?;
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO auth_sessions (token_hash, address, chain_id, domain, expires_at)
+        VALUES (?, ?, ?, ?, CURRENT_TIMESTAMP + INTERVAL ? SECOND)
+        "#,
+        token_hash, address, chain_id, domain, ttl_secs
+    )
+    .execute(&mut *tx)
+    .await?;
+    append_audit_log_db(&mut tx, &NewAuditEntry::new(address, "auth.login", Some(address), json!({
+        "sessionId": result.last_insert_id(),
+        "chainId": chain_id,
+        "domain": domain,
+    }))).await?;
+    tx.commit().await?;
+    let session = sqlx::query_as!(
+        AuthSession,
+        r#"
+        SELECT address, chain_id, expires_at as "expires_at!: chrono::DateTime<chrono::Utc>"
+        FROM auth_sessions WHERE id = ?
+        "#,
+        result.last_insert_id()
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(session)
+}
+
+// 按令牌哈希查询未过期的会话
+pub async fn get_auth_session_db(pool: &MySqlPool, token_hash: &str) -> Result<Option<AuthSession>, AppError> {
+    let session = sqlx::query_as!(
+        AuthSession,
+        r#"
+        SELECT address, chain_id, expires_at as "expires_at!: chrono::DateTime<chrono::Utc>"
+        FROM auth_sessions WHERE token_hash = ? AND expires_at > CURRENT_TIMESTAMP
+        "#,
+        token_hash
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(session)
+}
+
+// ------------------------------ 审计日志 ------------------------------
+
+// 在调用方的事务中追加一条审计记录：锁定链头、计算哈希、写入记录并推进链头，返回记录编号
+pub async fn append_audit_log_db(tx: &mut Transaction<'_, MySql>, entry: &NewAuditEntry) -> Result<u64, AppError> {
+    let head = sqlx::query!(r#"SELECT last_entry_id, last_hash FROM audit_chain_head WHERE id = 1 FOR UPDATE"#)
+        .fetch_one(&mut **tx)
+        .await?;
+    let id = head.last_entry_id + 1;
+    let created_at = audit::truncate_to_millis(chrono::Utc::now());
+    let (payload, payload_hash) = audit::canonical_payload(&entry.payload)?;
+    let entry_hash = audit::entry_hash(
+        id, &entry.actor, entry.action, entry.target.as_deref(), &payload_hash, &head.last_hash, created_at,
+    )?;
+    sqlx::query!(
+        r#"
+        INSERT INTO audit_log (id, actor, action, target, payload, payload_hash, prev_hash, entry_hash, created_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+        id, entry.actor, entry.action, entry.target, payload, payload_hash, head.last_hash, entry_hash, created_at
+    )
+    .execute(&mut **tx)
+    .await?;
+    sqlx::query!(
+        r#"UPDATE audit_chain_head SET last_entry_id = ?, last_hash = ? WHERE id = 1"#,
+        id, entry_hash
+    )
+    .execute(&mut **tx)
+    .await?;
+    Ok(id)
+}
+
+// 单独追加一条审计记录 (没有其他写入需要同一事务时使用)
+pub async fn insert_audit_log_db(pool: &MySqlPool, entry: &NewAuditEntry) -> Result<u64, AppError> {
+    let mut tx = pool.begin().await?;
+    let id = append_audit_log_db(&mut tx, entry).await?;
+    tx.commit().await?;
+    Ok(id)
+}
+
+pub async fn get_audit_chain_head_db(pool: &MySqlPool) -> Result<(u64, String), AppError> {
+    let head = sqlx::query!(r#"SELECT last_entry_id, last_hash FROM audit_chain_head WHERE id = 1"#)
+        .fetch_one(pool)
+        .await?;
+    Ok((head.last_entry_id, head.last_hash))
+}
+
+pub async fn list_audit_log_after_db(pool: &MySqlPool, after_id: u64, limit: u32) -> Result<Vec<AuditLogEntry>, AppError> {
+    let entries = sqlx::query_as!(
+        AuditLogEntry,
+        r#"
+        SELECT id, actor, action, target, payload, payload_hash, prev_hash, entry_hash,
+               created_at as "created_at!: chrono::DateTime<chrono::Utc>"
+        FROM audit_log WHERE id > ? ORDER BY id LIMIT ?
+        "#,
+        after_id, limit
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(entries)
+}
+
+// 审计核对用的全部记录，Merkle 批量上链的记录带上所在批次
+pub async fn list_audited_records_db(pool: &MySqlPool) -> Result<Vec<AuditedRecordRow>, AppError> {
+    let rows = sqlx::query_as!(
+        AuditedRecordRow,
+        r#"
+        SELECT t.product_id, t.onchain_metadata_hash, t.hash_scheme, t.recorder, t.blockchain_transaction_hash,
+               (SELECT MAX(l.batch_id) FROM merkle_leaves l WHERE l.product_id = t.product_id) as "merkle_batch_id: u64",
+               t.created_at as "created_at!: chrono::DateTime<chrono::Utc>"
+        FROM traceability_data t
+        ORDER BY t.product_id
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+// ------------------------------ 审计链头上链 ------------------------------
+
+pub async fn insert_audit_head_anchor_db(
+    pool: &MySqlPool,
+    entry_id: u64,
+    head_hash: &str,
+    chain_id: u64,
+    contract_address: &str,
+    transaction_hash: &str,
+) -> Result<u64, AppError> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO audit_head_anchors (entry_id, head_hash, chain_id, contract_address, status, transaction_hash)
+        VALUES (?, ?, ?, ?, 'submitted', ?)
+        "#,
+        entry_id, head_hash, chain_id, contract_address, transaction_hash
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.last_insert_id())
+}
+
+pub async fn update_audit_head_anchor_db(pool: &MySqlPool, id: u64, status: &str, block_number: Option<u64>) -> Result<(), AppError> {
+    sqlx::query!(
+        r#"UPDATE audit_head_anchors SET status = ?, block_number = ? WHERE id = ?"#,
+        status, block_number, id
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn list_audit_head_anchors_db(pool: &MySqlPool, status: &str) -> Result<Vec<AuditHeadAnchor>, AppError> {
+    let anchors = sqlx::query_as!(
+        AuditHeadAnchor,
+        r#"
+        SELECT id, entry_id, head_hash, chain_id, contract_address, status, transaction_hash, block_number,
+               created_at as "created_at!: chrono::DateTime<chrono::Utc>"
+        FROM audit_head_anchors WHERE status = ? ORDER BY id
+        "#,
+        status
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(anchors)
+}
+
+// 最近一次已发送或已确认的锚定
+pub async fn get_latest_audit_head_anchor_db(pool: &MySqlPool) -> Result<Option<AuditHeadAnchor>, AppError> {
+    let anchor = sqlx::query_as!(
+        AuditHeadAnchor,
+        r#"
+        SELECT id, entry_id, head_hash, chain_id, contract_address, status, transaction_hash, block_number,
+               created_at as "created_at!: chrono::DateTime<chrono::Utc>"
+        FROM audit_head_anchors WHERE status <> 'failed' ORDER BY id DESC LIMIT 1
+        "#
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(anchor)
+}
+
+// 指定链与合约上最近一次已确认的锚定 (合约中 records["audit-head"] 应为其链头哈希)
+pub async fn get_latest_confirmed_audit_head_anchor_db(
+    pool: &MySqlPool,
+    chain_id: u64,
+    contract_address: &str,
+) -> Result<Option<AuditHeadAnchor>, AppError> {
+    let anchor = sqlx::query_as!(
+        AuditHeadAnchor,
+        r#"
+        SELECT id, entry_id, head_hash, chain_id, contract_address, status, transaction_hash, block_number,
+               created_at as "created_at!: chrono::DateTime<chrono::Utc>"
+        FROM audit_head_anchors
+        WHERE status = 'confirmed' AND chain_id = ? AND contract_address = ?
+        ORDER BY id DESC LIMIT 1
+        "#,
+        chain_id, contract_address
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(anchor)
+}
+
+// salted-fields-v1 记录的全部字段 (按字段名排列)，其他方案的记录返回空列表
+pub async fn get_metadata_fields_db(pool: &MySqlPool, product_id: &str) -> Result<Vec<MetadataField>, AppError> {
+    let fields = sqlx::query_as!(
+        MetadataField,
+        r#"
+        SELECT field_name, value_canonical, salt, commitment, is_public as "is_public: bool"
+        FROM metadata_fields WHERE product_id = ? ORDER BY field_name
+        "#,
+        product_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(fields)
+}
+
+// 保存面向某一受众的披露授权，同一事务中写入审计日志；ttl_secs 为空表示长期有效，返回过期时间
+pub async fn create_disclosure_db(
+    pool: &MySqlPool,
+    actor: &str,
+    token_hash: &str,
+    product_id: &str,
+    audience: &str,
+    fields: &[String],
+    ttl_secs: Option<u64>,
+) -> Result<Option<chrono::DateTime<chrono::Utc>>, AppError> {
+    let mut tx = pool.begin().await?;
+    let fields_json = serde_json::to_string(fields)?;
+    let id = sqlx::query!(
+        r#"
+        INSERT INTO metadata_disclosures (token_hash, product_id, audience, fields, created_by, expires_at)
+        VALUES (?, ?, ?, ?, ?, IF(? IS NULL, NULL, CURRENT_TIMESTAMP + INTERVAL ? SECOND))
+        "#,
+        token_hash, product_id, audience, fields_json, actor, ttl_secs, ttl_secs
+    )
+    .execute(&mut *tx)
+    .await?
+    .last_insert_id();
+    let expires_at = sqlx::query_scalar!(
+        r#"SELECT expires_at as "expires_at: chrono::DateTime<chrono::Utc>" FROM metadata_disclosures WHERE id = ?"#,
+        id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    append_audit_log_db(&mut tx, &NewAuditEntry::new(actor, "disclosure.create", Some(product_id), json!({
+        "disclosureId": id,
+        "audience": audience,
+        "fields": fields,
+        "expiresAt": expires_at,
+    }))).await?;
+    tx.commit().await?;
+    Ok(expires_at)
+}
+
+// 按令牌哈希查询未过期的披露授权
+pub async fn get_disclosure_db(pool: &MySqlPool, token_hash: &str) -> Result<Option<DisclosureGrant>, AppError> {
+    let grant = sqlx::query_as!(
+        DisclosureGrant,
+        r#"
+        SELECT product_id, audience, fields as "fields: sqlx::types::Json<Vec<String>>",
+               expires_at as "expires_at: chrono::DateTime<chrono::Utc>"
+        FROM metadata_disclosures
+        WHERE token_hash = ? AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+        "#,
+        token_hash
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(grant)
+}
+
+// ------------------------------ 供应链事件 ------------------------------
+
+// 追加一条事件：锁定产品记录串行化同一产品的追加，取上一条事件的哈希计算本条的事件哈希
+pub async fn append_trace_event_db(
+    pool: &MySqlPool,
+    recorded_by: &str,
+    product_id: &str,
+    event: &NewTraceEvent,
+) -> Result<TraceEventRow, AppError> {
+    let mut tx = pool.begin().await?;
+    sqlx::query_scalar!(r#"SELECT product_id FROM traceability_data WHERE product_id = ? FOR UPDATE"#, product_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("未找到产品ID为 '{}' 的食品记录。", product_id)))?;
+    let last = sqlx::query!(
+        r#"SELECT sequence, event_hash FROM trace_events WHERE product_id = ? ORDER BY sequence DESC LIMIT 1"#,
+        product_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    let (sequence, prev_event_hash) = match last {
+        Some(last) => (last.sequence + 1, last.event_hash),
+        None => (1, trace_events::GENESIS_HASH.to_string()),
+    };
+    let event_hash = trace_events::event_hash(product_id, sequence, event, &prev_event_hash)?;
+    let payload = serde_json::to_string(&event.payload)?;
+    let id = sqlx::query!(
+        r#"
+        INSERT INTO trace_events (product_id, sequence, stage, actor, location, occurred_at, payload, prev_event_hash, event_hash, recorded_by)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+        product_id, sequence, event.stage.as_str(), event.actor, event.location, event.occurred_at,
+        payload, prev_event_hash, event_hash, recorded_by
+    )
+    .execute(&mut *tx)
+    .await?
+    .last_insert_id();
+    append_audit_log_db(&mut tx, &NewAuditEntry::new(recorded_by, "trace_event.append", Some(product_id), json!({
+        "eventId": id,
+        "sequence": sequence,
+        "stage": event.stage.as_str(),
+        "eventHash": event_hash,
+    }))).await?;
+    let row = sqlx::query_as!(
+        TraceEventRow,
+        r#"
+        SELECT id, product_id, sequence, stage, actor, location,
+               occurred_at as "occurred_at!: chrono::DateTime<chrono::Utc>",
+               payload as "payload: sqlx::types::Json<JsonValue>",
+               prev_event_hash, event_hash, recorded_by,
+               created_at as "created_at!: chrono::DateTime<chrono::Utc>"
+        FROM trace_events WHERE id = ?
+        "#,
+        id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(row)
+}
+
+// 产品的全部事件，按追加顺序
+pub async fn list_trace_events_db(pool: &MySqlPool, product_id: &str) -> Result<Vec<TraceEventRow>, AppError> {
+    let events = sqlx::query_as!(
+        TraceEventRow,
+        r#"
+        SELECT id, product_id, sequence, stage, actor, location,
+               occurred_at as "occurred_at!: chrono::DateTime<chrono::Utc>",
+               payload as "payload: sqlx::types::Json<JsonValue>",
+               prev_event_hash, event_hash, recorded_by,
+               created_at as "created_at!: chrono::DateTime<chrono::Utc>"
+        FROM trace_events WHERE product_id = ? ORDER BY sequence
+        "#,
+        product_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(events)
+}
+
+// ------------------------------ 批次谱系 ------------------------------
+
+pub async fn create_lineage_link_db(
+    pool: &MySqlPool,
+    actor: &str,
+    child_product_id: &str,
+    request: &LineageLinkRequest,
+    unit: Option<&str>,
+) -> Result<LineageLink, AppError> {
+    let mut tx = pool.begin().await?;
+    // 按产品ID顺序锁定两端，同一对产品上的建边互相排队
+    let mut product_ids = [request.parent_product_id.as_str(), child_product_id];
+    product_ids.sort();
+    for product_id in product_ids {
+        sqlx::query_scalar!(r#"SELECT product_id FROM traceability_data WHERE product_id = ? FOR UPDATE"#, product_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("未找到产品ID为 '{}' 的食品记录。", product_id)))?;
+    }
+    // 父批次已在子批次的下游时，新边会形成环；遍历读到的边加了共享锁，并发建立的边不会绕过这次检查
+    let downstream = lineage::walk_connection(&mut tx, child_product_id, LineageDirection::Downstream, lineage::MAX_TRANSITIVE_DEPTH).await?;
+    if downstream.nodes.iter().any(|node| node.product_id == request.parent_product_id) {
+        return Err(AppError::Conflict(format!(
+            "产品ID '{}' 已在 '{}' 的下游，不能再作为它的父批次。", request.parent_product_id, child_product_id
+        )));
+    }
+    if downstream.truncated {
+        return Err(AppError::Conflict(format!(
+            "'{}' 的下游超过遍历上限 ({} 层或 {} 行)，无法确认新边不会形成环。",
+            child_product_id, lineage::MAX_TRANSITIVE_DEPTH, lineage::MAX_WALK_ROWS
+        )));
+    }
+    let id = sqlx::query!(
+        r#"
+        INSERT INTO batch_lineage (parent_product_id, child_product_id, relationship, parent_quantity, child_quantity, unit, created_by)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
+        "#,
+        request.parent_product_id, child_product_id, request.relationship.as_str(),
+        request.parent_quantity, request.child_quantity, unit, actor
+    )
+    .execute(&mut *tx)
+    .await?
+    .last_insert_id();
+    append_audit_log_db(&mut tx, &NewAuditEntry::new(actor, "lineage.link", Some(child_product_id), json!({
+        "linkId": id,
+        "parentProductId": request.parent_product_id,
+        "relationship": request.relationship.as_str(),
+        "parentQuantity": request.parent_quantity,
+        "childQuantity": request.child_quantity,
+        "unit": unit,
+    }))).await?;
+    // 父批次处于未关闭的召回中时，子批次及其下游随新边一起记为受影响；上面的遍历未被截断，下游已经完整
+    let parent_recalls = sqlx::query!(
+        r#"
+        SELECT rp.recall_id, rp.source, rp.depth as "depth: u32", rp.via_product_id
+        FROM recall_products rp JOIN recalls r ON r.id = rp.recall_id
+        WHERE rp.product_id = ? AND r.status <> ?
+        ORDER BY rp.recall_id FOR SHARE
+        "#,
+        request.parent_product_id, RecallStatus::Closed.as_str()
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    for parent_recall in parent_recalls {
+        let via_product_id = match parent_recall.source.as_str() {
+            recall::SOURCE_DIRECT => request.parent_product_id.clone(),
+            _ => parent_recall.via_product_id.unwrap_or_else(|| request.parent_product_id.clone()),
+        };
+        let reached: Vec<(&str, u32)> = std::iter::once((child_product_id, 0))
+            .chain(downstream.nodes.iter().map(|node| (node.product_id.as_str(), node.depth)))
+            .collect();
+        for &(product_id, depth) in &reached {
+            // 已受影响的产品保留原来的来源，只在新路径更短时更新深度
+            sqlx::query!(
+                r#"
+                INSERT INTO recall_products (recall_id, product_id, source, depth, via_product_id) VALUES (?, ?, ?, ?, ?)
+                ON DUPLICATE KEY UPDATE via_product_id = IF(VALUES(depth) < depth, VALUES(via_product_id), via_product_id),
+                                        depth = LEAST(depth, VALUES(depth))
+                "#,
+                parent_recall.recall_id, product_id, recall::SOURCE_DOWNSTREAM, parent_recall.depth + 1 + depth, via_product_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        append_audit_log_db(&mut tx, &NewAuditEntry::new(actor, "recall.propagate", Some(&parent_recall.recall_id.to_string()), json!({
+            "linkId": id,
+            "parentProductId": request.parent_product_id,
+            "childProductId": child_product_id,
+            "propagatedCount": reached.len(),
+        }))).await?;
+    }
+    let link = sqlx::query_as!(
+        LineageLink,
+        r#"
+        SELECT id, parent_product_id, child_product_id, relationship, parent_quantity, child_quantity, unit, created_by,
+               created_at as "created_at!: chrono::DateTime<chrono::Utc>"
+        FROM batch_lineage WHERE id = ?
+        "#,
+        id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(link)
+}
+
+// 从 product_id 沿子批次方向递归展开：reach 按 (批次, 深度) 去重，nodes 取每个批次的最短深度，一次查询返回可达批次发出的全部边
+// to_depth 为边指向的批次的最短深度，为空表示该批次超出深度或行数上限；FOR SHARE 在事务中锁住读到的边与索引范围，并发事务不能在其中插入新边
+pub async fn walk_child_edges_db(
+    conn: &mut MySqlConnection,
+    product_id: &str,
+    max_depth: u32,
+    max_rows: u64,
+) -> Result<Vec<LineageWalkEdge>, AppError> {
+    let edges = sqlx::query_as!(
+        LineageWalkEdge,
+        r#"
+        WITH RECURSIVE reach (product_id, depth) AS (
+            SELECT product_id, CAST(0 AS UNSIGNED) FROM traceability_data WHERE product_id = ?
+            UNION DISTINCT
+            SELECT l.child_product_id, r.depth + 1
+            FROM reach r JOIN batch_lineage l ON l.parent_product_id = r.product_id
+            WHERE r.depth < ?
+            LIMIT ?
+        ),
+        nodes AS (SELECT product_id, MIN(depth) AS depth FROM reach GROUP BY product_id)
+        SELECT l.id, l.parent_product_id, l.child_product_id, l.relationship, l.parent_quantity, l.child_quantity, l.unit,
+               to_node.depth as "to_depth?: u64"
+        FROM batch_lineage l
+        JOIN nodes from_node ON from_node.product_id = l.parent_product_id
+        LEFT JOIN nodes to_node ON to_node.product_id = l.child_product_id
+        ORDER BY l.id
+        FOR SHARE OF l
+        "#,
+        product_id, max_depth, max_rows
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(edges)
+}
+
+// 沿父批次方向展开，规则同 walk_child_edges_db
+pub async fn walk_parent_edges_db(
+    conn: &mut MySqlConnection,
+    product_id: &str,
+    max_depth: u32,
+    max_rows: u64,
+) -> Result<Vec<LineageWalkEdge>, AppError> {
+    let edges = sqlx::query_as!(
+        LineageWalkEdge,
+        r#"
+        WITH RECURSIVE reach (product_id, depth) AS (
+            SELECT product_id, CAST(0 AS UNSIGNED) FROM traceability_data WHERE product_id = ?
+            UNION DISTINCT
+            SELECT l.parent_product_id, r.depth + 1
+            FROM reach r JOIN batch_lineage l ON l.child_product_id = r.product_id
+            WHERE r.depth < ?
+            LIMIT ?
+        ),
+        nodes AS (SELECT product_id, MIN(depth) AS depth FROM reach GROUP BY product_id)
+        SELECT l.id, l.parent_product_id, l.child_product_id, l.relationship, l.parent_quantity, l.child_quantity, l.unit,
+               to_node.depth as "to_depth?: u64"
+        FROM batch_lineage l
+        JOIN nodes from_node ON from_node.product_id = l.child_product_id
+        LEFT JOIN nodes to_node ON to_node.product_id = l.parent_product_id
+        ORDER BY l.id
+        FOR SHARE OF l
+        "#,
+        product_id, max_depth, max_rows
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(edges)
+}
+
+// ------------------------------ 召回 ------------------------------
+
+// 写入召回及全部受影响的产品，直接列出的产品必须存在；下游传播在同一事务中完成
+pub async fn create_recall_db(
+    pool: &MySqlPool,
+    actor: &str,
+    request: &RecallRequest,
+    product_ids: &[String],
+) -> Result<u64, AppError> {
+    let mut tx = pool.begin().await?;
+    for product_id in product_ids {
+        sqlx::query_scalar!(r#"SELECT product_id FROM traceability_data WHERE product_id = ?"#, product_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("未找到产品ID为 '{}' 的食品记录。", product_id)))?;
+    }
+    let (affected, propagation_truncated) = recall::affected_products(&mut tx, product_ids).await?;
+    let id = sqlx::query!(
+        r#"
+        INSERT INTO recalls (reason, severity_class, initiating_organization, status, propagation_truncated, opened_by)
+        VALUES (?, ?, ?, ?, ?, ?)
+        "#,
+        request.reason.trim(), request.severity_class.as_str(), request.initiating_organization.trim(),
+        RecallStatus::Open.as_str(), propagation_truncated, actor
+    )
+    .execute(&mut *tx)
+    .await?
+    .last_insert_id();
+    for product in &affected {
+        sqlx::query!(
+            r#"INSERT INTO recall_products (recall_id, product_id, source, depth, via_product_id) VALUES (?, ?, ?, ?, ?)"#,
+            id, product.product_id, product.source, product.depth, product.via_product_id
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+    append_audit_log_db(&mut tx, &NewAuditEntry::new(actor, "recall.open", Some(&id.to_string()), json!({
+        "severityClass": request.severity_class.as_str(),
+        "initiatingOrganization": request.initiating_organization.trim(),
+        "directProducts": affected.iter().filter(|p| p.source == recall::SOURCE_DIRECT).map(|p| &p.product_id).collect::<Vec<_>>(),
+        "affectedCount": affected.len(),
+        "propagationTruncated": propagation_truncated,
+    }))).await?;
+    tx.commit().await?;
+    Ok(id)
+}
+
+pub async fn get_recall_db(pool: &MySqlPool, recall_id: u64) -> Result<Recall, AppError> {
+    let recall = sqlx::query_as!(
+        Recall,
+        r#"
+        SELECT id, reason, severity_class, initiating_organization, status,
+               propagation_truncated as "propagation_truncated: bool", opened_by,
+               opened_at as "opened_at!: chrono::DateTime<chrono::Utc>",
+               updated_at as "updated_at!: chrono::DateTime<chrono::Utc>",
+               closed_at as "closed_at: chrono::DateTime<chrono::Utc>"
+        FROM recalls WHERE id = ?
+        "#,
+        recall_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("未找到编号为 {} 的召回。", recall_id)))?;
+    Ok(recall)
+}
+
+// 按状态过滤，最近发起的在前
+pub async fn list_recalls_db(pool: &MySqlPool, status: Option<&str>) -> Result<Vec<Recall>, AppError> {
+    let recalls = sqlx::query_as!(
+        Recall,
+        r#"
+        SELECT id, reason, severity_class, initiating_organization, status,
+               propagation_truncated as "propagation_truncated: bool", opened_by,
+               opened_at as "opened_at!: chrono::DateTime<chrono::Utc>",
+               updated_at as "updated_at!: chrono::DateTime<chrono::Utc>",
+               closed_at as "closed_at: chrono::DateTime<chrono::Utc>"
+        FROM recalls WHERE (? IS NULL OR status = ?)
+        ORDER BY id DESC
+        "#,
+        status, status
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(recalls)
+}
+
+pub async fn list_recall_products_db(pool: &MySqlPool, recall_id: u64) -> Result<Vec<RecallProduct>, AppError> {
+    let products = sqlx::query_as!(
+        RecallProduct,
+        r#"SELECT product_id, source, depth, via_product_id FROM recall_products WHERE recall_id = ? ORDER BY depth, product_id"#,
+        recall_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(products)
+}
+
+// 推进召回状态，closed 时记录关闭时间
+pub async fn update_recall_status_db(pool: &MySqlPool, actor: &str, recall_id: u64, next: RecallStatus) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+    let current = sqlx::query_scalar!(r#"SELECT status FROM recalls WHERE id = ? FOR UPDATE"#, recall_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("未找到编号为 {} 的召回。", recall_id)))?;
+    let current = RecallStatus::parse(&current)?;
+    current.check_transition(next)?;
+    sqlx::query!(
+        r#"UPDATE recalls SET status = ?, closed_at = IF(? = 'closed', CURRENT_TIMESTAMP, closed_at) WHERE id = ?"#,
+        next.as_str(), next.as_str(), recall_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    append_audit_log_db(&mut tx, &NewAuditEntry::new(actor, "recall.status", Some(&recall_id.to_string()), json!({
+        "from": current.as_str(),
+        "to": next.as_str(),
+    }))).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+// 影响某一产品的全部召回，最近发起的在前
+pub async fn list_product_recalls_db(pool: &MySqlPool, product_id: &str) -> Result<Vec<ProductRecall>, AppError> {
+    let recalls = sqlx::query_as!(
+        ProductRecall,
+        r#"
+        SELECT r.id as recall_id, r.status, r.severity_class, r.reason, r.initiating_organization,
+               rp.source, rp.via_product_id,
+               r.opened_at as "opened_at!: chrono::DateTime<chrono::Utc>"
+        FROM recall_products rp JOIN recalls r ON r.id = rp.recall_id
+        WHERE rp.product_id = ?
+        ORDER BY r.id DESC
+        "#,
+        product_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(recalls)
+}
+
+// ------------------------------ 认证证书 ------------------------------
+
+// 关联证书与产品记录，产品必须存在，已关联的忽略
+async fn link_certificate_products(tx: &mut Transaction<'_, MySql>, certificate_id: u64, product_ids: &[String]) -> Result<(), AppError> {
+    for product_id in product_ids {
+        sqlx::query_scalar!(r#"SELECT product_id FROM traceability_data WHERE product_id = ?"#, product_id)
+            .fetch_optional(&mut **tx)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("未找到产品ID为 '{}' 的食品记录。", product_id)))?;
+        sqlx::query!(
+            r#"INSERT IGNORE INTO certificate_products (certificate_id, product_id) VALUES (?, ?)"#,
+            certificate_id, product_id
+        )
+        .execute(&mut **tx)
+        .await?;
+    }
+    Ok(())
+}
+
+pub async fn create_certificate_db(
+    pool: &MySqlPool,
+    actor: &str,
+    request: &CertificateRequest,
+    document_hash: &str,
+    organizations: &[String],
+    product_ids: &[String],
+) -> Result<u64, AppError> {
+    let mut tx = pool.begin().await?;
+    let id = sqlx::query!(
+        r#"
+        INSERT INTO certificates (certificate_type, issuer, certificate_number, scope, valid_from, valid_until, document_hash, created_by)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+        request.certificate_type.as_str(), request.issuer.trim(), request.certificate_number.trim(), request.scope.trim(),
+        request.valid_from, request.valid_until, document_hash, actor
+    )
+    .execute(&mut *tx)
+    .await?
+    .last_insert_id();
+    for name in organizations {
+        sqlx::query!(r#"INSERT IGNORE INTO organizations (name) VALUES (?)"#, name)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO certificate_organizations (certificate_id, organization_id)
+            SELECT ?, id FROM organizations WHERE name = ?
+            "#,
+            id, name
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+    link_certificate_products(&mut tx, id, product_ids).await?;
+    append_audit_log_db(&mut tx, &NewAuditEntry::new(actor, "certificate.create", Some(&id.to_string()), json!({
+        "certificateType": request.certificate_type.as_str(),
+        "issuer": request.issuer.trim(),
+        "certificateNumber": request.certificate_number.trim(),
+        "validFrom": request.valid_from,
+        "validUntil": request.valid_until,
+        "documentHash": document_hash,
+        "organizations": organizations,
+        "productIds": product_ids,
+    }))).await?;
+    tx.commit().await?;
+    Ok(id)
+}
+
+pub async fn add_certificate_products_db(pool: &MySqlPool, actor: &str, certificate_id: u64, product_ids: &[String]) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+    sqlx::query_scalar!(r#"SELECT id FROM certificates WHERE id = ?"#, certificate_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("未找到编号为 {} 的证书。", certificate_id)))?;
+    link_certificate_products(&mut tx, certificate_id, product_ids).await?;
+    append_audit_log_db(&mut tx, &NewAuditEntry::new(actor, "certificate.link_products", Some(&certificate_id.to_string()), json!({
+        "productIds": product_ids,
+    }))).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+pub async fn get_certificate_db(pool: &MySqlPool, certificate_id: u64) -> Result<Certificate, AppError> {
+    let certificate = sqlx::query_as!(
+        Certificate,
+        r#"
+        SELECT id, certificate_type, issuer, certificate_number, scope, valid_from, valid_until, document_hash, created_by,
+               created_at as "created_at!: chrono::DateTime<chrono::Utc>"
+        FROM certificates WHERE id = ?
+        "#,
+        certificate_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("未找到编号为 {} 的证书。", certificate_id)))?;
+    Ok(certificate)
+}
+
+pub async fn list_certificate_organizations_db(pool: &MySqlPool, certificate_id: u64) -> Result<Vec<String>, AppError> {
+    let names = sqlx::query_scalar!(
+        r#"
+        SELECT o.name FROM certificate_organizations co JOIN organizations o ON o.id = co.organization_id
+        WHERE co.certificate_id = ? ORDER BY o.name
+        "#,
+        certificate_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(names)
+}
+
+pub async fn list_certificate_products_db(pool: &MySqlPool, certificate_id: u64) -> Result<Vec<String>, AppError> {
+    let product_ids = sqlx::query_scalar!(
+        r#"SELECT product_id FROM certificate_products WHERE certificate_id = ? ORDER BY product_id"#,
+        certificate_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(product_ids)
+}
+
+// 关联到某一产品记录的全部证书
+pub async fn list_product_certificates_db(pool: &MySqlPool, product_id: &str) -> Result<Vec<Certificate>, AppError> {
+    let certificates = sqlx::query_as!(
+        Certificate,
+        r#"
+        SELECT c.id, c.certificate_type, c.issuer, c.certificate_number, c.scope, c.valid_from, c.valid_until,
+               c.document_hash, c.created_by, c.created_at as "created_at!: chrono::DateTime<chrono::Utc>"
+        FROM certificate_products cp JOIN certificates c ON c.id = cp.certificate_id
+        WHERE cp.product_id = ?
+        ORDER BY c.certificate_type, c.id
+        "#,
+        product_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(certificates)
+}
+
+// 今天起 days 天内 (含当天) 到期、尚未过期的证书，最早到期的在前
+pub async fn list_expiring_certificates_db(pool: &MySqlPool, days: u32) -> Result<Vec<Certificate>, AppError> {
+    let certificates = sqlx::query_as!(
+        Certificate,
+        r#"
+        SELECT id, certificate_type, issuer, certificate_number, scope, valid_from, valid_until, document_hash, created_by,
+               created_at as "created_at!: chrono::DateTime<chrono::Utc>"
+        FROM certificates
+        WHERE valid_until >= CURRENT_DATE AND valid_until <= CURRENT_DATE + INTERVAL ? DAY
+        ORDER BY valid_until, id
+        "#,
+        days
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(certificates)
+}
+
+// ------------------------------ 实验室检测 ------------------------------
+
+// 写入检测结果；提交了类别时登记到产品上，产品已登记其他类别时拒绝
+pub async fn insert_lab_results_db(
+    pool: &MySqlPool,
+    actor: &str,
+    product_id: &str,
+    category: Option<&str>,
+    results: &[LabResultInput],
+    report_hashes: &[Option<String>],
+) -> Result<Vec<u64>, AppError> {
+    let mut tx = pool.begin().await?;
+    let registered = sqlx::query_scalar!(r#"SELECT product_category FROM traceability_data WHERE product_id = ? FOR UPDATE"#, product_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("未找到产品ID为 '{}' 的食品记录。", product_id)))?;
+    match (registered.as_deref(), category) {
+        (Some(registered), Some(category)) if !lab_results::same_category(registered, category) => {
+            return Err(AppError::Conflict(format!(
+                "产品ID '{}' 已登记为类别 '{}'，不能改为 '{}'。", product_id, registered, category
+            )));
+        }
+        (None, Some(category)) => {
+            sqlx::query!(r#"UPDATE traceability_data SET product_category = ? WHERE product_id = ?"#, category, product_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        _ => {}
+    }
+    let mut ids = Vec::with_capacity(results.len());
+    for (result, report_hash) in results.iter().zip(report_hashes) {
+        let id = sqlx::query!(
+            r#"
+            INSERT INTO lab_results (product_id, analyte, method, measured_value, unit, laboratory, sample_date, report_hash, created_by)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+            product_id, result.analyte.trim(), result.method.trim(), result.measured_value, result.unit.trim(),
+            result.laboratory.trim(), result.sample_date, report_hash, actor
+        )
+        .execute(&mut *tx)
+        .await?
+        .last_insert_id();
+        ids.push(id);
+    }
+    append_audit_log_db(&mut tx, &NewAuditEntry::new(actor, "lab_result.create", Some(product_id), json!({
+        "resultIds": ids,
+        "category": category,
+        "results": results.iter().zip(report_hashes).map(|(result, report_hash)| json!({
+            "analyte": result.analyte.trim(),
+            "measuredValue": result.measured_value,
+            "unit": result.unit.trim(),
+            "laboratory": result.laboratory.trim(),
+            "sampleDate": result.sample_date,
+            "reportHash": report_hash,
+        })).collect::<Vec<_>>(),
+    }))).await?;
+    tx.commit().await?;
+    Ok(ids)
+}
+
+// 产品登记的类别，记录不存在时返回 NotFound
+pub async fn get_product_category_db(pool: &MySqlPool, product_id: &str) -> Result<Option<String>, AppError> {
+    let category = sqlx::query_scalar!(r#"SELECT product_category FROM traceability_data WHERE product_id = ?"#, product_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("未找到产品ID为 '{}' 的食品记录。", product_id)))?;
+    Ok(category)
+}
+
+pub async fn list_lab_results_db(pool: &MySqlPool, product_id: &str) -> Result<Vec<LabResult>, AppError> {
+    let results = sqlx::query_as!(
+        LabResult,
+        r#"
+        SELECT id, product_id, analyte, method, measured_value, unit, laboratory, sample_date, report_hash, created_by,
+               created_at as "created_at!: chrono::DateTime<chrono::Utc>"
+        FROM lab_results WHERE product_id = ?
+        ORDER BY sample_date, id
+        "#,
+        product_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(results)
+}
@@ -0,0 +1,59 @@
+// 合约部署 (deploy 命令)
+// 使用 Hardhat 编译产物中的字节码，由配置的部署账户签名合约创建交易，等待回执后把合约地址和部署区块写入部署清单，
+// 后端下次启动时从清单读取 (见 chains.rs)，替代手动运行 npx hardhat run scripts/deploy.js 再复制地址的步骤
+use chrono::Utc;
+use log::info;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use crate::artifacts::{self, ContractArtifact, DeploymentManifest};
+use crate::chains::Chain;
+use crate::errors::AppError;
+use crate::eth_rpc::parse_quantity;
+use crate::signer::LocalSigner;
+
+const RECEIPT_TIMEOUT: Duration = Duration::from_secs(120);
+
+// 部署合约并写入部署清单，返回清单及其路径
+pub async fn run_deploy(
+    chain: &Chain,
+    signer: &LocalSigner,
+    artifact: &ContractArtifact,
+    deployments_dir: &Path,
+) -> Result<(DeploymentManifest, PathBuf), AppError> {
+    if artifact.bytecode.is_empty() {
+        return Err(AppError::InvalidInput(format!("合约 {} 的编译产物没有字节码。", artifact.contract_name)));
+    }
+    info!(
+        "正在链 {} ({}) 上部署合约 {}，部署账户 {}",
+        chain.config.name, chain.chain_id(), artifact.contract_name, signer.address()
+    );
+    let tx_hash = signer.deploy_contract(&chain.eth_client, chain.chain_id(), &artifact.bytecode).await?;
+    info!("合约创建交易已发送: {}", tx_hash);
+
+    let receipt = chain
+        .eth_client
+        .wait_for_receipt(&tx_hash, RECEIPT_TIMEOUT)
+        .await?
+        .ok_or_else(|| AppError::BlockchainError(format!("合约创建交易 {} 超过 {} 秒未被打包。", tx_hash, RECEIPT_TIMEOUT.as_secs())))?;
+    if !receipt.is_success() {
+        return Err(AppError::BlockchainError(format!("合约创建交易 {} 执行失败。", tx_hash)));
+    }
+    let address = receipt
+        .contract_address
+        .as_deref()
+        .ok_or_else(|| AppError::BlockchainError(format!("合约创建交易 {} 的回执中没有合约地址。", tx_hash)))?
+        .to_lowercase();
+
+    let manifest = DeploymentManifest {
+        chain_id: chain.chain_id(),
+        network: chain.config.name.clone(),
+        contract_name: artifact.contract_name.clone(),
+        address,
+        deploy_block: parse_quantity(&receipt.block_number)?,
+        transaction_hash: tx_hash,
+        deployer: signer.address().to_string(),
+        deployed_at: Utc::now(),
+    };
+    let path = artifacts::write_deployment(deployments_dir, &manifest)?;
+    Ok((manifest, path))
+}
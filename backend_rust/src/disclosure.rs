@@ -0,0 +1,165 @@
+// 加盐字段承诺与选择性披露 (salted-fields-v1)
+// producerInfo 等字段取值范围很小，对公开的元数据哈希穷举即可还原；该方案为每个字段生成 32 字节随机盐：
+//   fieldCommitment = keccak256(keccak256(utf8(field)) || salt || keccak256(utf8(JCS(value))))
+//   metadataHash    = keccak256(按字段名 UTF-8 字节序拼接的 fieldCommitment)
+// 披露时给出选定字段的明文与盐值以及其余字段的承诺，接收方能重新计算出上链的哈希，而未披露的字段无法穷举
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as JsonValue};
+use std::collections::BTreeMap;
+use crate::auth;
+use crate::canonical_json;
+use crate::errors::AppError;
+use crate::hashing::{normalize_hash, to_hex_string};
+use crate::models::MetadataField;
+
+// 字段承诺与承诺根的计算定义在 proof_core 中，离线验证程序使用相同的实现
+pub use proof_core::disclosure::{commitment_root, field_commitment, FIELD_COMMITMENT_ENCODING, ROOT_ENCODING};
+
+// 未指定 publicFields 时公开的字段 (列表页需要展示产品名称)
+pub const DEFAULT_PUBLIC_FIELDS: [&str; 2] = ["productId", "productName"];
+pub const PUBLIC_AUDIENCE: &str = "public";
+
+// 披露给受众的字段：明文与盐值
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RevealedField {
+    pub field: String,
+    pub value: JsonValue,
+    pub salt: String,
+}
+
+// 选择性披露包，不依赖本服务即可用 verify_disclosure 复核
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SelectiveDisclosure {
+    pub product_id: String,
+    pub audience: String,
+    pub metadata_hash: String,              // 上链的承诺根，可对照 /verify 或 /proof 确认已上链
+    pub revealed: Vec<RevealedField>,
+    pub commitments: BTreeMap<String, String>, // 全部字段的承诺，包括未披露的字段
+    pub commitment_encoding: String,
+    pub root_encoding: String,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+// 元数据中存在的默认公开字段
+pub fn default_public_fields(metadata: &JsonValue) -> Vec<String> {
+    DEFAULT_PUBLIC_FIELDS
+        .iter()
+        .filter(|field| metadata.get(**field).is_some())
+        .map(|field| field.to_string())
+        .collect()
+}
+
+// 32 字节随机盐，带 0x 前缀
+pub fn random_salt() -> String {
+    format!("0x{}", auth::random_hex(32))
+}
+
+// 为元数据的每个字段生成 (或使用客户端提交的) 盐值并计算承诺，返回 (公开字段的规范化 JSON, 承诺根, 全部字段)
+pub fn commit_metadata(
+    metadata: &JsonValue,
+    salts: Option<&BTreeMap<String, String>>,
+    public_fields: &[String],
+) -> Result<(String, String, Vec<MetadataField>), AppError> {
+    let object = metadata
+        .as_object()
+        .ok_or_else(|| AppError::InvalidInput("元数据必须是 JSON 对象。".to_string()))?;
+    if object.is_empty() {
+        return Err(AppError::InvalidInput("salted-fields-v1 的元数据至少需要一个字段。".to_string()));
+    }
+    if let Some(salts) = salts {
+        if let Some(field) = salts.keys().find(|field| !object.contains_key(field.as_str())) {
+            return Err(AppError::InvalidInput(format!("fieldSalts 中的字段 '{}' 不在元数据中。", field)));
+        }
+    }
+    if let Some(field) = public_fields.iter().find(|field| !object.contains_key(field.as_str())) {
+        return Err(AppError::InvalidInput(format!("publicFields 中的字段 '{}' 不在元数据中。", field)));
+    }
+
+    let mut fields = Vec::with_capacity(object.len());
+    let mut public_object = Map::new();
+    for (field_name, value) in object {
+        let salt = match salts {
+            Some(salts) => salts
+                .get(field_name)
+                .map(|salt| normalize_hash(salt))
+                .ok_or_else(|| AppError::InvalidInput(format!("fieldSalts 缺少字段 '{}' 的盐值。", field_name)))?,
+            None => random_salt(),
+        };
+        let value_canonical = canonical_json::canonicalize(value)?;
+        let commitment = to_hex_string(&field_commitment(field_name, &value_canonical, &salt)?);
+        let is_public = public_fields.contains(field_name);
+        if is_public {
+            public_object.insert(field_name.clone(), value.clone());
+        }
+        fields.push(MetadataField { field_name: field_name.clone(), value_canonical, salt, commitment, is_public });
+    }
+    let root = commitment_root(&commitments_of(&fields))?;
+    let public_canonical = canonical_json::canonicalize(&JsonValue::Object(public_object))?;
+    Ok((public_canonical, root, fields))
+}
+
+fn commitments_of(fields: &[MetadataField]) -> BTreeMap<String, String> {
+    fields.iter().map(|field| (field.field_name.clone(), field.commitment.clone())).collect()
+}
+
+// 由存储的明文与盐值重新计算承诺根 (不信任存储的承诺列)
+pub fn recompute_root(fields: &[MetadataField]) -> Result<String, AppError> {
+    if fields.is_empty() {
+        return Err(AppError::InternalError("salted-fields-v1 记录缺少字段承诺 (metadata_fields)。".to_string()));
+    }
+    let mut commitments = BTreeMap::new();
+    for field in fields {
+        let commitment = field_commitment(&field.field_name, &field.value_canonical, &field.salt)?;
+        commitments.insert(field.field_name.clone(), to_hex_string(&commitment));
+    }
+    Ok(commitment_root(&commitments)?)
+}
+
+// 披露 reveal 中的字段，其余字段只给出承诺
+pub fn build_disclosure(
+    product_id: &str,
+    metadata_hash: &str,
+    audience: &str,
+    fields: &[MetadataField],
+    reveal: &[String],
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<SelectiveDisclosure, AppError> {
+    let mut revealed = Vec::with_capacity(reveal.len());
+    for field in fields.iter().filter(|field| reveal.contains(&field.field_name)) {
+        let value = serde_json::from_str(&field.value_canonical)
+            .map_err(|e| AppError::InternalError(format!("字段 '{}' 的存储值不是有效的 JSON: {}", field.field_name, e)))?;
+        revealed.push(RevealedField { field: field.field_name.clone(), value, salt: field.salt.clone() });
+    }
+    Ok(SelectiveDisclosure {
+        product_id: product_id.to_string(),
+        audience: audience.to_string(),
+        metadata_hash: normalize_hash(metadata_hash),
+        revealed,
+        commitments: commitments_of(fields),
+        commitment_encoding: FIELD_COMMITMENT_ENCODING.to_string(),
+        root_encoding: ROOT_ENCODING.to_string(),
+        expires_at,
+    })
+}
+
+// 复核披露包：每个披露字段的承诺与列表一致，全部承诺的根等于上链的哈希
+pub fn verify_disclosure(disclosure: &SelectiveDisclosure) -> Result<(), AppError> {
+    for revealed in &disclosure.revealed {
+        let expected = disclosure
+            .commitments
+            .get(&revealed.field)
+            .ok_or_else(|| AppError::InvalidInput(format!("披露的字段 '{}' 不在承诺列表中。", revealed.field)))?;
+        let value_canonical = canonical_json::canonicalize(&revealed.value)?;
+        let commitment = to_hex_string(&field_commitment(&revealed.field, &value_canonical, &revealed.salt)?);
+        if commitment != normalize_hash(expected) {
+            return Err(AppError::InvalidInput(format!("字段 '{}' 的明文与盐值计算出的承诺与列表不一致。", revealed.field)));
+        }
+    }
+    let root = commitment_root(&disclosure.commitments)?;
+    if root != normalize_hash(&disclosure.metadata_hash) {
+        return Err(AppError::InvalidInput(format!("字段承诺的根为 {}，与元数据哈希 {} 不一致。", root, disclosure.metadata_hash)));
+    }
+    Ok(())
+}
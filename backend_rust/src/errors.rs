@@ -2,6 +2,7 @@ use actix_web::{error::ResponseError, guard::fn_guard, http::StatusCode, HttpRes
 use serde::Serialize;
 use std::fmt;
 use sqlx::Error as SqlxError;
+use proof_core::error::CoreError;
 
 #[derive(Debug)]
 pub enum AppError {
@@ -10,10 +11,13 @@ pub enum AppError {
     InvalidInput(String),   // 例如 Metadata format is invalid
     Conflict(String),       // 例如 Product ID X already exists
     InternalError(String),  // 通用内部错误
+    MetadataHashMismatch { expected: String, received: String }, // 服务端计算的元数据哈希与客户端提交的不一致
+    BlockchainError(String), // 以太坊节点调用失败或返回了无法解析的数据
+    AnchorValidationFailed(String), // 客户端提交的上链交易不存在、失败或与请求内容不符
+    Unauthorized(String),   // 未登录、会话无效或 SIWE 登录校验失败
+    Forbidden(String),      // 已登录但无权执行该操作
     // 可以根据需要添加更多错误变体，例如：
     // SerializationError(serde_json::Error),
-    // Unauthorized,
-    // Forbidden,
 }
 
 // 定义一个简单的 JSON 错误响应结构
@@ -34,6 +38,13 @@ impl fmt::Display for AppError {
             AppError::InvalidInput(msg) => write!(f, "Invalid Input: {}", msg),
             AppError::Conflict(msg) => write!(f, "Conflict: {}", msg),
             AppError::InternalError(msg) => write!(f, "Internal Server Error: {}", msg),
+            AppError::MetadataHashMismatch { expected, received } => {
+                write!(f, "Metadata hash mismatch: expected {}, received {}", expected, received)
+            }
+            AppError::BlockchainError(msg) => write!(f, "Blockchain error: {}", msg),
+            AppError::AnchorValidationFailed(msg) => write!(f, "Anchor validation failed: {}", msg),
+            AppError::Unauthorized(msg) => write!(f, "Unauthorized: {}", msg),
+            AppError::Forbidden(msg) => write!(f, "Forbidden: {}", msg),
         }
     }
 }
@@ -57,6 +68,11 @@ impl ResponseError for AppError {
             AppError::InvalidInput(_) => StatusCode::BAD_REQUEST,
             AppError::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::MetadataHashMismatch { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::BlockchainError(_) => StatusCode::BAD_GATEWAY,
+            AppError::AnchorValidationFailed(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
         }
     }
 
@@ -90,6 +106,13 @@ impl ResponseError for AppError {
                 AppError::InvalidInput(m) => m.clone(),
                 AppError::Conflict(m) => m.clone(),
                 AppError::InternalError(m) => m.clone(),
+                AppError::MetadataHashMismatch { expected, received } => format!(
+                    "元数据哈希不匹配：服务端计算值为 {}，客户端提交值为 {}。", expected, received
+                ),
+                AppError::BlockchainError(m) => m.clone(),
+                AppError::AnchorValidationFailed(m) => m.clone(),
+                AppError::Unauthorized(m) => m.clone(),
+                AppError::Forbidden(m) => m.clone(),
             },
             // detail: detail_message, // 如果使用上面的 ErrorResponse 结构
         })
@@ -119,3 +142,14 @@ impl From<serde_json::Error> for AppError {
         AppError::InvalidInput(format!("JSON 解析或序列化错误: {}", err))
     }
 }
+
+// proof_core 的错误变体与 AppError 中的同名变体一一对应
+impl From<CoreError> for AppError {
+    fn from(err: CoreError) -> Self {
+        match err {
+            CoreError::InvalidInput(msg) => AppError::InvalidInput(msg),
+            CoreError::InternalError(msg) => AppError::InternalError(msg),
+            CoreError::BlockchainError(msg) => AppError::BlockchainError(msg),
+        }
+    }
+}
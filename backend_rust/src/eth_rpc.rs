@@ -0,0 +1,247 @@
+// 以太坊 JSON-RPC 客户端 (HTTP)
+// 只实现后端实际用到的少量方法，兼容 Hardhat / anvil 本地节点以及标准以太坊节点
+use log::debug;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use crate::errors::AppError;
+
+// 事件日志结构与十六进制解析定义在 proof_core 中，离线验证程序使用相同的实现
+pub use proof_core::rpc_types::{decode_hex_bytes, parse_quantity, RpcLog};
+
+#[derive(Clone)]
+pub struct EthClient {
+    http: reqwest::Client,
+    rpc_url: String,
+    next_id: Arc<AtomicU64>, // JSON-RPC 请求 id
+}
+
+// eth_getTransactionReceipt 返回的交易回执 (只保留用到的字段，数值均为 0x 十六进制字符串)
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionReceipt {
+    pub block_number: String,
+    pub from: String,
+    pub to: Option<String>, // 合约创建交易为 null
+    pub contract_address: Option<String>, // 合约创建交易部署出的合约地址
+    pub status: Option<String>, // 0x1 成功，0x0 失败 (拜占庭分叉之前的回执没有该字段)
+    pub logs: Vec<RpcLog>,
+}
+
+// eth_getBlockByNumber 返回的区块头 (只保留用到的字段)
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockHeader {
+    pub hash: String,
+    pub base_fee_per_gas: Option<String>, // 伦敦分叉之前的区块没有该字段
+}
+
+impl TransactionReceipt {
+    pub fn is_success(&self) -> bool {
+        self.status.as_deref() == Some("0x1")
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct RpcErrorObject {
+    code: i64,
+    message: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<RpcErrorObject>,
+}
+
+impl EthClient {
+    pub fn new(rpc_url: &str) -> Self {
+        EthClient {
+            http: reqwest::Client::new(),
+            rpc_url: rpc_url.to_string(),
+            next_id: Arc::new(AtomicU64::new(1)),
+        }
+    }
+
+    // 发送 JSON-RPC 请求，result 为 null 时返回 None (例如交易回执尚不存在)
+    async fn request<T: DeserializeOwned>(&self, method: &str, params: JsonValue) -> Result<Option<T>, AppError> {
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": self.next_id.fetch_add(1, Ordering::Relaxed),
+            "method": method,
+            "params": params,
+        });
+
+        let response: RpcResponse<T> = self.http
+            .post(&self.rpc_url)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| AppError::BlockchainError(format!("请求以太坊节点 {} 失败: {}", self.rpc_url, e)))?
+            .json()
+            .await
+            .map_err(|e| AppError::BlockchainError(format!("解析 {} 的响应失败: {}", method, e)))?;
+
+        if let Some(err) = response.error {
+            return Err(AppError::BlockchainError(format!("{} 调用失败 (code {}): {}", method, err.code, err.message)));
+        }
+        Ok(response.result)
+    }
+
+    // 只读调用合约 (eth_call, latest 区块)，返回 ABI 编码的返回数据
+    pub async fn eth_call(&self, to: &str, data: &[u8]) -> Result<Vec<u8>, AppError> {
+        let result: Option<String> = self
+            .request("eth_call", json!([{ "to": to, "data": format!("0x{}", hex::encode(data)) }, "latest"]))
+            .await?;
+        let result = result.ok_or_else(|| AppError::BlockchainError("eth_call 返回空结果。".to_string()))?;
+        Ok(decode_hex_bytes(&result)?)
+    }
+
+    // 查询交易回执，交易不存在或尚未打包时返回 None
+    pub async fn get_transaction_receipt(&self, tx_hash: &str) -> Result<Option<TransactionReceipt>, AppError> {
+        self.request("eth_getTransactionReceipt", json!([tx_hash])).await
+    }
+
+    // 交易是否仍为节点所知 (已打包或在交易池中)，被节点丢弃的交易返回 false
+    pub async fn transaction_known(&self, tx_hash: &str) -> Result<bool, AppError> {
+        let result: Option<JsonValue> = self.request("eth_getTransactionByHash", json!([tx_hash])).await?;
+        Ok(result.is_some())
+    }
+
+    // 等待交易被打包，超时仍无回执时返回 None
+    pub async fn wait_for_receipt(&self, tx_hash: &str, timeout: Duration) -> Result<Option<TransactionReceipt>, AppError> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            if let Some(receipt) = self.get_transaction_receipt(tx_hash).await? {
+                return Ok(Some(receipt));
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Ok(None);
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+    }
+
+    // 节点托管的账户 (Hardhat / anvil 本地节点默认解锁)
+    pub async fn accounts(&self) -> Result<Vec<String>, AppError> {
+        Ok(self.request("eth_accounts", json!([])).await?.unwrap_or_default())
+    }
+
+    // 由节点使用已解锁账户签名并发送交易，仅适用于开发 / 测试节点
+    pub async fn send_transaction(&self, from: &str, to: &str, data: &[u8]) -> Result<String, AppError> {
+        let result: Option<String> = self
+            .request("eth_sendTransaction", json!([{ "from": from, "to": to, "data": format!("0x{}", hex::encode(data)) }]))
+            .await?;
+        result
+            .map(|hash| hash.to_lowercase())
+            .ok_or_else(|| AppError::BlockchainError("eth_sendTransaction 返回空结果。".to_string()))
+    }
+
+    // 广播已签名的原始交易，返回交易哈希
+    pub async fn send_raw_transaction(&self, raw_tx: &[u8]) -> Result<String, AppError> {
+        let result: Option<String> = self
+            .request("eth_sendRawTransaction", json!([format!("0x{}", hex::encode(raw_tx))]))
+            .await?;
+        result
+            .map(|hash| hash.to_lowercase())
+            .ok_or_else(|| AppError::BlockchainError("eth_sendRawTransaction 返回空结果。".to_string()))
+    }
+
+    // 账户的交易计数 (block_tag 为 pending 时包含交易池中待打包的交易)
+    pub async fn get_transaction_count(&self, address: &str, block_tag: &str) -> Result<u64, AppError> {
+        let result: Option<String> = self.request("eth_getTransactionCount", json!([address, block_tag])).await?;
+        Ok(parse_quantity(&result.ok_or_else(|| AppError::BlockchainError("eth_getTransactionCount 返回空结果。".to_string()))?)?)
+    }
+
+    // 估算交易所需 gas，to 为空表示合约创建交易
+    pub async fn estimate_gas(&self, from: &str, to: Option<&str>, data: &[u8]) -> Result<u64, AppError> {
+        let mut call = json!({ "from": from, "data": format!("0x{}", hex::encode(data)) });
+        if let Some(to) = to {
+            call["to"] = json!(to);
+        }
+        let result: Option<String> = self.request("eth_estimateGas", json!([call])).await?;
+        Ok(parse_quantity(&result.ok_or_else(|| AppError::BlockchainError("eth_estimateGas 返回空结果。".to_string()))?)?)
+    }
+
+    // 节点建议的 EIP-1559 小费 (maxPriorityFeePerGas)
+    pub async fn max_priority_fee_per_gas(&self) -> Result<u64, AppError> {
+        let result: Option<String> = self.request("eth_maxPriorityFeePerGas", json!([])).await?;
+        Ok(parse_quantity(&result.ok_or_else(|| AppError::BlockchainError("eth_maxPriorityFeePerGas 返回空结果。".to_string()))?)?)
+    }
+
+    // 节点所在链的链 ID
+    pub async fn chain_id(&self) -> Result<u64, AppError> {
+        let result: Option<String> = self.request("eth_chainId", json!([])).await?;
+        Ok(parse_quantity(&result.ok_or_else(|| AppError::BlockchainError("eth_chainId 返回空结果。".to_string()))?)?)
+    }
+
+    // 最新区块头
+    pub async fn latest_block(&self) -> Result<BlockHeader, AppError> {
+        let result: Option<BlockHeader> = self.request("eth_getBlockByNumber", json!(["latest", false])).await?;
+        result.ok_or_else(|| AppError::BlockchainError("eth_getBlockByNumber(latest) 返回空结果。".to_string()))
+    }
+
+    // 当前最新区块号
+    pub async fn block_number(&self) -> Result<u64, AppError> {
+        let result: Option<String> = self.request("eth_blockNumber", json!([])).await?;
+        Ok(parse_quantity(&result.ok_or_else(|| AppError::BlockchainError("eth_blockNumber 返回空结果。".to_string()))?)?)
+    }
+
+    // 按区块号查询区块头，区块不存在时返回 None
+    pub async fn get_block_by_number(&self, number: u64) -> Result<Option<BlockHeader>, AppError> {
+        self.request("eth_getBlockByNumber", json!([format!("0x{:x}", number), false])).await
+    }
+
+    // 节点返回的原始回执 JSON (生成离线证明时需要全部字段，包括 logsBloom、cumulativeGasUsed 与 type)
+    pub async fn get_transaction_receipt_json(&self, tx_hash: &str) -> Result<Option<JsonValue>, AppError> {
+        self.request("eth_getTransactionReceipt", json!([tx_hash])).await
+    }
+
+    // 按区块哈希查询原始区块 JSON (区块头全部字段，transactions 只包含交易哈希)
+    pub async fn get_block_json_by_hash(&self, block_hash: &str) -> Result<Option<JsonValue>, AppError> {
+        self.request("eth_getBlockByHash", json!([block_hash, false])).await
+    }
+
+    // 区块内全部交易的原始回执：优先使用 eth_getBlockReceipts，节点不支持时逐笔查询
+    pub async fn get_block_receipts_json(&self, block_number: u64, tx_hashes: &[String]) -> Result<Vec<JsonValue>, AppError> {
+        match self.request::<Vec<JsonValue>>("eth_getBlockReceipts", json!([format!("0x{:x}", block_number)])).await {
+            Ok(Some(receipts)) if receipts.len() == tx_hashes.len() => return Ok(receipts),
+            Ok(_) => {}
+            Err(e) => debug!("eth_getBlockReceipts 不可用，改为逐笔查询回执: {}", e),
+        }
+        let mut receipts = Vec::with_capacity(tx_hashes.len());
+        for tx_hash in tx_hashes {
+            let receipt = self
+                .get_transaction_receipt_json(tx_hash)
+                .await?
+                .ok_or_else(|| AppError::BlockchainError(format!("区块 {} 中交易 {} 的回执不存在。", block_number, tx_hash)))?;
+            receipts.push(receipt);
+        }
+        Ok(receipts)
+    }
+
+    // 查询指定合约在区块范围内 (闭区间) 的事件日志
+    pub async fn get_logs(&self, address: &str, topic0: &str, from_block: u64, to_block: u64) -> Result<Vec<RpcLog>, AppError> {
+        let filter = json!([{
+            "address": address,
+            "topics": [topic0],
+            "fromBlock": format!("0x{:x}", from_block),
+            "toBlock": format!("0x{:x}", to_block),
+        }]);
+        Ok(self.request("eth_getLogs", filter).await?.unwrap_or_default())
+    }
+
+    // 按 topic 查询指定合约从创世区块到最新区块的全部事件日志
+    pub async fn get_logs_by_topics(&self, address: &str, topics: &[&str]) -> Result<Vec<RpcLog>, AppError> {
+        let filter = json!([{
+            "address": address,
+            "topics": topics,
+            "fromBlock": "earliest",
+            "toBlock": "latest",
+        }]);
+        Ok(self.request("eth_getLogs", filter).await?.unwrap_or_default())
+    }
+}
@@ -0,0 +1,67 @@
+use actix_web::{get, post, web, HttpResponse};
+use crate::models::{AppState, ChainQuery, ReconciliationQuery, ReconciliationReportResponse};
+use crate::audit;
+use crate::auth::AdminAccess;
+use crate::db;
+use crate::errors::AppError;
+use crate::reconciliation;
+
+// 查询对账结果 (默认最近一次)，差异列表分页返回
+#[get("/api/admin/reconciliation")]
+pub async fn get_reconciliation_report_handler(
+    app_state: web::Data<AppState>,
+    query_params: web::Query<ReconciliationQuery>,
+    _access: AdminAccess,
+) -> Result<HttpResponse, AppError> {
+    let params = query_params.into_inner();
+    let page = params.page.unwrap_or(1).max(1);
+    let page_size = params.page_size.unwrap_or(20).max(1);
+
+    let chain = app_state.chains.resolve(params.chain_id)?;
+
+    let Some(run) = db::get_reconciliation_run_db(&app_state.db_pool, params.run_id, chain.chain_id()).await? else {
+        if let Some(run_id) = params.run_id {
+            return Err(AppError::NotFound(format!("未找到对账任务 #{}。", run_id)));
+        }
+        return Ok(HttpResponse::Ok().json(ReconciliationReportResponse {
+            run: None, items: Vec::new(), total_items: 0, page, page_size, total_pages: 0,
+        }));
+    };
+
+    let (total_items, items) = db::list_reconciliation_findings_db(
+        &app_state.db_pool,
+        run.id,
+        params.finding_type.as_deref(),
+        page_size,
+        (page - 1) * page_size,
+    ).await?;
+    let total_pages = (total_items as f64 / page_size as f64).ceil() as i64;
+    Ok(HttpResponse::Ok().json(ReconciliationReportResponse {
+        run: Some(run), items, total_items, page, page_size, total_pages,
+    }))
+}
+
+// 立即对指定链 (默认链) 执行一次对账
+#[post("/api/admin/reconciliation/run")]
+pub async fn run_reconciliation_handler(
+    app_state: web::Data<AppState>,
+    query_params: web::Query<ChainQuery>,
+    access: AdminAccess,
+) -> Result<HttpResponse, AppError> {
+    let chain = app_state.chains.resolve(query_params.chain_id)?;
+    let run = reconciliation::run_reconciliation(
+        &app_state.db_pool,
+        chain.chain_id(),
+        chain.contract_address(),
+        reconciliation::TRIGGER_MANUAL,
+        access.actor(),
+    ).await?;
+    Ok(HttpResponse::Ok().json(run))
+}
+
+// 校验审计日志哈希链与链上锚定的链头，报告第一处断链，并列出与审计记录不一致的产品记录
+#[get("/api/admin/audit-log/verify")]
+pub async fn verify_audit_log_handler(app_state: web::Data<AppState>, _access: AdminAccess) -> Result<HttpResponse, AppError> {
+    let verification = audit::verify_audit_chain(&app_state).await?;
+    Ok(HttpResponse::Ok().json(verification))
+}
@@ -0,0 +1,54 @@
+use actix_web::{get, post, web, HttpResponse};
+use chrono::Utc;
+use log::info;
+use crate::models::{AppState, AuthNonceResponse, SiweLoginRequest, SiweLoginResponse};
+use crate::auth;
+use crate::db;
+use crate::errors::AppError;
+use crate::siwe::{self, SiweMessage};
+
+// 签发一次性 nonce，客户端把它写入 SIWE 消息的 Nonce 字段
+#[get("/api/auth/nonce")]
+pub async fn get_auth_nonce_handler(app_state: web::Data<AppState>) -> Result<HttpResponse, AppError> {
+    let nonce = auth::random_hex(16);
+    let expires_at = db::create_auth_nonce_db(&app_state.db_pool, &nonce, app_state.auth.nonce_ttl_secs).await?;
+    Ok(HttpResponse::Ok().json(AuthNonceResponse { nonce, expires_at }))
+}
+
+// 校验钱包签名的 SIWE 消息 (域名、nonce、有效期、链 ID)，签发绑定该地址的会话令牌
+#[post("/api/auth/siwe")]
+pub async fn siwe_login_handler(
+    app_state: web::Data<AppState>,
+    login_request: web::Json<SiweLoginRequest>,
+) -> Result<HttpResponse, AppError> {
+    let request = login_request.into_inner();
+    let message = SiweMessage::parse(&request.message)?;
+    let now = Utc::now();
+    message.validate(&app_state.auth.domains, now)?;
+    if app_state.chains.resolve(Some(message.chain_id)).is_err() {
+        return Err(AppError::Unauthorized(format!("SIWE 消息的链 ID {} 不是本服务支持的链。", message.chain_id)));
+    }
+    siwe::verify_signature(&message, &request.message, &request.signature)?;
+    // 签名有效后才消耗 nonce，同一 nonce 只能登录一次
+    if !db::consume_auth_nonce_db(&app_state.db_pool, &message.nonce).await? {
+        return Err(AppError::Unauthorized("nonce 无效、已使用或已过期，请重新获取。".to_string()));
+    }
+
+    // 会话不超过 SIWE 消息自身的过期时间
+    let ttl_secs = match message.expiration_time {
+        Some(expiration) => (expiration - now).num_seconds().clamp(1, app_state.auth.session_ttl_secs as i64) as u64,
+        None => app_state.auth.session_ttl_secs,
+    };
+    let token = auth::random_hex(32);
+    let session = db::create_auth_session_db(
+        &app_state.db_pool, &auth::hash_token(&token), &message.address, message.chain_id, &message.domain, ttl_secs,
+    ).await?;
+    info!("地址 {} 已登录 (链 ID {}，URI {})", session.address, session.chain_id, message.uri);
+
+    Ok(HttpResponse::Ok().json(SiweLoginResponse {
+        token,
+        address: session.address,
+        chain_id: session.chain_id,
+        expires_at: session.expires_at,
+    }))
+}
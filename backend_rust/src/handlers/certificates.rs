@@ -0,0 +1,86 @@
+use actix_web::{get, post, web, HttpResponse};
+use log::info;
+use crate::models::{AppState, CertificateProductsRequest, CertificateRequest, CertificateResponse, ExpiringCertificatesQuery};
+use crate::auth::WriteAccess;
+use crate::certification;
+use crate::db;
+use crate::errors::AppError;
+
+async fn certificate_response(app_state: &AppState, certificate_id: u64) -> Result<CertificateResponse, AppError> {
+    let certificate = db::get_certificate_db(&app_state.db_pool, certificate_id).await?;
+    let organizations = db::list_certificate_organizations_db(&app_state.db_pool, certificate_id).await?;
+    let product_ids = db::list_certificate_products_db(&app_state.db_pool, certificate_id).await?;
+    Ok(CertificateResponse { certificate, organizations, product_ids })
+}
+
+// 只能把自己的记录 (签名者，未签名的记录为创建者) 关联到证书
+async fn require_product_owner(app_state: &AppState, access: &WriteAccess, product_ids: &[String]) -> Result<(), AppError> {
+    for product_id in product_ids {
+        let record = db::get_food_record_detail_db(&app_state.db_pool, product_id).await?;
+        access.require_record_owner(record.recorder.as_deref(), record.created_by.as_deref(), "将该记录关联到证书")?;
+    }
+    Ok(())
+}
+
+// 登记证书，可同时关联持证组织与产品记录
+#[post("/api/certificates")]
+pub async fn create_certificate_handler(
+    app_state: web::Data<AppState>,
+    certificate_request: web::Json<CertificateRequest>,
+    access: WriteAccess,
+) -> Result<HttpResponse, AppError> {
+    let request = certificate_request.into_inner();
+    let document_hash = certification::validate_request(&request)?;
+    let organizations = certification::normalize_list("organizations", &request.organizations)?;
+    let product_ids = certification::normalize_list("productIds", &request.product_ids)?;
+    require_product_owner(&app_state, &access, &product_ids).await?;
+    let certificate_id = db::create_certificate_db(
+        &app_state.db_pool, access.actor(), &request, &document_hash, &organizations, &product_ids,
+    ).await?;
+    info!(
+        "证书 #{} 已登记: {} {} ({} 至 {})",
+        certificate_id, request.certificate_type.as_str(), request.certificate_number.trim(), request.valid_from, request.valid_until
+    );
+    Ok(HttpResponse::Created().json(certificate_response(&app_state, certificate_id).await?))
+}
+
+// 将之后生产的批次关联到已登记的证书，只有证书的登记者或管理员可以操作
+#[post("/api/certificates/{certificate_id:\\d+}/products")]
+pub async fn add_certificate_products_handler(
+    app_state: web::Data<AppState>,
+    path: web::Path<u64>,
+    products_request: web::Json<CertificateProductsRequest>,
+    access: WriteAccess,
+) -> Result<HttpResponse, AppError> {
+    let certificate_id = path.into_inner();
+    let product_ids = certification::normalize_list("productIds", &products_request.product_ids)?;
+    if product_ids.is_empty() {
+        return Err(AppError::InvalidInput("productIds 不能为空。".to_string()));
+    }
+    let certificate = db::get_certificate_db(&app_state.db_pool, certificate_id).await?;
+    if certificate.created_by != access.actor() && !access.is_admin(&app_state.auth) {
+        return Err(AppError::Forbidden(format!("只有证书登记者 {} 或管理员可以为证书 #{} 关联产品。", certificate.created_by, certificate_id)));
+    }
+    require_product_owner(&app_state, &access, &product_ids).await?;
+    db::add_certificate_products_db(&app_state.db_pool, access.actor(), certificate_id, &product_ids).await?;
+    Ok(HttpResponse::Ok().json(certificate_response(&app_state, certificate_id).await?))
+}
+
+#[get("/api/certificates/{certificate_id:\\d+}")]
+pub async fn get_certificate_handler(
+    app_state: web::Data<AppState>,
+    path: web::Path<u64>,
+) -> Result<HttpResponse, AppError> {
+    Ok(HttpResponse::Ok().json(certificate_response(&app_state, path.into_inner()).await?))
+}
+
+// N 天内到期的证书，提醒持证方续证
+#[get("/api/certificates/expiring")]
+pub async fn list_expiring_certificates_handler(
+    app_state: web::Data<AppState>,
+    query: web::Query<ExpiringCertificatesQuery>,
+) -> Result<HttpResponse, AppError> {
+    let days = certification::clamp_days(query.days);
+    let certificates = db::list_expiring_certificates_db(&app_state.db_pool, days).await?;
+    Ok(HttpResponse::Ok().json(certificates))
+}
@@ -0,0 +1,39 @@
+use actix_web::{get, web, HttpResponse};
+use crate::models::{AppState, ChainConfigResponse, ChainQuery, ChainSummary};
+use crate::eip712::Eip712Domain;
+use crate::errors::AppError;
+
+// 指定链 (默认链) 的合约地址与 ABI，前端据此创建合约实例，重新部署后无需修改前端代码
+#[get("/api/chain/config")]
+pub async fn get_chain_config_handler(
+    app_state: web::Data<AppState>,
+    query_params: web::Query<ChainQuery>,
+) -> Result<HttpResponse, AppError> {
+    let chain = app_state.chains.resolve(query_params.chain_id)?;
+    let artifact = app_state.contract_artifact.as_ref().ok_or_else(|| {
+        AppError::NotFound("后端未加载合约编译产物，请先在 blockchain_hardhat 中运行 npx hardhat compile 并重启服务。".to_string())
+    })?;
+    let chains = app_state
+        .chains
+        .iter()
+        .map(|chain| ChainSummary {
+            chain_id: chain.chain_id(),
+            name: chain.config.name.clone(),
+            contract_address: chain.contract_address().to_string(),
+            deploy_block: chain.deploy_block(),
+            confirmations: chain.config.confirmations,
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(ChainConfigResponse {
+        chain_id: chain.chain_id(),
+        chain_name: chain.config.name.clone(),
+        contract_address: chain.contract_address().to_string(),
+        deploy_block: chain.deploy_block(),
+        contract_name: artifact.contract_name.clone(),
+        abi: artifact.abi.clone(),
+        eip712_domain: Eip712Domain::new(chain.chain_id(), chain.contract_address()),
+        default_chain_id: app_state.chains.default_chain().chain_id(),
+        chains,
+    }))
+}
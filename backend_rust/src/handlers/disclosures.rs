@@ -0,0 +1,104 @@
+use actix_web::{get, post, web, HttpResponse};
+use log::info;
+use crate::models::{AppState, DisclosureGrantResponse, DisclosureRequest, FoodRecordDetail, MetadataField};
+use crate::auth::{self, WriteAccess};
+use crate::db;
+use crate::disclosure::{self, SelectiveDisclosure};
+use crate::errors::AppError;
+use crate::hashing::HashScheme;
+
+const MAX_AUDIENCE_LEN: usize = 255;
+
+// 只有 salted-fields-v1 记录支持选择性披露
+async fn load_salted_record(app_state: &AppState, product_id: &str) -> Result<(FoodRecordDetail, Vec<MetadataField>), AppError> {
+    let record = db::get_food_record_detail_db(&app_state.db_pool, product_id).await?;
+    if HashScheme::parse(&record.hash_scheme)? != HashScheme::SaltedFieldsV1 {
+        return Err(AppError::Conflict(format!(
+            "产品ID '{}' 使用 {} 哈希方案，元数据已全部公开，不支持选择性披露。", product_id, record.hash_scheme
+        )));
+    }
+    let fields = db::get_metadata_fields_db(&app_state.db_pool, product_id).await?;
+    Ok((record, fields))
+}
+
+// 返回前自检，存储的明文、盐值与承诺不一致时拒绝导出
+fn checked(disclosure: SelectiveDisclosure) -> Result<SelectiveDisclosure, AppError> {
+    disclosure::verify_disclosure(&disclosure)
+        .map_err(|e| AppError::InternalError(format!("产品ID '{}' 的披露包未通过校验: {}", disclosure.product_id, e)))?;
+    Ok(disclosure)
+}
+
+// 公开披露：创建记录时指定的公开字段给出明文与盐值，其余字段只给出承诺
+#[get("/api/food-records/{product_id}/disclosure")]
+pub async fn get_public_disclosure_handler(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let product_id = path.into_inner();
+    let (record, fields) = load_salted_record(&app_state, &product_id).await?;
+    let public_fields: Vec<String> = fields.iter().filter(|f| f.is_public).map(|f| f.field_name.clone()).collect();
+    let disclosure = disclosure::build_disclosure(
+        &product_id, &record.onchain_metadata_hash, disclosure::PUBLIC_AUDIENCE, &fields, &public_fields, None,
+    )?;
+    Ok(HttpResponse::Ok().json(checked(disclosure)?))
+}
+
+// 为某一受众创建披露授权，返回的令牌交给受众查看选定字段；只有记录的签名者 (未签名的记录为创建者) 可以创建
+#[post("/api/food-records/{product_id}/disclosures")]
+pub async fn create_disclosure_handler(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+    disclosure_request: web::Json<DisclosureRequest>,
+    access: WriteAccess,
+) -> Result<HttpResponse, AppError> {
+    let product_id = path.into_inner();
+    let request = disclosure_request.into_inner();
+    let (record, fields) = load_salted_record(&app_state, &product_id).await?;
+
+    access.require_record_owner(record.recorder.as_deref(), record.created_by.as_deref(), "披露该记录的字段")?;
+
+    let audience = request.audience.trim().to_string();
+    if audience.is_empty() || audience.len() > MAX_AUDIENCE_LEN {
+        return Err(AppError::InvalidInput(format!("audience 不能为空且不能超过 {} 个字符。", MAX_AUDIENCE_LEN)));
+    }
+    if request.fields.is_empty() {
+        return Err(AppError::InvalidInput("fields 不能为空。".to_string()));
+    }
+    if let Some(unknown) = request.fields.iter().find(|name| !fields.iter().any(|f| f.field_name == **name)) {
+        return Err(AppError::InvalidInput(format!("记录没有字段 '{}'。", unknown)));
+    }
+    let mut disclosed = request.fields.clone();
+    disclosed.sort();
+    disclosed.dedup();
+
+    let token = auth::random_hex(32);
+    let expires_at = db::create_disclosure_db(
+        &app_state.db_pool, access.actor(), &auth::hash_token(&token), &product_id, &audience, &disclosed, request.ttl_secs,
+    ).await?;
+    info!("产品ID {} 已向 {} 披露字段 {}", product_id, audience, disclosed.join(", "));
+
+    Ok(HttpResponse::Created().json(DisclosureGrantResponse {
+        token,
+        product_id,
+        audience,
+        fields: disclosed,
+        expires_at,
+    }))
+}
+
+// 受众凭令牌查看披露内容
+#[get("/api/disclosures/{token}")]
+pub async fn get_disclosure_handler(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let token = path.into_inner();
+    let grant = db::get_disclosure_db(&app_state.db_pool, &auth::hash_token(&token))
+        .await?
+        .ok_or_else(|| AppError::NotFound("披露令牌不存在或已过期。".to_string()))?;
+    let (record, fields) = load_salted_record(&app_state, &grant.product_id).await?;
+    let disclosure = disclosure::build_disclosure(
+        &grant.product_id, &record.onchain_metadata_hash, &grant.audience, &fields, &grant.fields.0, grant.expires_at,
+    )?;
+    Ok(HttpResponse::Ok().json(checked(disclosure)?))
+}
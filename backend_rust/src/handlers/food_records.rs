@@ -2,10 +2,23 @@ use actix_web::{get, post, web, Responder, HttpResponse};
 // use serde_json::Value as JsonValue;
 use crate::models::{
     AppState, FoodRecordRequest, GenericResponse, PaginationParams, FoodRecordDetailResponse,
+    AnchorMode, MerkleProofResponse, RecordSignature, FoodRecordDetail, MerkleLeafDetail, RecordAnchor,
     // FoodListItem, RawFoodListItem, FoodRecordDetail,
     // PaginatedFoodListResponse
 };
+use crate::auth::WriteAccess;
 use crate::db;
+use crate::hashing;
+use crate::canonical_json;
+use crate::verification;
+use crate::audit;
+use crate::merkle;
+use crate::merkle_batch;
+use crate::disclosure;
+use crate::proof;
+use crate::certification;
+use crate::recall;
+use crate::eip712::{self, Eip712Domain};
 use sqlx::Error as SqlxError; // 引入 sqlx::Error 以便模式匹配
 use crate::errors::AppError;
 use log::{info, error, warn, debug}; // 引入日志宏
@@ -14,12 +27,122 @@ use log::{info, error, warn, debug}; // 引入日志宏
 pub async fn create_food_record_handler(
     app_state: web::Data<AppState>,
     record_request: web::Json<FoodRecordRequest>,
+    access: WriteAccess,
 ) -> Result<HttpResponse, AppError> { // 返回 Result<HttpResponse, AppError>
     let request_data = record_request.into_inner();
 
     info!("接收到创建食品记录的请求，产品ID: {}", request_data.product_id); // 日志：请求开始
 
-    let rows_affected = db::create_food_record_db(&app_state.db_pool, &request_data).await?; // '?' 将 AppError 传播
+    // 该前缀的 productId 在合约中保留给 Merkle 批次根，记录占用后批次根将无法上链或被冒充
+    if request_data.product_id.starts_with(merkle::MERKLE_ANCHOR_KEY_PREFIX) {
+        return Err(AppError::InvalidInput(format!(
+            "productId 不能以保留前缀 '{}' 开头。", merkle::MERKLE_ANCHOR_KEY_PREFIX
+        )));
+    }
+    if request_data.product_id == audit::HEAD_ANCHOR_KEY {
+        return Err(AppError::InvalidInput(format!("productId '{}' 保留给审计日志链头。", audit::HEAD_ANCHOR_KEY)));
+    }
+
+    // 服务端重新计算元数据哈希，客户端同时提交了哈希时，不一致直接拒绝
+    let public_fields = request_data
+        .public_fields
+        .clone()
+        .unwrap_or_else(|| disclosure::default_public_fields(&request_data.metadata));
+    let field_salts = request_data.field_salts.as_ref();
+    let canonical_metadata = match &request_data.metadata_hash_on_chain {
+        Some(received_hash) => hashing::verify_metadata_hash(
+            &request_data.metadata, request_data.hash_scheme, field_salts, &public_fields, received_hash,
+        ),
+        None => hashing::compute_metadata_hash(&request_data.metadata, request_data.hash_scheme, field_salts, &public_fields),
+    };
+    let canonical_metadata = match canonical_metadata {
+        Ok(canonical) => canonical,
+        Err(e) => {
+            warn!("产品ID {} 的元数据哈希校验失败: {}", request_data.product_id, e);
+            return Err(e);
+        }
+    };
+
+    let chain = app_state.chains.resolve(request_data.chain_id)?;
+
+    // 恢复 EIP-712 签名者作为记录者，域绑定记录所在的链与合约
+    let record_signature = match &request_data.signature {
+        Some(signature) => {
+            let domain = Eip712Domain::new(chain.chain_id(), chain.contract_address());
+            let recorder = eip712::recover_food_record_signer(
+                &domain, &request_data.product_id, &canonical_metadata.hash, signature,
+            )?;
+            debug!("产品ID {} 的记录签名者: {}", request_data.product_id, recorder);
+            // 登录用户只能提交自己签名的记录
+            if let Some(session) = &access.0 {
+                if session.address != recorder {
+                    return Err(AppError::Forbidden(format!(
+                        "记录签名者 {} 不是当前登录的地址 {}。", recorder, session.address
+                    )));
+                }
+            }
+            Some(RecordSignature { recorder, signature: signature.trim().to_lowercase() })
+        }
+        None if app_state.require_record_signature => {
+            return Err(AppError::InvalidInput("缺少 signature，请使用钱包对记录 (EIP-712 FoodRecord) 签名。".to_string()));
+        }
+        None => None,
+    };
+
+    // 客户端没有提交交易哈希时由后端托管上链：记录与发件箱条目 (或 Merkle 叶子) 同一事务写入，后台任务负责发送交易
+    let Some(transaction_hash) = &request_data.transaction_hash else {
+        if app_state.signer.is_none() {
+            return Err(AppError::InvalidInput("缺少 transactionHash，且后端未配置托管签名私钥 (SIGNER_PRIVATE_KEY)。".to_string()));
+        }
+        match request_data.anchor_mode {
+            AnchorMode::Record => {
+                let outbox_id = db::create_food_record_with_outbox_db(
+                    &app_state.db_pool, access.actor(), &request_data, &canonical_metadata, record_signature.as_ref(),
+                    chain.chain_id(), chain.contract_address(),
+                ).await?;
+                info!("产品ID {} 的记录已保存，等待后端上链 (发件箱 #{})。", request_data.product_id, outbox_id);
+            }
+            AnchorMode::Merkle => {
+                let leaf_hash = merkle::leaf_hash(&request_data.product_id, &canonical_metadata.hash)?;
+                let batch_id = db::create_food_record_in_merkle_batch_db(
+                    &app_state.db_pool, access.actor(), &request_data, &canonical_metadata, record_signature.as_ref(),
+                    &hashing::to_hex_string(&leaf_hash),
+                    chain.chain_id(), chain.contract_address(), app_state.merkle_batch.max_leaves,
+                ).await?;
+                info!("产品ID {} 的记录已保存，加入 Merkle 批次 #{}。", request_data.product_id, batch_id);
+            }
+        }
+        return Ok(HttpResponse::Accepted().json(GenericResponse {
+            status: "pending".to_string(),
+            message: format!("食品记录 {} 已保存，正在提交上链，可在详情中查看上链状态。", request_data.product_id),
+        }));
+    };
+
+    // 校验交易回执与 RecordAdded 事件，防止保存任意伪造的交易哈希
+    let anchor = verification::validate_anchor_transaction(
+        &chain.eth_client,
+        chain.contract_address(),
+        transaction_hash,
+        &request_data.product_id,
+        &canonical_metadata.hash,
+    ).await?;
+    debug!(
+        "产品ID {} 的上链交易已校验: 区块 {}，记录者 {}，链上时间戳 {}",
+        request_data.product_id, anchor.block_number, anchor.recorder, anchor.timestamp
+    );
+    // 客户端自行上链时，签名者必须就是发送上链交易的账户
+    if let Some(signature) = &record_signature {
+        if signature.recorder != anchor.sender {
+            return Err(AppError::AnchorValidationFailed(format!(
+                "记录签名者 {} 与上链交易 {} 的发送者 {} 不一致。", signature.recorder, transaction_hash, anchor.sender
+            )));
+        }
+    }
+
+    let record_anchor = anchor.to_record_anchor(&request_data.product_id, chain.chain_id(), chain.contract_address());
+    let rows_affected = db::create_food_record_db(
+        &app_state.db_pool, access.actor(), &request_data, &canonical_metadata, record_signature.as_ref(), &record_anchor,
+    ).await?; // '?' 将 AppError 传播
 
     if rows_affected > 0 {
         info!("产品ID {} 的记录已成功创建。", request_data.product_id); // 日志：成功
@@ -51,19 +174,177 @@ pub async fn get_food_record_detail_handler(
 ) -> Result<HttpResponse, AppError> {
     let product_id = path.into_inner();
     let record = db::get_food_record_detail_db(&app_state.db_pool, &product_id).await?;
+    let anchors = db::list_record_anchors_db(&app_state.db_pool, &product_id).await?;
+    let anchor_outbox = db::get_latest_outbox_entry_db(&app_state.db_pool, &product_id).await?;
+    let merkle_leaf = db::get_merkle_leaf_db(&app_state.db_pool, &product_id).await?;
+    let anchor_status = match &anchor_outbox {
+        Some(entry) => entry.status.clone(),
+        None => match &merkle_leaf {
+            // 批次收集中或已封存但尚未发送，对记录而言都是等待上链
+            Some(leaf) if leaf.batch_status == merkle_batch::BATCH_STATUS_OPEN || leaf.batch_status == merkle_batch::BATCH_STATUS_SEALED => {
+                "pending".to_string()
+            }
+            Some(leaf) => leaf.batch_status.clone(),
+            None => verification::ANCHOR_STATUS_CONFIRMED.to_string(), // 客户端自行上链，创建时已校验回执
+        },
+    };
+    let merkle_batch_id = current_merkle_leaf(merkle_leaf, anchors.last()).map(|leaf| leaf.batch_id);
+
+    let recalls = db::list_product_recalls_db(&app_state.db_pool, &product_id).await?;
+    let recall_status = recall::overall_status(&recalls);
+
+    let metadata_fields = db::get_metadata_fields_db(&app_state.db_pool, &product_id).await?;
+    let certificates = db::list_product_certificates_db(&app_state.db_pool, &product_id).await?;
+    let certification = certification::evaluate(
+        certification::production_date(&record.metadata_json.0, &metadata_fields), certificates,
+    );
+
+    // 早期记录没有保存规范化字节，JCS 与键顺序无关，可以从 JSON 列重新规范化
+    let metadata_canonical = match record.metadata_canonical {
+        Some(canonical) => canonical,
+        None => canonical_json::canonicalize(&record.metadata_json.0)?,
+    };
 
     // 转换 FoodRecordDetail 到 FoodRecordDetailResponse (解包 metadata_json.0)
     let response_payload = FoodRecordDetailResponse {
        product_id: record.product_id,
        metadata_json: record.metadata_json.0,
+       metadata_canonical,
        onchain_metadata_hash: record.onchain_metadata_hash,
+       hash_scheme: record.hash_scheme,
        blockchain_transaction_hash: record.blockchain_transaction_hash,
+       chain_id: record.chain_id,
+       contract_address: record.contract_address,
+       recorder: record.recorder,
+       recorder_signature: record.recorder_signature,
        created_at: record.created_at,
        updated_at: record.updated_at,
+       anchors,
+       anchor_status,
+       anchor_outbox,
+       merkle_batch_id,
+       recall_status,
+       recalls,
+       certification,
    };
    Ok(HttpResponse::Ok().json(response_payload))
 }
 
+// 验证与证明包到哪条链、哪个合约查询，以及是否按 Merkle 批次验证
+// Merkle 记录以批次为准；重新上链 (reanchor) 后记录按原哈希单独写入新合约，最新的上链记录不是批次交易时以它为准；
+// 尚未补全链信息的早期记录使用默认链
+async fn anchor_target(
+    app_state: &AppState,
+    record: &FoodRecordDetail,
+) -> Result<(u64, String, Option<MerkleLeafDetail>), AppError> {
+    let merkle_leaf = db::get_merkle_leaf_db(&app_state.db_pool, &record.product_id).await?;
+    let latest_anchor = db::list_record_anchors_db(&app_state.db_pool, &record.product_id).await?.pop();
+    let merkle_leaf = current_merkle_leaf(merkle_leaf, latest_anchor.as_ref());
+    let target = match (&merkle_leaf, latest_anchor, record.chain_id, &record.contract_address) {
+        (Some(leaf), _, _, _) => (leaf.chain_id, leaf.contract_address.clone()),
+        (None, Some(RecordAnchor { chain_id: Some(chain_id), contract_address: Some(contract_address), .. }), _, _) => {
+            (chain_id, contract_address)
+        }
+        (None, _, Some(chain_id), Some(contract_address)) => (chain_id, contract_address.clone()),
+        _ => {
+            let chain = app_state.chains.default_chain();
+            (chain.chain_id(), chain.contract_address().to_string())
+        }
+    };
+    Ok((target.0, target.1, merkle_leaf))
+}
+
+// 最新的上链记录是批次交易之后的重新上链时，批次叶子不再是记录的有效上链
+fn current_merkle_leaf(merkle_leaf: Option<MerkleLeafDetail>, latest_anchor: Option<&RecordAnchor>) -> Option<MerkleLeafDetail> {
+    match (merkle_leaf, latest_anchor) {
+        (Some(leaf), Some(anchor)) if leaf.transaction_hash.as_deref() != Some(anchor.transaction_hash.as_str()) => None,
+        (leaf, _) => leaf,
+    }
+}
+
+// 服务端链上验证，不依赖浏览器中的 Metamask
+#[get("/api/food-records/{product_id}/verify")]
+pub async fn verify_food_record_handler(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let product_id = path.into_inner();
+    let record = db::get_food_record_detail_db(&app_state.db_pool, &product_id).await?;
+    let metadata_fields = db::get_metadata_fields_db(&app_state.db_pool, &product_id).await?;
+    let (chain_id, contract_address, merkle_leaf) = anchor_target(&app_state, &record).await?;
+    let chain = app_state.chains.resolve(Some(chain_id))?;
+
+    let verification = verification::verify_record_on_chain(
+        &chain.eth_client,
+        chain_id,
+        &contract_address,
+        &record,
+        merkle_leaf.as_ref(),
+        &metadata_fields,
+    ).await?;
+    info!("产品ID {} 链上验证结果: {:?}", product_id, verification.verdict);
+    Ok(HttpResponse::Ok().json(verification))
+}
+
+// Merkle 批量上链记录的包含证明：叶子、到根的路径以及批次根的上链交易
+#[get("/api/food-records/{product_id}/merkle-proof")]
+pub async fn get_merkle_proof_handler(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let product_id = path.into_inner();
+    let record = db::get_food_record_detail_db(&app_state.db_pool, &product_id).await?;
+    let leaf = db::get_merkle_leaf_db(&app_state.db_pool, &product_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("产品ID '{}' 不是通过 Merkle 批量上链的记录。", product_id)))?;
+    let (Some(leaf_index), Some(proof), Some(merkle_root)) = (leaf.leaf_index, leaf.proof, leaf.merkle_root) else {
+        return Err(AppError::Conflict(format!("Merkle 批次 #{} 仍在收集记录，尚未生成证明。", leaf.batch_id)));
+    };
+
+    Ok(HttpResponse::Ok().json(MerkleProofResponse {
+        product_id: leaf.product_id,
+        metadata_hash: record.onchain_metadata_hash,
+        leaf_hash: leaf.leaf_hash,
+        leaf_index,
+        proof: proof.0,
+        merkle_root,
+        leaf_encoding: merkle::LEAF_ENCODING,
+        node_encoding: merkle::NODE_ENCODING,
+        batch_id: leaf.batch_id,
+        batch_status: leaf.batch_status,
+        anchor_key: merkle::anchor_key(leaf.batch_id),
+        chain_id: leaf.chain_id,
+        contract_address: leaf.contract_address,
+        transaction_hash: leaf.transaction_hash,
+        block_number: leaf.block_number,
+    }))
+}
+
+
+// 离线证明包：规范化元数据、上链交易回执与事件、区块头以及回执的 Merkle Patricia 证明，
+// 可以用 verify_proof 程序 (proof_core/src/bin/verify_proof.rs) 在不信任本服务的情况下离线验证
+#[get("/api/food-records/{product_id}/proof")]
+pub async fn get_proof_bundle_handler(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let product_id = path.into_inner();
+    let record = db::get_food_record_detail_db(&app_state.db_pool, &product_id).await?;
+    let (chain_id, contract_address, merkle_leaf) = anchor_target(&app_state, &record).await?;
+    let chain = app_state.chains.resolve(Some(chain_id))?;
+
+    let metadata_fields = db::get_metadata_fields_db(&app_state.db_pool, &product_id).await?;
+    let bundle = proof::build_proof_bundle(
+        &chain.eth_client, chain_id, &contract_address, &record, merkle_leaf.as_ref(), &metadata_fields,
+    ).await?;
+    // 导出前自检，避免把无法通过验证的证明包交给用户
+    let verification = proof::verify_proof_bundle(&bundle);
+    if let Some(failed) = verification.checks.iter().find(|check| !check.passed) {
+        error!("产品ID {} 的证明包自检失败 ({}): {}", product_id, failed.name, failed.detail);
+        return Err(AppError::InternalError(format!("生成的证明包未通过校验 ({}): {}", failed.name, failed.detail)));
+    }
+    Ok(HttpResponse::Ok().json(bundle))
+}
 
 // #[post("/api/food-records")]
 // pub async fn create_food_record_handler(
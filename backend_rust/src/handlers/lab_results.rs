@@ -0,0 +1,46 @@
+use actix_web::{get, post, web, HttpResponse};
+use log::info;
+use crate::models::{AppState, LabEvaluationQuery, LabResultsRequest};
+use crate::auth::WriteAccess;
+use crate::db;
+use crate::errors::AppError;
+use crate::lab_results;
+
+// 提交一份检测报告中的结果，返回按产品类别与请求中的法域判定后的全部结果
+#[post("/api/food-records/{product_id}/lab-results")]
+pub async fn create_lab_results_handler(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+    results_request: web::Json<LabResultsRequest>,
+    query: web::Query<LabEvaluationQuery>,
+    access: WriteAccess,
+) -> Result<HttpResponse, AppError> {
+    let product_id = path.into_inner();
+    let request = results_request.into_inner();
+    let category = lab_results::validate_category(request.category.as_deref())?;
+    let report_hashes = lab_results::validate_results(&request.results)?;
+    let ids = db::insert_lab_results_db(
+        &app_state.db_pool, access.actor(), &product_id, category.as_deref(), &request.results, &report_hashes,
+    ).await?;
+    info!("产品ID {} 新增 {} 条检测结果", product_id, ids.len());
+
+    let category = db::get_product_category_db(&app_state.db_pool, &product_id).await?;
+    let results = db::list_lab_results_db(&app_state.db_pool, &product_id).await?;
+    let evaluation = lab_results::evaluate(&app_state.mrl_tables, &product_id, category, query.into_inner().jurisdiction, results);
+    Ok(HttpResponse::Created().json(evaluation))
+}
+
+// 按产品登记的类别与法域的限量表判定全部检测结果: GET /api/food-records/{product_id}/lab-results?jurisdiction=
+#[get("/api/food-records/{product_id}/lab-results")]
+pub async fn get_lab_results_handler(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+    query: web::Query<LabEvaluationQuery>,
+) -> Result<HttpResponse, AppError> {
+    let product_id = path.into_inner();
+    // 同时区分没有检测结果的记录与不存在的记录
+    let category = db::get_product_category_db(&app_state.db_pool, &product_id).await?;
+    let results = db::list_lab_results_db(&app_state.db_pool, &product_id).await?;
+    let evaluation = lab_results::evaluate(&app_state.mrl_tables, &product_id, category, query.into_inner().jurisdiction, results);
+    Ok(HttpResponse::Ok().json(evaluation))
+}
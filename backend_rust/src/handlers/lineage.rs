@@ -0,0 +1,59 @@
+use actix_web::{get, post, web, HttpResponse};
+use log::info;
+use crate::models::{AppState, LineageLinkRequest, LineageQuery};
+use crate::auth::WriteAccess;
+use crate::db;
+use crate::errors::AppError;
+use crate::lineage::{self, LineageDirection};
+
+// 为子批次 (路径中的产品) 添加一个父批次；只有子批次记录的签名者 (未签名的记录为创建者) 可以添加
+#[post("/api/food-records/{product_id}/lineage")]
+pub async fn create_lineage_link_handler(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+    link_request: web::Json<LineageLinkRequest>,
+    access: WriteAccess,
+) -> Result<HttpResponse, AppError> {
+    let child_product_id = path.into_inner();
+    let request = link_request.into_inner();
+    let unit = lineage::validate_link(&child_product_id, &request)?;
+    let child = db::get_food_record_detail_db(&app_state.db_pool, &child_product_id).await?;
+    access.require_record_owner(child.recorder.as_deref(), child.created_by.as_deref(), "为该批次添加父批次")?;
+    // 环检查在 create_lineage_link_db 的事务中进行
+    let link = db::create_lineage_link_db(&app_state.db_pool, access.actor(), &child_product_id, &request, unit.as_deref()).await?;
+    info!("批次谱系: {} --{}--> {}", link.parent_product_id, link.relationship, link.child_product_id);
+    Ok(HttpResponse::Created().json(link))
+}
+
+async fn walk_handler(
+    app_state: &AppState,
+    product_id: &str,
+    direction: LineageDirection,
+    query: LineageQuery,
+) -> Result<HttpResponse, AppError> {
+    let max_depth = lineage::clamp_depth(query.max_depth)?;
+    // 确认起点存在，没有谱系的记录返回空列表
+    db::get_food_record_detail_db(&app_state.db_pool, product_id).await?;
+    let response = lineage::walk(&app_state.db_pool, product_id, direction, max_depth).await?;
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// 上游：该批次使用了哪些原料批次
+#[get("/api/food-records/{product_id}/upstream")]
+pub async fn get_upstream_handler(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+    query: web::Query<LineageQuery>,
+) -> Result<HttpResponse, AppError> {
+    walk_handler(&app_state, &path.into_inner(), LineageDirection::Upstream, query.into_inner()).await
+}
+
+// 下游：该批次最终进入了哪些批次与产品
+#[get("/api/food-records/{product_id}/downstream")]
+pub async fn get_downstream_handler(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+    query: web::Query<LineageQuery>,
+) -> Result<HttpResponse, AppError> {
+    walk_handler(&app_state, &path.into_inner(), LineageDirection::Downstream, query.into_inner()).await
+}
@@ -1,2 +1,11 @@
 pub mod health_check;
 pub mod food_records;
+pub mod admin;
+pub mod chain;
+pub mod auth;
+pub mod disclosures;
+pub mod trace_events;
+pub mod lineage;
+pub mod recalls;
+pub mod certificates;
+pub mod lab_results;
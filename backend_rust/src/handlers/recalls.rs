@@ -0,0 +1,67 @@
+use actix_web::{get, post, web, HttpResponse};
+use log::{info, warn};
+use crate::models::{AppState, RecallListQuery, RecallRequest, RecallResponse, RecallStatusRequest};
+use crate::auth::AdminAccess;
+use crate::db;
+use crate::errors::AppError;
+use crate::recall::{self, RecallStatus};
+
+async fn recall_response(app_state: &AppState, recall_id: u64) -> Result<RecallResponse, AppError> {
+    let recall = db::get_recall_db(&app_state.db_pool, recall_id).await?;
+    let affected_products = db::list_recall_products_db(&app_state.db_pool, recall_id).await?;
+    Ok(RecallResponse { recall, affected_products })
+}
+
+// 发起召回：直接列出的产品及其下游可达的全部批次标记为受影响，消费者在详情与列表中可见，仅管理员可操作
+#[post("/api/recalls")]
+pub async fn create_recall_handler(
+    app_state: web::Data<AppState>,
+    recall_request: web::Json<RecallRequest>,
+    access: AdminAccess,
+) -> Result<HttpResponse, AppError> {
+    let request = recall_request.into_inner();
+    let product_ids = recall::validate_request(&request)?;
+    let recall_id = db::create_recall_db(&app_state.db_pool, access.actor(), &request, &product_ids).await?;
+    let response = recall_response(&app_state, recall_id).await?;
+    info!(
+        "召回 #{} 已发起 ({}): 直接影响 {} 个产品，共 {} 个产品受影响",
+        recall_id, request.severity_class.as_str(), product_ids.len(), response.affected_products.len()
+    );
+    if response.recall.propagation_truncated {
+        warn!("召回 #{} 的下游遍历达到批次数上限，可能有批次未被标记", recall_id);
+    }
+    Ok(HttpResponse::Created().json(response))
+}
+
+#[get("/api/recalls")]
+pub async fn list_recalls_handler(
+    app_state: web::Data<AppState>,
+    query: web::Query<RecallListQuery>,
+) -> Result<HttpResponse, AppError> {
+    let status = query.into_inner().status.map(|status| RecallStatus::parse(&status)).transpose()?;
+    let recalls = db::list_recalls_db(&app_state.db_pool, status.map(|status| status.as_str())).await?;
+    Ok(HttpResponse::Ok().json(recalls))
+}
+
+#[get("/api/recalls/{recall_id}")]
+pub async fn get_recall_handler(
+    app_state: web::Data<AppState>,
+    path: web::Path<u64>,
+) -> Result<HttpResponse, AppError> {
+    Ok(HttpResponse::Ok().json(recall_response(&app_state, path.into_inner()).await?))
+}
+
+// 推进召回状态: open → in_progress → closed，仅管理员可操作
+#[post("/api/recalls/{recall_id}/status")]
+pub async fn update_recall_status_handler(
+    app_state: web::Data<AppState>,
+    path: web::Path<u64>,
+    status_request: web::Json<RecallStatusRequest>,
+    access: AdminAccess,
+) -> Result<HttpResponse, AppError> {
+    let recall_id = path.into_inner();
+    let next = status_request.into_inner().status;
+    db::update_recall_status_db(&app_state.db_pool, access.actor(), recall_id, next).await?;
+    info!("召回 #{} 状态变更为 {}", recall_id, next.as_str());
+    Ok(HttpResponse::Ok().json(recall_response(&app_state, recall_id).await?))
+}
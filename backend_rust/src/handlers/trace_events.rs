@@ -0,0 +1,37 @@
+use actix_web::{get, post, web, HttpResponse};
+use log::info;
+use crate::models::{AppState, TraceEventRequest};
+use crate::auth::WriteAccess;
+use crate::db;
+use crate::errors::AppError;
+use crate::trace_events;
+
+// 追加一个供应链环节事件，供应链上的各方登录后均可提交
+#[post("/api/food-records/{product_id}/events")]
+pub async fn append_trace_event_handler(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+    event_request: web::Json<TraceEventRequest>,
+    access: WriteAccess,
+) -> Result<HttpResponse, AppError> {
+    let product_id = path.into_inner();
+    let event = trace_events::new_event(event_request.into_inner())?;
+    let row = db::append_trace_event_db(&app_state.db_pool, access.actor(), &product_id, &event).await?;
+    info!("产品ID {} 追加 {} 事件 #{}，事件哈希 {}", product_id, row.stage, row.sequence, row.event_hash);
+    Ok(HttpResponse::Created().json(trace_events::event_response(row, true)))
+}
+
+// 产品的时间线：按发生时间排列，并逐条复核事件哈希链
+#[get("/api/food-records/{product_id}/events")]
+pub async fn get_trace_timeline_handler(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let product_id = path.into_inner();
+    let events = db::list_trace_events_db(&app_state.db_pool, &product_id).await?;
+    if events.is_empty() {
+        // 区分没有事件的记录与不存在的记录
+        db::get_food_record_detail_db(&app_state.db_pool, &product_id).await?;
+    }
+    Ok(HttpResponse::Ok().json(trace_events::build_timeline(&product_id, events)?))
+}
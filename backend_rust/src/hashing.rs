@@ -0,0 +1,85 @@
+use serde_json::Value as JsonValue;
+use crate::errors::AppError;
+use crate::canonical_json;
+use crate::disclosure;
+use crate::eip712;
+use crate::models::MetadataField;
+use std::collections::BTreeMap;
+
+// 哈希工具与哈希方案定义在 proof_core 中，离线验证程序使用相同的实现
+pub use proof_core::hashing::{hash_canonical_json, keccak256, legacy_stringify_hash, normalize_hash, to_hex_string, HashScheme};
+
+// 元数据的规范化字节及其哈希，二者始终一起存储
+#[derive(Debug, Clone)]
+pub struct CanonicalMetadata {
+    pub canonical_json: String, // RFC 8785 (JCS) 规范化后的 JSON 文本，保存提交的原始元数据 (salted-fields-v1 只保存公开字段)
+    pub hash: String,           // 按 scheme 计算的元数据哈希，带 0x 前缀
+    pub scheme: HashScheme,
+    pub fields: Vec<MetadataField>, // salted-fields-v1 的字段明文、盐值与承诺，其他方案为空
+}
+
+// 服务端重新计算元数据哈希，前端 calculateMetadataHash 使用相同的方案
+// field_salts 与 public_fields 只用于 salted-fields-v1，未提交盐值时由后端生成
+pub fn compute_metadata_hash(
+    metadata: &JsonValue,
+    scheme: HashScheme,
+    field_salts: Option<&BTreeMap<String, String>>,
+    public_fields: &[String],
+) -> Result<CanonicalMetadata, AppError> {
+    let (canonical_json, hash, fields) = match scheme {
+        HashScheme::LegacyJsonStringifyKeccak256 => {
+            return Err(AppError::InvalidInput(format!("{} 只用于复核早期记录，新记录请使用其他哈希方案。", scheme.as_str())));
+        }
+        HashScheme::JcsKeccak256 => {
+            let canonical_json = canonical_json::canonicalize(metadata)?;
+            let hash = hash_canonical_json(&canonical_json);
+            (canonical_json, hash, Vec::new())
+        }
+        HashScheme::Eip712FoodMetadataV1 => (
+            canonical_json::canonicalize(metadata)?,
+            to_hex_string(&eip712::food_metadata_hash(metadata)?),
+            Vec::new(),
+        ),
+        HashScheme::SaltedFieldsV1 => disclosure::commit_metadata(metadata, field_salts, public_fields)?,
+    };
+    Ok(CanonicalMetadata { canonical_json, hash, scheme, fields })
+}
+
+// 由存储的元数据按记录的方案重新计算哈希；JCS 与键顺序无关，没有规范化字节时可以从 JSON 列重新规范化
+// 引入哈希方案之前的记录按早期前端的键顺序重建 JSON.stringify 文本
+// salted-fields-v1 的 JSON 列只有公开字段，由 metadata_fields 中的明文与盐值重新计算
+pub fn recompute_stored_hash(
+    scheme: HashScheme,
+    metadata_canonical: Option<&str>,
+    metadata_json: &JsonValue,
+    metadata_fields: &[MetadataField],
+) -> Result<String, AppError> {
+    match (scheme, metadata_canonical) {
+        (HashScheme::LegacyJsonStringifyKeccak256, _) => Ok(legacy_stringify_hash(metadata_json)?),
+        (HashScheme::JcsKeccak256, Some(canonical)) => Ok(hash_canonical_json(canonical)),
+        (HashScheme::JcsKeccak256, None) => Ok(hash_canonical_json(&canonical_json::canonicalize(metadata_json)?)),
+        (HashScheme::Eip712FoodMetadataV1, _) => Ok(to_hex_string(&eip712::food_metadata_hash(metadata_json)?)),
+        (HashScheme::SaltedFieldsV1, _) => disclosure::recompute_root(metadata_fields),
+    }
+}
+
+// 校验客户端提交的哈希与服务端计算的哈希是否一致，一致时返回服务端计算的规范化结果
+pub fn verify_metadata_hash(
+    metadata: &JsonValue,
+    scheme: HashScheme,
+    field_salts: Option<&BTreeMap<String, String>>,
+    public_fields: &[String],
+    received_hash: &str,
+) -> Result<CanonicalMetadata, AppError> {
+    if scheme == HashScheme::SaltedFieldsV1 && field_salts.is_none() {
+        return Err(AppError::InvalidInput("客户端计算的 salted-fields-v1 哈希必须同时提交 fieldSalts。".to_string()));
+    }
+    let computed = compute_metadata_hash(metadata, scheme, field_salts, public_fields)?;
+    if normalize_hash(received_hash) != computed.hash {
+        return Err(AppError::MetadataHashMismatch {
+            expected: computed.hash,
+            received: received_hash.to_string(),
+        });
+    }
+    Ok(computed)
+}
@@ -0,0 +1,173 @@
+// RecordAdded 事件索引器
+// 后台任务定期通过 eth_getLogs 拉取合约事件写入 chain_events，让后端拥有自己的链上视图，而不是依赖客户端的说法
+// 只索引到 (最新区块 - 确认数)，并在每轮开始时检查游标区块哈希，发现重组时回滚受影响的事件
+// 链注册表中的每条链各运行一个索引任务，起始区块与确认数取自链配置
+use log::{debug, info, warn};
+use sqlx::MySqlPool;
+use std::time::Duration;
+use crate::chains::Chain;
+use crate::config::env_or;
+use crate::contract;
+use crate::db;
+use crate::errors::AppError;
+use crate::eth_rpc::{parse_quantity, EthClient, RpcLog};
+use crate::models::{IndexerCursor, NewChainEvent};
+
+#[derive(Debug, Clone)]
+pub struct IndexerConfig {
+    pub enabled: bool,
+    pub poll_interval: Duration,
+    pub max_block_range: u64, // 单次 eth_getLogs 查询的最大区块数
+}
+
+impl IndexerConfig {
+    pub fn from_env() -> Self {
+        IndexerConfig {
+            enabled: env_or("INDEXER_ENABLED", true),
+            poll_interval: Duration::from_secs(env_or("INDEXER_POLL_INTERVAL_SECS", 5)),
+            max_block_range: env_or("INDEXER_MAX_BLOCK_RANGE", 2000u64).max(1),
+        }
+    }
+}
+
+// 启动指定链的后台索引任务
+pub fn spawn_indexer(pool: MySqlPool, chain: Chain, config: IndexerConfig) {
+    if !config.enabled {
+        info!("事件索引器已禁用 (INDEXER_ENABLED=false)。");
+        return;
+    }
+    info!(
+        "事件索引器已启动: 链 {} ({})，合约 {}，起始区块 {}，确认数 {}",
+        chain.config.name, chain.chain_id(), chain.contract_address(), chain.deploy_block(), chain.config.confirmations
+    );
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config.poll_interval);
+        loop {
+            interval.tick().await;
+            if let Err(e) = index_once(&pool, &chain, &config).await {
+                warn!("事件索引失败 (链 {}，合约 {}): {}", chain.chain_id(), chain.contract_address(), e);
+            }
+        }
+    });
+}
+
+// 执行一轮索引：重组检测 -> 拉取日志 -> 写入事件并推进游标 (同一事务)
+async fn index_once(pool: &MySqlPool, chain: &Chain, config: &IndexerConfig) -> Result<(), AppError> {
+    let (eth_client, chain_id, contract_address) = (&chain.eth_client, chain.chain_id(), chain.contract_address());
+    let head = eth_client.block_number().await?;
+    let Some(safe_head) = head.checked_sub(chain.config.confirmations) else {
+        return Ok(()); // 链高度还不足确认数
+    };
+
+    let next_block = match db::get_indexer_cursor_db(pool, chain_id, contract_address).await? {
+        Some(cursor) => {
+            let chain_hash = eth_client
+                .get_block_by_number(cursor.last_block)
+                .await?
+                .map(|block| block.hash.to_lowercase());
+            if chain_hash.as_deref() != Some(cursor.last_block_hash.as_str()) {
+                warn!(
+                    "检测到链重组: 区块 {} 的哈希由 {} 变为 {:?}",
+                    cursor.last_block, cursor.last_block_hash, chain_hash
+                );
+                return rollback_reorg(pool, chain, &cursor).await;
+            }
+            cursor.last_block + 1
+        }
+        None => chain.deploy_block(),
+    };
+    if next_block > safe_head {
+        return Ok(());
+    }
+    let to_block = safe_head.min(next_block + config.max_block_range - 1);
+
+    // 拉取日志前后各读取一次目标区块哈希，期间发生重组则放弃本轮，避免写入旧分叉上的事件
+    let to_hash_before = block_hash_at(eth_client, to_block).await?;
+    let logs = eth_client
+        .get_logs(contract_address, &contract::record_added_topic(), next_block, to_block)
+        .await?;
+    let to_hash_after = block_hash_at(eth_client, to_block).await?;
+    if to_hash_before != to_hash_after {
+        warn!("索引区块 {}..={} 期间发生重组，下一轮重试。", next_block, to_block);
+        return Ok(());
+    }
+
+    let mut tx = pool.begin().await?;
+    let mut inserted = 0;
+    for log in &logs {
+        if let Some(event) = to_chain_event(chain_id, contract_address, log)? {
+            db::insert_chain_event_db(&mut tx, &event).await?;
+            inserted += 1;
+        }
+    }
+    db::upsert_indexer_cursor_db(&mut tx, chain_id, contract_address, to_block, &to_hash_after).await?;
+    tx.commit().await?;
+
+    if inserted > 0 {
+        info!("链 {} 已索引区块 {}..={}，新增 {} 条 RecordAdded 事件。", chain_id, next_block, to_block, inserted);
+    } else {
+        debug!("链 {} 已索引区块 {}..={}，无新事件。", chain_id, next_block, to_block);
+    }
+    Ok(())
+}
+
+async fn block_hash_at(eth_client: &EthClient, number: u64) -> Result<String, AppError> {
+    eth_client
+        .get_block_by_number(number)
+        .await?
+        .map(|block| block.hash.to_lowercase())
+        .ok_or_else(|| AppError::BlockchainError(format!("区块 {} 不存在。", number)))
+}
+
+// 链重组回滚：从新到旧检查已索引事件所在区块，找到哈希仍然一致的最近区块 (共同祖先)，
+// 删除其后的所有事件并把游标退回到该区块；一个都不一致时 (例如本地链被重置) 清空该合约的事件重新索引
+async fn rollback_reorg(pool: &MySqlPool, chain: &Chain, cursor: &IndexerCursor) -> Result<(), AppError> {
+    let (eth_client, chain_id, contract_address) = (&chain.eth_client, chain.chain_id(), chain.contract_address());
+    let mut common_ancestor: Option<(u64, String)> = None;
+    for (block_number, block_hash) in db::list_indexed_event_blocks_db(pool, chain_id, contract_address, cursor.last_block).await? {
+        let chain_hash = eth_client
+            .get_block_by_number(block_number)
+            .await?
+            .map(|block| block.hash.to_lowercase());
+        if chain_hash.as_deref() == Some(block_hash.as_str()) {
+            common_ancestor = Some((block_number, block_hash));
+            break;
+        }
+    }
+
+    let mut tx = pool.begin().await?;
+    match common_ancestor {
+        Some((block_number, block_hash)) => {
+            let removed = db::delete_chain_events_after_db(&mut tx, chain_id, contract_address, block_number).await?;
+            db::upsert_indexer_cursor_db(&mut tx, chain_id, contract_address, block_number, &block_hash).await?;
+            warn!("重组回滚完成: 回退到区块 {}，删除 {} 条事件。", block_number, removed);
+        }
+        None => {
+            let removed = db::delete_chain_events_after_db(&mut tx, chain_id, contract_address, 0).await?;
+            db::delete_indexer_cursor_db(&mut tx, chain_id, contract_address).await?;
+            warn!("未找到共同祖先区块，已清空合约 {} 的 {} 条事件并从起始区块重新索引。", contract_address, removed);
+        }
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+// 将 RPC 日志转换为待写入的事件记录，非 RecordAdded 日志返回 None
+fn to_chain_event(chain_id: u64, contract_address: &str, log: &RpcLog) -> Result<Option<NewChainEvent>, AppError> {
+    let Some(event) = contract::decode_record_added(log)? else {
+        return Ok(None);
+    };
+    let missing = |field: &str| AppError::BlockchainError(format!("eth_getLogs 返回的日志缺少 {} 字段。", field));
+    Ok(Some(NewChainEvent {
+        chain_id,
+        contract_address: contract_address.to_string(),
+        block_number: parse_quantity(log.block_number.as_deref().ok_or_else(|| missing("blockNumber"))?)?,
+        block_hash: log.block_hash.as_deref().ok_or_else(|| missing("blockHash"))?.to_lowercase(),
+        transaction_hash: log.transaction_hash.as_deref().ok_or_else(|| missing("transactionHash"))?.to_lowercase(),
+        log_index: parse_quantity(log.log_index.as_deref().ok_or_else(|| missing("logIndex"))?)? as u32,
+        product_id_hash: event.product_id_hash,
+        metadata_hash: event.metadata_hash,
+        recorder: event.recorder,
+        event_timestamp: event.timestamp,
+    }))
+}
@@ -0,0 +1,310 @@
+// 实验室检测结果与最大残留限量 (MRL) 判定
+// 限量表按 (法域, 产品类别) 组织，通过 MRL_TABLES_PATH 指定 JSON 文件 (见 mrl_tables.example.json)；
+// 每个分析物给出 pass / fail / unknown，批次结论取最差的一项 (fail > unknown > pass)
+use chrono::{Duration, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::env;
+use std::fs;
+use crate::contract;
+use crate::errors::AppError;
+use crate::hashing::normalize_hash;
+use crate::models::{AnalyteEvaluation, LabEvaluationResponse, LabResult, LabResultEvaluation, LabResultInput};
+use crate::validation::{check_text, MAX_TEXT_LEN};
+
+const MAX_RESULTS_PER_REQUEST: usize = 200;
+const MAX_UNIT_LEN: usize = 32;
+const MAX_CATEGORY_LEN: usize = 64;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum LabVerdict {
+    Pass,
+    Unknown, // 没有对应的限量表或限量、单位无法换算
+    Fail,
+}
+
+impl LabVerdict {
+    // 多项结论合并时取最差的一项
+    fn worst(self, other: LabVerdict) -> LabVerdict {
+        self.max(other)
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MrlLimit {
+    pub analyte: String,
+    pub max_value: f64,
+    pub unit: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MrlTable {
+    pub jurisdiction: String,
+    pub category: String,
+    pub limits: Vec<MrlLimit>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MrlTables {
+    pub default_jurisdiction: Option<String>, // 查询未指定法域时使用
+    pub tables: Vec<MrlTable>,
+}
+
+// 分析物与类别、法域按忽略大小写和首尾空白比较
+fn key(value: &str) -> String {
+    value.trim().to_lowercase()
+}
+
+// 质量分数单位相对 mg/kg 的十进制指数
+fn mass_fraction_exponent(unit: &str) -> Option<i32> {
+    match key(unit).as_str() {
+        "mg/kg" | "ppm" | "µg/g" | "μg/g" | "ug/g" => Some(0),
+        "µg/kg" | "μg/kg" | "ug/kg" | "ppb" | "ng/g" => Some(-3),
+        "g/kg" => Some(3),
+        _ => None,
+    }
+}
+
+// 把测定值换算到限量的单位；单位相同 (如 CFU/g) 时直接比较，无法换算时为 None
+// 按 10 的整数次幂乘除，再舍入到 f64 可靠的 15 位有效数字，350 µg/kg 换算为 0.35 mg/kg 而不是 0.35000000000000003，
+// 恰好等于限量的测定值不会因换算误差被判为超标
+fn convert(value: f64, from: &str, to: &str) -> Option<f64> {
+    if key(from) == key(to) {
+        return Some(value);
+    }
+    let exponent = mass_fraction_exponent(from)? - mass_fraction_exponent(to)?;
+    let scaled = if exponent >= 0 { value * 10f64.powi(exponent) } else { value / 10f64.powi(-exponent) };
+    format!("{:.*e}", f64::DIGITS as usize - 1, scaled).parse().ok()
+}
+
+impl MrlTables {
+    pub fn from_env() -> Result<Self, AppError> {
+        let path = match env::var("MRL_TABLES_PATH") {
+            Ok(path) => path,
+            Err(_) => {
+                warn!("未配置 MRL_TABLES_PATH，检测结果的判定均为 unknown。");
+                return Ok(MrlTables::default());
+            }
+        };
+        let content = fs::read_to_string(&path)
+            .map_err(|e| AppError::InvalidInput(format!("无法读取限量表 {}: {}", path, e)))?;
+        let tables: MrlTables = serde_json::from_str(&content)
+            .map_err(|e| AppError::InvalidInput(format!("限量表 {} 格式错误: {}", path, e)))?;
+        tables.validate()?;
+        info!("已加载 {} 张 MRL 限量表 ({})", tables.tables.len(), path);
+        Ok(tables)
+    }
+
+    fn validate(&self) -> Result<(), AppError> {
+        let mut seen_tables = HashSet::new();
+        for table in &self.tables {
+            if !seen_tables.insert((key(&table.jurisdiction), key(&table.category))) {
+                return Err(AppError::InvalidInput(format!("限量表 {}/{} 重复。", table.jurisdiction, table.category)));
+            }
+            let mut seen_analytes = HashSet::new();
+            for limit in &table.limits {
+                if !seen_analytes.insert(key(&limit.analyte)) {
+                    return Err(AppError::InvalidInput(format!(
+                        "限量表 {}/{} 中的分析物 '{}' 重复。", table.jurisdiction, table.category, limit.analyte
+                    )));
+                }
+                if !limit.max_value.is_finite() || limit.max_value < 0.0 {
+                    return Err(AppError::InvalidInput(format!(
+                        "限量表 {}/{} 中 '{}' 的限量必须是非负数。", table.jurisdiction, table.category, limit.analyte
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn table(&self, jurisdiction: &str, category: &str) -> Option<&MrlTable> {
+        self.tables
+            .iter()
+            .find(|table| key(&table.jurisdiction) == key(jurisdiction) && key(&table.category) == key(category))
+    }
+}
+
+// 产品已登记的类别与新提交的类别是否相同，比较规则与限量表一致
+pub fn same_category(registered: &str, category: &str) -> bool {
+    key(registered) == key(category)
+}
+
+// 校验提交的产品类别，返回去除首尾空白的类别
+pub fn validate_category(category: Option<&str>) -> Result<Option<String>, AppError> {
+    category
+        .map(|category| {
+            check_text("category", category, MAX_CATEGORY_LEN)?;
+            Ok(category.trim().to_string())
+        })
+        .transpose()
+}
+
+// 校验提交的结果，返回规范化的报告哈希 (与 results 一一对应)
+pub fn validate_results(results: &[LabResultInput]) -> Result<Vec<Option<String>>, AppError> {
+    if results.is_empty() || results.len() > MAX_RESULTS_PER_REQUEST {
+        return Err(AppError::InvalidInput(format!("results 不能为空且不能超过 {} 条。", MAX_RESULTS_PER_REQUEST)));
+    }
+    let today = Utc::now().date_naive() + Duration::days(1); // 允许时区差
+    let mut report_hashes = Vec::with_capacity(results.len());
+    for result in results {
+        check_text("analyte", &result.analyte, MAX_TEXT_LEN)?;
+        check_text("method", &result.method, MAX_TEXT_LEN)?;
+        check_text("unit", &result.unit, MAX_UNIT_LEN)?;
+        check_text("laboratory", &result.laboratory, MAX_TEXT_LEN)?;
+        if !result.measured_value.is_finite() || result.measured_value < 0.0 {
+            return Err(AppError::InvalidInput(format!("分析物 '{}' 的 measuredValue 必须是非负数。", result.analyte)));
+        }
+        if result.sample_date > today {
+            return Err(AppError::InvalidInput(format!("分析物 '{}' 的 sampleDate 不能晚于今天。", result.analyte)));
+        }
+        let report_hash = match &result.report_hash {
+            Some(hash) => {
+                contract::parse_bytes32(hash)
+                    .map_err(|_| AppError::InvalidInput("reportHash 必须是 32 字节十六进制哈希。".to_string()))?;
+                Some(normalize_hash(hash))
+            }
+            None => None,
+        };
+        report_hashes.push(report_hash);
+    }
+    Ok(report_hashes)
+}
+
+// 按分析物分组判定；产品未登记类别、jurisdiction 缺失或没有对应的限量表时全部为 unknown
+pub fn evaluate(
+    tables: &MrlTables,
+    product_id: &str,
+    category: Option<String>,
+    jurisdiction: Option<String>,
+    results: Vec<LabResult>,
+) -> LabEvaluationResponse {
+    let jurisdiction = jurisdiction.or_else(|| tables.default_jurisdiction.clone());
+    let (table, table_missing) = match (&jurisdiction, &category) {
+        (Some(jurisdiction), Some(category)) => match tables.table(jurisdiction, category) {
+            Some(table) => (Some(table), None),
+            None => (None, Some(format!("未配置法域 {} 中类别 {} 的限量表。", jurisdiction, category))),
+        },
+        _ => (None, Some("产品未登记类别 (category) 或未指定法域 (jurisdiction)。".to_string())),
+    };
+
+    let mut grouped: BTreeMap<String, Vec<LabResult>> = BTreeMap::new();
+    for result in results {
+        grouped.entry(key(&result.analyte)).or_default().push(result);
+    }
+
+    let mut analytes = Vec::with_capacity(grouped.len());
+    for (analyte_key, results) in grouped {
+        let limit = table.and_then(|table| table.limits.iter().find(|limit| key(&limit.analyte) == analyte_key));
+        let mut detail = match (&table_missing, limit) {
+            (Some(reason), _) => Some(reason.clone()),
+            (None, None) => Some("限量表中没有该分析物。".to_string()),
+            (None, Some(_)) => None,
+        };
+        let mut verdict = LabVerdict::Pass;
+        let mut evaluated = Vec::with_capacity(results.len());
+        for result in results {
+            let value_in_limit_unit = limit.and_then(|limit| convert(result.measured_value, &result.unit, &limit.unit));
+            let result_verdict = match (limit, value_in_limit_unit) {
+                (Some(limit), Some(value)) if value > limit.max_value => LabVerdict::Fail,
+                (Some(_), Some(_)) => LabVerdict::Pass,
+                (Some(limit), None) => {
+                    detail.get_or_insert_with(|| format!("单位 {} 无法换算为限量的单位 {}。", result.unit, limit.unit));
+                    LabVerdict::Unknown
+                }
+                (None, _) => LabVerdict::Unknown,
+            };
+            verdict = verdict.worst(result_verdict);
+            evaluated.push(LabResultEvaluation { result, verdict: result_verdict, value_in_limit_unit });
+        }
+        analytes.push(AnalyteEvaluation {
+            analyte: evaluated[0].result.analyte.clone(),
+            verdict,
+            limit: limit.map(|limit| limit.max_value),
+            limit_unit: limit.map(|limit| limit.unit.clone()),
+            detail,
+            results: evaluated,
+        });
+    }
+
+    // 没有检测结果时无法判定批次
+    let verdict = analytes
+        .iter()
+        .map(|analyte| analyte.verdict)
+        .reduce(LabVerdict::worst)
+        .unwrap_or(LabVerdict::Unknown);
+    LabEvaluationResponse {
+        product_id: product_id.to_string(),
+        category,
+        jurisdiction,
+        verdict,
+        analytes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn tables(max_value: f64, unit: &str) -> MrlTables {
+        MrlTables {
+            default_jurisdiction: Some("CN".to_string()),
+            tables: vec![MrlTable {
+                jurisdiction: "CN".to_string(),
+                category: "leafy_vegetables".to_string(),
+                limits: vec![MrlLimit { analyte: "chlorpyrifos".to_string(), max_value, unit: unit.to_string() }],
+            }],
+        }
+    }
+
+    fn result(measured_value: f64, unit: &str) -> LabResult {
+        LabResult {
+            id: 1,
+            product_id: "P-1".to_string(),
+            analyte: "Chlorpyrifos".to_string(),
+            method: "GB 23200.113-2018".to_string(),
+            measured_value,
+            unit: unit.to_string(),
+            laboratory: "lab".to_string(),
+            sample_date: NaiveDate::from_ymd_opt(2026, 3, 1).unwrap(),
+            report_hash: None,
+            created_by: "0x0".to_string(),
+            created_at: Utc::now(),
+        }
+    }
+
+    fn verdict(tables: &MrlTables, measured_value: f64, unit: &str) -> LabVerdict {
+        evaluate(tables, "P-1", Some("leafy_vegetables".to_string()), None, vec![result(measured_value, unit)]).verdict
+    }
+
+    #[test]
+    fn converts_by_powers_of_ten() {
+        assert_eq!(convert(350.0, "µg/kg", "mg/kg"), Some(0.35));
+        assert_eq!(convert(0.07, "mg/kg", "ppb"), Some(70.0));
+        assert_eq!(convert(0.3, "g/kg", "ug/kg"), Some(300_000.0));
+        assert_eq!(convert(12.0, "CFU/g", "cfu/g"), Some(12.0));
+        assert_eq!(convert(12.0, "CFU/g", "mg/kg"), None);
+    }
+
+    #[test]
+    fn value_at_limit_passes_after_conversion() {
+        let limit = tables(0.35, "mg/kg");
+        assert_eq!(verdict(&limit, 350.0, "µg/kg"), LabVerdict::Pass);
+        assert_eq!(verdict(&limit, 350.1, "µg/kg"), LabVerdict::Fail);
+        let limit = tables(70.0, "µg/kg");
+        assert_eq!(verdict(&limit, 0.07, "mg/kg"), LabVerdict::Pass);
+        assert_eq!(verdict(&limit, 0.0701, "mg/kg"), LabVerdict::Fail);
+        assert_eq!(verdict(&limit, 70.0, "ppb"), LabVerdict::Pass);
+    }
+
+    #[test]
+    fn unconvertible_unit_is_unknown() {
+        assert_eq!(verdict(&tables(100.0, "CFU/g"), 10.0, "mg/kg"), LabVerdict::Unknown);
+    }
+}
@@ -0,0 +1,34 @@
+// 后端库：HTTP 服务 (main.rs) 与管理命令 (commands.rs)
+pub mod models;
+pub mod errors;
+pub mod config;
+pub mod db;
+pub mod handlers;
+pub mod hashing;
+pub mod eth_rpc;
+pub mod verification;
+pub mod indexer;
+pub mod reconciliation;
+pub mod reanchor;
+pub mod commands;
+pub mod signer;
+pub mod outbox;
+pub mod merkle_batch;
+pub mod chains;
+pub mod artifacts;
+pub mod deploy;
+pub mod siwe;
+pub mod auth;
+pub mod audit;
+pub mod audit_anchor;
+pub mod proof;
+pub mod disclosure;
+pub mod trace_events;
+pub mod lineage;
+pub mod recall;
+pub mod certification;
+pub mod lab_results;
+pub mod validation;
+
+// 不依赖数据库的哈希、编码与证明模块在 proof_core 中，按原路径导出
+pub use proof_core::{canonical_json, contract, eip712, merkle, rlp, trie};
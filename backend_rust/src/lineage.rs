@@ -0,0 +1,220 @@
+// 批次谱系：父批次 → 子批次的拆分、混合与加工关系
+// 上游 (upstream) 回答 "这批产品用了哪些原料批次"，下游 (downstream) 回答 "这批原料最终进入了哪些产品"，召回依赖后者
+use serde::{Deserialize, Serialize};
+use sqlx::{MySqlConnection, MySqlPool};
+use std::collections::{BTreeMap, BTreeSet};
+use crate::db;
+use crate::errors::AppError;
+use crate::models::{LineageEdge, LineageLinkRequest, LineageNode, LineageResponse, LineageWalkEdge};
+
+pub const DEFAULT_MAX_DEPTH: u32 = 10;
+pub const MAX_DEPTH: u32 = 50;
+// 召回传播与建边环检查的遍历深度，低于 MySQL cte_max_recursion_depth 的默认值 1000
+pub const MAX_TRANSITIVE_DEPTH: u32 = 500;
+// 递归查询最多生成的 (批次, 深度) 行数，超过时标记为截断
+pub const MAX_WALK_ROWS: u64 = 100_000;
+
+const MAX_UNIT_LEN: usize = 32;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LineageRelationship {
+    Split,     // 一个父批次拆分为多个子批次 (托盘、零售包装)
+    Merge,     // 多个父批次混合为一个子批次
+    Transform, // 父批次加工为新产品
+}
+
+impl LineageRelationship {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineageRelationship::Split => "split",
+            LineageRelationship::Merge => "merge",
+            LineageRelationship::Transform => "transform",
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LineageDirection {
+    Upstream,
+    Downstream,
+}
+
+// 校验新边的取值，返回去除首尾空白的单位
+pub fn validate_link(child_product_id: &str, request: &LineageLinkRequest) -> Result<Option<String>, AppError> {
+    if request.parent_product_id == child_product_id {
+        return Err(AppError::InvalidInput("父批次与子批次不能是同一个产品。".to_string()));
+    }
+    for (name, quantity) in [("parentQuantity", request.parent_quantity), ("childQuantity", request.child_quantity)] {
+        if matches!(quantity, Some(quantity) if quantity < 0.0) {
+            return Err(AppError::InvalidInput(format!("{} 不能为负数。", name)));
+        }
+    }
+    let unit = request.unit.as_deref().map(str::trim).filter(|unit| !unit.is_empty());
+    if matches!(unit, Some(unit) if unit.len() > MAX_UNIT_LEN) {
+        return Err(AppError::InvalidInput(format!("unit 不能超过 {} 个字符。", MAX_UNIT_LEN)));
+    }
+    Ok(unit.map(str::to_string))
+}
+
+pub fn clamp_depth(max_depth: Option<u32>) -> Result<u32, AppError> {
+    match max_depth.unwrap_or(DEFAULT_MAX_DEPTH) {
+        0 => Err(AppError::InvalidInput("max_depth 必须大于 0。".to_string())),
+        depth => Ok(depth.min(MAX_DEPTH)),
+    }
+}
+
+// 从 product_id 出发沿谱系遍历 max_depth 层
+pub async fn walk(pool: &MySqlPool, product_id: &str, direction: LineageDirection, max_depth: u32) -> Result<LineageResponse, AppError> {
+    let mut conn = pool.acquire().await?;
+    walk_connection(&mut conn, product_id, direction, max_depth).await
+}
+
+// 一次递归查询得到可达批次发出的全部边 (见 db::walk_child_edges_db)；在事务中调用时读到的边会加共享锁
+pub async fn walk_connection(
+    conn: &mut MySqlConnection,
+    product_id: &str,
+    direction: LineageDirection,
+    max_depth: u32,
+) -> Result<LineageResponse, AppError> {
+    let edges = match direction {
+        LineageDirection::Upstream => db::walk_parent_edges_db(conn, product_id, max_depth, MAX_WALK_ROWS).await?,
+        LineageDirection::Downstream => db::walk_child_edges_db(conn, product_id, max_depth, MAX_WALK_ROWS).await?,
+    };
+    Ok(build_response(product_id, direction, max_depth, edges))
+}
+
+// 指向未到达批次的边说明遍历达到了深度或行数上限，这些边不列出并标记为截断
+fn build_response(product_id: &str, direction: LineageDirection, max_depth: u32, walk_edges: Vec<LineageWalkEdge>) -> LineageResponse {
+    let mut depths: BTreeMap<String, u32> = BTreeMap::new();
+    let mut edges = Vec::with_capacity(walk_edges.len());
+    let mut truncated = false;
+    for edge in walk_edges {
+        let Some(depth) = edge.to_depth else {
+            truncated = true;
+            continue;
+        };
+        let reached = match direction {
+            LineageDirection::Upstream => &edge.parent_product_id,
+            LineageDirection::Downstream => &edge.child_product_id,
+        };
+        if reached != product_id {
+            depths.insert(reached.clone(), depth as u32);
+        }
+        edges.push(LineageEdge {
+            id: edge.id,
+            parent_product_id: edge.parent_product_id,
+            child_product_id: edge.child_product_id,
+            relationship: edge.relationship,
+            parent_quantity: edge.parent_quantity,
+            child_quantity: edge.child_quantity,
+            unit: edge.unit,
+        });
+    }
+    let cycles = find_cycles(product_id, edges.iter().map(|edge| match direction {
+        LineageDirection::Upstream => (edge.child_product_id.as_str(), edge.parent_product_id.as_str()),
+        LineageDirection::Downstream => (edge.parent_product_id.as_str(), edge.child_product_id.as_str()),
+    }));
+    let mut nodes: Vec<LineageNode> = depths
+        .into_iter()
+        .map(|(product_id, depth)| LineageNode { product_id, depth })
+        .collect();
+    nodes.sort_by_key(|node| node.depth);
+    LineageResponse {
+        product_id: product_id.to_string(),
+        direction,
+        max_depth,
+        truncated,
+        nodes,
+        edges,
+        cycles,
+    }
+}
+
+// 从起点深度优先遍历 (from, to) 边，指回当前路径上产品的边记为环：返回从起点开始的路径加上被再次到达的产品
+fn find_cycles<'a>(start: &'a str, edges: impl Iterator<Item = (&'a str, &'a str)>) -> Vec<Vec<String>> {
+    let mut adjacency: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for (from, to) in edges {
+        adjacency.entry(from).or_default().push(to);
+    }
+    let mut finished = BTreeSet::new();
+    let mut cycles = BTreeSet::new();
+    // 栈即当前路径，每项记录产品与下一条待访问的边
+    let mut stack: Vec<(&str, usize)> = vec![(start, 0)];
+    while let Some((node, index)) = stack.pop() {
+        match adjacency.get(node).and_then(|next| next.get(index)).copied() {
+            None => {
+                finished.insert(node);
+            }
+            Some(next) => {
+                stack.push((node, index + 1));
+                if stack.iter().any(|&(on_path, _)| on_path == next) {
+                    cycles.insert(stack.iter().map(|&(on_path, _)| on_path).chain([next]).map(str::to_string).collect::<Vec<_>>());
+                } else if !finished.contains(next) {
+                    stack.push((next, 0));
+                }
+            }
+        }
+    }
+    cycles.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn walk_edge(id: u64, parent: &str, child: &str, to_depth: Option<u64>) -> LineageWalkEdge {
+        LineageWalkEdge {
+            id,
+            parent_product_id: parent.to_string(),
+            child_product_id: child.to_string(),
+            relationship: "split".to_string(),
+            parent_quantity: None,
+            child_quantity: None,
+            unit: None,
+            to_depth,
+        }
+    }
+
+    #[test]
+    fn edge_to_unreached_product_truncates() {
+        let edges = vec![
+            walk_edge(1, "a", "b", Some(1)),
+            walk_edge(2, "a", "c", Some(1)),
+            walk_edge(3, "b", "d", Some(2)),
+            walk_edge(4, "c", "d", Some(2)),
+            walk_edge(5, "d", "e", None),
+        ];
+        let response = build_response("a", LineageDirection::Downstream, 2, edges);
+        assert!(response.truncated);
+        assert_eq!(response.edges.iter().map(|edge| edge.id).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        let nodes: Vec<(&str, u32)> = response.nodes.iter().map(|node| (node.product_id.as_str(), node.depth)).collect();
+        assert_eq!(nodes, vec![("b", 1), ("c", 1), ("d", 2)]);
+        assert!(response.cycles.is_empty());
+    }
+
+    #[test]
+    fn edge_back_to_start_is_a_cycle_not_a_node() {
+        let edges = vec![walk_edge(1, "b", "a", Some(1)), walk_edge(2, "a", "b", Some(0))];
+        let response = build_response("a", LineageDirection::Upstream, 10, edges);
+        assert!(!response.truncated);
+        assert_eq!(response.nodes.len(), 1);
+        assert_eq!(response.cycles, vec![vec!["a".to_string(), "b".to_string(), "a".to_string()]]);
+    }
+
+    #[test]
+    fn diamond_is_not_a_cycle() {
+        let edges = [("a", "b"), ("a", "c"), ("b", "d"), ("c", "d"), ("d", "e")];
+        assert!(find_cycles("a", edges.into_iter()).is_empty());
+    }
+
+    #[test]
+    fn reports_path_to_revisited_product() {
+        let edges = [("a", "b"), ("b", "c"), ("c", "b"), ("c", "a")];
+        assert_eq!(find_cycles("a", edges.into_iter()), vec![
+            vec!["a".to_string(), "b".to_string(), "c".to_string(), "a".to_string()],
+            vec!["a".to_string(), "b".to_string(), "c".to_string(), "b".to_string()],
+        ]);
+    }
+}
@@ -1,14 +1,12 @@
-mod models;
-mod errors;
-mod db;
-mod handlers;
-
 use sqlx::mysql::MySqlPoolOptions;
 use std::env;
 use dotenvy::dotenv; // 用于加载 .env 文件中的环境变量
 use actix_web::{web, App, HttpServer, http};
-use models::AppState;
-use log::info; // 引入 info! 宏等
+use backend_rust::{
+    artifacts, audit_anchor, auth, commands, db, handlers, indexer, lab_results, merkle_batch, outbox, reconciliation,
+    chains::ChainRegistry, models::AppState, signer::LocalSigner,
+};
+use log::{info, warn}; // 引入 info! 宏等
 use actix_cors::Cors; // 引入 Cors
 
 #[actix_web::main]
@@ -22,6 +20,14 @@ async fn main() -> std::io::Result<()> {
     dotenv().ok();
     let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set in env.file");
     let server_address = env::var("SERVER_ADDRESS").unwrap_or_else(|_| "127.0.0.1:8080".to_string());
+    // 链注册表 (CHAINS_CONFIG 配置文件，或 ETH_RPC_URL / CHAIN_ID / CONTRACT_ADDRESS 单链配置)
+    let chains = match ChainRegistry::from_env() {
+        Ok(chains) => chains,
+        Err(e) => {
+            eprintln!("Invalid chain configuration: {}", e);
+            std::process::exit(1);
+        }
+    };
 
     let pool = match MySqlPoolOptions::new()
         .max_connections(10)
@@ -38,10 +44,115 @@ async fn main() -> std::io::Result<()> {
         }
     };
 
+    // 启动时执行数据库迁移 (migrations/ 目录)
+    if let Err(e) = sqlx::migrate!("./migrations").run(&pool).await {
+        eprintln!("Failed to run database migrations: {:?}", e);
+        std::process::exit(1);
+    }
+
+    // Hardhat 编译产物中的 ABI，与 contract.rs 的编码函数不一致时拒绝启动
+    let contract_artifact = match artifacts::ContractArtifact::from_env() {
+        Ok(artifact) => artifact,
+        Err(e) => {
+            eprintln!("Invalid contract artifact: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // 多链支持之前的记录都上链到当时唯一配置的链，即默认链
+    let default_chain = chains.default_chain();
+    match db::backfill_record_chain_db(&pool, default_chain.chain_id(), default_chain.contract_address()).await {
+        Ok(0) => {}
+        Ok(count) => info!("已为 {} 条早期记录补全链 ID {} 与合约地址。", count, default_chain.chain_id()),
+        Err(e) => {
+            eprintln!("Failed to backfill record chain: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    let signer = match LocalSigner::from_env() {
+        Ok(signer) => signer,
+        Err(e) => {
+            eprintln!("Invalid SIGNER_PRIVATE_KEY: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // 最大残留限量表 (MRL_TABLES_PATH)，格式错误时拒绝启动
+    let mrl_tables = match lab_results::MrlTables::from_env() {
+        Ok(tables) => tables,
+        Err(e) => {
+            eprintln!("Invalid MRL tables: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let app_state = web::Data::new(AppState {
+        db_pool: pool.clone(),
+        chains,
+        contract_artifact,
+        signer,
+        // 为 true 时创建记录必须附带 EIP-712 签名，默认关闭以兼容未签名的客户端
+        require_record_signature: env::var("REQUIRE_RECORD_SIGNATURE").map(|v| v == "true" || v == "1").unwrap_or(false),
+        auth: auth::AuthConfig::from_env(),
+        merkle_batch: merkle_batch::MerkleBatchConfig::from_env(),
+        mrl_tables,
+    });
+
+    // 带参数运行时执行管理命令而不是启动 HTTP 服务，例如 `cargo run -- reanchor 0x...`
+    let cli_args: Vec<String> = env::args().skip(1).collect();
+    if let Some((command, command_args)) = cli_args.split_first() {
+        if let Err(e) = commands::run_command(command, command_args, &app_state).await {
+            eprintln!("命令 {} 执行失败: {}", command, e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     println!("Starting HTTP server at http://{}", server_address);
 
     info!("数据库连接池已创建，最大连接数: {}", 10); // 示例日志
     info!("HTTP 服务器正在启动于 http://{}", server_address);
+    for chain in app_state.chains.iter() {
+        info!(
+            "链 {} (链 ID {}): 节点 {}，合约地址 {}",
+            chain.config.name, chain.chain_id(), chain.config.rpc_url, chain.contract_address()
+        );
+    }
+    info!("默认链 ID: {}", app_state.chains.default_chain().chain_id());
+    match &app_state.contract_artifact {
+        Some(artifact) => info!("已加载合约 {} 的 ABI，编码函数与合约接口一致。", artifact.contract_name),
+        None => warn!("未找到合约编译产物 (CONTRACT_ARTIFACT_PATH)，GET /api/chain/config 不可用。"),
+    }
+    match &app_state.signer {
+        Some(signer) => info!("后端托管签名已启用，签名账户: {}", signer.address()),
+        None => info!("未配置 SIGNER_PRIVATE_KEY，创建记录时必须提交客户端上链的 transactionHash。"),
+    }
+    if app_state.auth.required {
+        info!("写接口需要登录 (Sign-In With Ethereum)，允许的域名: {}", app_state.auth.domains.join(", "));
+    } else {
+        warn!("AUTH_REQUIRED=false，未登录的请求也可以调用写接口。");
+    }
+    if app_state.require_record_signature {
+        info!("创建记录必须附带 EIP-712 签名 (REQUIRE_RECORD_SIGNATURE)。");
+    }
+
+    // 后台 RecordAdded 事件索引器，每条链一个
+    let indexer_config = indexer::IndexerConfig::from_env();
+    for chain in app_state.chains.iter() {
+        indexer::spawn_indexer(pool.clone(), chain.clone(), indexer_config.clone());
+    }
+    // 定期对账 (RECONCILIATION_INTERVAL_SECS)
+    let reconciliation_targets = app_state
+        .chains
+        .iter()
+        .map(|chain| (chain.chain_id(), chain.contract_address().to_string()))
+        .collect();
+    reconciliation::spawn_scheduled_reconciliation(pool.clone(), reconciliation_targets);
+    // 后端托管上链的发件箱任务 (需要 SIGNER_PRIVATE_KEY)
+    outbox::spawn_outbox_worker(app_state.clone(), outbox::OutboxConfig::from_env());
+    merkle_batch::spawn_merkle_batcher(app_state.clone(), app_state.merkle_batch.clone(), outbox::OutboxConfig::from_env());
+    audit_anchor::spawn_audit_anchor(app_state.clone(), audit_anchor::AuditAnchorConfig::from_env(), outbox::OutboxConfig::from_env());
 
     HttpServer::new(move || {
         // 配置 CORS
@@ -63,11 +174,38 @@ async fn main() -> std::io::Result<()> {
         App::new()
             .wrap(actix_web::middleware::Logger::default()) // 请求日志
             .wrap(cors) // 应用 CORS 中间件
-            .app_data(web::Data::new(AppState { db_pool: pool.clone() }))
+            .app_data(app_state.clone())
             .service(handlers::health_check::health_check_handler)
             .service(handlers::food_records::create_food_record_handler)
             .service(handlers::food_records::get_food_records_list_handler)
             .service(handlers::food_records::get_food_record_detail_handler)
+            .service(handlers::food_records::verify_food_record_handler)
+            .service(handlers::food_records::get_merkle_proof_handler)
+            .service(handlers::food_records::get_proof_bundle_handler)
+            .service(handlers::disclosures::get_public_disclosure_handler)
+            .service(handlers::disclosures::create_disclosure_handler)
+            .service(handlers::disclosures::get_disclosure_handler)
+            .service(handlers::trace_events::append_trace_event_handler)
+            .service(handlers::trace_events::get_trace_timeline_handler)
+            .service(handlers::lineage::create_lineage_link_handler)
+            .service(handlers::lineage::get_upstream_handler)
+            .service(handlers::lineage::get_downstream_handler)
+            .service(handlers::recalls::create_recall_handler)
+            .service(handlers::recalls::list_recalls_handler)
+            .service(handlers::recalls::get_recall_handler)
+            .service(handlers::recalls::update_recall_status_handler)
+            .service(handlers::certificates::create_certificate_handler)
+            .service(handlers::certificates::add_certificate_products_handler)
+            .service(handlers::certificates::get_certificate_handler)
+            .service(handlers::certificates::list_expiring_certificates_handler)
+            .service(handlers::lab_results::create_lab_results_handler)
+            .service(handlers::lab_results::get_lab_results_handler)
+            .service(handlers::admin::get_reconciliation_report_handler)
+            .service(handlers::admin::run_reconciliation_handler)
+            .service(handlers::admin::verify_audit_log_handler)
+            .service(handlers::chain::get_chain_config_handler)
+            .service(handlers::auth::get_auth_nonce_handler)
+            .service(handlers::auth::siwe_login_handler)
     })
     .bind(&server_address)?
     .run()
@@ -0,0 +1,204 @@
+// Merkle 批量上链后台任务
+// 1. 封存：open 批次收集超过 MERKLE_BATCH_WINDOW_SECS 秒或叶子数达到上限后，计算根并保存每个叶子的路径
+// 2. 上链：托管账户发送 addRecord(anchor_key, root)，等待确认后为批次中的每条记录追加上链历史
+// 发送失败的重试策略与上链发件箱相同 (OUTBOX_MAX_ATTEMPTS / OUTBOX_RETRY_BASE_SECS / OUTBOX_RECEIPT_TIMEOUT_SECS)，
+// 已发送的根同样只跟踪同一笔交易，超时或查询失败不会另发新 nonce 的交易
+use actix_web::web;
+use log::{error, info, warn};
+use crate::config::env_or;
+use crate::contract;
+use crate::db;
+use crate::errors::AppError;
+use crate::hashing::to_hex_string;
+use crate::merkle;
+use crate::models::{AppState, MerkleBatch};
+use crate::outbox::{self, OutboxConfig, ReceiptStatus, SubmittedTransaction};
+use crate::signer::LocalSigner;
+
+pub const BATCH_STATUS_OPEN: &str = "open";
+pub const BATCH_STATUS_SEALED: &str = "sealed";
+pub const BATCH_STATUS_SUBMITTED: &str = "submitted";
+pub const BATCH_STATUS_FAILED: &str = "failed";
+
+const BATCH_ANCHOR_LIMIT: u32 = 10;
+
+#[derive(Debug, Clone)]
+pub struct MerkleBatchConfig {
+    pub window_secs: u64, // 批次从创建到封存的收集时间
+    pub max_leaves: u32,  // 单个批次的最大叶子数，达到后立即封存
+}
+
+impl MerkleBatchConfig {
+    pub fn from_env() -> Self {
+        MerkleBatchConfig {
+            window_secs: env_or("MERKLE_BATCH_WINDOW_SECS", 60),
+            max_leaves: env_or("MERKLE_BATCH_MAX_LEAVES", 1000u32).max(1),
+        }
+    }
+}
+
+// 启动批量上链任务，未配置托管签名账户时不启动
+pub fn spawn_merkle_batcher(app_state: web::Data<AppState>, config: MerkleBatchConfig, outbox_config: OutboxConfig) {
+    if app_state.signer.is_none() {
+        return;
+    }
+    info!(
+        "Merkle 批量上链任务已启动: 收集窗口 {} 秒，每批最多 {} 条记录",
+        config.window_secs, config.max_leaves
+    );
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(outbox_config.poll_interval);
+        loop {
+            interval.tick().await;
+            if let Err(e) = seal_due_batches(&app_state, &config).await {
+                warn!("封存 Merkle 批次失败: {}", e);
+            }
+            if let Err(e) = anchor_due_batches(&app_state, &outbox_config).await {
+                warn!("Merkle 批次上链失败: {}", e);
+            }
+        }
+    });
+}
+
+async fn seal_due_batches(app_state: &AppState, config: &MerkleBatchConfig) -> Result<(), AppError> {
+    for batch_id in db::list_sealable_merkle_batches_db(&app_state.db_pool, config.window_secs, config.max_leaves).await? {
+        seal_batch(app_state, batch_id).await?;
+    }
+    Ok(())
+}
+
+// 在锁定批次的事务中计算 Merkle 树并写入根与每个叶子的路径
+async fn seal_batch(app_state: &AppState, batch_id: u64) -> Result<(), AppError> {
+    let mut tx = app_state.db_pool.begin().await?;
+    if !db::lock_open_merkle_batch_db(&mut tx, batch_id).await? {
+        return Ok(());
+    }
+    let leaves = db::list_merkle_batch_leaves_db(&mut tx, batch_id).await?;
+    let leaf_hashes = leaves
+        .iter()
+        .map(|leaf| contract::parse_bytes32(&leaf.leaf_hash))
+        .collect::<Result<Vec<_>, _>>()?;
+    let (root, proofs) = merkle::build_tree(&leaf_hashes)?;
+
+    for (index, (leaf, proof)) in leaves.iter().zip(proofs).enumerate() {
+        db::update_merkle_leaf_proof_db(&mut tx, leaf.id, index as u32, &serde_json::to_string(&proof)?).await?;
+    }
+    let root = to_hex_string(&root);
+    db::seal_merkle_batch_db(&mut tx, batch_id, &root).await?;
+    tx.commit().await?;
+    info!("Merkle 批次 #{} 已封存: {} 条记录，根 {}", batch_id, leaves.len(), root);
+    Ok(())
+}
+
+async fn anchor_due_batches(app_state: &AppState, config: &OutboxConfig) -> Result<(), AppError> {
+    let Some(signer) = app_state.signer.as_ref() else {
+        return Ok(());
+    };
+    for batch in db::list_due_merkle_batches_db(&app_state.db_pool, BATCH_ANCHOR_LIMIT).await? {
+        match batch.status.as_str() {
+            BATCH_STATUS_SEALED => {
+                if let Err(e) = submit_batch(app_state, signer, &batch).await {
+                    record_failure(app_state, config, &batch, batch.attempts + 1, &e).await?;
+                }
+            }
+            BATCH_STATUS_SUBMITTED => {
+                if let Err(e) = check_submitted_batch(app_state, config, signer, &batch).await {
+                    let message = e.to_string();
+                    warn!("查询 Merkle 批次 #{} 的上链交易失败，稍后再查: {}", batch.id, message);
+                    db::reschedule_merkle_batch_db(
+                        &app_state.db_pool, batch.id, BATCH_STATUS_SUBMITTED, batch.attempts, Some(&message), config.retry_delay_secs(1),
+                    ).await?;
+                }
+            }
+            _ => continue,
+        }
+    }
+    Ok(())
+}
+
+fn batch_root(batch: &MerkleBatch) -> Result<&str, AppError> {
+    batch
+        .merkle_root
+        .as_deref()
+        .ok_or_else(|| AppError::InternalError(format!("Merkle 批次 #{} 已封存但没有根。", batch.id)))
+}
+
+async fn submit_batch(app_state: &AppState, signer: &LocalSigner, batch: &MerkleBatch) -> Result<(), AppError> {
+    let chain = app_state.chains.resolve(Some(batch.chain_id))?;
+    let sent = signer
+        .submit_add_record(&chain.eth_client, batch.chain_id, &batch.contract_address, &merkle::anchor_key(batch.id), batch_root(batch)?)
+        .await?;
+    db::mark_merkle_batch_submitted_db(&app_state.db_pool, batch.id, &sent, batch.attempts + 1).await?;
+    info!("Merkle 批次 #{} 的根已发送 (第 {} 次): {}", batch.id, batch.attempts + 1, sent.hash);
+    Ok(())
+}
+
+async fn check_submitted_batch(
+    app_state: &AppState,
+    config: &OutboxConfig,
+    signer: &LocalSigner,
+    batch: &MerkleBatch,
+) -> Result<(), AppError> {
+    let tx_hash = batch
+        .transaction_hash
+        .as_deref()
+        .ok_or_else(|| AppError::InternalError(format!("Merkle 批次 #{} 处于 submitted 状态但没有交易哈希。", batch.id)))?;
+    let anchor_key = merkle::anchor_key(batch.id);
+    let chain = app_state.chains.resolve(Some(batch.chain_id))?;
+
+    let sent = SubmittedTransaction {
+        hash: tx_hash,
+        nonce: batch.transaction_nonce,
+        raw: batch.raw_transaction.as_deref(),
+        submitted_at: batch.submitted_at,
+    };
+
+    let status = outbox::check_anchor_receipt(
+        &chain.eth_client, config, signer, &batch.contract_address, &sent, &anchor_key, batch_root(batch)?,
+    ).await?;
+    let anchor = match status {
+        ReceiptStatus::Confirmed(anchor) => anchor,
+        ReceiptStatus::Pending => {
+            return db::reschedule_merkle_batch_db(
+                &app_state.db_pool, batch.id, BATCH_STATUS_SUBMITTED, batch.attempts, None, config.poll_interval.as_secs(),
+            ).await;
+        }
+        ReceiptStatus::Overdue(message) => {
+            warn!("Merkle 批次 #{} 的上链交易仍未打包: {}", batch.id, message);
+            return db::reschedule_merkle_batch_db(
+                &app_state.db_pool, batch.id, BATCH_STATUS_SUBMITTED, batch.attempts, Some(&message), config.retry_delay_secs(1),
+            ).await;
+        }
+        ReceiptStatus::Dropped(reason) => {
+            return record_failure(app_state, config, batch, batch.attempts, &AppError::BlockchainError(reason)).await;
+        }
+    };
+    db::confirm_merkle_batch_db(
+        &app_state.db_pool,
+        batch.id,
+        &anchor.to_record_anchor(&anchor_key, batch.chain_id, &batch.contract_address),
+    ).await?;
+    info!(
+        "Merkle 批次 #{} 已上链确认: {} 条记录，交易 {}，区块 {}",
+        batch.id, batch.leaf_count, anchor.transaction_hash, anchor.block_number
+    );
+    Ok(())
+}
+
+// 发送失败或交易不会再被打包：未达到最大次数时退回 sealed 并按指数退避重试，否则标记为 failed
+async fn record_failure(
+    app_state: &AppState,
+    config: &OutboxConfig,
+    batch: &MerkleBatch,
+    attempts: u32,
+    err: &AppError,
+) -> Result<(), AppError> {
+    let message = err.to_string();
+    if attempts >= config.max_attempts {
+        error!("Merkle 批次 #{} 上链失败，已发送 {} 次，不再重试: {}", batch.id, attempts, message);
+        return db::reschedule_merkle_batch_db(&app_state.db_pool, batch.id, BATCH_STATUS_FAILED, attempts, Some(&message), 0).await;
+    }
+    let delay = config.retry_delay_secs(attempts.max(1));
+    warn!("Merkle 批次 #{} 上链失败 (第 {} 次)，{} 秒后重试: {}", batch.id, attempts, delay, message);
+    db::reschedule_merkle_batch_db(&app_state.db_pool, batch.id, BATCH_STATUS_SEALED, attempts, Some(&message), delay).await
+}
@@ -1,11 +1,32 @@
 use sqlx::MySqlPool;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
-use chrono::{Utc, DateTime};
+use std::collections::BTreeMap;
+use chrono::{Utc, DateTime, NaiveDate};
+use crate::artifacts::ContractArtifact;
+use crate::auth::AuthConfig;
+use crate::chains::ChainRegistry;
+use crate::certification::CertificateType;
+use crate::eip712::Eip712Domain;
+use crate::hashing::HashScheme;
+use crate::lab_results::{LabVerdict, MrlTables};
+use crate::lineage::{LineageDirection, LineageRelationship};
+use crate::merkle::ProofStep;
+use crate::merkle_batch::MerkleBatchConfig;
+use crate::recall::{RecallSeverity, RecallStatus};
+use crate::signer::LocalSigner;
+use crate::trace_events::{StagePayload, TraceStage};
 
 // 用于共享数据库连接池的状态
 pub struct AppState {
     pub db_pool: MySqlPool,
+    pub chains: ChainRegistry,       // 已配置的链及其 RPC 客户端、合约地址
+    pub contract_artifact: Option<ContractArtifact>, // Hardhat 编译产物 (ABI)，尚未编译合约时为空
+    pub signer: Option<LocalSigner>,
+    pub require_record_signature: bool, // 为 true 时创建记录必须附带 EIP-712 签名
+    pub auth: AuthConfig, // 后端托管签名账户，未配置 SIGNER_PRIVATE_KEY 时为 None
+    pub merkle_batch: MerkleBatchConfig, // Merkle 批量上链的收集窗口与批次大小
+    pub mrl_tables: MrlTables,           // 检测结果判定使用的最大残留限量表
 }
 // 定义前端发送过来的请求体结构
 #[derive(Deserialize, Debug)]
@@ -13,10 +34,43 @@ pub struct FoodRecordRequest {
     #[serde(rename = "productId")] // 对应前端JS的驼峰命名
     pub product_id: String,
     pub metadata: JsonValue, // 使用 serde_json::Value 来接收任意结构的JSON对象
+    // 以下两个字段由已通过 MetaMask 上链的客户端提交；缺少 transactionHash 时由后端托管签名并上链
     #[serde(rename = "metadataHashOnChain")]
-    pub metadata_hash_on_chain: String,
+    pub metadata_hash_on_chain: Option<String>,
     #[serde(rename = "transactionHash")]
-    pub transaction_hash: String,
+    pub transaction_hash: Option<String>,
+    // 上链所在的链，未指定时为默认链
+    #[serde(rename = "chainId")]
+    pub chain_id: Option<u64>,
+    // 后端托管上链的方式，默认每条记录单独上链
+    #[serde(rename = "anchorMode", default)]
+    pub anchor_mode: AnchorMode,
+    // 元数据哈希方案，默认 EIP-712 结构化哈希；旧客户端可指定 "jcs-keccak256"
+    #[serde(rename = "hashScheme", default)]
+    pub hash_scheme: HashScheme,
+    // 生产者对 FoodRecord(productId, metadataHash) 的 EIP-712 签名 (65 字节十六进制)
+    pub signature: Option<String>,
+    // salted-fields-v1：客户端自行计算承诺时提交的每个字段的盐值 (32 字节十六进制)，未提交时由后端生成
+    #[serde(rename = "fieldSalts")]
+    pub field_salts: Option<BTreeMap<String, String>>,
+    // salted-fields-v1：公开的字段，列表与详情只展示这些字段，默认 productId 与 productName
+    #[serde(rename = "publicFields")]
+    pub public_fields: Option<Vec<String>>,
+}
+
+// 通过 EIP-712 签名恢复出的记录者
+#[derive(Debug, Clone)]
+pub struct RecordSignature {
+    pub recorder: String,
+    pub signature: String,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AnchorMode {
+    #[default]
+    Record, // 每条记录一笔 addRecord 交易 (上链发件箱)
+    Merkle, // 加入当前 Merkle 批次，只上链批次根
 }
 
 // 定义一个简单的响应结构体
@@ -35,6 +89,8 @@ pub struct FoodListItem {
     // sqlx::FromRow 不能直接从 JSON 内部字段映射，需要在查询后手动处理或在查询中提取
     pub onchain_metadata_hash: String,
     pub created_at: DateTime<Utc>, // 使用 chrono 处理时间戳
+    pub latest_anchor: Option<RecordAnchor>, // 最近一次上链
+    pub recall_status: Option<String>, // 影响该产品的召回中最需要关注的状态，从未被召回时为空
 }
 
 // 用于食品详情的结构体 (完整信息)
@@ -43,8 +99,15 @@ pub struct FoodRecordDetail {
     pub product_id: String,
     // metadata_json 将直接从数据库 JSON 类型映射
     pub metadata_json: sqlx::types::Json<JsonValue>, // 使用 sqlx::types::Json
+    pub metadata_canonical: Option<String>, // JCS 规范化字节，早期记录可能为空
     pub onchain_metadata_hash: String,
-    pub blockchain_transaction_hash: String,
+    pub hash_scheme: String,                         // 元数据哈希方案，见 hashing::HashScheme
+    pub blockchain_transaction_hash: Option<String>, // 托管上链的记录在交易确认前为空
+    pub chain_id: Option<u64>,                       // 上链所在的链与合约，启动时为早期记录补全
+    pub contract_address: Option<String>,
+    pub recorder: Option<String>,                    // EIP-712 签名者地址，未签名的记录为空
+    pub recorder_signature: Option<String>,
+    pub created_by: Option<String>,                  // 创建记录的登录地址，匿名创建的记录为空
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -59,10 +122,23 @@ pub struct FoodRecordDetail {
 pub struct FoodRecordDetailResponse {
     pub product_id: String,
     pub metadata_json: JsonValue,
+    pub metadata_canonical: String, // 规范化 JSON 文本，jcs-keccak256 方案的记录可直接对其做 keccak256 复核
     pub onchain_metadata_hash: String,
-    pub blockchain_transaction_hash: String,
+    pub hash_scheme: String,
+    pub blockchain_transaction_hash: Option<String>,
+    pub chain_id: Option<u64>,
+    pub contract_address: Option<String>,
+    pub recorder: Option<String>,
+    pub recorder_signature: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub anchors: Vec<RecordAnchor>, // 全部上链历史，按时间先后排列
+    pub anchor_status: String, // 上链状态: pending / submitted / confirmed / failed，客户端自行上链的记录为 confirmed
+    pub anchor_outbox: Option<AnchorOutboxEntry>, // 后端托管上链的发件箱条目 (最近一条)
+    pub merkle_batch_id: Option<u64>, // 以 Merkle 批次上链时为批次ID，重新上链后为空
+    pub recall_status: Option<String>, // open / in_progress / closed，多个召回时取最需要关注的，从未被召回时为空
+    pub recalls: Vec<ProductRecall>,   // 影响该产品的全部召回 (直接列出或经谱系传播)
+    pub certification: CertificationStatus, // 关联证书在生产日期当天的有效性
 }
 
 
@@ -82,6 +158,62 @@ pub struct RawFoodListItem {
     pub metadata_json: String, // 这个字段是从 CAST(metadata_json AS CHAR) 获取的
     pub onchain_metadata_hash: String,
     pub created_at: DateTime<Utc>,
+    // 最近一次上链 (LEFT JOIN record_anchors，没有上链记录时全部为 NULL)
+    pub anchor_id: Option<u64>,
+    pub anchor_chain_id: Option<u64>,
+    pub anchor_contract_address: Option<String>,
+    pub anchor_transaction_hash: Option<String>,
+    pub anchor_block_number: Option<u64>,
+    pub anchor_block_timestamp: Option<DateTime<Utc>>,
+    pub anchor_status: Option<String>,
+    pub anchor_anchored_at: Option<DateTime<Utc>>,
+    pub recall_status: Option<String>,
+}
+
+// 一次上链记录 (record_anchors 表)
+#[derive(Serialize, Debug, Clone, sqlx::FromRow)]
+pub struct RecordAnchor {
+    pub id: u64,
+    pub chain_id: Option<u64>,
+    pub contract_address: Option<String>,
+    pub transaction_hash: String,
+    pub block_number: Option<u64>,
+    pub block_timestamp: Option<DateTime<Utc>>,
+    pub status: String,
+    pub anchored_at: DateTime<Utc>,
+}
+
+// 上链发件箱条目 (anchor_outbox 表)
+#[derive(Serialize, Debug, Clone, sqlx::FromRow)]
+pub struct AnchorOutboxEntry {
+    pub id: u64,
+    pub product_id: String,
+    pub chain_id: u64,
+    pub contract_address: String,
+    pub metadata_hash: String,
+    pub status: String,
+    pub attempts: u32,
+    pub transaction_hash: Option<String>,
+    pub transaction_nonce: Option<u64>,
+    #[serde(skip)]
+    pub raw_transaction: Option<String>, // 十六进制编码的已签名交易，超时未打包时重新广播
+    pub last_error: Option<String>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub submitted_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// 待写入的上链记录
+#[derive(Debug, Clone)]
+pub struct NewRecordAnchor {
+    pub product_id: String,
+    pub chain_id: u64,
+    pub contract_address: String,
+    pub transaction_hash: String,
+    pub block_number: u64,
+    pub block_timestamp: Option<DateTime<Utc>>,
+    pub status: String,
 }
 
 #[derive(Serialize, Debug)]
@@ -99,3 +231,744 @@ pub struct PaginationParams {
     pub page: Option<i64>,     // 当前页码
     pub page_size: Option<i64>, // 每页大小
 }
+
+// 链上验证结论
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VerificationVerdict {
+    Match,            // 数据库、重新计算的哈希与链上一致
+    DbTampered,       // 数据库中的元数据或哈希被改动
+    ChainOverwritten, // 链上记录被后续 addRecord 覆盖
+    NotAnchored,      // 合约中不存在该产品ID的记录
+}
+
+// GET /api/food-records/{product_id}/verify 的响应
+#[derive(Serialize, Debug)]
+pub struct VerificationResponse {
+    pub product_id: String,
+    pub verdict: VerificationVerdict,
+    pub hash_scheme: HashScheme,         // 记录的元数据哈希方案
+    pub db_hash: String,                 // 数据库 onchain_metadata_hash 列
+    pub recomputed_hash: String,         // 由存储的规范化元数据重新计算的哈希
+    pub chain_hash: Option<String>,      // 合约 getMetadataHash 的返回值
+    pub chain_recorder: Option<String>,  // 合约 records 中的 recorder
+    pub chain_timestamp: Option<u64>,    // 合约 records 中的 timestamp
+    pub chain_id: u64,                   // 查询的链与合约 (记录上链所在的链)
+    pub contract_address: String,
+    pub merkle_batch_id: Option<u64>,    // Merkle 批量上链时为批次ID，chain_hash 为批次根
+    pub message: String,
+}
+
+// 索引器写入 chain_events 的一条 RecordAdded 事件
+#[derive(Debug, Clone)]
+pub struct NewChainEvent {
+    pub chain_id: u64,
+    pub contract_address: String,
+    pub block_number: u64,
+    pub block_hash: String,
+    pub transaction_hash: String,
+    pub log_index: u32,
+    pub product_id_hash: String,
+    pub metadata_hash: String,
+    pub recorder: String,
+    pub event_timestamp: u64,
+}
+
+// 索引器游标
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct IndexerCursor {
+    pub last_block: u64,
+    pub last_block_hash: String,
+}
+
+// ------------------------------ 对账 ------------------------------
+
+// 对账时读取的数据库记录
+#[derive(Debug, sqlx::FromRow)]
+pub struct ReconciliationDbRecord {
+    pub product_id: String,
+    pub onchain_metadata_hash: String,
+    pub blockchain_transaction_hash: String,
+    pub anchor_block: Option<u64>, // 上链交易所在的区块，从旧列迁移的 legacy 记录未知
+}
+
+// 对账时读取的已索引链上事件
+#[derive(Debug, sqlx::FromRow)]
+pub struct IndexedChainEvent {
+    pub product_id_hash: String,
+    pub metadata_hash: String,
+    pub transaction_hash: String,
+    pub block_number: u64,
+    pub log_index: u32,
+}
+
+#[derive(Serialize, Debug, sqlx::FromRow)]
+pub struct ReconciliationRun {
+    pub id: u64,
+    pub chain_id: Option<u64>, // 早期对账任务没有记录链 ID
+    pub contract_address: String,
+    pub trigger_source: String,
+    pub db_record_count: u64,
+    pub chain_event_count: u64,
+    pub finding_count: u64,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Debug, sqlx::FromRow)]
+pub struct ReconciliationFinding {
+    pub id: u64,
+    pub run_id: u64,
+    pub finding_type: String,
+    pub product_id: Option<String>,
+    pub product_id_hash: String,
+    pub db_metadata_hash: Option<String>,
+    pub chain_metadata_hash: Option<String>,
+    pub transaction_hash: Option<String>,
+    pub block_number: Option<u64>,
+}
+
+// 对账任务生成、尚未写库的差异
+#[derive(Debug, Clone)]
+pub struct NewReconciliationFinding {
+    pub finding_type: &'static str,
+    pub product_id: Option<String>,
+    pub product_id_hash: String,
+    pub db_metadata_hash: Option<String>,
+    pub chain_metadata_hash: Option<String>,
+    pub transaction_hash: Option<String>,
+    pub block_number: Option<u64>,
+}
+
+// GET /api/auth/nonce 的响应
+#[derive(Serialize, Debug)]
+pub struct AuthNonceResponse {
+    pub nonce: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+// POST /api/auth/siwe 的请求体：EIP-4361 消息原文及钱包 personal_sign 签名
+#[derive(Deserialize, Debug)]
+pub struct SiweLoginRequest {
+    pub message: String,
+    pub signature: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SiweLoginResponse {
+    pub token: String, // 之后的写请求放在 Authorization: Bearer <token> 中
+    pub address: String,
+    pub chain_id: u64,
+    pub expires_at: DateTime<Utc>,
+}
+
+// 登录会话 (auth_sessions)
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct AuthSession {
+    pub address: String,
+    pub chain_id: u64,
+    pub expires_at: DateTime<Utc>,
+}
+
+// 审计日志中的一条记录
+#[derive(Serialize, Debug, Clone, sqlx::FromRow)]
+pub struct AuditLogEntry {
+    pub id: u64,
+    pub actor: String,
+    pub action: String,
+    pub target: Option<String>,
+    pub payload: String, // JCS 规范化的 JSON 文本
+    pub payload_hash: String,
+    pub prev_hash: String,
+    pub entry_hash: String,
+    pub created_at: DateTime<Utc>,
+}
+
+// 审计链校验结果
+#[derive(Serialize, Debug)]
+pub struct AuditVerificationResponse {
+    pub valid: bool,
+    pub verified_entries: u64,       // 第一处断链之前通过校验的记录数
+    pub head_entry_id: u64,          // 链头记录的最后一条编号及哈希
+    pub head_hash: String,
+    pub first_broken: Option<AuditBrokenLink>,
+    pub anchored_head: Option<AuditAnchoredHead>, // 链上核实过的最近一次锚定的链头
+    pub anchors_verified: u64,                    // 链上核实过的已确认锚定数
+    pub anchor_check_error: Option<String>,       // 查询链上锚定失败的原因 (此时未核对锚定)
+    pub records_checked: u64,                     // 与审计记录核对过的 traceability_data 行数
+    pub unaudited_records: u64,                   // 早于审计日志启用的记录，没有 record.create 可核对
+    pub record_mismatch_count: u64,
+    pub record_mismatches: Vec<AuditRecordMismatch>, // 最多列出前 100 条
+}
+
+#[derive(Serialize, Debug)]
+pub struct AuditBrokenLink {
+    pub entry_id: u64,
+    pub reason: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct AuditAnchoredHead {
+    pub entry_id: u64, // 锚定时链头的最后一条记录编号
+    pub head_hash: String,
+    pub transaction_hash: String,
+    pub anchored_at: u64, // RecordAdded 事件中的区块时间戳
+}
+
+// traceability_data 当前内容与最近一次审计记录不一致的产品
+#[derive(Serialize, Debug)]
+pub struct AuditRecordMismatch {
+    pub product_id: String,
+    pub reason: String,
+}
+
+// 与审计记录核对的 traceability_data 行
+#[derive(Debug, sqlx::FromRow)]
+pub struct AuditedRecordRow {
+    pub product_id: String,
+    pub onchain_metadata_hash: String,
+    pub hash_scheme: String,
+    pub recorder: Option<String>,
+    pub blockchain_transaction_hash: Option<String>,
+    pub merkle_batch_id: Option<u64>,
+    pub created_at: DateTime<Utc>,
+}
+
+// 审计链头的一次上链 (audit_head_anchors 表)
+#[derive(Serialize, Debug, Clone, sqlx::FromRow)]
+pub struct AuditHeadAnchor {
+    pub id: u64,
+    pub entry_id: u64,
+    pub head_hash: String,
+    pub chain_id: u64,
+    pub contract_address: String,
+    pub status: String,
+    pub transaction_hash: String,
+    pub block_number: Option<u64>,
+    pub created_at: DateTime<Utc>,
+}
+
+// 指定链的查询参数，未指定时为默认链
+#[derive(Deserialize, Debug)]
+pub struct ChainQuery {
+    pub chain_id: Option<u64>,
+}
+
+// GET /api/admin/reconciliation 的查询参数
+#[derive(Deserialize, Debug)]
+pub struct ReconciliationQuery {
+    pub run_id: Option<u64>,             // 默认最近一次对账
+    pub chain_id: Option<u64>,           // 未指定 run_id 时取该链最近一次对账，默认为默认链
+    pub finding_type: Option<String>,    // 按差异类型过滤
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ReconciliationReportResponse {
+    pub run: Option<ReconciliationRun>, // 尚未执行过对账时为 None
+    pub items: Vec<ReconciliationFinding>,
+    pub total_items: i64,
+    pub page: i64,
+    pub page_size: i64,
+    pub total_pages: i64,
+}
+
+// ------------------------------ 重新上链 ------------------------------
+
+// 尚未在目标合约上确认的记录及其上次进度
+#[derive(Debug, sqlx::FromRow)]
+pub struct ReanchorCandidate {
+    pub product_id: String,
+    pub onchain_metadata_hash: String,
+    pub progress_status: Option<String>,
+    pub progress_transaction_hash: Option<String>,
+}
+
+// ------------------------------ Merkle 批量上链 ------------------------------
+
+#[derive(Serialize, Debug, Clone, sqlx::FromRow)]
+pub struct MerkleBatch {
+    pub id: u64,
+    pub chain_id: u64,
+    pub contract_address: String,
+    pub status: String,
+    pub leaf_count: u32,
+    pub merkle_root: Option<String>,
+    pub attempts: u32,
+    pub transaction_hash: Option<String>,
+    pub transaction_nonce: Option<u64>,
+    #[serde(skip)]
+    pub raw_transaction: Option<String>,
+    pub block_number: Option<u64>,
+    pub last_error: Option<String>,
+    pub submitted_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub sealed_at: Option<DateTime<Utc>>,
+}
+
+// 封存批次时读取的叶子
+#[derive(Debug, sqlx::FromRow)]
+pub struct MerkleLeafRow {
+    pub id: u64,
+    pub leaf_hash: String,
+}
+
+// 记录所在的叶子及其批次
+#[derive(Debug, sqlx::FromRow)]
+pub struct MerkleLeafDetail {
+    pub product_id: String,
+    pub batch_id: u64,
+    pub leaf_hash: String,
+    pub leaf_index: Option<u32>,
+    pub proof: Option<sqlx::types::Json<Vec<ProofStep>>>,
+    pub batch_status: String,
+    pub merkle_root: Option<String>,
+    pub chain_id: u64,
+    pub contract_address: String,
+    pub transaction_hash: Option<String>,
+    pub block_number: Option<u64>,
+}
+
+// GET /api/food-records/{product_id}/merkle-proof
+#[derive(Serialize, Debug)]
+pub struct MerkleProofResponse {
+    pub product_id: String,
+    pub metadata_hash: String,
+    pub leaf_hash: String,
+    pub leaf_index: u32,
+    pub proof: Vec<ProofStep>,          // 从叶子到根，依次与兄弟节点合并
+    pub merkle_root: String,
+    pub leaf_encoding: &'static str,
+    pub node_encoding: &'static str,
+    pub batch_id: u64,
+    pub batch_status: String,
+    pub anchor_key: String,             // 批次根上链时使用的 productId，可通过合约 records(anchor_key) 查询根
+    pub chain_id: u64,
+    pub contract_address: String,
+    pub transaction_hash: Option<String>,
+    pub block_number: Option<u64>,
+}
+
+// GET /api/chain/config：客户端调用合约所需的链 ID、合约地址与 ABI
+#[derive(Serialize, Debug)]
+pub struct ChainConfigResponse {
+    pub chain_id: u64,
+    pub chain_name: String,
+    pub contract_address: String,
+    pub deploy_block: u64,
+    pub contract_name: String,
+    pub abi: JsonValue,                  // Hardhat 编译产物中的完整 ABI
+    pub eip712_domain: Eip712Domain,     // 对记录签名 (FoodRecord) 时使用的域
+    pub default_chain_id: u64,
+    pub chains: Vec<ChainSummary>,       // 后端支持的全部链
+}
+
+#[derive(Serialize, Debug)]
+pub struct ChainSummary {
+    pub chain_id: u64,
+    pub name: String,
+    pub contract_address: String,
+    pub deploy_block: u64,
+    pub confirmations: u64,
+}
+
+// salted-fields-v1 记录的一个字段：明文、盐值与承诺 (metadata_fields 表)
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct MetadataField {
+    pub field_name: String,
+    pub value_canonical: String, // 字段值的 JCS 规范化文本
+    pub salt: String,
+    pub commitment: String,
+    pub is_public: bool,
+}
+
+// POST /api/food-records/{product_id}/disclosures 的请求体
+#[derive(Deserialize, Debug)]
+pub struct DisclosureRequest {
+    pub audience: String,    // 受众，例如 regulator、retailer
+    pub fields: Vec<String>, // 向该受众披露的字段
+    #[serde(rename = "ttlSecs")]
+    pub ttl_secs: Option<u64>, // 有效期，未指定时长期有效
+}
+
+// 已创建的披露授权
+#[derive(Debug)]
+pub struct DisclosureGrant {
+    pub product_id: String,
+    pub audience: String,
+    pub fields: sqlx::types::Json<Vec<String>>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DisclosureGrantResponse {
+    pub token: String, // 受众凭 GET /api/disclosures/{token} 查看披露内容
+    pub product_id: String,
+    pub audience: String,
+    pub fields: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+// ------------------------------ 供应链事件 ------------------------------
+
+// POST /api/food-records/{product_id}/events 的请求体，stage 与 payload 见 trace_events::StagePayload
+#[derive(Deserialize, Debug)]
+pub struct TraceEventRequest {
+    pub actor: String,    // 执行该环节的组织或人员
+    pub location: String,
+    #[serde(rename = "occurredAt")]
+    pub occurred_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub details: StagePayload,
+}
+
+// 校验后待写入的事件，occurred_at 已截断到毫秒
+#[derive(Debug, Clone)]
+pub struct NewTraceEvent {
+    pub stage: TraceStage,
+    pub actor: String,
+    pub location: String,
+    pub occurred_at: DateTime<Utc>,
+    pub payload: JsonValue,
+}
+
+// trace_events 表的一行
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct TraceEventRow {
+    pub id: u64,
+    pub product_id: String,
+    pub sequence: u32,
+    pub stage: String,
+    pub actor: String,
+    pub location: String,
+    pub occurred_at: DateTime<Utc>,
+    pub payload: sqlx::types::Json<JsonValue>,
+    pub prev_event_hash: String,
+    pub event_hash: String,
+    pub recorded_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TraceEventResponse {
+    pub id: u64,
+    pub sequence: u32, // 追加顺序
+    pub stage: String,
+    pub actor: String,
+    pub location: String,
+    pub occurred_at: DateTime<Utc>,
+    pub payload: JsonValue,
+    pub prev_event_hash: String,
+    pub event_hash: String,
+    pub hash_valid: bool, // 事件哈希与哈希链复核通过
+    pub recorded_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+// GET /api/food-records/{product_id}/events：按发生时间排列的时间线
+#[derive(Serialize, Debug)]
+pub struct TraceTimelineResponse {
+    pub product_id: String,
+    pub event_count: usize,
+    pub valid: bool,                          // 全部事件的哈希链复核通过
+    pub head_event_hash: Option<String>,      // 最后追加的事件的哈希
+    pub first_broken: Option<TraceBrokenLink>,
+    pub events: Vec<TraceEventResponse>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TraceBrokenLink {
+    pub sequence: u32,
+    pub reason: String,
+}
+
+// ------------------------------ 批次谱系 ------------------------------
+
+// POST /api/food-records/{product_id}/lineage 的请求体，路径中的产品为子批次
+#[derive(Deserialize, Debug)]
+pub struct LineageLinkRequest {
+    #[serde(rename = "parentProductId")]
+    pub parent_product_id: String,
+    pub relationship: LineageRelationship,
+    #[serde(rename = "parentQuantity")]
+    pub parent_quantity: Option<f64>, // 从父批次投入的数量
+    #[serde(rename = "childQuantity")]
+    pub child_quantity: Option<f64>,  // 子批次中来自该父批次的数量
+    pub unit: Option<String>,
+}
+
+// batch_lineage 表的一条边
+#[derive(Serialize, Debug, Clone, sqlx::FromRow)]
+pub struct LineageLink {
+    pub id: u64,
+    pub parent_product_id: String,
+    pub child_product_id: String,
+    pub relationship: String,
+    pub parent_quantity: Option<f64>,
+    pub child_quantity: Option<f64>,
+    pub unit: Option<String>,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+// 遍历深度，未指定时为 lineage::DEFAULT_MAX_DEPTH
+#[derive(Deserialize, Debug)]
+pub struct LineageQuery {
+    pub max_depth: Option<u32>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct LineageResponse {
+    pub product_id: String,
+    pub direction: LineageDirection,
+    pub max_depth: u32,
+    pub truncated: bool,           // 达到深度或行数上限，更远的批次未列出
+    pub nodes: Vec<LineageNode>,   // 可达的批次 (不含起点)，按深度排列
+    pub edges: Vec<LineageEdge>,
+    pub cycles: Vec<Vec<String>>,  // 检测到的环，从起点开始的产品ID路径，最后一个产品已在路径中出现过
+}
+
+#[derive(Serialize, Debug)]
+pub struct LineageNode {
+    pub product_id: String,
+    pub depth: u32, // 与起点之间最短的边数
+}
+
+#[derive(Serialize, Debug)]
+pub struct LineageEdge {
+    pub id: u64,
+    pub parent_product_id: String,
+    pub child_product_id: String,
+    pub relationship: String,
+    pub parent_quantity: Option<f64>,
+    pub child_quantity: Option<f64>,
+    pub unit: Option<String>,
+}
+
+// 递归遍历返回的边，to_depth 为边指向的批次的最短深度，超出遍历范围时为空
+#[derive(Debug)]
+pub struct LineageWalkEdge {
+    pub id: u64,
+    pub parent_product_id: String,
+    pub child_product_id: String,
+    pub relationship: String,
+    pub parent_quantity: Option<f64>,
+    pub child_quantity: Option<f64>,
+    pub unit: Option<String>,
+    pub to_depth: Option<u64>,
+}
+
+// ------------------------------ 召回 ------------------------------
+
+// POST /api/recalls 的请求体
+#[derive(Deserialize, Debug)]
+pub struct RecallRequest {
+    pub reason: String,
+    #[serde(rename = "severityClass")]
+    pub severity_class: RecallSeverity,
+    #[serde(rename = "initiatingOrganization")]
+    pub initiating_organization: String,
+    #[serde(rename = "productIds")]
+    pub product_ids: Vec<String>, // 直接受影响的产品，下游批次由后端沿谱系传播
+}
+
+// POST /api/recalls/{recall_id}/status 的请求体
+#[derive(Deserialize, Debug)]
+pub struct RecallStatusRequest {
+    pub status: RecallStatus,
+}
+
+// GET /api/recalls 的查询参数
+#[derive(Deserialize, Debug)]
+pub struct RecallListQuery {
+    pub status: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, sqlx::FromRow)]
+pub struct Recall {
+    pub id: u64,
+    pub reason: String,
+    pub severity_class: String,
+    pub initiating_organization: String,
+    pub status: String,
+    pub propagation_truncated: bool, // 下游遍历被截断，可能有批次未被标记
+    pub opened_by: String,
+    pub opened_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub closed_at: Option<DateTime<Utc>>,
+}
+
+// 召回影响的一个产品 (recall_products 表)
+#[derive(Serialize, Debug, Clone, sqlx::FromRow)]
+pub struct RecallProduct {
+    pub product_id: String,
+    pub source: String,                 // direct / downstream
+    pub depth: u32,
+    pub via_product_id: Option<String>, // downstream 产品由哪个直接受影响的产品传播而来
+}
+
+#[derive(Serialize, Debug)]
+pub struct RecallResponse {
+    #[serde(flatten)]
+    pub recall: Recall,
+    pub affected_products: Vec<RecallProduct>,
+}
+
+// 影响某一产品的召回，显示在记录详情中
+#[derive(Serialize, Debug, Clone, sqlx::FromRow)]
+pub struct ProductRecall {
+    pub recall_id: u64,
+    pub status: String,
+    pub severity_class: String,
+    pub reason: String,
+    pub initiating_organization: String,
+    pub source: String,
+    pub via_product_id: Option<String>,
+    pub opened_at: DateTime<Utc>,
+}
+
+// ------------------------------ 认证证书 ------------------------------
+
+// POST /api/certificates 的请求体
+#[derive(Deserialize, Debug)]
+pub struct CertificateRequest {
+    #[serde(rename = "certificateType")]
+    pub certificate_type: CertificateType,
+    pub issuer: String,
+    #[serde(rename = "certificateNumber")]
+    pub certificate_number: String,
+    pub scope: String,
+    #[serde(rename = "validFrom")]
+    pub valid_from: NaiveDate,
+    #[serde(rename = "validUntil")]
+    pub valid_until: NaiveDate, // 有效期最后一天
+    #[serde(rename = "documentHash")]
+    pub document_hash: String,  // 证书文件的 32 字节哈希
+    #[serde(default)]
+    pub organizations: Vec<String>, // 持证组织名称，不存在时自动登记
+    #[serde(rename = "productIds", default)]
+    pub product_ids: Vec<String>,
+}
+
+// POST /api/certificates/{certificate_id}/products 的请求体
+#[derive(Deserialize, Debug)]
+pub struct CertificateProductsRequest {
+    #[serde(rename = "productIds")]
+    pub product_ids: Vec<String>,
+}
+
+// GET /api/certificates/expiring 的查询参数
+#[derive(Deserialize, Debug)]
+pub struct ExpiringCertificatesQuery {
+    pub days: Option<u32>, // 默认 30 天
+}
+
+#[derive(Serialize, Debug, Clone, sqlx::FromRow)]
+pub struct Certificate {
+    pub id: u64,
+    pub certificate_type: String,
+    pub issuer: String,
+    pub certificate_number: String,
+    pub scope: String,
+    pub valid_from: NaiveDate,
+    pub valid_until: NaiveDate,
+    pub document_hash: String,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CertificateResponse {
+    #[serde(flatten)]
+    pub certificate: Certificate,
+    pub organizations: Vec<String>,
+    pub product_ids: Vec<String>,
+}
+
+// 记录详情中的认证情况
+#[derive(Serialize, Debug)]
+pub struct CertificationStatus {
+    pub production_date: Option<NaiveDate>,             // 元数据中的 productionDate，缺失、未公开或无法解析时为空
+    pub all_valid_on_production_date: Option<bool>,     // 生产日期未知 (包括未公开) 或没有关联证书时为空
+    pub certificates: Vec<CertificateValidity>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CertificateValidity {
+    #[serde(flatten)]
+    pub certificate: Certificate,
+    pub valid_on_production_date: Option<bool>,
+}
+
+// ------------------------------ 实验室检测 ------------------------------
+
+// 一条检测结果 (一个分析物的一次测定)
+#[derive(Deserialize, Debug)]
+pub struct LabResultInput {
+    pub analyte: String,
+    pub method: String,
+    #[serde(rename = "measuredValue")]
+    pub measured_value: f64,
+    pub unit: String,
+    pub laboratory: String,
+    #[serde(rename = "sampleDate")]
+    pub sample_date: NaiveDate,
+    #[serde(rename = "reportHash")]
+    pub report_hash: Option<String>, // 检测报告文件的 32 字节哈希
+}
+
+// POST /api/food-records/{product_id}/lab-results 的请求体，通常为一份检测报告中的全部结果
+#[derive(Deserialize, Debug)]
+pub struct LabResultsRequest {
+    pub category: Option<String>, // 产品类别，首次提交时登记到产品上，之后可以省略
+    pub results: Vec<LabResultInput>,
+}
+
+// GET /api/food-records/{product_id}/lab-results 的查询参数，法域未指定时为限量表的 defaultJurisdiction；类别取产品登记的类别
+#[derive(Deserialize, Debug)]
+pub struct LabEvaluationQuery {
+    pub jurisdiction: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, sqlx::FromRow)]
+pub struct LabResult {
+    pub id: u64,
+    pub product_id: String,
+    pub analyte: String,
+    pub method: String,
+    pub measured_value: f64,
+    pub unit: String,
+    pub laboratory: String,
+    pub sample_date: NaiveDate,
+    pub report_hash: Option<String>,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct LabEvaluationResponse {
+    pub product_id: String,
+    pub category: Option<String>,
+    pub jurisdiction: Option<String>,
+    pub verdict: LabVerdict,              // 批次结论：任一分析物 fail 为 fail，否则任一 unknown 为 unknown
+    pub analytes: Vec<AnalyteEvaluation>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct AnalyteEvaluation {
+    pub analyte: String,
+    pub verdict: LabVerdict,
+    pub limit: Option<f64>,          // 限量表中的最大残留限量
+    pub limit_unit: Option<String>,
+    pub detail: Option<String>,      // 无法判定的原因
+    pub results: Vec<LabResultEvaluation>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct LabResultEvaluation {
+    #[serde(flatten)]
+    pub result: LabResult,
+    pub verdict: LabVerdict,
+    pub value_in_limit_unit: Option<f64>, // 换算到限量单位的测定值
+}
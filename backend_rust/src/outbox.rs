@@ -0,0 +1,232 @@
+// 上链发件箱 (transactional outbox) 后台任务
+// 托管上链的记录与发件箱条目在同一数据库事务中写入，这里负责发送交易、等待确认，失败时按指数退避重试
+// 状态流转: pending -> submitted -> confirmed，超过最大发送次数后为 failed
+// submitted 的条目只跟踪同一笔交易：超时未打包时重新广播原交易 (哈希不变)，只有交易执行失败或其 nonce 已被其他交易占用
+// (原交易不可能再被打包) 时才退回 pending 用新的 nonce 重新发送，因此超时与查询失败都不会导致重复上链
+// 交易广播成功但写回 submitted 状态失败时，条目仍为 pending 并会被再次发送，同一哈希可能重复上链 (至少一次语义)
+use actix_web::web;
+use chrono::{DateTime, Utc};
+use log::{error, info, warn};
+use std::time::Duration;
+use crate::config::env_or;
+use crate::db;
+use crate::errors::AppError;
+use crate::eth_rpc::{decode_hex_bytes, EthClient};
+use crate::models::{AnchorOutboxEntry, AppState};
+use crate::signer::LocalSigner;
+use crate::verification::{self, ValidatedAnchor};
+
+pub const OUTBOX_STATUS_PENDING: &str = "pending";
+pub const OUTBOX_STATUS_SUBMITTED: &str = "submitted";
+pub const OUTBOX_STATUS_FAILED: &str = "failed";
+
+const OUTBOX_BATCH_SIZE: u32 = 20;
+const MAX_RETRY_DELAY_SECS: u64 = 600;
+
+#[derive(Debug, Clone)]
+pub struct OutboxConfig {
+    pub poll_interval: Duration,
+    pub max_attempts: u32,      // 最多发送几次交易，之后标记为 failed
+    pub retry_base_secs: u64,   // 第 n 次失败后等待 retry_base_secs * 2^(n-1) 秒再重试
+    pub receipt_timeout: Duration, // 交易发送后超过该时间仍未打包时，检查交易是否被丢弃并重新广播
+}
+
+impl OutboxConfig {
+    pub fn from_env() -> Self {
+        OutboxConfig {
+            poll_interval: Duration::from_secs(env_or("OUTBOX_POLL_INTERVAL_SECS", 2)),
+            max_attempts: env_or("OUTBOX_MAX_ATTEMPTS", 5u32).max(1),
+            retry_base_secs: env_or("OUTBOX_RETRY_BASE_SECS", 5),
+            receipt_timeout: Duration::from_secs(env_or("OUTBOX_RECEIPT_TIMEOUT_SECS", 120)),
+        }
+    }
+
+    pub fn retry_delay_secs(&self, attempts: u32) -> u64 {
+        let exponent = attempts.saturating_sub(1).min(16);
+        self.retry_base_secs.saturating_mul(1 << exponent).min(MAX_RETRY_DELAY_SECS)
+    }
+}
+
+// 启动发件箱任务，未配置托管签名账户时不启动
+pub fn spawn_outbox_worker(app_state: web::Data<AppState>, config: OutboxConfig) {
+    if app_state.signer.is_none() {
+        return;
+    }
+    info!(
+        "上链发件箱任务已启动: 最多发送 {} 次，重试基础间隔 {} 秒",
+        config.max_attempts, config.retry_base_secs
+    );
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config.poll_interval);
+        loop {
+            interval.tick().await;
+            if let Err(e) = process_due_entries(&app_state, &config).await {
+                warn!("处理上链发件箱失败: {}", e);
+            }
+        }
+    });
+}
+
+async fn process_due_entries(app_state: &AppState, config: &OutboxConfig) -> Result<(), AppError> {
+    let Some(signer) = app_state.signer.as_ref() else {
+        return Ok(());
+    };
+    for entry in db::list_due_outbox_entries_db(&app_state.db_pool, OUTBOX_BATCH_SIZE).await? {
+        match entry.status.as_str() {
+            OUTBOX_STATUS_PENDING => {
+                if let Err(e) = submit_entry(app_state, signer, &entry).await {
+                    record_failure(app_state, config, &entry, entry.attempts + 1, &e).await?;
+                }
+            }
+            OUTBOX_STATUS_SUBMITTED => {
+                // 查询失败时保持 submitted 稍后再查：已发送的交易仍可能被打包，不能另发新交易
+                if let Err(e) = check_submitted_entry(app_state, config, signer, &entry).await {
+                    let message = e.to_string();
+                    warn!("查询产品ID {} 的上链交易失败，稍后再查: {}", entry.product_id, message);
+                    db::reschedule_outbox_entry_db(
+                        &app_state.db_pool, entry.id, OUTBOX_STATUS_SUBMITTED, entry.attempts, Some(&message), config.retry_delay_secs(1),
+                    ).await?;
+                }
+            }
+            _ => continue,
+        }
+    }
+    Ok(())
+}
+
+async fn submit_entry(app_state: &AppState, signer: &LocalSigner, entry: &AnchorOutboxEntry) -> Result<(), AppError> {
+    let chain = app_state.chains.resolve(Some(entry.chain_id))?;
+    let sent = signer
+        .submit_add_record(&chain.eth_client, entry.chain_id, &entry.contract_address, &entry.product_id, &entry.metadata_hash)
+        .await?;
+    db::mark_outbox_submitted_db(&app_state.db_pool, entry.id, &sent, entry.attempts + 1).await?;
+    info!("产品ID {} 的上链交易已发送 (第 {} 次): {}", entry.product_id, entry.attempts + 1, sent.hash);
+    Ok(())
+}
+
+// 检查已发送交易：尚未打包时稍后再查，打包后校验回执与 RecordAdded 事件并确认
+async fn check_submitted_entry(
+    app_state: &AppState,
+    config: &OutboxConfig,
+    signer: &LocalSigner,
+    entry: &AnchorOutboxEntry,
+) -> Result<(), AppError> {
+    let tx_hash = entry
+        .transaction_hash
+        .as_deref()
+        .ok_or_else(|| AppError::InternalError(format!("发件箱条目 #{} 处于 submitted 状态但没有交易哈希。", entry.id)))?;
+    let chain = app_state.chains.resolve(Some(entry.chain_id))?;
+    let sent = SubmittedTransaction {
+        hash: tx_hash,
+        nonce: entry.transaction_nonce,
+        raw: entry.raw_transaction.as_deref(),
+        submitted_at: entry.submitted_at,
+    };
+
+    let status = check_anchor_receipt(
+        &chain.eth_client, config, signer, &entry.contract_address, &sent, &entry.product_id, &entry.metadata_hash,
+    ).await?;
+    let anchor = match status {
+        ReceiptStatus::Confirmed(anchor) => anchor,
+        ReceiptStatus::Pending => {
+            return db::reschedule_outbox_entry_db(
+                &app_state.db_pool, entry.id, OUTBOX_STATUS_SUBMITTED, entry.attempts, None, config.poll_interval.as_secs(),
+            ).await;
+        }
+        ReceiptStatus::Overdue(message) => {
+            warn!("产品ID {} 的上链交易仍未打包: {}", entry.product_id, message);
+            return db::reschedule_outbox_entry_db(
+                &app_state.db_pool, entry.id, OUTBOX_STATUS_SUBMITTED, entry.attempts, Some(&message), config.retry_delay_secs(1),
+            ).await;
+        }
+        ReceiptStatus::Dropped(reason) => {
+            return record_failure(app_state, config, entry, entry.attempts, &AppError::BlockchainError(reason)).await;
+        }
+    };
+    db::confirm_outbox_entry_db(
+        &app_state.db_pool,
+        entry.id,
+        &anchor.to_record_anchor(&entry.product_id, entry.chain_id, &entry.contract_address),
+    ).await?;
+    info!("产品ID {} 已上链确认: 交易 {}，区块 {}", entry.product_id, anchor.transaction_hash, anchor.block_number);
+    Ok(())
+}
+
+// 已发送、等待确认的交易 (发件箱条目与 Merkle 批次根共用)
+pub struct SubmittedTransaction<'a> {
+    pub hash: &'a str,
+    pub nonce: Option<u64>,   // 早于记录 nonce 的条目为空，只能继续等待
+    pub raw: Option<&'a str>, // 十六进制编码的已签名交易
+    pub submitted_at: Option<DateTime<Utc>>,
+}
+
+pub enum ReceiptStatus {
+    Confirmed(ValidatedAnchor),
+    Pending,
+    Overdue(String), // 超过 receipt_timeout 仍未打包，继续等待同一笔交易
+    Dropped(String), // 交易执行失败或其 nonce 已被其他交易占用，不会再被打包，可以用新的 nonce 重新发送
+}
+
+// 查询已发送 addRecord 交易的回执，打包后校验回执与 RecordAdded 事件 (Merkle 批次根的确认同样使用)
+// 超时未打包时：账户已打包的交易数超过该 nonce 说明 nonce 被其他交易占用；否则节点不再持有该交易时重新广播原交易
+pub async fn check_anchor_receipt(
+    eth_client: &EthClient,
+    config: &OutboxConfig,
+    signer: &LocalSigner,
+    contract_address: &str,
+    sent: &SubmittedTransaction<'_>,
+    product_id: &str,
+    metadata_hash: &str,
+) -> Result<ReceiptStatus, AppError> {
+    if eth_client.get_transaction_receipt(sent.hash).await?.is_some() {
+        return match verification::validate_anchor_transaction(eth_client, contract_address, sent.hash, product_id, metadata_hash).await {
+            Ok(anchor) => Ok(ReceiptStatus::Confirmed(anchor)),
+            Err(AppError::AnchorValidationFailed(reason)) => Ok(ReceiptStatus::Dropped(reason)),
+            Err(e) => Err(e),
+        };
+    }
+    let waited = sent.submitted_at.map(|at| (Utc::now() - at).num_seconds().max(0) as u64).unwrap_or(0);
+    if waited < config.receipt_timeout.as_secs() {
+        return Ok(ReceiptStatus::Pending);
+    }
+
+    if let Some(nonce) = sent.nonce {
+        let mined_nonce = eth_client.get_transaction_count(signer.address(), "latest").await?;
+        if mined_nonce > nonce {
+            // 两次查询之间交易可能刚好被打包，再查一次回执
+            if eth_client.get_transaction_receipt(sent.hash).await?.is_some() {
+                return Ok(ReceiptStatus::Pending);
+            }
+            return Ok(ReceiptStatus::Dropped(format!(
+                "交易 {} 的 nonce {} 已被其他交易占用，该交易不会再被打包。", sent.hash, nonce
+            )));
+        }
+    }
+    if let Some(raw) = sent.raw {
+        if !eth_client.transaction_known(sent.hash).await? {
+            match eth_client.send_raw_transaction(&decode_hex_bytes(raw)?).await {
+                Ok(_) => info!("交易 {} 已被节点丢弃，已重新广播原交易。", sent.hash),
+                Err(e) => warn!("重新广播交易 {} 失败: {}", sent.hash, e),
+            }
+        }
+    }
+    Ok(ReceiptStatus::Overdue(format!("交易 {} 已等待 {} 秒仍未打包。", sent.hash, waited)))
+}
+
+// 发送失败或交易不会再被打包：未达到最大次数时退回 pending 并按指数退避重试，否则标记为 failed
+async fn record_failure(
+    app_state: &AppState,
+    config: &OutboxConfig,
+    entry: &AnchorOutboxEntry,
+    attempts: u32,
+    err: &AppError,
+) -> Result<(), AppError> {
+    let message = err.to_string();
+    if attempts >= config.max_attempts {
+        error!("产品ID {} 上链失败，已发送 {} 次，不再重试: {}", entry.product_id, attempts, message);
+        return db::reschedule_outbox_entry_db(&app_state.db_pool, entry.id, OUTBOX_STATUS_FAILED, attempts, Some(&message), 0).await;
+    }
+    let delay = config.retry_delay_secs(attempts.max(1));
+    warn!("产品ID {} 上链失败 (第 {} 次)，{} 秒后重试: {}", entry.product_id, attempts, delay, message);
+    db::reschedule_outbox_entry_db(&app_state.db_pool, entry.id, OUTBOX_STATUS_PENDING, attempts, Some(&message), delay).await
+}
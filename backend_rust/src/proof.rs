@@ -0,0 +1,139 @@
+// 离线证明包的生成
+// 证明包结构与离线校验 (verify_proof_bundle) 定义在 proof_core 中，verify_proof 程序不依赖数据库即可复核
+use serde_json::{Map, Value as JsonValue};
+use crate::canonical_json;
+use crate::errors::AppError;
+use crate::eth_rpc::{parse_quantity, EthClient};
+use crate::hashing::{normalize_hash, to_hex_string, HashScheme};
+use crate::merkle;
+use crate::models::{FoodRecordDetail, MerkleLeafDetail, MetadataField};
+use crate::rlp;
+use crate::trie;
+
+pub use proof_core::proof::{
+    block_header_hash, encode_receipt, find_record_added, verify_proof_bundle, BundleAnchor, BundleEvent, BundleMerkle,
+    BundleMetadata, BundleReceiptProof, ProofBundle, ProofCheck, ProofVerification, PROOF_BUNDLE_VERSION,
+};
+use proof_core::proof::{field, HeaderField, HEADER_FIELDS};
+
+// 由数据库记录与链上数据生成证明包
+pub async fn build_proof_bundle(
+    eth_client: &EthClient,
+    chain_id: u64,
+    contract_address: &str,
+    record: &FoodRecordDetail,
+    merkle_leaf: Option<&MerkleLeafDetail>,
+    metadata_fields: &[MetadataField],
+) -> Result<ProofBundle, AppError> {
+    let hash_scheme = HashScheme::parse(&record.hash_scheme)?;
+    let canonical = match &record.metadata_canonical {
+        Some(canonical) => canonical.clone(),
+        None => canonical_json::canonicalize(&record.metadata_json.0)?,
+    };
+    let metadata_hash = normalize_hash(&record.onchain_metadata_hash);
+    let field_commitments = (hash_scheme == HashScheme::SaltedFieldsV1).then(|| {
+        metadata_fields.iter().map(|field| (field.field_name.clone(), field.commitment.clone())).collect()
+    });
+    let public_salts = (hash_scheme == HashScheme::SaltedFieldsV1).then(|| {
+        metadata_fields
+            .iter()
+            .filter(|field| field.is_public)
+            .map(|field| (field.field_name.clone(), field.salt.clone()))
+            .collect()
+    });
+
+    let (merkle, anchor_key, anchored_hash, transaction_hash) = match merkle_leaf {
+        Some(leaf) => {
+            let (Some(leaf_index), Some(proof), Some(root), Some(tx_hash)) =
+                (leaf.leaf_index, &leaf.proof, &leaf.merkle_root, &leaf.transaction_hash)
+            else {
+                return Err(AppError::Conflict(format!("Merkle 批次 #{} 的批次根尚未上链，无法导出证明。", leaf.batch_id)));
+            };
+            let merkle = BundleMerkle {
+                batch_id: leaf.batch_id,
+                leaf_hash: leaf.leaf_hash.clone(),
+                leaf_index,
+                proof: proof.0.clone(),
+                root: root.clone(),
+            };
+            (Some(merkle), merkle::anchor_key(leaf.batch_id), root.clone(), tx_hash.clone())
+        }
+        None => {
+            let tx_hash = record
+                .blockchain_transaction_hash
+                .clone()
+                .ok_or_else(|| AppError::Conflict(format!("产品ID '{}' 尚未上链，无法导出证明。", record.product_id)))?;
+            (None, record.product_id.clone(), metadata_hash.clone(), tx_hash)
+        }
+    };
+
+    let receipt = eth_client
+        .get_transaction_receipt_json(&transaction_hash)
+        .await?
+        .ok_or_else(|| AppError::BlockchainError(format!("上链交易 {} 的回执不存在。", transaction_hash)))?;
+    let block_hash = field(&receipt, "blockHash")?.to_lowercase();
+    let block_number = parse_quantity(field(&receipt, "blockNumber")?)?;
+    let transaction_index = parse_quantity(field(&receipt, "transactionIndex")?)?;
+
+    let block = eth_client
+        .get_block_json_by_hash(&block_hash)
+        .await?
+        .ok_or_else(|| AppError::BlockchainError(format!("区块 {} 不存在 (可能已被重组)。", block_hash)))?;
+    let mut block_header = Map::new();
+    block_header.insert("hash".to_string(), JsonValue::String(field(&block, "hash")?.to_lowercase()));
+    for header_field in HEADER_FIELDS {
+        let (HeaderField::Quantity(name) | HeaderField::Data(name)) = header_field;
+        if let Some(value) = block.get(name).filter(|value| value.is_string()) {
+            block_header.insert(name.to_string(), value.clone());
+        }
+    }
+    let block_header = JsonValue::Object(block_header);
+
+    // 用区块内全部回执重建回执树，根必须与区块头一致
+    let tx_hashes: Vec<String> = serde_json::from_value(block.get("transactions").cloned().unwrap_or_default())
+        .map_err(|e| AppError::BlockchainError(format!("区块 {} 的交易列表格式错误: {}", block_hash, e)))?;
+    let receipts = eth_client.get_block_receipts_json(block_number, &tx_hashes).await?;
+    let mut entries = Vec::with_capacity(receipts.len());
+    for block_receipt in &receipts {
+        let index = parse_quantity(field(block_receipt, "transactionIndex")?)?;
+        entries.push((rlp::encode_uint(index), encode_receipt(block_receipt)?));
+    }
+    let key = rlp::encode_uint(transaction_index);
+    let (receipts_root, nodes) = trie::build_proof(&entries, &key)?;
+    if to_hex_string(&receipts_root) != field(&block_header, "receiptsRoot")?.to_lowercase() {
+        return Err(AppError::BlockchainError(format!(
+            "由区块 {} 的回执重建的 receiptsRoot {} 与区块头不一致 (节点返回的回执不完整或包含不支持的交易类型)。",
+            block_hash, to_hex_string(&receipts_root)
+        )));
+    }
+
+    let anchor = BundleAnchor {
+        chain_id,
+        contract_address: contract_address.to_lowercase(),
+        anchor_key,
+        anchored_hash,
+        transaction_hash: transaction_hash.to_lowercase(),
+        transaction_index,
+        block_number,
+        block_hash,
+    };
+    let event = find_record_added(&receipt, &anchor)?.ok_or_else(|| {
+        AppError::BlockchainError(format!("上链交易 {} 的回执中没有对应的 RecordAdded 事件。", anchor.transaction_hash))
+    })?;
+
+    Ok(ProofBundle {
+        version: PROOF_BUNDLE_VERSION.to_string(),
+        product_id: record.product_id.clone(),
+        metadata: BundleMetadata { canonical, hash_scheme, metadata_hash, field_commitments, public_salts },
+        merkle,
+        anchor,
+        receipt,
+        event,
+        block_header,
+        receipt_proof: BundleReceiptProof {
+            receipts_root: to_hex_string(&receipts_root),
+            key: to_hex_string(&key),
+            nodes: nodes.iter().map(|node| to_hex_string(node)).collect(),
+        },
+    })
+}
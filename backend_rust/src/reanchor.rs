@@ -0,0 +1,128 @@
+// 重新上链 (re-anchor)
+// 本地 Hardhat 网络重启后链上记录全部丢失，把 traceability_data 中上链在该链的每条记录按原哈希重新提交到新部署的合约，
+// 进度保存在 reanchor_progress 表中，中断后再次运行会跳过已确认的记录并复查上次提交的交易
+use log::{info, warn};
+use sqlx::MySqlPool;
+use std::time::Duration;
+use crate::contract;
+use crate::db;
+use crate::errors::AppError;
+use crate::eth_rpc::EthClient;
+use crate::hashing::normalize_hash;
+use crate::models::ReanchorCandidate;
+use crate::verification;
+
+pub const STATUS_SUBMITTED: &str = "submitted";
+pub const STATUS_FAILED: &str = "failed";
+
+const RECEIPT_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Default)]
+pub struct ReanchorSummary {
+    pub total: usize,
+    pub confirmed: usize,
+    pub pending: usize, // 交易已提交但超时前没有回执，保持 submitted 状态，下次运行时复查而不是重新提交
+    pub failed: usize,
+}
+
+enum ReanchorOutcome {
+    Confirmed(String),
+    Pending(String),
+}
+
+// from_account 为空时使用节点的第一个解锁账户 (Hardhat / anvil 默认账户)
+pub async fn run_reanchor(
+    pool: &MySqlPool,
+    eth_client: &EthClient,
+    chain_id: u64,
+    target_contract: &str,
+    from_account: Option<String>,
+) -> Result<ReanchorSummary, AppError> {
+    let target_contract = target_contract.to_lowercase();
+    let from = match from_account {
+        Some(account) => account,
+        None => eth_client
+            .accounts()
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| AppError::BlockchainError("节点没有可用的解锁账户，请通过 --from 指定发送账户。".to_string()))?,
+    };
+
+    let candidates = db::list_reanchor_candidates_db(pool, chain_id, &target_contract).await?;
+    info!("开始重新上链到合约 {}: 待处理 {} 条记录，发送账户 {}", target_contract, candidates.len(), from);
+
+    let mut summary = ReanchorSummary { total: candidates.len(), ..Default::default() };
+    for (index, candidate) in candidates.iter().enumerate() {
+        match reanchor_record(pool, eth_client, chain_id, &target_contract, &from, candidate).await {
+            Ok(ReanchorOutcome::Confirmed(tx_hash)) => {
+                summary.confirmed += 1;
+                info!("[{}/{}] 产品ID {} 已重新上链: {}", index + 1, summary.total, candidate.product_id, tx_hash);
+            }
+            Ok(ReanchorOutcome::Pending(tx_hash)) => {
+                summary.pending += 1;
+                let message = format!("等待交易 {} 回执超时，交易仍可能上链。", tx_hash);
+                warn!("[{}/{}] 产品ID {}: {}", index + 1, summary.total, candidate.product_id, message);
+                db::upsert_reanchor_progress_db(pool, &target_contract, &candidate.product_id, Some(&tx_hash), STATUS_SUBMITTED, Some(&message)).await?;
+            }
+            Err(e) => {
+                summary.failed += 1;
+                warn!("[{}/{}] 产品ID {} 重新上链失败: {}", index + 1, summary.total, candidate.product_id, e);
+                db::upsert_reanchor_progress_db(pool, &target_contract, &candidate.product_id, None, STATUS_FAILED, Some(&e.to_string())).await?;
+            }
+        }
+    }
+    Ok(summary)
+}
+
+async fn reanchor_record(
+    pool: &MySqlPool,
+    eth_client: &EthClient,
+    chain_id: u64,
+    target_contract: &str,
+    from: &str,
+    candidate: &ReanchorCandidate,
+) -> Result<ReanchorOutcome, AppError> {
+    let metadata_hash = normalize_hash(&candidate.onchain_metadata_hash);
+
+    // 续跑：上次记录的交易无论状态为 submitted 还是 failed 都先复查；
+    // 已打包或仍在交易池中的交易可能上链，只等待它，不能再提交一笔 addRecord
+    if let Some(previous_tx) = candidate.progress_transaction_hash.as_deref() {
+        let receipt = eth_client.get_transaction_receipt(previous_tx).await?;
+        if receipt.is_some() || eth_client.transaction_known(previous_tx).await? {
+            match await_anchor(pool, eth_client, chain_id, target_contract, candidate, previous_tx, &metadata_hash).await {
+                // 交易已打包但执行失败或与记录不符，重新提交
+                Err(AppError::AnchorValidationFailed(reason)) => {
+                    warn!("产品ID {} 上次提交的交易无效 ({})，重新提交。", candidate.product_id, reason);
+                }
+                outcome => return outcome,
+            }
+        }
+    }
+
+    let call_data = contract::encode_add_record(&candidate.product_id, &contract::parse_bytes32(&metadata_hash)?);
+    let tx_hash = eth_client.send_transaction(from, target_contract, &call_data).await?;
+    // 先记录已提交的交易哈希，进程在等待回执时中断也能在下次运行时找回
+    db::upsert_reanchor_progress_db(pool, target_contract, &candidate.product_id, Some(&tx_hash), STATUS_SUBMITTED, None).await?;
+    await_anchor(pool, eth_client, chain_id, target_contract, candidate, &tx_hash, &metadata_hash).await
+}
+
+// 等待交易回执并校验 RecordAdded 事件，超时返回 Pending
+async fn await_anchor(
+    pool: &MySqlPool,
+    eth_client: &EthClient,
+    chain_id: u64,
+    target_contract: &str,
+    candidate: &ReanchorCandidate,
+    tx_hash: &str,
+    metadata_hash: &str,
+) -> Result<ReanchorOutcome, AppError> {
+    if eth_client.wait_for_receipt(tx_hash, RECEIPT_TIMEOUT).await?.is_none() {
+        return Ok(ReanchorOutcome::Pending(tx_hash.to_string()));
+    }
+    let anchor = verification::validate_anchor_transaction(
+        eth_client, target_contract, tx_hash, &candidate.product_id, metadata_hash,
+    ).await?;
+    db::confirm_reanchor_db(pool, &anchor.to_record_anchor(&candidate.product_id, chain_id, target_contract)).await?;
+    Ok(ReanchorOutcome::Confirmed(anchor.transaction_hash))
+}
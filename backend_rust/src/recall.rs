@@ -0,0 +1,132 @@
+// 召回管理：发起召回时沿批次谱系向下游传播，受影响的产品在详情与列表中显示召回状态
+use serde::{Deserialize, Serialize};
+use sqlx::MySqlConnection;
+use std::collections::BTreeMap;
+use crate::errors::AppError;
+use crate::lineage::{self, LineageDirection};
+use crate::models::{ProductRecall, RecallProduct, RecallRequest};
+
+pub const SOURCE_DIRECT: &str = "direct";
+pub const SOURCE_DOWNSTREAM: &str = "downstream";
+
+const MAX_PRODUCTS: usize = 1000;
+const MAX_REASON_LEN: usize = 4000;
+const MAX_ORGANIZATION_LEN: usize = 255;
+
+// 召回等级，参照 FDA 的三级分类
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RecallSeverity {
+    ClassI,   // 可能导致严重健康后果或死亡
+    ClassII,  // 可能导致暂时性或可逆的健康影响
+    ClassIII, // 不太可能导致健康影响 (如标签不符)
+}
+
+impl RecallSeverity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RecallSeverity::ClassI => "class_i",
+            RecallSeverity::ClassII => "class_ii",
+            RecallSeverity::ClassIII => "class_iii",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum RecallStatus {
+    Open,
+    InProgress,
+    Closed,
+}
+
+impl RecallStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RecallStatus::Open => "open",
+            RecallStatus::InProgress => "in_progress",
+            RecallStatus::Closed => "closed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, AppError> {
+        match value {
+            "open" => Ok(RecallStatus::Open),
+            "in_progress" => Ok(RecallStatus::InProgress),
+            "closed" => Ok(RecallStatus::Closed),
+            _ => Err(AppError::InvalidInput(format!("未知的召回状态 '{}'。", value))),
+        }
+    }
+
+    // 状态只能向前推进
+    pub fn check_transition(self, next: RecallStatus) -> Result<(), AppError> {
+        if next <= self {
+            return Err(AppError::Conflict(format!("召回状态不能从 {} 变更为 {}。", self.as_str(), next.as_str())));
+        }
+        Ok(())
+    }
+}
+
+// 校验请求，返回去重后的直接受影响产品
+pub fn validate_request(request: &RecallRequest) -> Result<Vec<String>, AppError> {
+    let reason = request.reason.trim();
+    if reason.is_empty() || reason.len() > MAX_REASON_LEN {
+        return Err(AppError::InvalidInput(format!("reason 不能为空且不能超过 {} 个字符。", MAX_REASON_LEN)));
+    }
+    let organization = request.initiating_organization.trim();
+    if organization.is_empty() || organization.len() > MAX_ORGANIZATION_LEN {
+        return Err(AppError::InvalidInput(format!("initiatingOrganization 不能为空且不能超过 {} 个字符。", MAX_ORGANIZATION_LEN)));
+    }
+    let mut product_ids = request.product_ids.clone();
+    product_ids.sort();
+    product_ids.dedup();
+    if product_ids.is_empty() || product_ids.len() > MAX_PRODUCTS {
+        return Err(AppError::InvalidInput(format!("productIds 不能为空且不能超过 {} 个。", MAX_PRODUCTS)));
+    }
+    Ok(product_ids)
+}
+
+// 直接受影响的产品及其下游可达的全部批次，同一批次经多条路径到达时取最短的一条；第二个返回值表示遍历达到批次数上限被截断
+// 在发起召回的事务中调用，读到的边加共享锁，与并发建立的谱系边互相排队 (见 db::create_lineage_link_db)
+pub async fn affected_products(conn: &mut MySqlConnection, product_ids: &[String]) -> Result<(Vec<RecallProduct>, bool), AppError> {
+    let mut affected: BTreeMap<String, RecallProduct> = product_ids
+        .iter()
+        .map(|product_id| (product_id.clone(), RecallProduct {
+            product_id: product_id.clone(),
+            source: SOURCE_DIRECT.to_string(),
+            depth: 0,
+            via_product_id: None,
+        }))
+        .collect();
+    let mut truncated = false;
+    for product_id in product_ids {
+        let downstream = lineage::walk_connection(conn, product_id, LineageDirection::Downstream, lineage::MAX_TRANSITIVE_DEPTH).await?;
+        truncated |= downstream.truncated;
+        for node in downstream.nodes {
+            let candidate = RecallProduct {
+                product_id: node.product_id.clone(),
+                source: SOURCE_DOWNSTREAM.to_string(),
+                depth: node.depth,
+                via_product_id: Some(product_id.clone()),
+            };
+            match affected.get(&node.product_id) {
+                Some(existing) if existing.depth <= node.depth => {}
+                _ => {
+                    affected.insert(node.product_id, candidate);
+                }
+            }
+        }
+    }
+    let mut affected: Vec<RecallProduct> = affected.into_values().collect();
+    affected.sort_by_key(|product| product.depth);
+    Ok((affected, truncated))
+}
+
+// 产品最需要关注的召回状态 (open 优先于 in_progress，再到 closed)，从未被召回时为 None
+pub fn overall_status(recalls: &[ProductRecall]) -> Option<String> {
+    recalls
+        .iter()
+        .filter_map(|recall| RecallStatus::parse(&recall.status).ok())
+        .min()
+        .map(|status| status.as_str().to_string())
+}
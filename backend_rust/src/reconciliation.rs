@@ -0,0 +1,167 @@
+// 数据库与链上事件对账
+// 基于索引器写入的 chain_events 与 traceability_data 比较，找出两边的差异并保存为一次对账结果
+// 每次对账针对一条链上的一个合约，只比较标记为该链与合约的记录
+use log::{info, warn};
+use serde_json::json;
+use sqlx::MySqlPool;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use crate::audit::{self, NewAuditEntry};
+use crate::config::env_or;
+use crate::contract;
+use crate::db;
+use crate::errors::AppError;
+use crate::hashing::normalize_hash;
+use crate::merkle;
+use crate::models::{IndexedChainEvent, NewReconciliationFinding, ReconciliationDbRecord, ReconciliationRun};
+
+pub const FINDING_MISSING_ON_CHAIN: &str = "missing_on_chain";
+pub const FINDING_MISSING_IN_DB: &str = "missing_in_db";
+pub const FINDING_HASH_MISMATCH: &str = "hash_mismatch";
+
+pub const TRIGGER_MANUAL: &str = "manual";
+pub const TRIGGER_SCHEDULED: &str = "scheduled";
+
+// 执行一次对账并保存结果
+pub async fn run_reconciliation(
+    pool: &MySqlPool,
+    chain_id: u64,
+    contract_address: &str,
+    trigger_source: &str,
+    actor: &str,
+) -> Result<ReconciliationRun, AppError> {
+    let mut records = db::list_reconciliation_db_records_db(pool, chain_id, contract_address).await?;
+    // Merkle 批量上链的记录没有自己的链上事件，以批次根作为数据库一方参与对账
+    records.extend(db::list_confirmed_merkle_batches_db(pool, chain_id, contract_address).await?.into_iter().filter_map(|batch| {
+        Some(ReconciliationDbRecord {
+            product_id: merkle::anchor_key(batch.id),
+            onchain_metadata_hash: batch.merkle_root?,
+            blockchain_transaction_hash: batch.transaction_hash?,
+            anchor_block: batch.block_number,
+        })
+    }));
+    // 审计链头锚定同样以最近一次已确认的链头哈希参与对账
+    if let Some(anchor) = db::get_latest_confirmed_audit_head_anchor_db(pool, chain_id, contract_address).await? {
+        records.push(ReconciliationDbRecord {
+            product_id: audit::HEAD_ANCHOR_KEY.to_string(),
+            onchain_metadata_hash: anchor.head_hash,
+            blockchain_transaction_hash: anchor.transaction_hash,
+            anchor_block: anchor.block_number,
+        });
+    }
+    // 索引器只索引到 (最新区块 - 确认数)，上链区块还没被索引的记录暂不对账，下次再比较
+    let indexed_through = db::get_indexer_cursor_db(pool, chain_id, contract_address).await?.map(|cursor| cursor.last_block);
+    let before = records.len();
+    records.retain(|record| match (record.anchor_block, indexed_through) {
+        (Some(anchor_block), Some(indexed_through)) => anchor_block <= indexed_through,
+        (Some(_), None) => false,
+        (None, _) => true,
+    });
+    if records.len() < before {
+        info!("链 {} 上有 {} 条记录的上链区块尚未被索引，本次不对账。", chain_id, before - records.len());
+    }
+    let events = db::list_indexed_chain_events_db(pool, chain_id, contract_address).await?;
+
+    // 链上事件按 keccak256(productId) 分组 (查询结果已按区块号、日志序号升序排列)
+    let mut events_by_product: HashMap<&str, Vec<&IndexedChainEvent>> = HashMap::new();
+    for event in &events {
+        events_by_product.entry(event.product_id_hash.as_str()).or_default().push(event);
+    }
+
+    let mut findings: Vec<NewReconciliationFinding> = Vec::new();
+    let mut db_product_hashes: HashSet<String> = HashSet::with_capacity(records.len());
+
+    for record in &records {
+        let product_id_hash = contract::product_id_topic(&record.product_id);
+        let db_hash = normalize_hash(&record.onchain_metadata_hash);
+        let product_events = events_by_product.get(product_id_hash.as_str()).map(Vec::as_slice).unwrap_or(&[]);
+
+        let anchored = product_events.iter().any(|event| event.metadata_hash == db_hash);
+        if !anchored {
+            findings.push(NewReconciliationFinding {
+                finding_type: FINDING_MISSING_ON_CHAIN,
+                product_id: Some(record.product_id.clone()),
+                product_id_hash: product_id_hash.clone(),
+                db_metadata_hash: Some(db_hash.clone()),
+                chain_metadata_hash: None,
+                transaction_hash: Some(record.blockchain_transaction_hash.clone()),
+                block_number: None,
+            });
+        }
+
+        // records[productId] 只保留最后一次 addRecord 的哈希
+        let latest = product_events.iter().max_by_key(|event| (event.block_number, event.log_index));
+        if let Some(latest) = latest {
+            if latest.metadata_hash != db_hash {
+                findings.push(NewReconciliationFinding {
+                    finding_type: FINDING_HASH_MISMATCH,
+                    product_id: Some(record.product_id.clone()),
+                    product_id_hash: product_id_hash.clone(),
+                    db_metadata_hash: Some(db_hash.clone()),
+                    chain_metadata_hash: Some(latest.metadata_hash.clone()),
+                    transaction_hash: Some(latest.transaction_hash.clone()),
+                    block_number: Some(latest.block_number),
+                });
+            }
+        }
+        db_product_hashes.insert(product_id_hash);
+    }
+
+    for event in &events {
+        if !db_product_hashes.contains(&event.product_id_hash) {
+            findings.push(NewReconciliationFinding {
+                finding_type: FINDING_MISSING_IN_DB,
+                product_id: None,
+                product_id_hash: event.product_id_hash.clone(),
+                db_metadata_hash: None,
+                chain_metadata_hash: Some(event.metadata_hash.clone()),
+                transaction_hash: Some(event.transaction_hash.clone()),
+                block_number: Some(event.block_number),
+            });
+        }
+    }
+
+    let mut tx = pool.begin().await?;
+    let run_id = db::create_reconciliation_run_db(&mut tx, chain_id, contract_address, trigger_source).await?;
+    for finding in &findings {
+        db::insert_reconciliation_finding_db(&mut tx, run_id, finding).await?;
+    }
+    db::finish_reconciliation_run_db(&mut tx, run_id, records.len() as u64, events.len() as u64, findings.len() as u64).await?;
+    db::append_audit_log_db(&mut tx, &NewAuditEntry::new(actor, "reconciliation.run", Some(&run_id.to_string()), json!({
+        "runId": run_id,
+        "chainId": chain_id,
+        "contractAddress": contract_address,
+        "triggerSource": trigger_source,
+        "findingCount": findings.len(),
+    }))).await?;
+    tx.commit().await?;
+
+    info!(
+        "对账 #{} 完成 (链 {}，{}): 数据库记录 {} 条，链上事件 {} 条，差异 {} 条",
+        run_id, chain_id, trigger_source, records.len(), events.len(), findings.len()
+    );
+    db::get_reconciliation_run_db(pool, Some(run_id), chain_id)
+        .await?
+        .ok_or_else(|| AppError::InternalError(format!("对账 #{} 已保存但无法读取。", run_id)))
+}
+
+// 按 RECONCILIATION_INTERVAL_SECS 定期对账 (依次对每个 (链 ID, 合约地址))，未设置或为 0 时不启动
+pub fn spawn_scheduled_reconciliation(pool: MySqlPool, targets: Vec<(u64, String)>) {
+    let interval_secs: u64 = env_or("RECONCILIATION_INTERVAL_SECS", 0);
+    if interval_secs == 0 {
+        return;
+    }
+    info!("定期对账已启用，间隔 {} 秒。", interval_secs);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+        interval.tick().await; // 第一次 tick 立即返回，跳过以便索引器先运行
+        loop {
+            interval.tick().await;
+            for (chain_id, contract_address) in &targets {
+                if let Err(e) = run_reconciliation(&pool, *chain_id, contract_address, TRIGGER_SCHEDULED, audit::ACTOR_RECONCILIATION).await {
+                    warn!("定期对账失败 (链 {}): {}", chain_id, e);
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,287 @@
+// 后端托管签名：使用本地配置的 secp256k1 私钥签名 EIP-1559 (type 2) 交易，通过 eth_sendRawTransaction 广播
+// 供没有 MetaMask 的生产者使用，私钥由 SIGNER_PRIVATE_KEY 配置，不依赖节点解锁账户，Hardhat / anvil 与标准节点均可用
+use k256::ecdsa::SigningKey;
+use log::{info, warn};
+use std::collections::HashMap;
+use std::env;
+use tokio::sync::Mutex;
+use crate::contract;
+use crate::errors::AppError;
+use crate::eth_rpc::{parse_quantity, EthClient};
+use crate::hashing::{keccak256, to_hex_string};
+use crate::rlp;
+
+const EIP1559_TX_TYPE: u8 = 0x02;
+const DEFAULT_PRIORITY_FEE_PER_GAS: u64 = 1_500_000_000; // 节点不支持 eth_maxPriorityFeePerGas 时使用 1.5 gwei
+const GAS_LIMIT_BUFFER_PERCENT: u64 = 20; // 在 eth_estimateGas 结果上额外预留的 gas
+
+pub struct LocalSigner {
+    signing_key: SigningKey,
+    address: String,
+    // 每条链下一个可用的 nonce，没有条目表示需要从节点重新同步
+    // 从分配 nonce 到交易广播完成期间一直持有锁，并发请求因此按顺序使用连续的 nonce
+    next_nonces: Mutex<HashMap<u64, u64>>,
+}
+
+// 已广播的交易：超时未打包时按原样重新广播 raw (交易哈希不变)，不会另签一笔新 nonce 的交易
+#[derive(Debug, Clone)]
+pub struct SentTransaction {
+    pub hash: String,
+    pub nonce: u64,
+    pub raw: Vec<u8>,
+}
+
+// 待签名的 EIP-1559 交易 (value 固定为 0，access list 为空)
+struct Eip1559Transaction<'a> {
+    chain_id: u64,
+    nonce: u64,
+    max_priority_fee_per_gas: u64,
+    max_fee_per_gas: u64,
+    gas_limit: u64,
+    to: Option<[u8; 20]>, // 为空表示合约创建交易
+    data: &'a [u8],
+}
+
+impl LocalSigner {
+    pub fn from_private_key(private_key: &str) -> Result<Self, AppError> {
+        let key_bytes = hex::decode(private_key.trim().trim_start_matches("0x"))
+            .map_err(|_| AppError::InvalidInput("签名私钥必须是十六进制字符串。".to_string()))?;
+        let signing_key = SigningKey::from_slice(&key_bytes)
+            .map_err(|_| AppError::InvalidInput("签名私钥不是有效的 secp256k1 私钥。".to_string()))?;
+
+        // 地址 = keccak256(未压缩公钥去掉 0x04 前缀) 的后 20 字节
+        let public_key = signing_key.verifying_key().to_encoded_point(false);
+        let address = to_hex_string(&keccak256(&public_key.as_bytes()[1..])[12..]);
+
+        Ok(LocalSigner {
+            signing_key,
+            address,
+            next_nonces: Mutex::new(HashMap::new()),
+        })
+    }
+
+    // 读取 SIGNER_PRIVATE_KEY，未配置时返回 None (托管签名模式关闭)
+    // 同一账户在所有已配置的链上签名
+    pub fn from_env() -> Result<Option<Self>, AppError> {
+        match env::var("SIGNER_PRIVATE_KEY") {
+            Ok(key) if !key.trim().is_empty() => Ok(Some(Self::from_private_key(&key)?)),
+            _ => Ok(None),
+        }
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    // 签名并广播 addRecord(productId, metadataHash)
+    pub async fn submit_add_record(
+        &self,
+        eth_client: &EthClient,
+        chain_id: u64,
+        contract_address: &str,
+        product_id: &str,
+        metadata_hash: &str,
+    ) -> Result<SentTransaction, AppError> {
+        let data = contract::encode_add_record(product_id, &contract::parse_bytes32(metadata_hash)?);
+        self.sign_and_send(eth_client, chain_id, Some(contract_address), &data).await
+    }
+
+    // 签名并广播调用合约的交易，返回交易哈希
+    pub async fn send_transaction(&self, eth_client: &EthClient, chain_id: u64, to: &str, data: &[u8]) -> Result<String, AppError> {
+        Ok(self.sign_and_send(eth_client, chain_id, Some(to), data).await?.hash)
+    }
+
+    // 签名并广播合约创建交易 (data 为合约字节码及编码后的构造参数)，返回交易哈希
+    pub async fn deploy_contract(&self, eth_client: &EthClient, chain_id: u64, init_code: &[u8]) -> Result<String, AppError> {
+        Ok(self.sign_and_send(eth_client, chain_id, None, init_code).await?.hash)
+    }
+
+    async fn sign_and_send(&self, eth_client: &EthClient, chain_id: u64, to: Option<&str>, data: &[u8]) -> Result<SentTransaction, AppError> {
+        let to_bytes: Option<[u8; 20]> = match to {
+            Some(to) => Some(
+                hex::decode(to.trim_start_matches("0x"))
+                    .ok()
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .ok_or_else(|| AppError::InvalidInput(format!("无效的合约地址: {}", to)))?,
+            ),
+            None => None,
+        };
+
+        let mut next_nonces = self.next_nonces.lock().await;
+        let nonce = match next_nonces.get(&chain_id) {
+            Some(nonce) => *nonce,
+            None => self.sync_nonce(eth_client, chain_id).await?,
+        };
+        next_nonces.insert(chain_id, nonce);
+
+        let estimated_gas = eth_client.estimate_gas(&self.address, to, data).await?;
+        let (max_priority_fee_per_gas, max_fee_per_gas) = self.suggest_fees(eth_client).await?;
+        let tx = Eip1559Transaction {
+            chain_id,
+            nonce,
+            max_priority_fee_per_gas,
+            max_fee_per_gas,
+            gas_limit: estimated_gas + estimated_gas * GAS_LIMIT_BUFFER_PERCENT / 100,
+            to: to_bytes,
+            data,
+        };
+        let raw_tx = self.sign(&tx)?;
+
+        match eth_client.send_raw_transaction(&raw_tx).await {
+            Ok(tx_hash) => {
+                next_nonces.insert(chain_id, nonce + 1);
+                info!("托管账户 {} 已在链 {} 上发送交易 {} (nonce {})", self.address, chain_id, tx_hash, nonce);
+                Ok(SentTransaction { hash: tx_hash, nonce, raw: raw_tx })
+            }
+            Err(e) => {
+                // 广播失败时无法确定节点是否收到交易 (例如 nonce 已被占用或请求超时)，下次发送前重新同步
+                next_nonces.remove(&chain_id);
+                Err(e)
+            }
+        }
+    }
+
+    // 从节点读取包含待打包交易在内的 nonce，同时确认节点的链 ID 与配置一致，避免签出在目标链上无效的交易
+    async fn sync_nonce(&self, eth_client: &EthClient, chain_id: u64) -> Result<u64, AppError> {
+        let node_chain_id = eth_client.chain_id().await?;
+        if node_chain_id != chain_id {
+            return Err(AppError::BlockchainError(format!(
+                "节点链 ID {} 与配置的链 ID {} 不一致。", node_chain_id, chain_id
+            )));
+        }
+        let nonce = eth_client.get_transaction_count(&self.address, "pending").await?;
+        info!("托管账户 {} 在链 {} 上的 nonce 已同步为 {}", self.address, chain_id, nonce);
+        Ok(nonce)
+    }
+
+    // 返回 (maxPriorityFeePerGas, maxFeePerGas)，maxFeePerGas 按两倍当前基础费预留，可承受连续几个区块的基础费上涨
+    async fn suggest_fees(&self, eth_client: &EthClient) -> Result<(u64, u64), AppError> {
+        let latest = eth_client.latest_block().await?;
+        let base_fee = latest
+            .base_fee_per_gas
+            .as_deref()
+            .map(parse_quantity)
+            .transpose()?
+            .ok_or_else(|| AppError::BlockchainError("节点未返回 baseFeePerGas，不支持 EIP-1559 交易。".to_string()))?;
+        let priority_fee = match eth_client.max_priority_fee_per_gas().await {
+            Ok(fee) => fee,
+            Err(e) => {
+                warn!("获取 maxPriorityFeePerGas 失败，使用默认值 {} wei: {}", DEFAULT_PRIORITY_FEE_PER_GAS, e);
+                DEFAULT_PRIORITY_FEE_PER_GAS
+            }
+        };
+        Ok((priority_fee, base_fee.saturating_mul(2).saturating_add(priority_fee)))
+    }
+
+    // 0x02 || rlp([chainId, nonce, maxPriorityFeePerGas, maxFeePerGas, gasLimit, to, value, data, accessList, yParity, r, s])
+    fn sign(&self, tx: &Eip1559Transaction) -> Result<Vec<u8>, AppError> {
+        let mut fields = vec![
+            rlp::encode_uint(tx.chain_id),
+            rlp::encode_uint(tx.nonce),
+            rlp::encode_uint(tx.max_priority_fee_per_gas),
+            rlp::encode_uint(tx.max_fee_per_gas),
+            rlp::encode_uint(tx.gas_limit),
+            rlp::encode_bytes(tx.to.as_ref().map(|to| to.as_slice()).unwrap_or_default()),
+            rlp::encode_uint(0),
+            rlp::encode_bytes(tx.data),
+            rlp::encode_list(&[]),
+        ];
+        let signing_hash = keccak256(&typed_payload(&fields));
+        let (signature, recovery_id) = self
+            .signing_key
+            .sign_prehash_recoverable(&signing_hash)
+            .map_err(|e| AppError::InternalError(format!("交易签名失败: {}", e)))?;
+
+        let signature_bytes = signature.to_bytes();
+        fields.push(rlp::encode_uint(recovery_id.to_byte() as u64));
+        fields.push(rlp::encode_bytes(rlp::trim_leading_zeros(&signature_bytes[..32])));
+        fields.push(rlp::encode_bytes(rlp::trim_leading_zeros(&signature_bytes[32..])));
+        Ok(typed_payload(&fields))
+    }
+}
+
+fn typed_payload(fields: &[Vec<u8>]) -> Vec<u8> {
+    let mut payload = vec![EIP1559_TX_TYPE];
+    payload.extend_from_slice(&rlp::encode_list(fields));
+    payload
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eip712;
+
+    // Hardhat 默认账户 #0
+    const HARDHAT_PRIVATE_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const HARDHAT_ADDRESS: &str = "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266";
+    const HARDHAT_FIRST_CONTRACT: &str = "5fbdb2315678afecb367f032d93f642f64180aa3";
+
+    // 链 1337 上 nonce 0 的 addRecord("apple-001", keccak256("apple-001"))，
+    // 期望值由独立实现 (Python cryptography 的 RFC 6979 确定性签名 + 低 s 规范化) 计算，签名者即 HARDHAT_ADDRESS
+    const SIGNED_ADD_RECORD: &str = "02f8f2820539808459682f0084b2d05e00830186a0945fbdb2315678afecb367f032d93f642f64180aa380b884308631e1\
+        00000000000000000000000000000000000000000000000000000000000000407626c88d11cf45375af23314a64fc29925196a7a46d336d2b6541ec7ccaa9639\
+        00000000000000000000000000000000000000000000000000000000000000096170706c652d3030310000000000000000000000000000000000000000000000\
+        c001a0588389615915910d7b40e6e6ed05317e1b84469de628b1c0f8c98697af5a653ba031b86144434c21e8e7f29909fed7a3864f4496871740ff9a7fb1eef645b68d02";
+
+    fn add_record_transaction(data: &[u8]) -> Eip1559Transaction<'_> {
+        Eip1559Transaction {
+            chain_id: 1337,
+            nonce: 0,
+            max_priority_fee_per_gas: 1_500_000_000,
+            max_fee_per_gas: 3_000_000_000,
+            gas_limit: 100_000,
+            to: Some(hex::decode(HARDHAT_FIRST_CONTRACT).unwrap().try_into().unwrap()),
+            data,
+        }
+    }
+
+    #[test]
+    fn derives_address_from_private_key() {
+        let signer = LocalSigner::from_private_key(HARDHAT_PRIVATE_KEY).unwrap();
+        assert_eq!(signer.address(), HARDHAT_ADDRESS);
+    }
+
+    #[test]
+    fn signs_eip1559_add_record_transaction() {
+        let signer = LocalSigner::from_private_key(HARDHAT_PRIVATE_KEY).unwrap();
+        let data = contract::encode_add_record("apple-001", &keccak256(b"apple-001"));
+        let raw_tx = signer.sign(&add_record_transaction(&data)).unwrap();
+        assert_eq!(hex::encode(&raw_tx), SIGNED_ADD_RECORD);
+        assert_eq!(
+            to_hex_string(&keccak256(&raw_tx)),
+            "0x5628861c548649bec3239937797acacc50cb872fac6132c11cb3432dd9c2d1c1"
+        );
+    }
+
+    // 解码已签名交易，从签名恢复出的地址必须是签名账户
+    #[test]
+    fn signature_recovers_to_signer_address() {
+        let signer = LocalSigner::from_private_key(HARDHAT_PRIVATE_KEY).unwrap();
+        let data = contract::encode_add_record("apple-002", &keccak256(b"apple-002"));
+        let raw_tx = signer.sign(&add_record_transaction(&data)).unwrap();
+        assert_eq!(raw_tx[0], EIP1559_TX_TYPE);
+
+        let decoded = rlp::decode(&raw_tx[1..]).unwrap();
+        let fields = decoded.as_list().unwrap();
+        assert_eq!(fields.len(), 12);
+        let unsigned: Vec<Vec<u8>> = fields[..9]
+            .iter()
+            .map(|field| match field {
+                rlp::RlpItem::Bytes(bytes) => rlp::encode_bytes(bytes),
+                rlp::RlpItem::List(items) => {
+                    assert!(items.is_empty());
+                    rlp::encode_list(&[])
+                }
+            })
+            .collect();
+        let signing_hash = keccak256(&typed_payload(&unsigned));
+
+        let mut signature = [0u8; 65];
+        let (r, s) = (fields[10].as_bytes().unwrap(), fields[11].as_bytes().unwrap());
+        signature[32 - r.len()..32].copy_from_slice(r);
+        signature[64 - s.len()..64].copy_from_slice(s);
+        signature[64] = fields[9].as_bytes().unwrap().first().copied().unwrap_or(0);
+        let recovered = eip712::recover_signer(&signing_hash, &to_hex_string(&signature)).unwrap();
+        assert_eq!(recovered, HARDHAT_ADDRESS);
+    }
+}
@@ -0,0 +1,244 @@
+// Sign-In With Ethereum (EIP-4361) 消息解析与校验
+// 钱包对如下格式的文本做 personal_sign (EIP-191)，后端恢复签名者并逐项校验消息字段：
+//   {domain} wants you to sign in with your Ethereum account:
+//   {address}
+//
+//   {statement}            (可选)
+//
+//   URI: {uri}
+//   Version: 1
+//   Chain ID: {chain_id}
+//   Nonce: {nonce}
+//   Issued At: {RFC 3339}
+//   Expiration Time / Not Before / Request ID / Resources (可选)
+use chrono::{DateTime, Duration, Utc};
+use crate::eip712;
+use crate::errors::AppError;
+use crate::hashing::keccak256;
+
+const HEADER_SUFFIX: &str = " wants you to sign in with your Ethereum account:";
+const MAX_CLOCK_SKEW_SECS: i64 = 300; // 允许 Issued At 比服务器时间超前的秒数
+
+#[derive(Debug, Clone)]
+pub struct SiweMessage {
+    pub domain: String,
+    pub address: String, // 小写
+    pub uri: String,
+    pub version: String,
+    pub chain_id: u64,
+    pub nonce: String,
+    pub issued_at: DateTime<Utc>,
+    pub expiration_time: Option<DateTime<Utc>>,
+    pub not_before: Option<DateTime<Utc>>,
+}
+
+fn invalid(reason: impl Into<String>) -> AppError {
+    AppError::InvalidInput(format!("无效的 SIWE 消息: {}", reason.into()))
+}
+
+fn parse_time(field: &str, value: &str) -> Result<DateTime<Utc>, AppError> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|_| invalid(format!("{} 不是 RFC 3339 时间: {}", field, value)))
+}
+
+impl SiweMessage {
+    pub fn parse(message: &str) -> Result<Self, AppError> {
+        let lines: Vec<&str> = message.split('\n').map(|line| line.trim_end_matches('\r')).collect();
+        let mut lines = lines.into_iter().peekable();
+
+        let header = lines.next().unwrap_or_default();
+        let domain = header
+            .strip_suffix(HEADER_SUFFIX)
+            .filter(|domain| !domain.is_empty())
+            .ok_or_else(|| invalid("缺少首行 \"<domain> wants you to sign in with your Ethereum account:\""))?;
+        // 新版规范允许 domain 带 scheme，只比较主机部分
+        let domain = domain.split_once("://").map(|(_, host)| host).unwrap_or(domain).to_string();
+
+        let address = lines.next().unwrap_or_default().trim().to_lowercase();
+        if !address.starts_with("0x") || address.len() != 42 || hex::decode(&address[2..]).is_err() {
+            return Err(invalid(format!("地址格式错误: {}", address)));
+        }
+        if lines.next() != Some("") {
+            return Err(invalid("地址之后应为空行"));
+        }
+        // 没有 statement 时地址之后是两个空行；statement 只用于向用户展示，不参与校验
+        if lines.next_if_eq(&"").is_none()
+            && lines.next_if(|line| !line.starts_with("URI: ")).is_some()
+            && lines.next() != Some("")
+        {
+            return Err(invalid("statement 之后应为空行"));
+        }
+
+        let (mut uri, mut version, mut chain_id, mut nonce, mut issued_at) = (None, None, None, None, None);
+        let (mut expiration_time, mut not_before) = (None, None);
+        while let Some(line) = lines.next() {
+            // Resources 列表与 Request ID 不参与校验
+            if line == "Resources:" {
+                while lines.next_if(|line| line.starts_with("- ")).is_some() {}
+                continue;
+            }
+            let (key, value) = line.split_once(": ").ok_or_else(|| invalid(format!("无法解析的行: {}", line)))?;
+            match key {
+                "URI" => uri = Some(value.to_string()),
+                "Version" => version = Some(value.to_string()),
+                "Chain ID" => chain_id = Some(value.parse::<u64>().map_err(|_| invalid(format!("Chain ID 无效: {}", value)))?),
+                "Nonce" => nonce = Some(value.to_string()),
+                "Issued At" => issued_at = Some(parse_time(key, value)?),
+                "Expiration Time" => expiration_time = Some(parse_time(key, value)?),
+                "Not Before" => not_before = Some(parse_time(key, value)?),
+                "Request ID" => {}
+                _ => return Err(invalid(format!("未知字段 {}", key))),
+            }
+        }
+
+        Ok(SiweMessage {
+            domain,
+            address,
+            uri: uri.ok_or_else(|| invalid("缺少 URI"))?,
+            version: version.ok_or_else(|| invalid("缺少 Version"))?,
+            chain_id: chain_id.ok_or_else(|| invalid("缺少 Chain ID"))?,
+            nonce: nonce.ok_or_else(|| invalid("缺少 Nonce"))?,
+            issued_at: issued_at.ok_or_else(|| invalid("缺少 Issued At"))?,
+            expiration_time,
+            not_before,
+        })
+    }
+
+    // 校验版本、域名与时间窗口 (nonce 与链 ID 由调用方对照数据库和链配置校验)
+    pub fn validate(&self, allowed_domains: &[String], now: DateTime<Utc>) -> Result<(), AppError> {
+        if self.version != "1" {
+            return Err(invalid(format!("不支持的版本 {}", self.version)));
+        }
+        if !allowed_domains.iter().any(|domain| domain.eq_ignore_ascii_case(&self.domain)) {
+            return Err(AppError::Unauthorized(format!("SIWE 消息的域名 {} 不是本服务的域名。", self.domain)));
+        }
+        if self.issued_at > now + Duration::seconds(MAX_CLOCK_SKEW_SECS) {
+            return Err(AppError::Unauthorized("SIWE 消息的签发时间晚于服务器时间。".to_string()));
+        }
+        if self.expiration_time.is_some_and(|expiration| expiration <= now) {
+            return Err(AppError::Unauthorized("SIWE 消息已过期。".to_string()));
+        }
+        if self.not_before.is_some_and(|not_before| not_before > now) {
+            return Err(AppError::Unauthorized("SIWE 消息尚未生效 (Not Before)。".to_string()));
+        }
+        Ok(())
+    }
+}
+
+// EIP-191 personal_sign 摘要：keccak256("\x19Ethereum Signed Message:\n" || len || message)
+pub fn personal_message_digest(message: &str) -> [u8; 32] {
+    let mut data = format!("\x19Ethereum Signed Message:\n{}", message.len()).into_bytes();
+    data.extend_from_slice(message.as_bytes());
+    keccak256(&data)
+}
+
+// 恢复签名者并确认与消息中的地址一致
+pub fn verify_signature(message: &SiweMessage, raw_message: &str, signature: &str) -> Result<(), AppError> {
+    let signer = eip712::recover_signer(&personal_message_digest(raw_message), signature)?;
+    if signer != message.address {
+        return Err(AppError::Unauthorized(format!("签名者 {} 与 SIWE 消息中的地址 {} 不一致。", signer, message.address)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::ecdsa::SigningKey;
+
+    // Hardhat 默认账户 #0
+    const PRIVATE_KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const ADDRESS: &str = "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266";
+
+    fn message(statement: Option<&str>, extra: &str) -> String {
+        let statement = statement.map(|statement| format!("{}\n", statement)).unwrap_or_default();
+        format!(
+            "example.com wants you to sign in with your Ethereum account:\n0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266\n\n{}\n\
+             URI: https://example.com/login\nVersion: 1\nChain ID: 1337\nNonce: 32891756\nIssued At: 2026-10-01T08:00:00Z{}",
+            statement, extra
+        )
+    }
+
+    fn now() -> DateTime<Utc> {
+        parse_time("now", "2026-10-01T08:05:00Z").unwrap()
+    }
+
+    fn personal_sign(message: &str) -> String {
+        let signing_key = SigningKey::from_slice(&hex::decode(PRIVATE_KEY).unwrap()).unwrap();
+        let (signature, recovery_id) = signing_key.sign_prehash_recoverable(&personal_message_digest(message)).unwrap();
+        let mut bytes = signature.to_bytes().to_vec();
+        bytes.push(27 + recovery_id.to_byte());
+        format!("0x{}", hex::encode(bytes))
+    }
+
+    #[test]
+    fn parses_message_with_statement() {
+        let raw = message(Some("Sign in to the food traceability console."), "");
+        let parsed = SiweMessage::parse(&raw).unwrap();
+        assert_eq!(parsed.domain, "example.com");
+        assert_eq!(parsed.address, ADDRESS);
+        assert_eq!(parsed.uri, "https://example.com/login");
+        assert_eq!(parsed.chain_id, 1337);
+        assert_eq!(parsed.nonce, "32891756");
+        parsed.validate(&["example.com".to_string()], now()).unwrap();
+    }
+
+    // EIP-4361: address LF LF LF "URI: "
+    #[test]
+    fn parses_message_without_statement() {
+        let raw = message(None, "");
+        assert!(raw.contains("92266\n\n\nURI: "));
+        let parsed = SiweMessage::parse(&raw).unwrap();
+        assert_eq!(parsed.address, ADDRESS);
+        assert_eq!(parsed.nonce, "32891756");
+    }
+
+    #[test]
+    fn skips_resources_and_request_id() {
+        let raw = message(
+            Some("Sign in."),
+            "\nExpiration Time: 2026-10-01T09:00:00Z\nRequest ID: 42\nResources:\n- ipfs://bafybeiemxf5abjwjbikoz4mc3a3dla6ual3jsgpdr4cjr3oz3evfyavhwq/\n- https://example.com/terms",
+        );
+        let parsed = SiweMessage::parse(&raw).unwrap();
+        assert_eq!(parsed.expiration_time, Some(parse_time("Expiration Time", "2026-10-01T09:00:00Z").unwrap()));
+        parsed.validate(&["example.com".to_string()], now()).unwrap();
+    }
+
+    #[test]
+    fn rejects_expired_message() {
+        let parsed = SiweMessage::parse(&message(None, "\nExpiration Time: 2026-10-01T08:01:00Z")).unwrap();
+        assert!(matches!(parsed.validate(&["example.com".to_string()], now()), Err(AppError::Unauthorized(_))));
+    }
+
+    #[test]
+    fn rejects_other_domain() {
+        let parsed = SiweMessage::parse(&message(None, "")).unwrap();
+        assert!(matches!(parsed.validate(&["food.example.org".to_string()], now()), Err(AppError::Unauthorized(_))));
+    }
+
+    #[test]
+    fn rejects_statement_without_blank_line() {
+        let raw = message(Some("Sign in."), "").replace("Sign in.\n\n", "Sign in.\n");
+        assert!(matches!(SiweMessage::parse(&raw), Err(AppError::InvalidInput(_))));
+    }
+
+    // web3.js 文档中 accounts.sign("Some data", "0x4c0883a6...") 的签名
+    #[test]
+    fn recovers_known_personal_sign_vector() {
+        let digest = personal_message_digest("Some data");
+        assert_eq!(hex::encode(digest), "1da44b586eb0729ff70a73c326926f6ed5a25f5b056e7f47fbc6e58d86871655");
+        let signature = "0xb91467e570a6466aa9e9876cbcd013baba02900b8979d43fe208a4a4f339f5fd\
+                         6007e74cd82e037b800186422fc2da167c747ef045e5d18a5f5d4300f8e1a0291c";
+        assert_eq!(eip712::recover_signer(&digest, signature).unwrap(), "0x2c7536e3605d9c16a7a3d7b1898e529396a65c23");
+    }
+
+    #[test]
+    fn verifies_signature_against_message_address() {
+        let raw = message(None, "");
+        let parsed = SiweMessage::parse(&raw).unwrap();
+        verify_signature(&parsed, &raw, &personal_sign(&raw)).unwrap();
+        let tampered = raw.replace("Nonce: 32891756", "Nonce: 32891757");
+        assert!(matches!(verify_signature(&parsed, &tampered, &personal_sign(&raw)), Err(AppError::Unauthorized(_))));
+    }
+}
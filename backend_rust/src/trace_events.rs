@@ -0,0 +1,414 @@
+// 多环节供应链事件：采收 → 加工 → 包装 → 仓储 → 运输 → 零售
+// 每条事件记录执行者、地点、发生时间与该环节特有的结构化数据 (StagePayload)，并有自己的事件哈希；
+// 事件哈希覆盖同一产品上一条事件的哈希，时间线接口逐条复核，数据库中被改动、删除或插入的事件会被标记出来
+use chrono::{Duration, NaiveDate, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+use crate::audit;
+use crate::canonical_json;
+use crate::errors::AppError;
+use crate::hashing::hash_canonical_json;
+use crate::models::{NewTraceEvent, TraceBrokenLink, TraceEventRequest, TraceEventResponse, TraceEventRow, TraceTimelineResponse};
+use crate::validation::{check_text, MAX_TEXT_LEN};
+
+pub const GENESIS_HASH: &str = audit::GENESIS_HASH;
+
+// 允许提交者与服务器之间的时钟误差
+const MAX_CLOCK_SKEW_SECS: i64 = 300;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TraceStage {
+    Harvest,
+    Processing,
+    Packaging,
+    Storage,
+    Shipping,
+    Retail,
+}
+
+impl TraceStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TraceStage::Harvest => "harvest",
+            TraceStage::Processing => "processing",
+            TraceStage::Packaging => "packaging",
+            TraceStage::Storage => "storage",
+            TraceStage::Shipping => "shipping",
+            TraceStage::Retail => "retail",
+        }
+    }
+}
+
+// 各环节的结构化数据，请求体中的 stage 决定 payload 的字段
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "stage", content = "payload", rename_all = "lowercase")]
+pub enum StagePayload {
+    Harvest(HarvestPayload),
+    Processing(ProcessingPayload),
+    Packaging(PackagingPayload),
+    Storage(StoragePayload),
+    Shipping(ShippingPayload),
+    Retail(RetailPayload),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct HarvestPayload {
+    pub plot: Option<String>,    // 地块编号
+    pub variety: Option<String>, // 品种
+    pub quantity: Option<f64>,
+    pub unit: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ProcessingPayload {
+    pub process: String, // 工艺，例如 washing、pasteurization
+    pub facility: Option<String>,
+    pub input_quantity: Option<f64>,
+    pub output_quantity: Option<f64>,
+    pub unit: Option<String>,
+    pub temperature_c: Option<f64>,
+    pub duration_minutes: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PackagingPayload {
+    pub package_type: String,
+    pub package_count: Option<u32>,
+    pub lot_number: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct StoragePayload {
+    pub facility: Option<String>,
+    pub temperature_c: Option<f64>,
+    pub humidity_percent: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ShippingPayload {
+    pub carrier: String,
+    pub vehicle_id: Option<String>,
+    pub destination: Option<String>,
+    pub temperature_c: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RetailPayload {
+    pub store: String,
+    pub sell_by: Option<NaiveDate>,
+}
+
+impl StagePayload {
+    pub fn stage(&self) -> TraceStage {
+        match self {
+            StagePayload::Harvest(_) => TraceStage::Harvest,
+            StagePayload::Processing(_) => TraceStage::Processing,
+            StagePayload::Packaging(_) => TraceStage::Packaging,
+            StagePayload::Storage(_) => TraceStage::Storage,
+            StagePayload::Shipping(_) => TraceStage::Shipping,
+            StagePayload::Retail(_) => TraceStage::Retail,
+        }
+    }
+
+    // 由存储的 stage 与 payload 列还原
+    pub fn from_stored(stage: &str, payload: &JsonValue) -> Result<Self, AppError> {
+        serde_json::from_value(json!({ "stage": stage, "payload": payload }))
+            .map_err(|e| AppError::InternalError(format!("{} 事件的存储数据不符合该环节的结构: {}", stage, e)))
+    }
+
+    fn validate(&self) -> Result<(), AppError> {
+        match self {
+            StagePayload::Harvest(p) => {
+                check_quantity("quantity", p.quantity)?;
+                check_texts(&[("plot", &p.plot), ("variety", &p.variety), ("unit", &p.unit)])
+            }
+            StagePayload::Processing(p) => {
+                check_text("process", &p.process, MAX_TEXT_LEN)?;
+                check_quantity("inputQuantity", p.input_quantity)?;
+                check_quantity("outputQuantity", p.output_quantity)?;
+                check_texts(&[("facility", &p.facility), ("unit", &p.unit)])
+            }
+            StagePayload::Packaging(p) => {
+                check_text("packageType", &p.package_type, MAX_TEXT_LEN)?;
+                check_texts(&[("lotNumber", &p.lot_number)])
+            }
+            StagePayload::Storage(p) => {
+                if let Some(humidity) = p.humidity_percent {
+                    if !(0.0..=100.0).contains(&humidity) {
+                        return Err(AppError::InvalidInput("humidityPercent 必须在 0 到 100 之间。".to_string()));
+                    }
+                }
+                check_texts(&[("facility", &p.facility)])
+            }
+            StagePayload::Shipping(p) => {
+                check_text("carrier", &p.carrier, MAX_TEXT_LEN)?;
+                check_texts(&[("vehicleId", &p.vehicle_id), ("destination", &p.destination)])
+            }
+            StagePayload::Retail(p) => check_text("store", &p.store, MAX_TEXT_LEN),
+        }
+    }
+
+    // payload 列的内容 (不含 stage)
+    fn payload_value(&self) -> Result<JsonValue, AppError> {
+        let mut tagged = serde_json::to_value(self)?;
+        Ok(tagged.get_mut("payload").map(JsonValue::take).unwrap_or(JsonValue::Null))
+    }
+}
+
+fn check_texts(values: &[(&str, &Option<String>)]) -> Result<(), AppError> {
+    for (name, value) in values {
+        if let Some(value) = value {
+            check_text(name, value, MAX_TEXT_LEN)?;
+        }
+    }
+    Ok(())
+}
+
+fn check_quantity(name: &str, value: Option<f64>) -> Result<(), AppError> {
+    match value {
+        Some(value) if value < 0.0 => Err(AppError::InvalidInput(format!("{} 不能为负数。", name))),
+        _ => Ok(()),
+    }
+}
+
+// 校验请求并转换为待写入的事件
+pub fn new_event(request: TraceEventRequest) -> Result<NewTraceEvent, AppError> {
+    check_text("actor", &request.actor, MAX_TEXT_LEN)?;
+    check_text("location", &request.location, MAX_TEXT_LEN)?;
+    if request.occurred_at > Utc::now() + Duration::seconds(MAX_CLOCK_SKEW_SECS) {
+        return Err(AppError::InvalidInput("occurredAt 不能晚于当前时间。".to_string()));
+    }
+    request.details.validate()?;
+    Ok(NewTraceEvent {
+        stage: request.details.stage(),
+        actor: request.actor.trim().to_string(),
+        location: request.location.trim().to_string(),
+        occurred_at: audit::truncate_to_millis(request.occurred_at),
+        payload: request.details.payload_value()?,
+    })
+}
+
+// event_hash = keccak256(JCS{productId, sequence, stage, actor, location, occurredAt, payload, prevEventHash})
+pub fn event_hash(product_id: &str, sequence: u32, event: &NewTraceEvent, prev_event_hash: &str) -> Result<String, AppError> {
+    let content = json!({
+        "productId": product_id,
+        "sequence": sequence,
+        "stage": event.stage.as_str(),
+        "actor": event.actor,
+        "location": event.location,
+        "occurredAt": event.occurred_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+        "payload": event.payload,
+        "prevEventHash": prev_event_hash,
+    });
+    Ok(hash_canonical_json(&canonical_json::canonicalize(&content)?))
+}
+
+// 检查单条事件，返回第一处不一致的原因
+fn check_event(event: &TraceEventRow, expected_sequence: u32, expected_prev_hash: &str) -> Result<Option<String>, AppError> {
+    if event.sequence != expected_sequence {
+        return Ok(Some(format!("事件编号不连续：期望 #{}，实际 #{} (中间的事件被删除或插入)。", expected_sequence, event.sequence)));
+    }
+    if event.prev_event_hash != expected_prev_hash {
+        return Ok(Some(format!("prev_event_hash {} 与上一条事件的哈希 {} 不一致。", event.prev_event_hash, expected_prev_hash)));
+    }
+    let details = match StagePayload::from_stored(&event.stage, &event.payload.0) {
+        Ok(details) => details,
+        Err(e) => return Ok(Some(e.to_string())),
+    };
+    let stored = NewTraceEvent {
+        stage: details.stage(),
+        actor: event.actor.clone(),
+        location: event.location.clone(),
+        occurred_at: event.occurred_at,
+        payload: event.payload.0.clone(),
+    };
+    let recomputed = event_hash(&event.product_id, event.sequence, &stored, &event.prev_event_hash)?;
+    if recomputed != event.event_hash {
+        return Ok(Some(format!("重新计算的事件哈希 {} 与存储的 {} 不一致 (事件内容被改动)。", recomputed, event.event_hash)));
+    }
+    Ok(None)
+}
+
+pub fn event_response(event: TraceEventRow, hash_valid: bool) -> TraceEventResponse {
+    TraceEventResponse {
+        id: event.id,
+        sequence: event.sequence,
+        stage: event.stage,
+        actor: event.actor,
+        location: event.location,
+        occurred_at: event.occurred_at,
+        payload: event.payload.0,
+        prev_event_hash: event.prev_event_hash,
+        event_hash: event.event_hash,
+        hash_valid,
+        recorded_by: event.recorded_by,
+        created_at: event.created_at,
+    }
+}
+
+// events 按 sequence 升序；逐条复核哈希链后按发生时间排列成时间线
+pub fn build_timeline(product_id: &str, events: Vec<TraceEventRow>) -> Result<TraceTimelineResponse, AppError> {
+    let mut prev_hash = GENESIS_HASH.to_string();
+    let mut first_broken = None;
+    let mut timeline = Vec::with_capacity(events.len());
+    for (index, event) in events.into_iter().enumerate() {
+        let hash_valid = match check_event(&event, index as u32 + 1, &prev_hash)? {
+            None => true,
+            Some(reason) => {
+                if first_broken.is_none() {
+                    first_broken = Some(TraceBrokenLink { sequence: event.sequence, reason });
+                }
+                false
+            }
+        };
+        prev_hash = event.event_hash.clone();
+        timeline.push(event_response(event, hash_valid));
+    }
+    let head_event_hash = timeline.last().map(|event| event.event_hash.clone());
+    // 发生时间相同的事件按追加顺序排列
+    timeline.sort_by(|a, b| a.occurred_at.cmp(&b.occurred_at).then(a.sequence.cmp(&b.sequence)));
+    Ok(TraceTimelineResponse {
+        product_id: product_id.to_string(),
+        event_count: timeline.len(),
+        valid: first_broken.is_none(),
+        head_event_hash,
+        first_broken,
+        events: timeline,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, TimeZone};
+
+    const PRODUCT_ID: &str = "apple-001";
+
+    fn at(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 9, 1, hour, 0, 0).unwrap()
+    }
+
+    fn row(sequence: u32, details: &StagePayload, occurred_at: DateTime<Utc>, prev_event_hash: &str) -> TraceEventRow {
+        let event = NewTraceEvent {
+            stage: details.stage(),
+            actor: "Orchard Co.".to_string(),
+            location: "Yantai".to_string(),
+            occurred_at,
+            payload: details.payload_value().unwrap(),
+        };
+        let event_hash = event_hash(PRODUCT_ID, sequence, &event, prev_event_hash).unwrap();
+        TraceEventRow {
+            id: sequence as u64,
+            product_id: PRODUCT_ID.to_string(),
+            sequence,
+            stage: event.stage.as_str().to_string(),
+            actor: event.actor,
+            location: event.location,
+            occurred_at,
+            payload: sqlx::types::Json(event.payload),
+            prev_event_hash: prev_event_hash.to_string(),
+            event_hash,
+            recorded_by: "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266".to_string(),
+            created_at: occurred_at,
+        }
+    }
+
+    // 按给定的发生时间依次追加 harvest → storage → shipping
+    fn chain(hours: [u32; 3]) -> Vec<TraceEventRow> {
+        let details = [
+            StagePayload::Harvest(HarvestPayload { plot: Some("A-12".to_string()), variety: None, quantity: Some(120.0), unit: Some("kg".to_string()) }),
+            StagePayload::Storage(StoragePayload { facility: Some("Cold room 3".to_string()), temperature_c: Some(2.5), humidity_percent: Some(90.0) }),
+            StagePayload::Shipping(ShippingPayload { carrier: "FastFreight".to_string(), vehicle_id: None, destination: None, temperature_c: None }),
+        ];
+        let mut prev_hash = GENESIS_HASH.to_string();
+        let mut rows = Vec::new();
+        for (index, (details, hour)) in details.iter().zip(hours).enumerate() {
+            let event = row(index as u32 + 1, details, at(hour), &prev_hash);
+            prev_hash = event.event_hash.clone();
+            rows.push(event);
+        }
+        rows
+    }
+
+    fn hash_valid(timeline: &TraceTimelineResponse) -> Vec<(u32, bool)> {
+        let mut flags: Vec<(u32, bool)> = timeline.events.iter().map(|event| (event.sequence, event.hash_valid)).collect();
+        flags.sort();
+        flags
+    }
+
+    #[test]
+    fn intact_chain_is_valid() {
+        let events = chain([6, 8, 10]);
+        let head = events[2].event_hash.clone();
+        let timeline = build_timeline(PRODUCT_ID, events).unwrap();
+        assert!(timeline.valid);
+        assert!(timeline.first_broken.is_none());
+        assert_eq!(timeline.head_event_hash, Some(head));
+        assert_eq!(hash_valid(&timeline), vec![(1, true), (2, true), (3, true)]);
+    }
+
+    #[test]
+    fn changed_payload_is_flagged() {
+        let mut events = chain([6, 8, 10]);
+        events[1].payload.0["temperatureC"] = json!(8.0);
+        let timeline = build_timeline(PRODUCT_ID, events).unwrap();
+        assert!(!timeline.valid);
+        let broken = timeline.first_broken.as_ref().unwrap();
+        assert_eq!(broken.sequence, 2);
+        assert!(broken.reason.contains("事件内容被改动"), "{}", broken.reason);
+        // 后一条事件仍指向存储的哈希，自身没有被改动
+        assert_eq!(hash_valid(&timeline), vec![(1, true), (2, false), (3, true)]);
+    }
+
+    #[test]
+    fn deleted_event_is_first_broken_link() {
+        let mut events = chain([6, 8, 10]);
+        events.remove(1);
+        let timeline = build_timeline(PRODUCT_ID, events).unwrap();
+        let broken = timeline.first_broken.unwrap();
+        assert_eq!(broken.sequence, 3);
+        assert!(broken.reason.contains("期望 #2，实际 #3"), "{}", broken.reason);
+    }
+
+    #[test]
+    fn inserted_event_is_first_broken_link() {
+        let mut events = chain([6, 8, 10]);
+        // 插入一条哈希自洽的 #2，原来的 #2 及之后的事件不再连续
+        let forged = StagePayload::Retail(RetailPayload { store: "Unknown".to_string(), sell_by: None });
+        let forged = row(2, &forged, at(7), &events[0].event_hash);
+        events.insert(1, forged);
+        let timeline = build_timeline(PRODUCT_ID, events).unwrap();
+        assert!(!timeline.valid);
+        let broken = timeline.first_broken.unwrap();
+        assert_eq!(broken.sequence, 2);
+        assert!(broken.reason.contains("期望 #3，实际 #2"), "{}", broken.reason);
+    }
+
+    #[test]
+    fn timeline_is_ordered_by_occurred_at_then_sequence() {
+        // 事件可以补录：#1 发生得最晚，#2 与 #3 同时发生
+        let timeline = build_timeline(PRODUCT_ID, chain([12, 9, 9])).unwrap();
+        assert!(timeline.valid);
+        let order: Vec<u32> = timeline.events.iter().map(|event| event.sequence).collect();
+        assert_eq!(order, vec![2, 3, 1]);
+    }
+
+    #[test]
+    fn from_stored_rejects_unknown_payload_fields() {
+        let payload = json!({ "facility": "Cold room 3", "note": "added later" });
+        assert!(matches!(StagePayload::from_stored("storage", &payload), Err(AppError::InternalError(_))));
+        assert!(StagePayload::from_stored("storage", &json!({ "facility": "Cold room 3" })).is_ok());
+
+        let mut events = chain([6, 8, 10]);
+        events[1].payload = sqlx::types::Json(payload);
+        let broken = build_timeline(PRODUCT_ID, events).unwrap().first_broken.unwrap();
+        assert_eq!(broken.sequence, 2);
+    }
+}
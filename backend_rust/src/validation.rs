@@ -0,0 +1,13 @@
+// 请求字段的通用校验，错误信息使用请求中的字段名
+use crate::errors::AppError;
+
+// 文本字段默认的长度上限，对应 VARCHAR(255) 列
+pub const MAX_TEXT_LEN: usize = 255;
+
+// 必填文本：去除首尾空白后不能为空，且不能超过 max_len 字节
+pub fn check_text(name: &str, value: &str, max_len: usize) -> Result<(), AppError> {
+    if value.trim().is_empty() || value.len() > max_len {
+        return Err(AppError::InvalidInput(format!("{} 不能为空且不能超过 {} 个字符。", name, max_len)));
+    }
+    Ok(())
+}
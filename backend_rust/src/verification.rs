@@ -0,0 +1,198 @@
+// 服务端链上验证：对比数据库中的哈希、由存储元数据重新计算的哈希以及合约中的哈希
+use crate::contract;
+use crate::errors::AppError;
+use crate::eth_rpc::{parse_quantity, EthClient};
+use crate::hashing::{self, normalize_hash, HashScheme};
+use crate::merkle;
+use crate::models::{FoodRecordDetail, MerkleLeafDetail, MetadataField, NewRecordAnchor, VerificationResponse, VerificationVerdict};
+
+pub async fn verify_record_on_chain(
+    eth_client: &EthClient,
+    chain_id: u64,
+    contract_address: &str,
+    record: &FoodRecordDetail,
+    merkle_leaf: Option<&MerkleLeafDetail>, // Merkle 批量上链的记录所在叶子
+    metadata_fields: &[MetadataField],      // salted-fields-v1 记录的字段明文与盐值
+) -> Result<VerificationResponse, AppError> {
+    let db_hash = normalize_hash(&record.onchain_metadata_hash);
+
+    // 按记录保存的哈希方案重新计算
+    let hash_scheme = HashScheme::parse(&record.hash_scheme)?;
+    let recomputed_hash = hashing::recompute_stored_hash(hash_scheme, record.metadata_canonical.as_deref(), &record.metadata_json.0, metadata_fields)?;
+
+    let mut response = VerificationResponse {
+        product_id: record.product_id.clone(),
+        verdict: VerificationVerdict::NotAnchored,
+        hash_scheme,
+        db_hash: db_hash.clone(),
+        recomputed_hash: recomputed_hash.clone(),
+        chain_hash: None,
+        chain_recorder: None,
+        chain_timestamp: None,
+        chain_id,
+        contract_address: contract_address.to_string(),
+        merkle_batch_id: merkle_leaf.map(|leaf| leaf.batch_id),
+        message: String::new(),
+    };
+
+    // 链上查询的 productId 及期望的哈希：Merkle 批量上链的记录查询批次根，其余记录查询自身的元数据哈希
+    let (anchor_key, expected_hash) = match merkle_leaf {
+        Some(leaf) => match &leaf.merkle_root {
+            Some(root) => (merkle::anchor_key(leaf.batch_id), root.clone()),
+            None => {
+                response.message = format!("记录所在的 Merkle 批次 #{} 尚未封存。", leaf.batch_id);
+                return Ok(response);
+            }
+        },
+        None => (record.product_id.clone(), db_hash.clone()),
+    };
+
+    // records 映射不会 revert，先用它判断是否上链；getMetadataHash 在未上链时会 revert
+    let raw_record = eth_client
+        .eth_call(contract_address, &contract::encode_records(&anchor_key))
+        .await?;
+    let on_chain = contract::decode_records(&raw_record)?;
+
+    if on_chain.timestamp == 0 {
+        response.message = "合约中不存在该产品ID的记录。".to_string();
+        return Ok(response);
+    }
+
+    let raw_hash = eth_client
+        .eth_call(contract_address, &contract::encode_get_metadata_hash(&anchor_key))
+        .await?;
+    let chain_hash = contract::decode_get_metadata_hash(&raw_hash)?;
+    if chain_hash != on_chain.metadata_hash {
+        return Err(AppError::BlockchainError(format!(
+            "合约返回的哈希不一致: getMetadataHash={}, records={}",
+            chain_hash, on_chain.metadata_hash
+        )));
+    }
+
+    response.chain_hash = Some(chain_hash.clone());
+    response.chain_recorder = Some(on_chain.recorder);
+    response.chain_timestamp = Some(on_chain.timestamp);
+
+    (response.verdict, response.message) = if recomputed_hash != db_hash {
+        (VerificationVerdict::DbTampered, "存储的元数据与数据库中的哈希不一致，数据库记录可能被篡改。".to_string())
+    } else if !merkle_proof_matches(record, merkle_leaf, &expected_hash)? {
+        (VerificationVerdict::DbTampered, "记录的 Merkle 路径无法计算出批次根，数据库记录可能被篡改。".to_string())
+    } else if chain_hash == expected_hash {
+        (VerificationVerdict::Match, "数据库记录与链上哈希一致。".to_string())
+    } else {
+        // 链上哈希不同：若该产品ID曾以数据库中的哈希上链，说明合约记录被后续 addRecord 覆盖；否则是数据库哈希被改写
+        // 不能用全局的 checkMetadataHashExists：把其他产品的元数据与哈希复制到本行时，该哈希也 "曾经上链"
+        if anchored_with_hash(eth_client, contract_address, &anchor_key, &expected_hash).await? {
+            (VerificationVerdict::ChainOverwritten, "该产品ID曾以数据库中的哈希上链，但链上记录已被后续交易覆盖。".to_string())
+        } else {
+            (VerificationVerdict::DbTampered, "该产品ID从未以数据库中的哈希上链，数据库记录可能被篡改。".to_string())
+        }
+    };
+    Ok(response)
+}
+
+// 合约中是否有 productId 与 metadataHash 都匹配的 RecordAdded 事件
+async fn anchored_with_hash(eth_client: &EthClient, contract_address: &str, anchor_key: &str, metadata_hash: &str) -> Result<bool, AppError> {
+    let product_topic = contract::product_id_topic(anchor_key);
+    let metadata_hash = normalize_hash(metadata_hash);
+    let logs = eth_client
+        .get_logs_by_topics(contract_address, &[&contract::record_added_topic(), &product_topic, &metadata_hash])
+        .await?;
+    for log in &logs {
+        if let Some(event) = contract::decode_record_added(log)? {
+            if event.product_id_hash == product_topic && event.metadata_hash == metadata_hash {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+// 非 Merkle 批量上链的记录直接返回 true
+fn merkle_proof_matches(record: &FoodRecordDetail, merkle_leaf: Option<&MerkleLeafDetail>, root: &str) -> Result<bool, AppError> {
+    let Some(leaf) = merkle_leaf else {
+        return Ok(true);
+    };
+    let Some(proof) = &leaf.proof else {
+        return Ok(false);
+    };
+    let leaf_hash = merkle::leaf_hash(&record.product_id, &record.onchain_metadata_hash)?;
+    Ok(merkle::verify_proof(&leaf_hash, &proof.0, root)?)
+}
+
+// 通过交易回执校验过的上链信息
+#[derive(Debug, Clone)]
+pub struct ValidatedAnchor {
+    pub transaction_hash: String,
+    pub block_number: u64,
+    pub sender: String,   // 交易发送者 (from)
+    pub recorder: String, // RecordAdded 事件中的 recorder (msg.sender)
+    pub timestamp: u64,
+}
+
+pub const ANCHOR_STATUS_CONFIRMED: &str = "confirmed";
+
+impl ValidatedAnchor {
+    // 转换为 record_anchors 表中的一行
+    pub fn to_record_anchor(&self, product_id: &str, chain_id: u64, contract_address: &str) -> NewRecordAnchor {
+        NewRecordAnchor {
+            product_id: product_id.to_string(),
+            chain_id,
+            contract_address: contract_address.to_string(),
+            transaction_hash: self.transaction_hash.clone(),
+            block_number: self.block_number,
+            block_timestamp: chrono::DateTime::from_timestamp(self.timestamp as i64, 0),
+            status: ANCHOR_STATUS_CONFIRMED.to_string(),
+        }
+    }
+}
+
+// 校验客户端提交的 transactionHash：
+// 交易必须已成功执行、调用的是配置的合约，并且产生了 productId 与 metadataHash 都匹配的 RecordAdded 事件
+pub async fn validate_anchor_transaction(
+    eth_client: &EthClient,
+    contract_address: &str,
+    transaction_hash: &str,
+    product_id: &str,
+    metadata_hash: &str,
+) -> Result<ValidatedAnchor, AppError> {
+    let tx_hash = normalize_hash(transaction_hash);
+    if contract::parse_bytes32(&tx_hash).is_err() {
+        return Err(AppError::InvalidInput(format!("无效的交易哈希: {}", transaction_hash)));
+    }
+
+    let receipt = eth_client
+        .get_transaction_receipt(&tx_hash)
+        .await?
+        .ok_or_else(|| AppError::AnchorValidationFailed(format!("未找到交易 {} 的回执，交易可能不存在或尚未打包。", tx_hash)))?;
+
+    if !receipt.is_success() {
+        return Err(AppError::AnchorValidationFailed(format!("交易 {} 执行失败。", tx_hash)));
+    }
+    if receipt.to.as_deref().map(str::to_lowercase).as_deref() != Some(contract_address) {
+        return Err(AppError::AnchorValidationFailed(format!(
+            "交易 {} 调用的合约 {:?} 不是配置的合约 {}。", tx_hash, receipt.to, contract_address
+        )));
+    }
+
+    let expected_product_topic = contract::product_id_topic(product_id);
+    let expected_metadata_hash = normalize_hash(metadata_hash);
+    for log in receipt.logs.iter().filter(|log| log.address.to_lowercase() == contract_address) {
+        let Some(event) = contract::decode_record_added(log)? else {
+            continue;
+        };
+        if event.product_id_hash == expected_product_topic && event.metadata_hash == expected_metadata_hash {
+            return Ok(ValidatedAnchor {
+                transaction_hash: tx_hash,
+                block_number: parse_quantity(&receipt.block_number)?,
+                sender: receipt.from.to_lowercase(),
+                recorder: event.recorder,
+                timestamp: event.timestamp,
+            });
+        }
+    }
+
+    Err(AppError::AnchorValidationFailed(format!(
+        "交易 {} 中没有产品ID '{}' 与元数据哈希 {} 对应的 RecordAdded 事件。", tx_hash, product_id, expected_metadata_hash
+    )))
+}
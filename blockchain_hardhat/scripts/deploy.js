@@ -1,3 +1,9 @@
+const fs = require("fs");
+const path = require("path");
+
+// 部署清单目录，后端 (DEPLOYMENTS_DIR) 启动时按链 ID 读取合约地址和部署区块
+const DEPLOYMENTS_DIR = path.join(__dirname, "..", "deployments");
+
 async function main() {
   const [deployer] = await ethers.getSigners(); // 获取部署者账户
 
@@ -19,10 +25,26 @@ async function main() {
   const contractAddress = await foodTraceability.getAddress();
 
   console.log("FoodTraceability contract deployed to:", contractAddress);
-  console.log(
-    "Transaction hash:",
-    foodTraceability.deploymentTransaction().hash
-  );
+  const deploymentTx = foodTraceability.deploymentTransaction();
+  console.log("Transaction hash:", deploymentTx.hash);
+
+  // 写入部署清单 deployments/<chainId>.json
+  const receipt = await deploymentTx.wait();
+  const { chainId } = await ethers.provider.getNetwork();
+  const manifest = {
+    chainId: Number(chainId),
+    network: network.name,
+    contractName: "FoodTraceability",
+    address: contractAddress,
+    deployBlock: receipt.blockNumber,
+    transactionHash: deploymentTx.hash,
+    deployer: deployer.address,
+    deployedAt: new Date().toISOString(),
+  };
+  fs.mkdirSync(DEPLOYMENTS_DIR, { recursive: true });
+  const manifestPath = path.join(DEPLOYMENTS_DIR, `${manifest.chainId}.json`);
+  fs.writeFileSync(manifestPath, JSON.stringify(manifest, null, 2) + "\n");
+  console.log("Deployment manifest written to:", manifestPath);
 }
 
 main()
@@ -7,7 +7,7 @@ import {
 import { WalletOutlined, CloudUploadOutlined, /* CheckCircleOutlined, WarningOutlined */ } from '@ant-design/icons';
 import dayjs, { type Dayjs } from 'dayjs'; // Dayjs 只作用类型
 import type { ValidateErrorEntity } from 'rc-field-form/lib/interface'; // 用于 onFinishFailed
-import { EXPECTED_NETWORK_NAME, EXPECTED_CHAIN_ID, getProviderAndSigner, getFoodTraceabilityContract, calculateMetadataHash } from '../utils/blockchain';
+import { EXPECTED_NETWORK_NAME, EXPECTED_CHAIN_ID, getProviderAndSigner, getFoodTraceabilityContract, calculateMetadataHash, signFoodRecord, signInWithEthereum, HASH_SCHEME_EIP712 } from '../utils/blockchain';
 import 'dayjs/locale/zh-cn';
 dayjs.locale('zh-cn'); // 全局设置
 
@@ -99,8 +99,11 @@ const AddFoodPage: React.FC = () => {
         if (connection) {
             setSigner(connection.signer);
             setSignerAddress(connection.signerAddress);
-            const foodContract = getFoodTraceabilityContract(connection.signer);
-            setContract(foodContract);
+            try {
+                setContract(await getFoodTraceabilityContract(connection.signer));
+            } catch (err) {
+                antdMessage.error(`加载合约配置失败: ${(err as Error).message}`);
+            }
 
             if (connection.isExpectedNetwork) {
                 antdMessage.success(`钱包已连接: ${connection.signerAddress.substring(0,6)}...${connection.signerAddress.substring(connection.signerAddress.length - 4)}`);
@@ -208,13 +211,21 @@ const AddFoodPage: React.FC = () => {
         };
 
         try {
+            // 0. 使用钱包登录 (Sign-In With Ethereum)，写接口需要登录会话
+            setStatusMessage('正在请求 Metamask 签名登录...');
+            const session = await signInWithEthereum(signer);
+
             // 1. 准备元数据并计算哈希
             const metadataHash = calculateMetadataHash(metadataToSubmit);
             console.log("元数据 (提交用):", metadataToSubmit);
             console.log("元数据哈希 (前端计算):", metadataHash);
-            setStatusMessage('哈希计算完成，正在请求 Metamask 授权交易...');
+            setStatusMessage('哈希计算完成，正在请求 Metamask 签名记录...');
+
+            // 2. 对记录进行 EIP-712 签名，后端据此确认记录者 (须与发送交易的账户一致)
+            const signature = await signFoodRecord(signer, metadataToSubmit.productId, metadataHash);
+            setStatusMessage('签名完成，正在请求 Metamask 授权交易...');
 
-            // 2. 调用智能合约的 addRecord 方法
+            // 3. 调用智能合约的 addRecord 方法
             const tx = await contract.addRecord(metadataToSubmit.productId, metadataHash);
             setStatusMessage('交易已发送，等待区块链确认...');
             console.log("交易发送:", tx);
@@ -229,19 +240,21 @@ const AddFoodPage: React.FC = () => {
             const transactionHash = receipt.hash;
             setStatusMessage(`数据哈希已成功上链！交易哈希: ${transactionHash.substring(0,10)}...`);
             antdMessage.loading({ content: '数据已上链，正在发送到后端...', key: 'submitting', duration: 0 });
-            // 3. 将元数据和交易信息发送到后端进行持久化
+            // 4. 将元数据和交易信息发送到后端进行持久化
             const backendPayload = {
                 productId: metadataToSubmit.productId,
                 metadata: metadataToSubmit, // 发送转换后的元数据
                 metadataHashOnChain: metadataHash,
+                hashScheme: HASH_SCHEME_EIP712,
                 transactionHash: transactionHash,
+                signature: signature,
             };
             console.log("准备发送到后端的数据:", backendPayload);
 
             const backendUrl = '/api/food-records';
             const response = await fetch(backendUrl, {
                 method: 'POST',
-                headers: { 'Content-Type': 'application/json' },
+                headers: { 'Content-Type': 'application/json', Authorization: `Bearer ${session.token}` },
                 body: JSON.stringify(backendPayload),
             });
 
@@ -6,7 +6,7 @@ import {
 } from 'antd';
 import { RollbackOutlined, CheckCircleOutlined, WarningOutlined, SafetyCertificateOutlined, QuestionCircleOutlined, WalletOutlined } from '@ant-design/icons';
 
-import { getProviderAndSigner, getFoodTraceabilityContract, EXPECTED_NETWORK_NAME, EXPECTED_CHAIN_ID } from '../utils/blockchain';
+import { getProviderAndSigner, getFoodTraceabilityContract, computeMerkleRoot, type MerkleProof, EXPECTED_NETWORK_NAME, EXPECTED_CHAIN_ID } from '../utils/blockchain';
 
 // 解决 window.ethereum 类型问题
 // import { type Eip1193Provider } from 'ethers';
@@ -29,11 +29,65 @@ interface FoodDetailFromAPI {
     product_id: string;
     metadata_json: JsonObject;
     onchain_metadata_hash: string;
-    blockchain_transaction_hash: string;
+    hash_scheme: 'legacy-json-stringify-keccak256' | 'jcs-keccak256' | 'eip712-food-metadata-v1' | 'salted-fields-v1'; // 元数据哈希方案
+    blockchain_transaction_hash: string | null; // 后端托管上链的记录在交易确认前为空
+    chain_id: number | null; // 上链所在的链与合约
+    contract_address: string | null;
+    recorder: string | null; // EIP-712 签名恢复出的记录者地址，未签名的记录为空
+    recorder_signature: string | null;
     created_at: string;
     updated_at: string;
+    anchor_status: 'pending' | 'submitted' | 'confirmed' | 'failed';
+    anchor_outbox: { attempts: number; last_error: string | null; next_attempt_at: string } | null;
+    merkle_batch_id: number | null; // 以 Merkle 批次上链时为批次ID，链上只有批次根；重新上链后为空
+    recall_status: RecallStatus | null; // 影响该产品的召回中最需要关注的状态，从未被召回时为空
+    recalls: ProductRecall[];
+    certification: {
+        production_date: string | null;
+        all_valid_on_production_date: boolean | null; // 生产日期未知或没有关联证书时为空
+        certificates: {
+            id: number;
+            certificate_type: 'organic' | 'haccp' | 'iso_22000' | 'gap' | 'halal';
+            issuer: string;
+            certificate_number: string;
+            valid_from: string;
+            valid_until: string;
+            valid_on_production_date: boolean | null;
+        }[];
+    };
+}
+
+type RecallStatus = 'open' | 'in_progress' | 'closed';
+
+interface ProductRecall {
+    recall_id: number;
+    status: RecallStatus;
+    severity_class: 'class_i' | 'class_ii' | 'class_iii';
+    reason: string;
+    initiating_organization: string;
+    source: 'direct' | 'downstream'; // downstream: 经批次谱系从上游批次传播而来
+    via_product_id: string | null;
+    opened_at: string;
 }
 
+const HASH_SCHEME_LABELS: Record<FoodDetailFromAPI['hash_scheme'], string> = {
+    'legacy-json-stringify-keccak256': '早期 JSON.stringify 哈希 (legacy-json-stringify-keccak256)',
+    'jcs-keccak256': 'JSON 文本哈希 (jcs-keccak256)',
+    'eip712-food-metadata-v1': 'EIP-712 结构化哈希 (eip712-food-metadata-v1)',
+    'salted-fields-v1': '加盐字段承诺，可选择性披露 (salted-fields-v1)',
+};
+
+const RECALL_SEVERITY_LABELS: Record<ProductRecall['severity_class'], string> = {
+    class_i: 'I 级', class_ii: 'II 级', class_iii: 'III 级',
+};
+
+const ANCHOR_STATUS_TAGS: Record<FoodDetailFromAPI['anchor_status'], { color: string; label: string }> = {
+    pending: { color: 'default', label: '等待上链' },
+    submitted: { color: 'processing', label: '交易已发送，等待确认' },
+    confirmed: { color: 'success', label: '已上链' },
+    failed: { color: 'error', label: '上链失败' },
+};
+
 interface VerificationState {
     chainHash: string | null;
     loading: boolean;
@@ -170,9 +224,24 @@ const FoodDetailPage: React.FC = () => {
         }
 
         try {
-            const contractInstance = getFoodTraceabilityContract(currentSigner);
-            const hashFromChain = await contractInstance.getMetadataHash(productId);
-            const isMatch = hashFromChain === foodDetail.onchain_metadata_hash;
+            let anchorKey = productId;
+            let expectedHash = foodDetail.onchain_metadata_hash;
+            let chainId = foodDetail.chain_id;
+            if (foodDetail.merkle_batch_id !== null) {
+                // 批量上链的记录没有以产品ID为键的链上记录 (getMetadataHash(productId) 会 revert)：
+                // 用包含证明从数据库中的哈希算出批次根，再与链上批次根比对
+                const response = await fetch(`/api/food-records/${productId}/merkle-proof`);
+                if (!response.ok) {
+                    throw new Error(`获取 Merkle 包含证明失败 (状态码: ${response.status})`);
+                }
+                const merkleProof: MerkleProof = await response.json();
+                anchorKey = merkleProof.anchor_key;
+                expectedHash = computeMerkleRoot(productId, foodDetail.onchain_metadata_hash, merkleProof.proof);
+                chainId = merkleProof.chain_id;
+            }
+            const contractInstance = await getFoodTraceabilityContract(currentSigner, chainId ?? undefined);
+            const hashFromChain = await contractInstance.getMetadataHash(anchorKey);
+            const isMatch = hashFromChain === expectedHash;
             // 链上哈希为空值处理
             if (!hashFromChain) {
                 throw new Error("链上未找到对应哈希记录。");
@@ -233,7 +302,33 @@ const FoodDetailPage: React.FC = () => {
     const coreInfoItems = [
         { key: '1', label: '产品ID (数据库)', children: <Text copyable>{foodDetail.product_id}</Text> },
         { key: '2', label: '链上元数据哈希', children: <Text copyable style={{wordBreak: 'break-all'}}>{foodDetail.onchain_metadata_hash}</Text> },
-        { key: '3', label: '区块链交易哈希', children: <Text copyable style={{wordBreak: 'break-all'}}>{foodDetail.blockchain_transaction_hash}</Text> },
+        { key: '8', label: '哈希方案', children: <Tag>{HASH_SCHEME_LABELS[foodDetail.hash_scheme]}</Tag> },
+        { key: '3', label: '区块链交易哈希', children: foodDetail.blockchain_transaction_hash
+            ? <Text copyable style={{wordBreak: 'break-all'}}>{foodDetail.blockchain_transaction_hash}</Text>
+            : <Tag>N/A</Tag> },
+        { key: '7', label: '记录者 (签名地址)', children: foodDetail.recorder
+            ? <Text copyable style={{wordBreak: 'break-all'}}>{foodDetail.recorder}</Text>
+            : <Tag>未签名</Tag> },
+        { key: '6', label: '上链状态', children: (
+            <Tooltip title={foodDetail.anchor_outbox?.last_error ?? undefined}>
+                <Tag color={ANCHOR_STATUS_TAGS[foodDetail.anchor_status].color}>
+                    {ANCHOR_STATUS_TAGS[foodDetail.anchor_status].label}
+                    {foodDetail.anchor_outbox && foodDetail.anchor_outbox.attempts > 1 ? ` (已尝试 ${foodDetail.anchor_outbox.attempts} 次)` : ''}
+                </Tag>
+            </Tooltip>
+        ) },
+        { key: '9', label: '认证证书 (生产日期当天)', children: foodDetail.certification.certificates.length === 0
+            ? <Tag>无关联证书</Tag>
+            : <Space wrap>
+                {foodDetail.certification.certificates.map(c => (
+                    <Tooltip key={c.id} title={`${c.issuer} ${c.certificate_number}，有效期 ${c.valid_from} 至 ${c.valid_until}`}>
+                        <Tag color={c.valid_on_production_date === null ? 'default' : c.valid_on_production_date ? 'success' : 'error'}>
+                            {c.certificate_type.toUpperCase().replace('_', ' ')}
+                            {c.valid_on_production_date === null ? ' (生产日期未知)' : c.valid_on_production_date ? ' 有效' : ' 无效'}
+                        </Tag>
+                    </Tooltip>
+                ))}
+            </Space> },
         { key: '4', label: '首次录入时间', children: new Date(foodDetail.created_at).toLocaleString('zh-CN') },
         { key: '5', label: '最后更新时间', children: new Date(foodDetail.updated_at).toLocaleString('zh-CN') },
     ];
@@ -261,6 +356,26 @@ const FoodDetailPage: React.FC = () => {
                 </Button>
             </Space>
 
+            {foodDetail.recall_status && (() => {
+                // 链上哈希一致只说明数据未被篡改，召回中的产品仍然不应食用
+                const active = foodDetail.recalls.filter(r => r.status !== 'closed');
+                const shown = active.length > 0 ? active : foodDetail.recalls;
+                return (
+                    <Alert
+                        type={active.length > 0 ? 'error' : 'info'}
+                        showIcon
+                        style={{ marginBottom: 24 }}
+                        message={active.length > 0 ? '该产品正在召回中' : '该产品曾被召回 (已结束)'}
+                        description={shown.map(r => (
+                            <div key={r.recall_id}>
+                                召回 #{r.recall_id} ({RECALL_SEVERITY_LABELS[r.severity_class]}，{r.initiating_organization}): {r.reason}
+                                {r.source === 'downstream' && r.via_product_id ? ` (原料批次 ${r.via_product_id} 被召回)` : ''}
+                            </div>
+                        ))}
+                    />
+                );
+            })()}
+
             <Descriptions title="核心存证信息" bordered column={1} items={coreInfoItems} style={{ marginBottom: 24 }} />
 
             <Descriptions title="元数据详情 (来自数据库)" bordered column={{ xxl: 3, xl: 2, lg: 2, md: 2, sm: 1, xs: 1 }} items={metadataItems} style={{ marginBottom: 24 }} />
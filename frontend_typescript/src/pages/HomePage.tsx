@@ -11,8 +11,15 @@ interface FoodListItemFromAPI {
     onchain_metadata_hash: string;
     created_at: string; // 后端返回的是 ISO 格式字符串
     product_name: string | null;
+    recall_status: 'open' | 'in_progress' | 'closed' | null; // 从未被召回时为空
 }
 
+const RECALL_STATUS_TAGS: Record<NonNullable<FoodListItemFromAPI['recall_status']>, { color: string; label: string }> = {
+    open: { color: 'red', label: '召回中' },
+    in_progress: { color: 'volcano', label: '召回处理中' },
+    closed: { color: 'default', label: '召回已结束' },
+};
+
 // 定义后端分页响应的完整结构
 interface PaginatedApiResponse {
     items: FoodListItemFromAPI[];
@@ -39,6 +46,14 @@ const columns = (navigate: ReturnType<typeof useNavigate>) => [ // 将 navigate
         render: (name: string | null) => name || <Tag color="orange">N/A</Tag>, // 如果为 null 显示 N/A 标签
         // sorter: (a: FoodListItemFromAPI, b: FoodListItemFromAPI) => (a.product_name || '').localeCompare(b.product_name || ''),
     },
+    {
+        title: '召回',
+        dataIndex: 'recall_status',
+        key: 'recall_status',
+        render: (status: FoodListItemFromAPI['recall_status']) => status
+            ? <Tag color={RECALL_STATUS_TAGS[status].color}>{RECALL_STATUS_TAGS[status].label}</Tag>
+            : null,
+    },
     {
         title: '链上元数据哈希',
         dataIndex: 'onchain_metadata_hash',
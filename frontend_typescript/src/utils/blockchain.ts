@@ -1,179 +1,39 @@
 // 最好是在这里定义常量并导出，保证一致性
-import { ethers, BrowserProvider, Contract, type Signer, type Eip1193Provider } from "ethers";
-// 从 Hardhat 部署脚本中获取的合约地址
-// 将这里的地址替换为实际部署的合约地址
-const FOOD_TRACEABILITY_CONTRACT_ADDRESS = "0x5fbdb2315678afecb367f032d93f642f64180aa3";
-
-// 从 Hardhat artifacts 中获取的 ABI (部分)
-// 从 artifacts/contracts/FoodTraceability.sol/FoodTraceability.json 获取完整的 ABI
-const FOOD_TRACEABILITY_ABI = [
-  {
-    inputs: [],
-    stateMutability: "nonpayable",
-    type: "constructor",
-  },
-  {
-    anonymous: false,
-    inputs: [
-      {
-        indexed: true,
-        internalType: "string",
-        name: "productId",
-        type: "string",
-      },
-      {
-        indexed: true,
-        internalType: "bytes32",
-        name: "metadataHash",
-        type: "bytes32",
-      },
-      {
-        indexed: true,
-        internalType: "address",
-        name: "recorder",
-        type: "address",
-      },
-      {
-        indexed: false,
-        internalType: "uint256",
-        name: "timestamp",
-        type: "uint256",
-      },
-    ],
-    name: "RecordAdded",
-    type: "event",
-  },
-  {
-    inputs: [
-      {
-        internalType: "string",
-        name: "_productId",
-        type: "string",
-      },
-      {
-        internalType: "bytes32",
-        name: "_metadataHash",
-        type: "bytes32",
-      },
-    ],
-    name: "addRecord",
-    outputs: [],
-    stateMutability: "nonpayable",
-    type: "function",
-  },
-  {
-    inputs: [
-      {
-        internalType: "bytes32",
-        name: "_metadataHash",
-        type: "bytes32",
-      },
-    ],
-    name: "checkMetadataHashExists",
-    outputs: [
-      {
-        internalType: "bool",
-        name: "",
-        type: "bool",
-      },
-    ],
-    stateMutability: "view",
-    type: "function",
-  },
-  {
-    inputs: [
-      {
-        internalType: "string",
-        name: "_productId",
-        type: "string",
-      },
-    ],
-    name: "getMetadataHash",
-    outputs: [
-      {
-        internalType: "bytes32",
-        name: "",
-        type: "bytes32",
-      },
-    ],
-    stateMutability: "view",
-    type: "function",
-  },
-  {
-    inputs: [
-      {
-        internalType: "bytes32",
-        name: "",
-        type: "bytes32",
-      },
-    ],
-    name: "metadataHashExists",
-    outputs: [
-      {
-        internalType: "bool",
-        name: "",
-        type: "bool",
-      },
-    ],
-    stateMutability: "view",
-    type: "function",
-  },
-  {
-    inputs: [],
-    name: "owner",
-    outputs: [
-      {
-        internalType: "address",
-        name: "",
-        type: "address",
-      },
-    ],
-    stateMutability: "view",
-    type: "function",
-  },
-  {
-    inputs: [
-      {
-        internalType: "string",
-        name: "",
-        type: "string",
-      },
-    ],
-    name: "records",
-    outputs: [
-      {
-        internalType: "bytes32",
-        name: "metadataHash",
-        type: "bytes32",
-      },
-      {
-        internalType: "address",
-        name: "recorder",
-        type: "address",
-      },
-      {
-        internalType: "uint256",
-        name: "timestamp",
-        type: "uint256",
-      },
-    ],
-    stateMutability: "view",
-    type: "function",
-  },
-  {
-    inputs: [
-      {
-        internalType: "address",
-        name: "newOwner",
-        type: "address",
-      },
-    ],
-    name: "transferOwnership",
-    outputs: [],
-    stateMutability: "nonpayable",
-    type: "function",
-  },
-];
+import { ethers, BrowserProvider, Contract, type Signer, type Eip1193Provider, type InterfaceAbi, type TypedDataDomain } from "ethers";
+
+// 合约地址与 ABI 由后端 GET /api/chain/config 提供 (来自 Hardhat 编译产物与部署清单)，重新部署后无需修改前端
+export interface ChainConfig {
+  chain_id: number;
+  chain_name: string;
+  contract_address: string;
+  deploy_block: number;
+  contract_name: string;
+  abi: InterfaceAbi;
+  eip712_domain: TypedDataDomain; // 对记录签名时使用的 EIP-712 域
+  default_chain_id: number;
+}
+
+const chainConfigCache = new Map<string, Promise<ChainConfig>>();
+
+// 获取指定链 (未指定时为后端默认链) 的合约配置，结果按链缓存
+export const fetchChainConfig = (chainId?: number): Promise<ChainConfig> => {
+  const key = chainId === undefined ? "default" : String(chainId);
+  let config = chainConfigCache.get(key);
+  if (!config) {
+    const query = chainId === undefined ? "" : `?chain_id=${chainId}`;
+    config = fetch(`/api/chain/config${query}`).then(async (response) => {
+      const body = await response.json();
+      if (!response.ok) {
+        throw new Error(body.message || `获取合约配置失败 (HTTP ${response.status})`);
+      }
+      return body as ChainConfig;
+    });
+    // 请求失败时不缓存，下次调用重新获取
+    config.catch(() => chainConfigCache.delete(key));
+    chainConfigCache.set(key, config);
+  }
+  return config;
+};
 
 // 定义期望的网络信息 (Hardhat 本地网络)，用于导出配置
 export const EXPECTED_CHAIN_ID = "0x539"; // 期望的 Hardhat 本地网络 Chain ID，1337 的十六进制形式 (默认为：0x7A69 for 31337)
@@ -332,19 +192,161 @@ export const getProviderAndSigner = async (
   }
 };
 
-// 获取智能合约实例
-export const getFoodTraceabilityContract = (
+// 获取智能合约实例，chainId 为记录上链所在的链 (未指定时为后端默认链)
+export const getFoodTraceabilityContract = async (
   signerOrProvider: Signer | BrowserProvider,
-): Contract => {
-  return new ethers.Contract(
-    FOOD_TRACEABILITY_CONTRACT_ADDRESS,
-    FOOD_TRACEABILITY_ABI,
-    signerOrProvider,
-  );
+  chainId?: number,
+): Promise<Contract> => {
+  const config = await fetchChainConfig(chainId);
+  return new ethers.Contract(config.contract_address, config.abi, signerOrProvider);
+};
+
+// Sign-In With Ethereum (EIP-4361) 登录会话，令牌保存在 localStorage，写接口通过 Authorization 头携带
+export interface AuthSession {
+  token: string;
+  address: string; // 小写
+  chain_id: number;
+  expires_at: string;
+}
+
+const AUTH_SESSION_KEY = "siweSession";
+
+// 读取指定地址未过期的登录会话
+export const getAuthSession = (address: string): AuthSession | null => {
+  try {
+    const session = JSON.parse(localStorage.getItem(AUTH_SESSION_KEY) ?? "null") as AuthSession | null;
+    if (session && session.address === address.toLowerCase() && new Date(session.expires_at).getTime() > Date.now()) {
+      return session;
+    }
+  } catch {
+    // 格式错误的会话按未登录处理
+  }
+  return null;
+};
+
+// 使用钱包签名 SIWE 消息登录，已有未过期的会话时直接复用
+export const signInWithEthereum = async (signer: Signer): Promise<AuthSession> => {
+  const address = await signer.getAddress();
+  const existing = getAuthSession(address);
+  if (existing) {
+    return existing;
+  }
+  const nonceResponse = await fetch("/api/auth/nonce");
+  const nonceBody = await nonceResponse.json();
+  if (!nonceResponse.ok) {
+    throw new Error(nonceBody.message || `获取登录 nonce 失败 (HTTP ${nonceResponse.status})`);
+  }
+  const network = await signer.provider?.getNetwork();
+  const message = [
+    `${window.location.host} wants you to sign in with your Ethereum account:`,
+    address,
+    "",
+    "登录食品溯源系统",
+    "",
+    `URI: ${window.location.origin}`,
+    "Version: 1",
+    `Chain ID: ${network?.chainId ?? 0}`,
+    `Nonce: ${nonceBody.nonce}`,
+    `Issued At: ${new Date().toISOString()}`,
+  ].join("\n");
+  const signature = await signer.signMessage(message);
+  const response = await fetch("/api/auth/siwe", {
+    method: "POST",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify({ message, signature }),
+  });
+  const body = await response.json();
+  if (!response.ok) {
+    throw new Error(body.message || `登录失败 (HTTP ${response.status})`);
+  }
+  localStorage.setItem(AUTH_SESSION_KEY, JSON.stringify(body));
+  return body as AuthSession;
 };
 
-// 计算元数据的 Keccak256 哈希
+// EIP-712 记录类型，与后端 eip712.rs 中的 FoodRecord 类型一致
+const FOOD_RECORD_TYPES = {
+  FoodRecord: [
+    { name: "productId", type: "string" },
+    { name: "metadataHash", type: "bytes32" },
+  ],
+};
+
+// 使用钱包对 (productId, metadataHash) 进行 EIP-712 签名，后端据此恢复记录者地址
+export const signFoodRecord = async (
+  signer: Signer,
+  productId: string,
+  metadataHash: string,
+  chainId?: number,
+): Promise<string> => {
+  const config = await fetchChainConfig(chainId);
+  return signer.signTypedData(config.eip712_domain, FOOD_RECORD_TYPES, { productId, metadataHash });
+};
+
+// RFC 8785 (JCS) 规范化：对象键按 UTF-16 码元排序，基本类型沿用 JSON.stringify 的格式
+// 后端 canonical_json.rs 使用相同规则，保证同一份元数据在前后端得到相同的哈希
+export const canonicalizeJson = (value: unknown): string => {
+  if (value === null || typeof value !== "object") {
+    if (typeof value === "number" && !Number.isFinite(value)) {
+      throw new Error(`JSON 中不允许出现非有限数字: ${value}`);
+    }
+    return JSON.stringify(value);
+  }
+  if (Array.isArray(value)) {
+    return `[${value.map((item) => canonicalizeJson(item)).join(",")}]`;
+  }
+  const entries = Object.entries(value as Record<string, unknown>)
+    .filter(([, v]) => v !== undefined) // 与 JSON.stringify 一致，忽略 undefined 字段
+    .sort(([a], [b]) => (a < b ? -1 : a > b ? 1 : 0));
+  return `{${entries.map(([k, v]) => `${JSON.stringify(k)}:${canonicalizeJson(v)}`).join(",")}}`;
+};
+
+// 元数据哈希方案，与后端 hashing::HashScheme 一致
+export const HASH_SCHEME_LEGACY = "legacy-json-stringify-keccak256";
+export const HASH_SCHEME_JCS = "jcs-keccak256";
+export const HASH_SCHEME_EIP712 = "eip712-food-metadata-v1";
+export const HASH_SCHEME_SALTED = "salted-fields-v1";
+
+// EIP-712 元数据类型，与后端 eip712.rs 中的 FoodMetadata 类型一致，缺少的字段按空字符串编码
+const FOOD_METADATA_TYPES = {
+  FoodMetadata: [
+    { name: "productId", type: "string" },
+    { name: "productName", type: "string" },
+    { name: "producerInfo", type: "string" },
+    { name: "productionDate", type: "string" },
+    { name: "origin", type: "string" },
+    { name: "processingSteps", type: "string" },
+  ],
+};
+
+// 计算元数据的 EIP-712 hashStruct(FoodMetadata) (哈希方案 eip712-food-metadata-v1)
 export const calculateMetadataHash = (metadata: object): string => {
-  const metadataString = JSON.stringify(metadata);
+  const fields = metadata as Record<string, unknown>;
+  const value = Object.fromEntries(FOOD_METADATA_TYPES.FoodMetadata.map(({ name }) => [name, fields[name] ?? ""]));
+  return ethers.TypedDataEncoder.hashStruct("FoodMetadata", FOOD_METADATA_TYPES, value);
+};
+
+// JCS 规范化后字节的 Keccak256 (哈希方案 jcs-keccak256)；引入哈希方案之前的记录是 keccak256(JSON.stringify(metadata))
+export const calculateJcsMetadataHash = (metadata: object): string => {
+  const metadataString = canonicalizeJson(metadata);
   return ethers.keccak256(ethers.toUtf8Bytes(metadataString));
 };
+
+// Merkle 批量上链记录的包含证明 (GET /api/food-records/{product_id}/merkle-proof)，链上只有批次根，键为 anchor_key
+export interface MerkleProof {
+  leaf_hash: string;
+  proof: { position: "left" | "right"; hash: string }[];
+  merkle_root: string;
+  anchor_key: string;
+  chain_id: number;
+  contract_address: string;
+}
+
+// 与后端 merkle.rs 的编码一致，从记录哈希沿路径计算批次根：
+// 叶子 keccak256(0x00 || keccak256(productId) || metadataHash)，内部节点 keccak256(0x01 || left || right)
+export const computeMerkleRoot = (productId: string, metadataHash: string, proof: MerkleProof["proof"]): string => {
+  let node = ethers.keccak256(ethers.concat(["0x00", ethers.keccak256(ethers.toUtf8Bytes(productId)), metadataHash]));
+  for (const step of proof) {
+    node = ethers.keccak256(ethers.concat(step.position === "left" ? ["0x01", step.hash, node] : ["0x01", node, step.hash]));
+  }
+  return node;
+};